@@ -0,0 +1,18 @@
+CREATE TABLE organizations (
+    created_at timestamptz NOT NULL DEFAULT now(),
+    id bytea PRIMARY KEY,
+    name text NOT NULL
+);
+
+CREATE TYPE organization_role AS ENUM ('member', 'admin', 'owner');
+
+CREATE TABLE organization_members (
+    created_at timestamptz NOT NULL DEFAULT now(),
+    organization_id bytea NOT NULL REFERENCES organizations (id) ON DELETE CASCADE,
+    user_id bytea NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    role organization_role NOT NULL,
+
+    PRIMARY KEY (organization_id, user_id)
+);
+
+CREATE INDEX organization_members_by_user_id ON organization_members (user_id);
@@ -0,0 +1,7 @@
+CREATE TABLE invites (
+    created_at timestamptz NOT NULL DEFAULT now(),
+    token_hash bytea PRIMARY KEY,
+    created_by bytea NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    organization_id bytea REFERENCES organizations (id) ON DELETE CASCADE,
+    email citext
+);
@@ -0,0 +1,58 @@
+ALTER TABLE users
+    ADD COLUMN storage_used bigint NOT NULL DEFAULT 0,
+    ADD COLUMN file_count bigint NOT NULL DEFAULT 0;
+
+ALTER TABLE folders
+    ADD COLUMN file_count bigint NOT NULL DEFAULT 0;
+
+CREATE TABLE monthly_bandwidth (
+    user_id bytea NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    month date NOT NULL,
+    bytes bigint NOT NULL DEFAULT 0,
+
+    PRIMARY KEY (user_id, month)
+);
+
+-- Keeps the storage counters of users and their folders in sync with their files, so usage never
+-- needs to be computed by scanning every file.
+CREATE FUNCTION count_file_usage() RETURNS trigger AS $$
+BEGIN
+    IF TG_OP IN ('UPDATE', 'DELETE') THEN
+        UPDATE users
+            SET storage_used = storage_used - OLD.size, file_count = file_count - 1
+            WHERE id = OLD.owner_id;
+
+        UPDATE folders
+            SET size = size - OLD.size, file_count = file_count - 1
+            WHERE id = ANY (OLD.parent_id_path);
+    END IF;
+
+    IF TG_OP IN ('INSERT', 'UPDATE') THEN
+        UPDATE users
+            SET storage_used = storage_used + NEW.size, file_count = file_count + 1
+            WHERE id = NEW.owner_id;
+
+        UPDATE folders
+            SET size = size + NEW.size, file_count = file_count + 1
+            WHERE id = ANY (NEW.parent_id_path);
+    END IF;
+
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER count_file_usage
+    AFTER INSERT OR DELETE OR UPDATE OF owner_id, parent_id_path, size ON files
+    FOR EACH ROW EXECUTE FUNCTION count_file_usage();
+
+UPDATE users
+    SET (storage_used, file_count) = (
+        SELECT coalesce(sum(files.size), 0), count(*) FROM files
+            WHERE files.owner_id = users.id
+    );
+
+UPDATE folders
+    SET (size, file_count) = (
+        SELECT coalesce(sum(files.size), 0), count(*) FROM files
+            WHERE folders.id = ANY (files.parent_id_path)
+    );
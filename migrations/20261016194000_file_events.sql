@@ -0,0 +1,65 @@
+CREATE TYPE file_event_type AS ENUM ('uploaded', 'renamed', 'moved', 'shared', 'unshared', 'deleted');
+
+CREATE TABLE file_events (
+    created_at timestamptz NOT NULL DEFAULT now(),
+    id bigint GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+    user_id bytea NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    file_id bytea NOT NULL,
+    type file_event_type NOT NULL,
+    name text NOT NULL,
+    parent_name_path text[] NOT NULL,
+    previous_name text,
+    previous_parent_name_path text[]
+);
+
+CREATE INDEX file_events_by_user_id ON file_events (user_id, id);
+
+-- Records an event for each change to a file's lifecycle, so every code path changing files is
+-- reflected in users' activity logs.
+CREATE FUNCTION record_file_event() RETURNS trigger AS $$
+BEGIN
+    IF TG_OP = 'INSERT' THEN
+        INSERT INTO file_events (user_id, file_id, type, name, parent_name_path)
+            VALUES (NEW.owner_id, NEW.id, 'uploaded', NEW.name, NEW.parent_name_path);
+    ELSIF TG_OP = 'DELETE' THEN
+        -- If the file is being deleted because its owner is, there's no activity log to add to.
+        INSERT INTO file_events (user_id, file_id, type, name, parent_name_path)
+            SELECT OLD.owner_id, OLD.id, 'deleted', OLD.name, OLD.parent_name_path
+                WHERE EXISTS (SELECT 1 FROM users WHERE id = OLD.owner_id);
+    ELSE
+        IF NEW.parent_id_path IS DISTINCT FROM OLD.parent_id_path THEN
+            INSERT INTO file_events (
+                user_id, file_id, type, name, parent_name_path, previous_name,
+                previous_parent_name_path
+            )
+                VALUES (
+                    NEW.owner_id, NEW.id, 'moved', NEW.name, NEW.parent_name_path, OLD.name,
+                    OLD.parent_name_path
+                );
+        ELSIF NEW.name IS DISTINCT FROM OLD.name THEN
+            INSERT INTO file_events (
+                user_id, file_id, type, name, parent_name_path, previous_name,
+                previous_parent_name_path
+            )
+                VALUES (
+                    NEW.owner_id, NEW.id, 'renamed', NEW.name, NEW.parent_name_path, OLD.name,
+                    OLD.parent_name_path
+                );
+        END IF;
+
+        IF NEW.shared AND NOT OLD.shared THEN
+            INSERT INTO file_events (user_id, file_id, type, name, parent_name_path)
+                VALUES (NEW.owner_id, NEW.id, 'shared', NEW.name, NEW.parent_name_path);
+        ELSIF OLD.shared AND NOT NEW.shared THEN
+            INSERT INTO file_events (user_id, file_id, type, name, parent_name_path)
+                VALUES (NEW.owner_id, NEW.id, 'unshared', NEW.name, NEW.parent_name_path);
+        END IF;
+    END IF;
+
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER record_file_event
+    AFTER INSERT OR DELETE OR UPDATE OF name, parent_id_path, shared ON files
+    FOR EACH ROW EXECUTE FUNCTION record_file_event();
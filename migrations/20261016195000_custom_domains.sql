@@ -0,0 +1,16 @@
+CREATE TABLE custom_domains (
+    created_at timestamptz NOT NULL DEFAULT now(),
+    name text NOT NULL,
+    user_id bytea NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    verification_token bytea NOT NULL,
+    verified_at timestamptz,
+    canonical boolean NOT NULL DEFAULT FALSE,
+
+    PRIMARY KEY (name, user_id)
+);
+
+CREATE UNIQUE INDEX verified_custom_domains ON custom_domains (name)
+    WHERE verified_at IS NOT NULL;
+CREATE UNIQUE INDEX canonical_custom_domains ON custom_domains (user_id)
+    WHERE canonical;
+CREATE INDEX custom_domains_by_user_id ON custom_domains (user_id);
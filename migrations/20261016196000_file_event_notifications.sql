@@ -0,0 +1,12 @@
+-- Announces each new file event by its ID, so the server can push it to the user's open clients.
+CREATE FUNCTION notify_file_event() RETURNS trigger AS $$
+BEGIN
+    PERFORM pg_notify('file_events', NEW.id::text);
+
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER notify_file_event
+    AFTER INSERT ON file_events
+    FOR EACH ROW EXECUTE FUNCTION notify_file_event();
@@ -0,0 +1,35 @@
+ALTER TABLE users
+    ADD COLUMN legal_hold boolean NOT NULL DEFAULT FALSE;
+
+ALTER TABLE files
+    ADD COLUMN legal_hold boolean NOT NULL DEFAULT FALSE;
+
+ALTER TABLE folders
+    ADD COLUMN retention_days integer;
+
+-- Blocks deleting anything under a legal hold, no matter which code path tries to delete it.
+CREATE FUNCTION enforce_legal_hold() RETURNS trigger AS $$
+DECLARE
+    is_held boolean := OLD.legal_hold;
+BEGIN
+    -- Files are also held if their owner is.
+    IF NOT is_held AND TG_TABLE_NAME = 'files' THEN
+        is_held := EXISTS (SELECT 1 FROM users WHERE id = OLD.owner_id AND legal_hold);
+    END IF;
+
+    IF is_held THEN
+        RAISE EXCEPTION 'cannot delete from % while under a legal hold', TG_TABLE_NAME
+            USING CONSTRAINT = 'legal_hold';
+    END IF;
+
+    RETURN OLD;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER enforce_legal_hold
+    BEFORE DELETE ON users
+    FOR EACH ROW EXECUTE FUNCTION enforce_legal_hold();
+
+CREATE TRIGGER enforce_legal_hold
+    BEFORE DELETE ON files
+    FOR EACH ROW EXECUTE FUNCTION enforce_legal_hold();
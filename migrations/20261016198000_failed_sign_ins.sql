@@ -0,0 +1,9 @@
+CREATE TABLE failed_sign_ins (
+    created_at timestamptz NOT NULL DEFAULT now(),
+    email citext NOT NULL,
+    ip text NOT NULL
+);
+
+CREATE INDEX failed_sign_ins_by_email ON failed_sign_ins (email, created_at);
+CREATE INDEX failed_sign_ins_by_ip ON failed_sign_ins (ip, created_at);
+CREATE INDEX failed_sign_ins_by_created_at ON failed_sign_ins (created_at);
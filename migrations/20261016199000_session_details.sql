@@ -0,0 +1,12 @@
+ALTER TABLE sessions
+    ADD COLUMN id bytea UNIQUE,
+    ADD COLUMN ip text,
+    ADD COLUMN user_agent text;
+
+-- Existing sessions need IDs too. These are derived from their token hashes since there's no way to
+-- generate random bytes here without an extension.
+UPDATE sessions
+    SET id = substring(sha256(token_hash) FROM 1 FOR 8);
+
+ALTER TABLE sessions
+    ALTER COLUMN id SET NOT NULL;
@@ -0,0 +1,20 @@
+ALTER TABLE sessions
+    ADD COLUMN device_fingerprint bytea,
+    ADD COLUMN revocation_token_hash bytea UNIQUE;
+
+CREATE TABLE known_devices (
+    user_id bytea NOT NULL REFERENCES users ON DELETE CASCADE,
+    fingerprint bytea NOT NULL,
+    created_at timestamptz NOT NULL DEFAULT now(),
+    PRIMARY KEY (user_id, fingerprint)
+);
+
+-- Devices with existing sessions are already known, so signing in from them again shouldn't notify
+-- their users.
+UPDATE sessions
+    SET device_fingerprint = sha256(convert_to(ip || E'\n' || coalesce(user_agent, ''), 'UTF8'))
+    WHERE ip IS NOT NULL;
+
+INSERT INTO known_devices (user_id, fingerprint)
+    SELECT DISTINCT user_id, device_fingerprint FROM sessions
+        WHERE device_fingerprint IS NOT NULL;
@@ -0,0 +1,13 @@
+CREATE TYPE notification_category AS ENUM (
+    'security_alerts',
+    'quota_warnings',
+    'moderation_notices',
+    'product_updates'
+);
+
+CREATE TABLE notification_opt_outs (
+    user_id bytea NOT NULL REFERENCES users ON DELETE CASCADE,
+    category notification_category NOT NULL,
+    created_at timestamptz NOT NULL DEFAULT now(),
+    PRIMARY KEY (user_id, category)
+);
@@ -0,0 +1,36 @@
+ALTER TABLE files
+    ADD COLUMN version integer NOT NULL DEFAULT 1;
+
+ALTER TABLE folders
+    ADD COLUMN version integer NOT NULL DEFAULT 1;
+
+-- Increments an entity's version whenever its metadata changes, so clients can tell if their copy
+-- is stale before overwriting someone else's changes.
+CREATE FUNCTION increment_version() RETURNS trigger AS $$
+BEGIN
+    NEW.version := OLD.version + 1;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER increment_file_version
+    BEFORE UPDATE OF name, parent_id_path, parent_name_path, shared, type ON files
+    FOR EACH ROW
+    WHEN (
+        (OLD.name, OLD.parent_id_path, OLD.parent_name_path, OLD.shared, OLD.type)
+            IS DISTINCT FROM (NEW.name, NEW.parent_id_path, NEW.parent_name_path, NEW.shared, NEW.type)
+    )
+    EXECUTE FUNCTION increment_version();
+
+-- Folder size counters change constantly, so they don't count as metadata.
+CREATE TRIGGER increment_folder_version
+    BEFORE UPDATE OF name, parent_id_path, parent_name_path, share_key, retention_days ON folders
+    FOR EACH ROW
+    WHEN (
+        (OLD.name, OLD.parent_id_path, OLD.parent_name_path, OLD.share_key, OLD.retention_days)
+            IS DISTINCT FROM (
+                NEW.name, NEW.parent_id_path, NEW.parent_name_path, NEW.share_key,
+                NEW.retention_days
+            )
+    )
+    EXECUTE FUNCTION increment_version();
@@ -0,0 +1,12 @@
+ALTER TABLE users
+    ADD COLUMN handle citext UNIQUE;
+
+-- Handles users changed away from, kept so their old file URLs can redirect to their new ones for a
+-- grace period, and so no one else can claim them in the meantime.
+CREATE TABLE previous_handles (
+    handle citext PRIMARY KEY,
+    user_id bytea NOT NULL REFERENCES users ON DELETE CASCADE,
+    released_at timestamptz NOT NULL DEFAULT now()
+);
+
+CREATE INDEX previous_handles_by_released_at ON previous_handles (released_at);
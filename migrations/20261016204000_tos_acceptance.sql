@@ -0,0 +1,5 @@
+-- The version of the terms of service and privacy policy each user last accepted. Users who signed
+-- up before this was tracked haven't accepted any version yet.
+ALTER TABLE users
+    ADD COLUMN tos_version text,
+    ADD COLUMN tos_accepted_at timestamptz;
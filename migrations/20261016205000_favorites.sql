@@ -0,0 +1,12 @@
+-- When the owner starred each file and folder, or null if it isn't starred.
+ALTER TABLE files
+    ADD COLUMN favorited_at timestamptz;
+
+ALTER TABLE folders
+    ADD COLUMN favorited_at timestamptz;
+
+CREATE INDEX files_favorited ON files (owner_id, favorited_at)
+    WHERE favorited_at IS NOT NULL;
+
+CREATE INDEX folders_favorited ON folders (owner_id, favorited_at)
+    WHERE favorited_at IS NOT NULL;
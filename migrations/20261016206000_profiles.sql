@@ -0,0 +1,14 @@
+ALTER TABLE users
+    ADD COLUMN bio text NOT NULL DEFAULT '',
+    ADD COLUMN avatar_file_id bytea REFERENCES files ON DELETE SET NULL;
+
+-- The files and folders users chose to show on their public profiles, in order.
+CREATE TABLE profile_pins (
+    user_id bytea NOT NULL REFERENCES users ON DELETE CASCADE,
+    position integer NOT NULL,
+    file_id bytea UNIQUE REFERENCES files ON DELETE CASCADE,
+    folder_id bytea UNIQUE REFERENCES folders ON DELETE CASCADE,
+
+    PRIMARY KEY (user_id, position),
+    CHECK ((file_id IS NULL) <> (folder_id IS NULL))
+);
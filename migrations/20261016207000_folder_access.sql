@@ -0,0 +1,13 @@
+CREATE TYPE folder_access AS ENUM ('read', 'write');
+
+-- Access other users were granted to folders, which extends to everything in them.
+CREATE TABLE folder_access_grants (
+    created_at timestamptz NOT NULL DEFAULT now(),
+    folder_id bytea NOT NULL REFERENCES folders ON DELETE CASCADE,
+    user_id bytea NOT NULL REFERENCES users ON DELETE CASCADE,
+    access folder_access NOT NULL,
+
+    PRIMARY KEY (folder_id, user_id)
+);
+
+CREATE INDEX folder_access_grants_by_user ON folder_access_grants (user_id, folder_id);
@@ -0,0 +1,7 @@
+-- Compact links redirecting to files. Each file has at most one, which is deleted when revoked.
+CREATE TABLE short_links (
+    created_at timestamptz NOT NULL DEFAULT now(),
+    code bytea PRIMARY KEY,
+    file_id bytea NOT NULL UNIQUE REFERENCES files ON DELETE CASCADE,
+    clicks bigint NOT NULL DEFAULT 0
+);
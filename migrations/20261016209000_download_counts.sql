@@ -0,0 +1,4 @@
+ALTER TABLE files
+    ADD COLUMN download_count bigint NOT NULL DEFAULT 0;
+
+CREATE INDEX files_by_download_count ON files (owner_id, download_count DESC);
@@ -0,0 +1,21 @@
+-- Each file's traffic, bucketed by the hour (in UTC) it was served in.
+CREATE TABLE file_traffic (
+    file_id bytea NOT NULL REFERENCES files ON DELETE CASCADE,
+    hour timestamptz NOT NULL,
+    requests bigint NOT NULL,
+    bytes bigint NOT NULL,
+
+    PRIMARY KEY (file_id, hour)
+);
+
+-- Keyed hashes of the IP addresses each file was served to, bucketed like `file_traffic`, so unique
+-- visitors can be counted over any range of buckets. These are only kept for a limited time.
+CREATE TABLE file_visitors (
+    file_id bytea NOT NULL REFERENCES files ON DELETE CASCADE,
+    hour timestamptz NOT NULL,
+    visitor_hash bytea NOT NULL,
+
+    PRIMARY KEY (file_id, hour, visitor_hash)
+);
+
+CREATE INDEX file_visitors_by_hour ON file_visitors (hour);
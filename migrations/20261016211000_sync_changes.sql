@@ -0,0 +1,65 @@
+CREATE TYPE sync_item_type AS ENUM ('file', 'folder');
+
+-- A log of which of each user's files and folders changed, for clients to sync incrementally.
+-- Changes are ordered by the ID of the transaction that made them and then by their own ID, since
+-- a change's own ID alone could become visible only after later ones are read.
+CREATE TABLE sync_changes (
+    id bigint GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+    transaction_id bigint NOT NULL DEFAULT pg_current_xact_id()::text::bigint,
+    user_id bytea NOT NULL REFERENCES users ON DELETE CASCADE,
+    item_type sync_item_type NOT NULL,
+    item_id bytea NOT NULL
+);
+
+CREATE INDEX sync_changes_by_user ON sync_changes (user_id, transaction_id, id);
+
+-- Records a change whenever a file or folder is created, deleted, or has its metadata or contents
+-- changed.
+CREATE FUNCTION record_sync_change() RETURNS trigger AS $$
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        -- If the item is being deleted because its owner is, there's no one left to sync it.
+        INSERT INTO sync_changes (user_id, item_type, item_id)
+            SELECT OLD.owner_id, TG_ARGV[0]::sync_item_type, OLD.id
+                WHERE EXISTS (SELECT 1 FROM users WHERE id = OLD.owner_id);
+    ELSE
+        INSERT INTO sync_changes (user_id, item_type, item_id)
+            VALUES (NEW.owner_id, TG_ARGV[0]::sync_item_type, NEW.id);
+    END IF;
+
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER record_file_sync_change
+    AFTER INSERT OR DELETE ON files
+    FOR EACH ROW EXECUTE FUNCTION record_sync_change('file');
+
+-- Versions are incremented by other triggers rather than in `UPDATE` statements themselves, so
+-- these can't be limited to updates of specific columns.
+CREATE TRIGGER record_file_update_sync_change
+    AFTER UPDATE ON files
+    FOR EACH ROW
+    WHEN (
+        (OLD.version, OLD.size, OLD.modified_at)
+            IS DISTINCT FROM (NEW.version, NEW.size, NEW.modified_at)
+    )
+    EXECUTE FUNCTION record_sync_change('file');
+
+CREATE TRIGGER record_folder_sync_change
+    AFTER INSERT OR DELETE ON folders
+    FOR EACH ROW EXECUTE FUNCTION record_sync_change('folder');
+
+-- Folder size counters change constantly, so only changes to a folder's version count.
+CREATE TRIGGER record_folder_update_sync_change
+    AFTER UPDATE ON folders
+    FOR EACH ROW
+    WHEN (OLD.version IS DISTINCT FROM NEW.version)
+    EXECUTE FUNCTION record_sync_change('folder');
+
+-- Existing items are logged as changes so clients syncing from the start of the log get them.
+INSERT INTO sync_changes (user_id, item_type, item_id)
+    SELECT owner_id, 'folder', id FROM folders ORDER BY parent_id_path;
+
+INSERT INTO sync_changes (user_id, item_type, item_id)
+    SELECT owner_id, 'file', id FROM files;
@@ -0,0 +1,11 @@
+-- Locks on files, so concurrent clients editing the same file don't overwrite each other's changes.
+-- A lock stops applying once it expires, even before it's deleted.
+CREATE TABLE file_locks (
+    created_at timestamptz NOT NULL DEFAULT now(),
+    file_id bytea PRIMARY KEY REFERENCES files ON DELETE CASCADE,
+    token_hash bytea NOT NULL,
+    user_id bytea NOT NULL REFERENCES users ON DELETE CASCADE,
+    expires_at timestamptz NOT NULL
+);
+
+CREATE INDEX file_locks_by_expires_at ON file_locks (expires_at);
@@ -0,0 +1,19 @@
+CREATE TYPE file_status AS ENUM ('active', 'pending_scan', 'quarantined', 'taken_down');
+
+-- Whether a file is available, or why it isn't. Unavailable files are still listed to their owners
+-- so they can tell why their links stopped working.
+ALTER TABLE files
+    ADD COLUMN status file_status NOT NULL DEFAULT 'active';
+
+DROP TRIGGER increment_file_version ON files;
+
+CREATE TRIGGER increment_file_version
+    BEFORE UPDATE OF name, parent_id_path, parent_name_path, shared, type, status ON files
+    FOR EACH ROW
+    WHEN (
+        (OLD.name, OLD.parent_id_path, OLD.parent_name_path, OLD.shared, OLD.type, OLD.status)
+            IS DISTINCT FROM (
+                NEW.name, NEW.parent_id_path, NEW.parent_name_path, NEW.shared, NEW.type, NEW.status
+            )
+    )
+    EXECUTE FUNCTION increment_version();
@@ -0,0 +1,61 @@
+CREATE TYPE plan AS ENUM ('free', 'supporter', 'pro');
+
+-- The limits and features of each plan.
+CREATE TABLE plan_limits (
+    plan plan PRIMARY KEY,
+    storage_quota bigint NOT NULL,
+    max_file_size bigint NOT NULL,
+    monthly_bandwidth bigint NOT NULL,
+    custom_domains boolean NOT NULL,
+    hls boolean NOT NULL
+);
+
+INSERT INTO plan_limits (
+    plan, storage_quota, max_file_size, monthly_bandwidth, custom_domains, hls
+)
+    VALUES
+        ('free', 5::bigint << 30, 100::bigint << 20, 50::bigint << 30, FALSE, FALSE),
+        ('supporter', 100::bigint << 30, 1::bigint << 30, 1::bigint << 40, TRUE, FALSE),
+        ('pro', 1::bigint << 40, 10::bigint << 30, 10::bigint << 40, TRUE, TRUE);
+
+ALTER TABLE users
+    ADD COLUMN plan plan NOT NULL DEFAULT 'free';
+
+-- Keeps files within their owners' plans' maximum file size and storage quota, so every code path
+-- adding to a user's storage is limited the same way. Files only shrinking or staying the same size
+-- are always allowed, so users over their quota (e.g. after changing plans) can still manage them.
+CREATE FUNCTION enforce_plan_limits() RETURNS trigger AS $$
+DECLARE
+    limits record;
+    added_size bigint := NEW.size;
+BEGIN
+    IF TG_OP = 'UPDATE' AND NEW.owner_id = OLD.owner_id THEN
+        added_size := NEW.size - OLD.size;
+    END IF;
+
+    IF added_size <= 0 THEN
+        RETURN NEW;
+    END IF;
+
+    SELECT users.storage_used, plan_limits.storage_quota, plan_limits.max_file_size
+        INTO limits
+        FROM users JOIN plan_limits ON plan_limits.plan = users.plan
+        WHERE users.id = NEW.owner_id;
+
+    IF NEW.size > limits.max_file_size THEN
+        RAISE EXCEPTION 'file exceeds the maximum file size of its owner''s plan'
+            USING CONSTRAINT = 'max_file_size';
+    END IF;
+
+    IF limits.storage_used + added_size > limits.storage_quota THEN
+        RAISE EXCEPTION 'file exceeds the storage quota of its owner''s plan'
+            USING CONSTRAINT = 'storage_quota';
+    END IF;
+
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER enforce_plan_limits
+    BEFORE INSERT OR UPDATE OF owner_id, size ON files
+    FOR EACH ROW EXECUTE FUNCTION enforce_plan_limits();
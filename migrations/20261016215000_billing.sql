@@ -0,0 +1,14 @@
+-- Users' Stripe customers and subscriptions, which determine their plans.
+ALTER TABLE users
+    ADD COLUMN stripe_customer_id text UNIQUE,
+    ADD COLUMN stripe_subscription_id text UNIQUE,
+    ADD COLUMN subscription_status text,
+    ADD COLUMN subscription_period_end timestamptz,
+    ADD COLUMN subscription_canceling boolean NOT NULL DEFAULT FALSE,
+    ADD COLUMN payment_failed_at timestamptz;
+
+-- The IDs of Stripe webhook events already handled, since Stripe can send an event more than once.
+CREATE TABLE stripe_events (
+    id text PRIMARY KEY,
+    received_at timestamptz NOT NULL DEFAULT now()
+);
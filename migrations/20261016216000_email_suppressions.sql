@@ -0,0 +1,12 @@
+CREATE TYPE email_suppression_reason AS ENUM ('bounce', 'complaint');
+
+-- Addresses that bounced or complained about our emails, which no more emails are sent to.
+CREATE TABLE email_suppressions (
+    created_at timestamptz NOT NULL DEFAULT now(),
+    email citext PRIMARY KEY,
+    reason email_suppression_reason NOT NULL
+);
+
+-- Whether a user's email was found undeliverable, so they must verify a working email.
+ALTER TABLE users
+    ADD COLUMN email_reverification_required boolean NOT NULL DEFAULT FALSE;
@@ -0,0 +1,8 @@
+-- When users deleted their accounts, and when everything they owned finished being purged. A
+-- deleted user's row is kept, anonymized, so records referencing them stay intact.
+ALTER TABLE users
+    ADD COLUMN deleted_at timestamptz,
+    ADD COLUMN purged_at timestamptz;
+
+CREATE INDEX users_pending_purge ON users (deleted_at)
+    WHERE deleted_at IS NOT NULL AND purged_at IS NULL;
@@ -0,0 +1,4 @@
+-- Whether a file was marked as sensitive, such as for being sexually explicit, so the content server
+-- can label it or warn visitors before showing it.
+ALTER TABLE files
+    ADD COLUMN sensitive boolean NOT NULL DEFAULT FALSE;
@@ -0,0 +1,3 @@
+-- Whether search engines may index a user's shared files.
+ALTER TABLE users
+    ADD COLUMN indexable boolean NOT NULL DEFAULT TRUE;
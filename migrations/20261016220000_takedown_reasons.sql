@@ -0,0 +1,14 @@
+CREATE TYPE takedown_reason AS ENUM ('legal', 'terms_violation');
+
+-- Why a taken down file was taken down, which determines how it's served.
+ALTER TABLE files
+    ADD COLUMN takedown_reason takedown_reason;
+
+-- Every takedown was served as unavailable for legal reasons before reasons were recorded.
+UPDATE files
+    SET takedown_reason = 'legal'
+    WHERE status = 'taken_down';
+
+ALTER TABLE files
+    ADD CONSTRAINT takedown_reason_set
+        CHECK ((status = 'taken_down') = (takedown_reason IS NOT NULL));
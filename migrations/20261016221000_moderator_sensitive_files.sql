@@ -0,0 +1,4 @@
+-- Whether a moderator marked a file as sensitive. This is separate from the mark set by the file's
+-- owner or collaborators, so they can't clear it, and a file is sensitive if either is set.
+ALTER TABLE files
+    ADD COLUMN sensitive_by_moderator boolean NOT NULL DEFAULT FALSE;
@@ -0,0 +1,7 @@
+-- The Stripe subscriptions of deleted users still waiting to be canceled. Cancellations are queued
+-- in the same transaction that deletes the user, then sent to Stripe in the background, so a
+-- failed request to Stripe can't leave a deleted user being billed.
+CREATE TABLE subscription_cancellations (
+    subscription_id text PRIMARY KEY,
+    queued_at timestamptz NOT NULL DEFAULT now()
+);
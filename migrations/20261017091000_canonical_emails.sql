@@ -0,0 +1,19 @@
+-- Users' email addresses with subaddresses stripped at providers that deliver them to the base
+-- mailbox, so sign-ups can be checked against mailboxes already in use. Users still sign in and
+-- receive mail at `email`, exactly as they entered it.
+ALTER TABLE users
+    ADD COLUMN canonical_email citext;
+
+-- The domains match `SUBADDRESSING_DOMAINS` in `src/api/validation.rs`. Quoted user portions are
+-- left alone.
+UPDATE users
+    SET canonical_email = regexp_replace(
+        email::text,
+        '^([^"+][^+]*)\+[^@]*@(fastmail\.com|gmail\.com|googlemail\.com|hotmail\.com|icloud\.com|live\.com|mac\.com|me\.com|msn\.com|outlook\.com|pm\.me|proton\.me|protonmail\.com)$',
+        '\1@\2'
+    );
+
+ALTER TABLE users
+    ALTER COLUMN canonical_email SET NOT NULL;
+
+CREATE INDEX users_canonical_email ON users (canonical_email);
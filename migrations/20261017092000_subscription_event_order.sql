@@ -0,0 +1,4 @@
+-- When the Stripe event last applied to each user's subscription was created, so events arriving
+-- out of order can't overwrite newer changes.
+ALTER TABLE users
+    ADD COLUMN subscription_updated_at timestamptz;
@@ -0,0 +1,10 @@
+CREATE TYPE maintenance_mode AS ENUM ('off', 'writes', 'all');
+
+-- The maintenance mode shared by every server process. This table always has exactly one row.
+CREATE TABLE maintenance (
+    singleton boolean PRIMARY KEY DEFAULT TRUE CHECK (singleton),
+    mode maintenance_mode NOT NULL DEFAULT 'off',
+    updated_at timestamptz NOT NULL DEFAULT now()
+);
+
+INSERT INTO maintenance DEFAULT VALUES;
@@ -0,0 +1,12 @@
+-- Invites to access folders sent by email. Whether or not the email has an account, access is only
+-- granted once the invite is accepted by a user with that email, so sharing a folder can't reveal
+-- which emails have accounts.
+CREATE TABLE folder_access_invites (
+    created_at timestamptz NOT NULL DEFAULT now(),
+    folder_id bytea NOT NULL REFERENCES folders ON DELETE CASCADE,
+    email citext NOT NULL,
+    token_hash bytea NOT NULL UNIQUE,
+    access folder_access NOT NULL,
+
+    PRIMARY KEY (folder_id, email)
+);
@@ -0,0 +1,22 @@
+-- Folders their owners shared with organizations they're members of, which grants every member of
+-- the organization access to everything in them.
+CREATE TABLE organization_folders (
+    created_at timestamptz NOT NULL DEFAULT now(),
+    organization_id bytea NOT NULL REFERENCES organizations (id) ON DELETE CASCADE,
+    folder_id bytea NOT NULL REFERENCES folders ON DELETE CASCADE,
+    access folder_access NOT NULL,
+
+    PRIMARY KEY (organization_id, folder_id)
+);
+
+CREATE INDEX organization_folders_by_folder_id ON organization_folders (folder_id);
+
+-- The access each user has to each folder, whether it was granted to them directly or through an
+-- organization they're a member of.
+CREATE VIEW effective_folder_access_grants AS
+    SELECT folder_id, user_id, access FROM folder_access_grants
+    UNION ALL
+    SELECT organization_folders.folder_id, organization_members.user_id, organization_folders.access
+        FROM organization_folders
+        JOIN organization_members
+            ON organization_members.organization_id = organization_folders.organization_id;
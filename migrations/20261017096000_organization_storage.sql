@@ -0,0 +1,175 @@
+-- Organizations have their own plans, whose storage quota is shared by everything in their storage.
+ALTER TABLE organizations
+    ADD COLUMN plan plan NOT NULL DEFAULT 'free';
+
+-- Top-level folders their owners moved into an organization's storage. Everything in them counts
+-- toward the organization's storage quota instead of their owner's, and is served under the
+-- organization's ID.
+ALTER TABLE folders
+    ADD COLUMN organization_id bytea REFERENCES organizations (id) ON DELETE SET NULL,
+    ADD CONSTRAINT folders_organization_storage_top_level
+        CHECK (organization_id IS NULL OR parent_id_path = '{}');
+
+-- Each folder's name is the first segment of its files' paths under the organization's ID, so
+-- these must be unique.
+CREATE UNIQUE INDEX organization_storage_folder_names ON folders (organization_id, name)
+    WHERE organization_id IS NOT NULL;
+
+-- Gets the ID of the organization whose storage an item with the specified parent ID path is in,
+-- or `NULL` if it's in its owner's storage.
+CREATE FUNCTION storage_organization_id(item_parent_id_path bytea[]) RETURNS bytea AS $$
+    SELECT organization_id FROM folders WHERE id = item_parent_id_path[1];
+$$ LANGUAGE sql STABLE;
+
+-- Users' storage counters only include their files in their own storage. Organizations' usage is
+-- the total of their folders' counters.
+CREATE OR REPLACE FUNCTION count_file_usage() RETURNS trigger AS $$
+BEGIN
+    IF TG_OP IN ('UPDATE', 'DELETE') THEN
+        UPDATE users
+            SET storage_used = storage_used - OLD.size, file_count = file_count - 1
+            WHERE id = OLD.owner_id AND storage_organization_id(OLD.parent_id_path) IS NULL;
+
+        UPDATE folders
+            SET size = size - OLD.size, file_count = file_count - 1
+            WHERE id = ANY (OLD.parent_id_path);
+    END IF;
+
+    IF TG_OP IN ('INSERT', 'UPDATE') THEN
+        UPDATE users
+            SET storage_used = storage_used + NEW.size, file_count = file_count + 1
+            WHERE id = NEW.owner_id AND storage_organization_id(NEW.parent_id_path) IS NULL;
+
+        UPDATE folders
+            SET size = size + NEW.size, file_count = file_count + 1
+            WHERE id = ANY (NEW.parent_id_path);
+    END IF;
+
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+-- Files in an organization's storage are limited by the organization's plan instead of their
+-- owner's. Moving a file between storages adds its whole size to the storage it's moved into.
+CREATE OR REPLACE FUNCTION enforce_plan_limits() RETURNS trigger AS $$
+DECLARE
+    limits record;
+    storage_organization bytea := storage_organization_id(NEW.parent_id_path);
+    added_size bigint := NEW.size;
+BEGIN
+    IF TG_OP = 'UPDATE' AND NEW.owner_id = OLD.owner_id
+        AND storage_organization_id(OLD.parent_id_path) IS NOT DISTINCT FROM storage_organization
+    THEN
+        added_size := NEW.size - OLD.size;
+    END IF;
+
+    IF added_size <= 0 THEN
+        RETURN NEW;
+    END IF;
+
+    IF storage_organization IS NULL THEN
+        SELECT users.storage_used, plan_limits.storage_quota, plan_limits.max_file_size
+            INTO limits
+            FROM users JOIN plan_limits ON plan_limits.plan = users.plan
+            WHERE users.id = NEW.owner_id;
+    ELSE
+        SELECT (
+            SELECT coalesce(sum(folders.size), 0) FROM folders
+                WHERE folders.organization_id = organizations.id
+        ) as storage_used, plan_limits.storage_quota, plan_limits.max_file_size
+            INTO limits
+            FROM organizations JOIN plan_limits ON plan_limits.plan = organizations.plan
+            WHERE organizations.id = storage_organization;
+    END IF;
+
+    IF NEW.size > limits.max_file_size THEN
+        RAISE EXCEPTION 'file exceeds the maximum file size of its storage''s plan'
+            USING CONSTRAINT = 'max_file_size';
+    END IF;
+
+    IF limits.storage_used + added_size > limits.storage_quota THEN
+        RAISE EXCEPTION 'file exceeds the storage quota of its storage''s plan'
+            USING CONSTRAINT = 'storage_quota';
+    END IF;
+
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER enforce_plan_limits ON files;
+
+CREATE TRIGGER enforce_plan_limits
+    BEFORE INSERT OR UPDATE OF owner_id, parent_id_path, size ON files
+    FOR EACH ROW EXECUTE FUNCTION enforce_plan_limits();
+
+-- Moves a folder's usage between its owner's storage and an organization's, keeping the storage
+-- it's moved into within its plan's storage quota. Like files, folders moved out of an
+-- organization's storage (e.g. when their owner leaves it) are always allowed.
+CREATE FUNCTION move_folder_storage() RETURNS trigger AS $$
+DECLARE
+    limits record;
+BEGIN
+    IF NEW.organization_id IS NOT DISTINCT FROM OLD.organization_id THEN
+        RETURN NEW;
+    END IF;
+
+    IF NEW.organization_id IS NOT NULL AND NEW.size > 0 THEN
+        SELECT (
+            SELECT coalesce(sum(folders.size), 0) FROM folders
+                WHERE folders.organization_id = organizations.id
+        ) as storage_used, plan_limits.storage_quota
+            INTO limits
+            FROM organizations JOIN plan_limits ON plan_limits.plan = organizations.plan
+            WHERE organizations.id = NEW.organization_id;
+
+        IF limits.storage_used + NEW.size > limits.storage_quota THEN
+            RAISE EXCEPTION 'folder exceeds the storage quota of its organization''s plan'
+                USING CONSTRAINT = 'storage_quota';
+        END IF;
+    END IF;
+
+    IF OLD.organization_id IS NULL THEN
+        UPDATE users
+            SET storage_used = storage_used - NEW.size, file_count = file_count - NEW.file_count
+            WHERE id = NEW.owner_id;
+    ELSIF NEW.organization_id IS NULL THEN
+        UPDATE users
+            SET storage_used = storage_used + NEW.size, file_count = file_count + NEW.file_count
+            WHERE id = NEW.owner_id;
+    END IF;
+
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER move_folder_storage
+    BEFORE UPDATE OF organization_id ON folders
+    FOR EACH ROW EXECUTE FUNCTION move_folder_storage();
+
+-- Members who leave an organization take their folders back out of its storage.
+CREATE FUNCTION remove_member_storage() RETURNS trigger AS $$
+BEGIN
+    UPDATE folders
+        SET organization_id = NULL
+        WHERE organization_id = OLD.organization_id AND owner_id = OLD.user_id;
+
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER remove_member_storage
+    AFTER DELETE ON organization_members
+    FOR EACH ROW EXECUTE FUNCTION remove_member_storage();
+
+-- Every member of an organization can change everything in its storage.
+CREATE OR REPLACE VIEW effective_folder_access_grants AS
+    SELECT folder_id, user_id, access FROM folder_access_grants
+    UNION ALL
+    SELECT organization_folders.folder_id, organization_members.user_id, organization_folders.access
+        FROM organization_folders
+        JOIN organization_members
+            ON organization_members.organization_id = organization_folders.organization_id
+    UNION ALL
+    SELECT folders.id, organization_members.user_id, 'write'::folder_access
+        FROM folders
+        JOIN organization_members ON organization_members.organization_id = folders.organization_id;
@@ -0,0 +1,11 @@
+-- A one-off script to move users who set up custom domains before plans existed onto the Supporter
+-- plan, so their domains keep working. Run it once after deploying plans, with e.g.:
+--
+--     psql "$DATABASE_URL" -f scripts/upgrade_custom_domain_users.sql
+--
+-- This isn't a migration since it's a one-time business decision rather than a schema change, and
+-- migrations also run against fresh databases where it makes no sense.
+UPDATE users
+    SET plan = 'supporter'
+    WHERE plan = 'free'
+        AND EXISTS (SELECT 1 FROM custom_domains WHERE user_id = users.id);
@@ -1,26 +1,46 @@
 //! A web server for the HTTP API. File Garden exposes this via `https://filegarden.com/api/`.
 
-use std::error::Error as _;
+use std::{
+    error::Error as _,
+    fmt::{self, Display, Formatter},
+    time::Duration,
+};
 
 use axum::{
+    async_trait,
+    body::Body,
     extract::{
-        rejection::{JsonRejection, QueryRejection},
-        Request, State,
+        rejection::{JsonRejection, PathRejection},
+        FromRequestParts, Request, State,
+    },
+    http::{
+        header::{ACCEPT, CONTENT_LENGTH, CONTENT_TYPE, RETRY_AFTER},
+        request::Parts,
+        HeaderValue, StatusCode,
     },
-    http::StatusCode,
     response::IntoResponse,
 };
 use axum_macros::{FromRequest, FromRequestParts};
-use routes::ROUTER;
-use serde::Serialize;
+use chrono::{DateTime, Utc};
+use serde::{de::DeserializeOwned, Serialize};
 use strum_macros::IntoStaticStr;
 use thiserror::Error;
+use tokio::time::Instant;
 use tower::ServiceExt;
 
-use crate::AppState;
+use crate::{
+    api::auth::{AuthAllowingOutdatedTos, Permission},
+    config::ReloadError,
+    telemetry, AppState,
+};
 
+pub mod auth;
 mod captcha;
+pub mod client_ip;
+pub mod if_match;
+pub mod lock_token;
 pub mod routes;
+pub(crate) mod stripe;
 pub mod validation;
 
 /// An API error.
@@ -28,6 +48,22 @@ pub mod validation;
 #[strum(serialize_all = "SCREAMING_SNAKE_CASE")]
 #[non_exhaustive]
 pub enum Error {
+    /// The request requires a sign-in session, but no valid session was specified.
+    #[error("You must be signed in to do this.")]
+    AuthFailed,
+
+    /// An operation in a batch failed, so none of the batch's operations were performed.
+    #[error(
+        "Operation {index} failed, so none of the batch's operations were performed: {source}"
+    )]
+    BatchOperationFailed {
+        /// The index of the operation that failed in the batch.
+        index: usize,
+
+        /// The error the operation failed with.
+        source: Box<Self>,
+    },
+
     /// The request body is too large.
     #[error("The request body is too large.")]
     BodyTooLarge,
@@ -36,10 +72,62 @@ pub enum Error {
     #[error("CAPTCHA verification failed.")]
     CaptchaFailed,
 
+    /// The specified domain is already verified by a different user.
+    #[error("This domain is already in use by another user.")]
+    DomainTaken,
+
+    /// The specified domain's DNS records don't include its verification record.
+    #[error(
+        "The domain's verification record wasn't found. DNS changes can take a while to apply."
+    )]
+    DomainVerificationFailed,
+
+    /// The specified email address's domain is a disposable email service.
+    #[error("Disposable email addresses aren't allowed. Please use a different email.")]
+    EmailDomainDisposable,
+
+    /// The specified email address's mailbox is already used by a different user.
+    #[error("This email address is already in use by another user.")]
+    EmailTaken,
+
     /// An email verification code specified in the request is incorrect.
     #[error("Incorrect email verification code.")]
     EmailVerificationCodeWrong,
 
+    /// The request would change a file locked by a different client, without specifying its lock
+    /// token.
+    #[error("This file is locked by another client.")]
+    FileLocked,
+
+    /// The request would put a file in a folder already containing something with the same name.
+    #[error("A file with this name already exists in this folder.")]
+    FileNameTaken,
+
+    /// The request needs a file to be accessible to anyone with its link, but it isn't.
+    #[error("This file isn't accessible to anyone with its link.")]
+    FileNotShared,
+
+    /// The request would add a file larger than the plan of its owner or the organization whose
+    /// storage it's in allows.
+    #[error("This file is larger than your plan allows.")]
+    FileTooLarge,
+
+    /// The request would put a folder in a folder already containing something with the same name.
+    #[error("A folder with this name already exists in this folder.")]
+    FolderNameTaken,
+
+    /// The request needs a top-level folder, but the specified folder is in another folder.
+    #[error("This folder is in another folder.")]
+    FolderNotTopLevel,
+
+    /// The specified handle belongs to, or recently belonged to, a different user.
+    #[error("This handle is already taken.")]
+    HandleTaken,
+
+    /// The specified handle is reserved for the system or isn't allowed.
+    #[error("This handle isn't available.")]
+    HandleUnavailable,
+
     /// An internal error occurred on the server which is unknown or expected never to happen.
     ///
     /// For security, this must not expose error details to clients since there's no way to tell if
@@ -49,11 +137,19 @@ pub enum Error {
 
     /// The request body doesn't match the required target type.
     #[error("Invalid request body: {0}")]
-    InvalidBodyData(String),
+    InvalidBodyData(FieldError),
 
     /// The request URI query doesn't match the required target type.
     #[error("Invalid URI query: {0}")]
-    InvalidQueryData(String),
+    InvalidQueryData(FieldError),
+
+    /// The specified invite doesn't exist, has expired, or is for a different email.
+    #[error("The specified invite is invalid or expired.")]
+    InviteInvalid,
+
+    /// Signing up is invite-only, but no invite was specified.
+    #[error("An invite is required to sign up.")]
+    InviteRequired,
 
     /// The `Content-Type` header isn't set to `application/json`.
     #[error("Header `Content-Type: application/json` must be set.")]
@@ -63,6 +159,39 @@ pub enum Error {
     #[error("Invalid JSON syntax in request body: {0}")]
     JsonSyntax(String),
 
+    /// The request would delete something under a legal hold.
+    #[error("This is under a legal hold and can't be deleted.")]
+    LegalHoldActive,
+
+    /// The server is down for maintenance and isn't accepting this kind of request.
+    #[error("File Garden is down for maintenance. Please try again later.")]
+    Maintenance,
+
+    /// The request would leave an organization without any owners.
+    #[error("An organization must always have at least one owner.")]
+    OrganizationOwnerRequired,
+
+    /// The signed-in user doesn't have permission to perform the request.
+    #[error("You don't have permission to do this.")]
+    PermissionDenied,
+
+    /// The request needs a feature the user's plan doesn't include.
+    #[error("Your plan doesn't include this feature.")]
+    PlanFeatureRequired,
+
+    /// The request's `If-Match` header doesn't match the resource's current version, meaning it was
+    /// changed since the client last fetched it.
+    #[error("This was changed by someone else. Refresh and try again.")]
+    PreconditionFailed,
+
+    /// The request must have an `If-Match` header with the resource's current version.
+    #[error("Header `If-Match` must be set to the version being changed.")]
+    PreconditionRequired,
+
+    /// Too many requests have been made recently, and more can't be made until the specified time.
+    #[error("Too many requests. Please try again later.")]
+    RateLimited(DateTime<Utc>),
+
     /// The requested API route exists, but the specified resource was not found.
     #[error("Resource not found.")]
     ResourceNotFound,
@@ -71,26 +200,85 @@ pub enum Error {
     #[error("The requested API route doesn't exist.")]
     RouteNotFound,
 
+    /// The request would put a folder in an organization's storage already containing a folder with
+    /// the same name.
+    #[error("A folder with this name is already in this organization's storage.")]
+    StorageFolderNameTaken,
+
+    /// The request would exceed the storage quota of the plan of the owner or the organization whose
+    /// storage it's in.
+    #[error("This would exceed your plan's storage quota.")]
+    StorageQuotaExceeded,
+
+    /// The request would start a subscription for a user who already has one.
+    #[error("You already have a subscription. Change it from the billing portal instead.")]
+    SubscriptionActive,
+
+    /// There have been too many failed attempts to do this recently, so it's locked or delayed until
+    /// the specified time. Unlike [`Error::RateLimited`], this is for failures rather than any
+    /// requests, such as failed sign-ins.
+    #[error("Too many failed attempts. Please try again later.")]
+    TooManyAttempts(DateTime<Utc>),
+
+    /// The signed-in user hasn't accepted the current version of the terms of service and privacy
+    /// policy, or a request accepting them specified a different version.
+    #[error("You must accept the latest terms of service and privacy policy to continue.")]
+    TosReacceptanceRequired,
+
     /// Credentials specified in the request (such as email and password) don't match any user.
     #[error("The specified user credentials are incorrect.")]
     UserCredentialsWrong,
+
+    /// A webhook request's signature is missing, invalid, or too old.
+    #[error("Invalid webhook signature.")]
+    WebhookSignatureInvalid,
 }
 
 impl Error {
     /// Gets the HTTP response status code corresponding to the API error.
-    pub const fn status(&self) -> StatusCode {
+    pub fn status(&self) -> StatusCode {
         match self {
+            Self::AuthFailed => StatusCode::UNAUTHORIZED,
+            Self::BatchOperationFailed { source, .. } => source.status(),
             Self::BodyTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
             Self::CaptchaFailed => StatusCode::FORBIDDEN,
+            Self::DomainTaken => StatusCode::CONFLICT,
+            Self::DomainVerificationFailed => StatusCode::FORBIDDEN,
+            Self::EmailDomainDisposable => StatusCode::FORBIDDEN,
+            Self::EmailTaken => StatusCode::CONFLICT,
             Self::EmailVerificationCodeWrong => StatusCode::FORBIDDEN,
+            Self::FileLocked => StatusCode::LOCKED,
+            Self::FileNameTaken => StatusCode::CONFLICT,
+            Self::FileNotShared => StatusCode::CONFLICT,
+            Self::FileTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+            Self::FolderNameTaken => StatusCode::CONFLICT,
+            Self::FolderNotTopLevel => StatusCode::CONFLICT,
+            Self::HandleTaken => StatusCode::CONFLICT,
+            Self::HandleUnavailable => StatusCode::FORBIDDEN,
             Self::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
             Self::InvalidBodyData(_) => StatusCode::BAD_REQUEST,
             Self::InvalidQueryData(_) => StatusCode::BAD_REQUEST,
+            Self::InviteInvalid => StatusCode::FORBIDDEN,
+            Self::InviteRequired => StatusCode::FORBIDDEN,
             Self::JsonContentType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
             Self::JsonSyntax(_) => StatusCode::BAD_REQUEST,
+            Self::LegalHoldActive => StatusCode::LOCKED,
+            Self::Maintenance => StatusCode::SERVICE_UNAVAILABLE,
+            Self::OrganizationOwnerRequired => StatusCode::CONFLICT,
+            Self::PermissionDenied => StatusCode::FORBIDDEN,
+            Self::PlanFeatureRequired => StatusCode::FORBIDDEN,
+            Self::PreconditionFailed => StatusCode::PRECONDITION_FAILED,
+            Self::PreconditionRequired => StatusCode::PRECONDITION_REQUIRED,
+            Self::RateLimited(_) => StatusCode::TOO_MANY_REQUESTS,
             Self::ResourceNotFound => StatusCode::NOT_FOUND,
             Self::RouteNotFound => StatusCode::NOT_FOUND,
+            Self::StorageFolderNameTaken => StatusCode::CONFLICT,
+            Self::StorageQuotaExceeded => StatusCode::FORBIDDEN,
+            Self::SubscriptionActive => StatusCode::CONFLICT,
+            Self::TooManyAttempts(_) => StatusCode::TOO_MANY_REQUESTS,
+            Self::TosReacceptanceRequired => StatusCode::FORBIDDEN,
             Self::UserCredentialsWrong => StatusCode::FORBIDDEN,
+            Self::WebhookSignatureInvalid => StatusCode::BAD_REQUEST,
         }
     }
 
@@ -98,17 +286,29 @@ impl Error {
     fn code(&self) -> &'static str {
         self.into()
     }
+
+    /// Gets how many seconds the client must wait before retrying the request, if the API error
+    /// specifies.
+    fn retry_after_seconds(&self) -> Option<u64> {
+        let (Self::RateLimited(reset_at) | Self::TooManyAttempts(reset_at)) = self else {
+            return None;
+        };
+
+        let milliseconds = (*reset_at - Utc::now()).num_milliseconds();
+
+        // Round up so the client never retries too early, and never tell it to wait zero seconds.
+        Some(
+            u64::try_from(milliseconds)
+                .map_or(1, |milliseconds| milliseconds.div_ceil(1000).max(1)),
+        )
+    }
 }
 
-impl From<QueryRejection> for Error {
-    fn from(error: QueryRejection) -> Self {
+impl From<PathRejection> for Error {
+    fn from(error: PathRejection) -> Self {
         match error {
-            QueryRejection::FailedToDeserializeQueryString(_) => {
-                Self::InvalidQueryData(match error.source() {
-                    Some(source) => source.to_string(),
-                    None => error.body_text(),
-                })
-            }
+            // A path parameter that fails to deserialize can't identify any existing resource.
+            PathRejection::FailedToDeserializePathParams(_) => Self::ResourceNotFound,
             error => Self::Internal(error.into()),
         }
     }
@@ -121,10 +321,26 @@ impl From<JsonRejection> for Error {
         }
 
         match error {
-            JsonRejection::JsonDataError(error) => Self::InvalidBodyData(match error.source() {
-                Some(source) => source.to_string(),
-                None => error.body_text(),
-            }),
+            JsonRejection::JsonDataError(error) => {
+                // The underlying error is nested in the rejection's chain of sources.
+                let mut source = error.source();
+
+                while let Some(error) = source {
+                    if let Some(error) =
+                        error.downcast_ref::<serde_path_to_error::Error<serde_json::Error>>()
+                    {
+                        return Self::InvalidBodyData(FieldError::from_path_error(error));
+                    }
+
+                    source = error.source();
+                }
+
+                Self::InvalidBodyData(FieldError {
+                    field: String::new(),
+                    code: "INVALID_VALUE",
+                    message: error.body_text(),
+                })
+            }
             JsonRejection::JsonSyntaxError(error) => Self::JsonSyntax(match error.source() {
                 Some(source) => source.to_string(),
                 None => error.body_text(),
@@ -137,6 +353,16 @@ impl From<JsonRejection> for Error {
 
 impl From<sqlx::Error> for Error {
     fn from(error: sqlx::Error) -> Self {
+        // The `enforce_plan_limits` and `move_folder_storage` triggers raise these whenever a file
+        // or folder would exceed the limits of its storage's plan.
+        if let sqlx::Error::Database(database_error) = &error {
+            match database_error.constraint() {
+                Some("max_file_size") => return Self::FileTooLarge,
+                Some("storage_quota") => return Self::StorageQuotaExceeded,
+                _ => {}
+            }
+        }
+
         Self::Internal(error.into())
     }
 }
@@ -147,14 +373,38 @@ impl From<rand::Error> for Error {
     }
 }
 
+impl From<hickory_resolver::error::ResolveError> for Error {
+    fn from(error: hickory_resolver::error::ResolveError) -> Self {
+        Self::Internal(error.into())
+    }
+}
+
 impl From<reqwest::Error> for Error {
     fn from(error: reqwest::Error) -> Self {
         Self::Internal(error.into())
     }
 }
 
+impl From<qrcode::types::QrError> for Error {
+    fn from(error: qrcode::types::QrError) -> Self {
+        Self::Internal(error.into())
+    }
+}
+
+impl From<image::ImageError> for Error {
+    fn from(error: image::ImageError) -> Self {
+        Self::Internal(error.into())
+    }
+}
+
+impl From<ReloadError> for Error {
+    fn from(error: ReloadError) -> Self {
+        Self::Internal(error.into())
+    }
+}
+
 /// An API error's response body.
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct ErrorBody {
     /// The computer-friendly error code in `SCREAMING_SNAKE_CASE`. See [`Error`] for error codes.
@@ -162,19 +412,223 @@ pub struct ErrorBody {
 
     /// The human-friendly error message.
     pub message: String,
+
+    /// The problems with specific fields of the request, if any.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub details: Vec<FieldError>,
+
+    /// How many seconds the client must wait before retrying the request, if the error specifies.
+    /// This is also sent in the `Retry-After` header.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_after_seconds: Option<u64>,
+}
+
+impl From<&Error> for ErrorBody {
+    fn from(error: &Error) -> Self {
+        Self {
+            code: error.code(),
+            message: error.to_string(),
+            details: match error {
+                Error::BatchOperationFailed { index, source } => vec![FieldError {
+                    field: format!("operations[{index}]"),
+                    code: source.code(),
+                    message: source.to_string(),
+                }],
+                Error::InvalidBodyData(field_error) | Error::InvalidQueryData(field_error) => {
+                    vec![field_error.clone()]
+                }
+                _ => Vec::new(),
+            },
+            retry_after_seconds: error.retry_after_seconds(),
+        }
+    }
+}
+
+/// A problem with a specific field of a request, letting clients tell which field to highlight.
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldError {
+    /// The path to the field, with object keys separated by `.` and array indexes in brackets
+    /// (e.g. `operations[0].type`). This is empty if the problem is with the whole request rather
+    /// than a field.
+    pub field: String,
+
+    /// The computer-friendly problem code in `SCREAMING_SNAKE_CASE`: `MISSING_FIELD`,
+    /// `UNKNOWN_FIELD`, `INVALID_TYPE`, `INVALID_LENGTH`, or `INVALID_VALUE`.
+    pub code: &'static str,
+
+    /// The human-friendly problem message.
+    pub message: String,
+}
+
+impl FieldError {
+    /// Constructs a [`FieldError`] from a deserialization error and the path it occurred at.
+    fn from_path_error<E: serde::de::Error>(error: &serde_path_to_error::Error<E>) -> Self {
+        let mut field = error.path().to_string();
+        if field == "." {
+            field.clear();
+        }
+
+        let message = error.inner().to_string();
+
+        // Serde's built-in errors have a consistent format, so the problem code can be derived from
+        // the message's prefix.
+        let code = if message.starts_with("missing field") {
+            "MISSING_FIELD"
+        } else if message.starts_with("unknown field") {
+            "UNKNOWN_FIELD"
+        } else if message.starts_with("invalid type") {
+            "INVALID_TYPE"
+        } else if message.starts_with("invalid length") {
+            "INVALID_LENGTH"
+        } else {
+            "INVALID_VALUE"
+        };
+
+        // Missing field errors occur on the containing object, so the field's name must be taken
+        // from the message.
+        if code == "MISSING_FIELD" {
+            if let Some(name) = message.split('`').nth(1) {
+                if !field.is_empty() {
+                    field.push('.');
+                }
+                field.push_str(name);
+            }
+        }
+
+        Self {
+            field,
+            code,
+            message,
+        }
+    }
+}
+
+impl Display for FieldError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.field.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "{}: {}", self.field, self.message)
+        }
+    }
 }
 
 impl IntoResponse for Error {
     fn into_response(self) -> axum::response::Response {
-        let body = ErrorBody {
-            code: self.code(),
-            message: self.to_string(),
-        };
+        if let Self::Internal(source) = &self {
+            telemetry::report_error(&**source);
+        }
+
+        let body = ErrorBody::from(&self);
+        let retry_after_seconds = body.retry_after_seconds;
+
+        let mut response = (self.status(), Json(body.clone())).into_response();
 
-        (self.status(), Json(body)).into_response()
+        // This lets the body be re-rendered in a different format if the client requests it.
+        response.extensions_mut().insert(body);
+
+        if let Some(retry_after_seconds) = retry_after_seconds {
+            response
+                .headers_mut()
+                .insert(RETRY_AFTER, retry_after_seconds.into());
+        }
+
+        response
     }
 }
 
+/// An API error's response body in the format specified by
+/// [RFC 9457](https://www.rfc-editor.org/rfc/rfc9457), sent instead of an [`ErrorBody`] if the
+/// request's `Accept` header includes `application/problem+json`.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ProblemDetails {
+    /// A URI identifying the problem type. This is always `about:blank`, since the problem type is
+    /// identified by `code` instead.
+    pub r#type: &'static str,
+
+    /// The HTTP status code's reason phrase.
+    pub title: &'static str,
+
+    /// The HTTP status code.
+    pub status: u16,
+
+    /// The human-friendly error message.
+    pub detail: String,
+
+    /// The path of the request that caused the error.
+    pub instance: String,
+
+    /// See [`ErrorBody::code`].
+    pub code: &'static str,
+
+    /// See [`ErrorBody::details`].
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub details: Vec<FieldError>,
+
+    /// See [`ErrorBody::retry_after_seconds`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_after_seconds: Option<u64>,
+}
+
+/// The media type for [`ProblemDetails`].
+const PROBLEM_JSON: &str = "application/problem+json";
+
+/// Checks whether an `Accept` header value includes [`PROBLEM_JSON`] with a nonzero quality value.
+fn accepts_problem_json(accept: &str) -> bool {
+    accept.split(',').any(|media_range| {
+        let mut params = media_range.split(';');
+        let media_type = params.next().unwrap_or_default();
+
+        if !media_type.trim().eq_ignore_ascii_case(PROBLEM_JSON) {
+            return false;
+        }
+
+        // A quality value of 0 means the media type is explicitly not acceptable. Invalid quality
+        // values are ignored rather than rejecting the media type.
+        let quality = params
+            .filter_map(|param| param.split_once('='))
+            .find(|(name, _)| name.trim().eq_ignore_ascii_case("q"))
+            .and_then(|(_, quality)| quality.trim().parse::<f32>().ok());
+
+        quality.is_none_or(|quality| quality > 0.0)
+    })
+}
+
+/// Re-renders an error response's [`ErrorBody`] as [`ProblemDetails`]. Responses without an
+/// [`ErrorBody`] are returned unchanged.
+fn into_problem_response(
+    response: axum::response::Response,
+    instance: String,
+) -> axum::response::Response {
+    let (mut parts, body) = response.into_parts();
+
+    let Some(error_body) = parts.extensions.remove::<ErrorBody>() else {
+        return axum::response::Response::from_parts(parts, body);
+    };
+
+    let problem = ProblemDetails {
+        r#type: "about:blank",
+        title: parts.status.canonical_reason().unwrap_or_default(),
+        status: parts.status.as_u16(),
+        detail: error_body.message,
+        instance,
+        code: error_body.code,
+        details: error_body.details,
+        retry_after_seconds: error_body.retry_after_seconds,
+    };
+
+    let problem = serde_json::to_vec(&problem).expect("problem details should be serializable");
+
+    parts.headers.remove(CONTENT_LENGTH);
+    parts
+        .headers
+        .insert(CONTENT_TYPE, HeaderValue::from_static(PROBLEM_JSON));
+
+    axum::response::Response::from_parts(parts, Body::from(problem))
+}
+
 /// Equivalent to [`axum::Json`], but fails with an [`Error`] JSON response instead of a plain text
 /// response.
 #[derive(FromRequest, Clone, Copy, Default, Debug)]
@@ -190,25 +644,227 @@ impl<T: Serialize> IntoResponse for Json<T> {
 
 /// Equivalent to [`axum::extract::Query`], but fails with an [`Error`] JSON response instead of a
 /// plain text response.
-#[derive(FromRequestParts, Clone, Copy, Default, Debug)]
-#[from_request(via(axum::extract::Query), rejection(Error))]
+#[derive(Clone, Copy, Default, Debug)]
 pub struct Query<T>(pub T);
 
+#[async_trait]
+impl<T: DeserializeOwned, S: Send + Sync> FromRequestParts<S> for Query<T> {
+    type Rejection = Error;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let query = parts.uri.query().unwrap_or_default();
+        let deserializer =
+            serde_urlencoded::Deserializer::new(form_urlencoded::parse(query.as_bytes()));
+
+        // Unlike `axum::extract::Query`, this tracks the path to any field that fails to
+        // deserialize.
+        serde_path_to_error::deserialize(deserializer)
+            .map(Self)
+            .map_err(|error| Error::InvalidQueryData(FieldError::from_path_error(&error)))
+    }
+}
+
+/// Equivalent to [`axum::extract::Path`], but fails with an [`Error`] JSON response instead of a
+/// plain text response.
+#[derive(FromRequestParts, Clone, Copy, Default, Debug)]
+#[from_request(via(axum::extract::Path), rejection(Error))]
+pub struct Path<T>(pub T);
+
 /// An API response type.
 pub type Response<T> = std::result::Result<(StatusCode, Json<T>), Error>;
 
-/// Routes a request to an API endpoint.
+/// How long responses take at minimum from routes that must respond the same whether or not an
+/// account exists. This is longer than handling such a request normally takes, so response times
+/// can't be used for user enumeration.
+const UNIFORM_RESPONSE_DURATION: Duration = Duration::from_secs(2);
+
+/// Waits until [`UNIFORM_RESPONSE_DURATION`] has passed since the specified time a request started
+/// being handled.
+pub(crate) async fn pad_response_time(started_at: Instant) {
+    tokio::time::sleep_until(started_at + UNIFORM_RESPONSE_DURATION).await;
+}
+
+/// The path of the API route for health checks, which still works during maintenance so the server
+/// stays in its load balancer's rotation.
+const HEALTH_CHECK_PATH: &str = "/api/v1/health";
+
+/// Checks whether a request still works during maintenance. Besides health checks, this is only
+/// requests from users with [`Permission::ManageConfig`], so maintenance can always be ended.
+async fn is_maintenance_exempt(state: &AppState, parts: &mut Parts) -> bool {
+    if parts.uri.path() == HEALTH_CHECK_PATH {
+        return true;
+    }
+
+    AuthAllowingOutdatedTos::from_request_parts(parts, state)
+        .await
+        .is_ok_and(|AuthAllowingOutdatedTos(auth)| auth.require(Permission::ManageConfig).is_ok())
+}
+
+/// Routes a request to an API endpoint, unless the request is rejected by the current maintenance
+/// mode.
 pub(super) async fn handle(
     State(state): State<AppState>,
     request: Request,
 ) -> axum::response::Response {
-    // Calling the router needs a mutable reference to it (even though it shouldn't), so the router
-    // must either have restricted access via a mutex or be cloned on each request. The former would
-    // allow only one request at a time, so the latter is faster.
-    ROUTER
-        .clone()
-        .with_state(state)
-        .oneshot(request)
-        .await
-        .into_response()
+    // If the client accepts problem details, error responses are re-rendered as such, which needs
+    // the request's path.
+    let problem_json_instance = request
+        .headers()
+        .get(ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .is_some_and(accepts_problem_json)
+        .then(|| request.uri().path().to_owned());
+
+    let (mut parts, body) = request.into_parts();
+    let is_rejected = state.maintenance_mode.get().await.rejects(&parts.method)
+        && !is_maintenance_exempt(&state, &mut parts).await;
+    let request = Request::from_parts(parts, body);
+
+    let response = if is_rejected {
+        Error::Maintenance.into_response()
+    } else {
+        // Calling the router needs a mutable reference to it (even though it shouldn't), so the
+        // router must either have restricted access via a mutex or be cloned on each request. The
+        // former would allow only one request at a time, so the latter is faster.
+        state
+            .api_router
+            .clone()
+            .with_state(state)
+            .oneshot(request)
+            .await
+            .into_response()
+    };
+
+    match problem_json_instance {
+        Some(instance) => into_problem_response(response, instance),
+        None => response,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    /// A request body to deserialize in [`FieldError`] tests.
+    #[derive(Deserialize, Debug)]
+    #[serde(rename_all = "camelCase", deny_unknown_fields)]
+    #[expect(dead_code, reason = "only deserialization errors are tested")]
+    struct TestBody {
+        /// A required field.
+        name: String,
+
+        /// A nested object.
+        nested: Option<TestNested>,
+
+        /// An array of numbers.
+        numbers: Option<Vec<u8>>,
+
+        /// A fixed-length array.
+        pair: Option<[u8; 2]>,
+    }
+
+    /// A nested object in [`TestBody`].
+    #[derive(Deserialize, Debug)]
+    #[serde(rename_all = "camelCase", deny_unknown_fields)]
+    #[expect(dead_code, reason = "only deserialization errors are tested")]
+    struct TestNested {
+        /// A required nested field.
+        value: u8,
+    }
+
+    /// Deserializes a [`TestBody`], returning the [`FieldError`] it fails with.
+    fn field_error(json: &str) -> FieldError {
+        let deserializer = &mut serde_json::Deserializer::from_str(json);
+        let error = serde_path_to_error::deserialize::<_, TestBody>(deserializer)
+            .expect_err("deserialization should fail");
+
+        FieldError::from_path_error(&error)
+    }
+
+    #[test]
+    fn field_errors() {
+        let cases = [
+            ("{}", "name", "MISSING_FIELD"),
+            (
+                r#"{"name":"a","nested":{}}"#,
+                "nested.value",
+                "MISSING_FIELD",
+            ),
+            (r#"{"name":"a","other":1}"#, "other", "UNKNOWN_FIELD"),
+            (r#"{"name":1}"#, "name", "INVALID_TYPE"),
+            (
+                r#"{"name":"a","numbers":[1,"2"]}"#,
+                "numbers[1]",
+                "INVALID_TYPE",
+            ),
+            (r#"{"name":"a","pair":[1]}"#, "pair", "INVALID_LENGTH"),
+            (
+                r#"{"name":"a","numbers":[256]}"#,
+                "numbers[0]",
+                "INVALID_VALUE",
+            ),
+            ("1", "", "INVALID_TYPE"),
+        ];
+
+        for (json, field, code) in cases {
+            let error = field_error(json);
+
+            assert_eq!(field, error.field, "field of error for {json}");
+            assert_eq!(code, error.code, "code of error for {json}");
+        }
+    }
+
+    #[test]
+    fn field_error_display() {
+        let error = FieldError {
+            field: "operations[0].type".into(),
+            code: "INVALID_VALUE",
+            message: "unknown variant".into(),
+        };
+
+        assert_eq!("operations[0].type: unknown variant", error.to_string());
+
+        let error = FieldError {
+            field: String::new(),
+            code: "INVALID_TYPE",
+            message: "expected a map".into(),
+        };
+
+        assert_eq!("expected a map", error.to_string());
+    }
+
+    #[test]
+    fn problem_json_acceptance() {
+        let accepted = [
+            "application/problem+json",
+            "Application/Problem+JSON",
+            "application/json, application/problem+json",
+            "application/json,application/problem+json;q=0.9",
+            " application/problem+json ; charset=utf-8",
+            "application/problem+json;q=0.001",
+            "application/problem+json;q=invalid",
+        ];
+
+        for accept in accepted {
+            assert!(accepts_problem_json(accept), "checking {accept:?}");
+        }
+
+        let rejected = [
+            "",
+            "*/*",
+            "application/json",
+            "application/*",
+            "application/problem+jsonx",
+            "text/plain; profile=application/problem+json",
+            "application/problem+json;q=0",
+            "application/problem+json; Q=0.000",
+            "application/json, application/problem+json; q=0",
+        ];
+
+        for accept in rejected {
+            assert!(!accepts_problem_json(accept), "checking {accept:?}");
+        }
+    }
 }
@@ -0,0 +1,320 @@
+//! Utilities for authenticating and authorizing API requests.
+
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{header::COOKIE, request::Parts},
+};
+use serde::{Deserialize, Serialize};
+use sqlx::PgConnection;
+use tower_cookies::Cookie;
+
+use crate::{
+    api::{
+        self,
+        routes::v1::{sessions::SESSION_MAX_AGE, users::tos_acceptance::TOS_VERSION},
+    },
+    crypto::hash_without_salt,
+    db::{self, TxResult},
+    id::{Id, Token},
+    telemetry, AppState,
+};
+
+/// A user's role, determining which [`Permission`]s they have.
+#[derive(sqlx::Type, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[sqlx(type_name = "user_role", rename_all = "lowercase")]
+#[serde(rename_all = "camelCase")]
+pub enum Role {
+    /// A regular user without any special permissions.
+    User,
+
+    /// A user who moderates content on behalf of File Garden.
+    Moderator,
+
+    /// A user with every permission.
+    Admin,
+}
+
+impl Role {
+    /// Returns whether the role has the specified permission.
+    pub const fn has_permission(self, permission: Permission) -> bool {
+        match permission {
+            Permission::CreateInvites
+            | Permission::ManageConfig
+            | Permission::ManageLegalHolds
+            | Permission::ManagePlans
+            | Permission::ManageRoles
+            | Permission::ManageUsers => matches!(self, Self::Admin),
+            Permission::ModerateFiles => matches!(self, Self::Moderator | Self::Admin),
+        }
+    }
+}
+
+/// A capability only some [`Role`]s have.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[non_exhaustive]
+pub enum Permission {
+    /// Creating invites to sign up which aren't for any organization.
+    CreateInvites,
+
+    /// Reloading the server's settings and switching maintenance mode.
+    ManageConfig,
+
+    /// Placing and lifting legal holds on users and files.
+    ManageLegalHolds,
+
+    /// Changing which plan any user is on.
+    ManagePlans,
+
+    /// Changing any user's role.
+    ManageRoles,
+
+    /// Accessing and changing any user's account as if it were one's own.
+    ManageUsers,
+
+    /// Changing the status of any file, such as quarantining it or taking it down.
+    ModerateFiles,
+}
+
+/// Access to a folder and everything in it, granted to a user other than its owner or to an
+/// organization's members. Each level of access includes the ones before it.
+#[derive(
+    sqlx::Type, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug,
+)]
+#[sqlx(type_name = "folder_access", rename_all = "lowercase")]
+#[serde(rename_all = "camelCase")]
+pub enum FolderAccess {
+    /// Viewing the folder's metadata and contents.
+    Read,
+
+    /// Changing the folder's metadata and contents.
+    Write,
+}
+
+/// An extractor for the user signed into the sign-in session specified by the request's session
+/// cookie. Fails with [`api::Error::AuthFailed`] if there's no valid session, or with
+/// [`api::Error::TosReacceptanceRequired`] if the user hasn't accepted the current terms of service.
+#[derive(Clone, Debug)]
+pub struct Auth {
+    /// The ID of the signed-in user.
+    pub user_id: Id,
+
+    /// The ID of the sign-in session.
+    pub session_id: Id,
+
+    /// The role of the signed-in user.
+    pub role: Role,
+}
+
+impl Auth {
+    /// Checks that the signed-in user's role has the specified permission.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`api::Error::PermissionDenied`] if the user doesn't have the permission.
+    pub const fn require(&self, permission: Permission) -> Result<(), api::Error> {
+        if self.role.has_permission(permission) {
+            Ok(())
+        } else {
+            Err(api::Error::PermissionDenied)
+        }
+    }
+
+    /// Checks that the signed-in user is the specified user, or otherwise has
+    /// [`Permission::ManageUsers`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`api::Error::PermissionDenied`] if the user isn't allowed to manage the specified
+    /// user.
+    pub fn require_self_or_manager(&self, user_id: &Id) -> Result<(), api::Error> {
+        if self.user_id == *user_id {
+            Ok(())
+        } else {
+            self.require(Permission::ManageUsers)
+        }
+    }
+
+    /// Checks that the signed-in user owns the specified file, has [`Permission::ManageUsers`], or
+    /// was granted at least the specified access to a folder the file is in, directly or through an
+    /// organization.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`api::Error::ResourceNotFound`] if the file doesn't exist, or
+    /// [`api::Error::PermissionDenied`] if the user isn't allowed to access it.
+    pub async fn require_file_access(
+        &self,
+        conn: &mut PgConnection,
+        file_id: &Id,
+        access: FolderAccess,
+    ) -> Result<(), api::Error> {
+        let Some(file) = sqlx::query!(
+            r#"SELECT owner_id, (
+                SELECT max(access) FROM effective_folder_access_grants
+                    WHERE user_id = $2 AND folder_id = ANY(files.parent_id_path)
+            ) as "access: FolderAccess"
+                FROM files
+                WHERE id = $1"#,
+            file_id.as_slice(),
+            self.user_id.as_slice(),
+        )
+        .fetch_optional(conn)
+        .await?
+        else {
+            return Err(api::Error::ResourceNotFound);
+        };
+
+        self.require_owner_or_access(&file.owner_id.into(), file.access, access)
+    }
+
+    /// Checks that the signed-in user owns the specified folder, has [`Permission::ManageUsers`],
+    /// or was granted at least the specified access to the folder or a folder it's in, directly or
+    /// through an organization.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`api::Error::ResourceNotFound`] if the folder doesn't exist, or
+    /// [`api::Error::PermissionDenied`] if the user isn't allowed to access it.
+    pub async fn require_folder_access(
+        &self,
+        conn: &mut PgConnection,
+        folder_id: &Id,
+        access: FolderAccess,
+    ) -> Result<(), api::Error> {
+        let Some(folder) = sqlx::query!(
+            r#"SELECT owner_id, (
+                SELECT max(access) FROM effective_folder_access_grants
+                    WHERE user_id = $2 AND folder_id = ANY(folders.parent_id_path || folders.id)
+            ) as "access: FolderAccess"
+                FROM folders
+                WHERE id = $1"#,
+            folder_id.as_slice(),
+            self.user_id.as_slice(),
+        )
+        .fetch_optional(conn)
+        .await?
+        else {
+            return Err(api::Error::ResourceNotFound);
+        };
+
+        self.require_owner_or_access(&folder.owner_id.into(), folder.access, access)
+    }
+
+    /// Checks that the signed-in user is the specified owner, has [`Permission::ManageUsers`], or
+    /// was granted at least the required access.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`api::Error::PermissionDenied`] if none of those are true.
+    fn require_owner_or_access(
+        &self,
+        owner_id: &Id,
+        granted_access: Option<FolderAccess>,
+        required_access: FolderAccess,
+    ) -> Result<(), api::Error> {
+        if granted_access.is_some_and(|granted_access| granted_access >= required_access) {
+            Ok(())
+        } else {
+            self.require_self_or_manager(owner_id)
+        }
+    }
+}
+
+#[async_trait]
+impl FromRequestParts<AppState> for Auth {
+    type Rejection = api::Error;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let (auth, tos_version) = authenticate(parts, state).await?;
+
+        if tos_version.as_deref() != Some(TOS_VERSION.as_str()) {
+            return Err(api::Error::TosReacceptanceRequired);
+        }
+
+        Ok(auth)
+    }
+}
+
+/// An extractor like [`Auth`], but which doesn't require the user to have accepted the current terms
+/// of service. This is only for routes a user must be able to use before accepting them, like
+/// accepting them or signing out.
+#[derive(Clone, Debug)]
+pub struct AuthAllowingOutdatedTos(pub Auth);
+
+#[async_trait]
+impl FromRequestParts<AppState> for AuthAllowingOutdatedTos {
+    type Rejection = api::Error;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let (auth, _) = authenticate(parts, state).await?;
+
+        Ok(Self(auth))
+    }
+}
+
+/// Gets the user signed into the sign-in session specified by the request's session cookie, along
+/// with the version of the terms of service they last accepted.
+///
+/// # Errors
+///
+/// Returns [`api::Error::AuthFailed`] if there's no valid session.
+async fn authenticate(
+    parts: &Parts,
+    state: &AppState,
+) -> Result<(Auth, Option<String>), api::Error> {
+    // The cookie is parsed from the request's headers rather than through `Cookies`, since
+    // requests are authenticated during maintenance before they reach the router that adds it.
+    let Some(token) = parts
+        .headers
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|header| header.to_str().ok())
+        .flat_map(Cookie::split_parse)
+        .filter_map(Result::ok)
+        .find(|cookie| cookie.name() == "token")
+        .and_then(|cookie| cookie.value().parse::<Token>().ok())
+    else {
+        return Err(api::Error::AuthFailed);
+    };
+
+    let token_hash = hash_without_salt(&token);
+
+    let Some(session) = db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+        Ok(sqlx::query!(
+            r#"UPDATE sessions
+                    SET accessed_at = now()
+                    FROM users
+                    WHERE sessions.token_hash = $1
+                        AND sessions.created_at > now() - make_interval(secs => $2)
+                        AND users.id = sessions.user_id
+                    RETURNING
+                        users.id as user_id, users.role as "role: Role",
+                        sessions.id as session_id, users.tos_version"#,
+            token_hash.as_ref(),
+            SESSION_MAX_AGE.as_seconds_f64(),
+        )
+        .fetch_optional(tx.as_mut())
+        .await?)
+    })
+    .await?
+    else {
+        return Err(api::Error::AuthFailed);
+    };
+
+    let auth = Auth {
+        user_id: session.user_id.into(),
+        session_id: session.session_id.into(),
+        role: session.role,
+    };
+
+    telemetry::set_user(&auth.user_id);
+
+    Ok((auth, session.tos_version))
+}
@@ -4,9 +4,11 @@ use std::sync::LazyLock;
 
 use serde_json::{json, Value};
 
+use crate::config;
+
 /// The mailbox automated emails are sent from.
 static SECRET_KEY: LazyLock<String> = LazyLock::new(|| {
-    dotenvy::var("TURNSTILE_SECRET_KEY")
+    config::var("TURNSTILE_SECRET_KEY")
         .expect("environment variable `TURNSTILE_SECRET_KEY` should be a valid string")
 });
 
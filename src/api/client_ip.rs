@@ -0,0 +1,61 @@
+//! See [`ClientIp`].
+
+use std::{
+    env::VarError,
+    net::{IpAddr, SocketAddr},
+    sync::LazyLock,
+};
+
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, FromRequestParts},
+    http::{request::Parts, HeaderName},
+};
+
+use crate::{api, config};
+
+/// The name of the request header a trusted reverse proxy in front of the server sets to the
+/// client's IP address, if there is such a proxy.
+static CLIENT_IP_HEADER: LazyLock<Option<HeaderName>> = LazyLock::new(|| {
+    match config::var("CLIENT_IP_HEADER") {
+        // If the environment variable is unset, clients connect to the server directly.
+        Err(dotenvy::Error::EnvVar(VarError::NotPresent)) => None,
+
+        header => Some(
+            header
+                .expect("environment variable `CLIENT_IP_HEADER` should be a valid string if set")
+                .parse()
+                .expect("environment variable `CLIENT_IP_HEADER` should be a header name if set"),
+        ),
+    }
+});
+
+/// An extractor for the IP address of the client sending the request.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct ClientIp(pub IpAddr);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for ClientIp {
+    type Rejection = api::Error;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        if let Some(header) = &*CLIENT_IP_HEADER {
+            if let Some(ip) = parts
+                .headers
+                .get(header)
+                .and_then(|ip| ip.to_str().ok())
+                .and_then(|ip| ip.trim().parse().ok())
+            {
+                return Ok(Self(ip));
+            }
+        }
+
+        let Some(ConnectInfo(address)) = parts.extensions.get::<ConnectInfo<SocketAddr>>() else {
+            return Err(api::Error::Internal(
+                "request should have connection info".into(),
+            ));
+        };
+
+        Ok(Self(address.ip()))
+    }
+}
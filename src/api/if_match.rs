@@ -0,0 +1,77 @@
+//! See [`IfMatch`] and [`ETag`].
+
+use std::convert::Infallible;
+
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{
+        header::{ETAG, IF_MATCH},
+        request::Parts,
+        HeaderValue,
+    },
+    response::{IntoResponseParts, ResponseParts},
+};
+
+use crate::api;
+
+/// An extractor for the resource version a request's `If-Match` header requires, so changes based
+/// on a stale version of a resource can be rejected rather than silently overwriting newer changes.
+///
+/// The header must be set to the resource's version as a strong entity tag (e.g. `"3"`), or to `*`
+/// to allow any version.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct IfMatch(
+    /// The required version, or `None` if any version is allowed.
+    pub Option<i32>,
+);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for IfMatch {
+    type Rejection = api::Error;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let Some(header) = parts.headers.get(IF_MATCH) else {
+            return Err(api::Error::PreconditionRequired);
+        };
+
+        let header = header
+            .to_str()
+            .map_err(|_| api::Error::PreconditionFailed)?
+            .trim();
+
+        if header == "*" {
+            return Ok(Self(None));
+        }
+
+        // A malformed or weak entity tag can never match, since versions are compared strongly.
+        header
+            .strip_prefix('"')
+            .and_then(|header| header.strip_suffix('"'))
+            .and_then(|version| version.parse().ok())
+            .map(|version| Self(Some(version)))
+            .ok_or(api::Error::PreconditionFailed)
+    }
+}
+
+/// A response part setting the `ETag` header to a resource's version as a strong entity tag, for
+/// clients to set the `If-Match` header to when changing the resource.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct ETag(
+    /// The resource's version.
+    pub i32,
+);
+
+impl IntoResponseParts for ETag {
+    type Error = Infallible;
+
+    fn into_response_parts(self, mut res: ResponseParts) -> Result<ResponseParts, Self::Error> {
+        res.headers_mut().insert(
+            ETAG,
+            HeaderValue::try_from(format!("\"{}\"", self.0))
+                .expect("entity tag should be a valid header value"),
+        );
+
+        Ok(res)
+    }
+}
@@ -0,0 +1,46 @@
+//! See [`LockToken`].
+
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{request::Parts, HeaderName},
+};
+
+use crate::{api, id::Token};
+
+/// The `Lock-Token` header, as specified by RFC 4918 (WebDAV).
+static LOCK_TOKEN: HeaderName = HeaderName::from_static("lock-token");
+
+/// An extractor for the file lock token a request's `Lock-Token` header specifies, needed to change
+/// a file locked via `/api/v1/files/:id/lock`.
+///
+/// Like in WebDAV, the token can be wrapped in angle brackets (e.g. `<token>`). A malformed token
+/// is treated as unspecified, since it can't match any lock.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct LockToken(
+    /// The specified token, or `None` if none was specified.
+    pub Option<Token>,
+);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for LockToken {
+    type Rejection = api::Error;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let token = parts
+            .headers
+            .get(&LOCK_TOKEN)
+            .and_then(|header| header.to_str().ok())
+            .map(|header| {
+                let header = header.trim();
+
+                header
+                    .strip_prefix('<')
+                    .and_then(|header| header.strip_suffix('>'))
+                    .unwrap_or(header)
+            })
+            .and_then(|token| token.parse().ok());
+
+        Ok(Self(token))
+    }
+}
@@ -1,45 +1,18 @@
 //! All routes for the HTTP API.
 
-use std::sync::LazyLock;
-
-use axum::{
-    routing::{get, post},
-    Router,
-};
+use axum::Router;
 use tower_cookies::CookieManagerLayer;
 
 use crate::{api, AppState};
 
-pub mod v1 {
-    //! The routes for version 1 of the HTTP API.
-
-    pub mod email_verification;
-    pub mod password_reset;
-    pub mod sessions;
-    pub mod users;
-}
+pub mod v1;
+pub mod v2;
 
-/// The API router.
-pub(super) static ROUTER: LazyLock<Router<AppState>> = LazyLock::new(|| {
+/// Builds the API router. Each [`crate::app::App`] builds its own.
+pub(crate) fn router() -> Router<AppState> {
     Router::new()
-        .route(
-            "/api/v1/email-verification",
-            get(v1::email_verification::get).post(v1::email_verification::post),
-        )
-        .route(
-            "/api/v1/email-verification/code",
-            post(v1::email_verification::code::post),
-        )
-        .route(
-            "/api/v1/password-reset",
-            get(v1::password_reset::get).post(v1::password_reset::post),
-        )
-        .route(
-            "/api/v1/password-reset/password",
-            post(v1::password_reset::password::post),
-        )
-        .route("/api/v1/sessions", post(v1::sessions::post))
-        .route("/api/v1/users", post(v1::users::post))
+        .nest("/api/v1", v1::router())
+        .nest("/api/v2", v2::router())
         .fallback(|| async { api::Error::RouteNotFound })
         .layer(CookieManagerLayer::new())
-});
+}
@@ -0,0 +1,258 @@
+//! The routes for version 1 of the HTTP API.
+
+use axum::{
+    http::{header::LINK, HeaderName, HeaderValue},
+    middleware::map_response,
+    response::Response,
+    routing::{delete, get, post, put},
+    Router,
+};
+use chrono::{DateTime, Utc};
+
+use crate::{config::Reloadable, AppState};
+
+pub mod billing;
+pub mod changes;
+pub mod config;
+pub mod email_feedback;
+pub mod email_verification;
+pub mod events;
+pub mod files;
+pub mod folder_access_invites;
+pub mod folders;
+pub mod health;
+pub mod invites;
+pub mod oembed;
+pub mod organizations;
+pub mod password_reset;
+pub mod plans;
+pub mod profiles;
+pub mod sessions;
+pub mod unsubscribe;
+pub mod users;
+
+/// The `Deprecation` header, as specified by RFC 9745.
+static DEPRECATION: HeaderName = HeaderName::from_static("deprecation");
+
+/// The `Sunset` header, as specified by RFC 8594.
+static SUNSET: HeaderName = HeaderName::from_static("sunset");
+
+/// When this version of the API was deprecated, if it has been.
+pub(crate) static DEPRECATED_AT: Reloadable<Option<DateTime<Utc>>> =
+    Reloadable::new(|| date_var("API_V1_DEPRECATED_AT"));
+
+/// When this version of the API will stop working, if planned.
+pub(crate) static SUNSET_AT: Reloadable<Option<DateTime<Utc>>> =
+    Reloadable::new(|| date_var("API_V1_SUNSET_AT"));
+
+/// Gets an optional environment variable's value as an RFC 3339 date and time.
+///
+/// # Errors
+///
+/// Returns an error message if the environment variable is set but isn't a valid RFC 3339 date and
+/// time.
+fn date_var(name: &str) -> Result<Option<DateTime<Utc>>, String> {
+    crate::config::optional_var(name)?
+        .map(|date| {
+            date.parse().map_err(|_| {
+                format!("environment variable `{name}` should be an RFC 3339 date and time if set")
+            })
+        })
+        .transpose()
+}
+
+/// Adds headers to a response signaling this version's deprecation and sunset, if configured.
+async fn signal_deprecation(mut response: Response) -> Response {
+    let headers = response.headers_mut();
+
+    if let Some(deprecated_at) = *DEPRECATED_AT.get() {
+        headers.insert(
+            DEPRECATION.clone(),
+            HeaderValue::try_from(format!("@{}", deprecated_at.timestamp()))
+                .expect("deprecation header should be valid"),
+        );
+        headers.insert(
+            LINK,
+            HeaderValue::from_static("</api/v2>; rel=\"successor-version\""),
+        );
+    }
+
+    if let Some(sunset_at) = *SUNSET_AT.get() {
+        headers.insert(
+            SUNSET.clone(),
+            HeaderValue::try_from(sunset_at.format("%a, %d %b %Y %H:%M:%S GMT").to_string())
+                .expect("sunset header should be valid"),
+        );
+    }
+
+    response
+}
+
+/// Builds the router for this version of the API, to be nested under `/api/v1`.
+pub(super) fn router() -> Router<AppState> {
+    Router::new()
+        .route(
+            "/billing/stripe-webhook",
+            post(billing::stripe_webhook::post),
+        )
+        .route("/changes", get(changes::get))
+        .route(
+            "/config/maintenance",
+            get(config::maintenance::get).put(config::maintenance::put),
+        )
+        .route("/config/reload", post(config::reload::post))
+        .route("/email-feedback", post(email_feedback::post))
+        .route(
+            "/email-verification",
+            get(email_verification::get).post(email_verification::post),
+        )
+        .route(
+            "/email-verification/code",
+            post(email_verification::code::post),
+        )
+        .route("/events/stream", get(events::stream::get))
+        .route("/files/batch", post(files::batch::post))
+        .route("/files/:id", get(files::get).patch(files::patch))
+        .route("/files/:id/analytics", get(files::analytics::get))
+        .route("/files/:id/favorite", put(files::favorite::put))
+        .route("/files/:id/legal-hold", put(files::legal_hold::put))
+        .route(
+            "/files/:id/lock",
+            get(files::lock::get)
+                .post(files::lock::post)
+                .put(files::lock::put)
+                .delete(files::lock::delete),
+        )
+        .route("/files/:id/qr", get(files::qr::get))
+        .route("/files/:id/sensitive", put(files::sensitive::put))
+        .route(
+            "/files/:id/shortlink",
+            get(files::shortlink::get)
+                .post(files::shortlink::post)
+                .delete(files::shortlink::delete),
+        )
+        .route("/files/:id/status", put(files::status::put))
+        .route("/files/:id/view-url", get(files::view_url::get))
+        .route(
+            "/folder-access-invites/acceptance",
+            post(folder_access_invites::post),
+        )
+        .route("/folders/:id", get(folders::get).patch(folders::patch))
+        .route(
+            "/folders/:id/access",
+            get(folders::access::get).post(folders::access::post),
+        )
+        .route(
+            "/folders/:id/access/:user_id",
+            delete(folders::access::delete),
+        )
+        .route(
+            "/folders/:id/access-invites/:email",
+            delete(folders::access_invites::delete),
+        )
+        .route("/folders/:id/favorite", put(folders::favorite::put))
+        .route("/folders/:id/retention", put(folders::retention::put))
+        .route("/health", get(health::get))
+        .route("/invites", post(invites::post))
+        .route("/invites/acceptance", post(invites::acceptance::post))
+        .route("/oembed", get(oembed::get))
+        .route("/organizations", post(organizations::post))
+        .route(
+            "/organizations/:id/folders",
+            get(organizations::folders::get),
+        )
+        .route(
+            "/organizations/:id/folders/:folder_id",
+            put(organizations::folders::put).delete(organizations::folders::delete),
+        )
+        .route(
+            "/organizations/:id/members",
+            get(organizations::members::get),
+        )
+        .route(
+            "/organizations/:id/members/:user_id",
+            put(organizations::members::put).delete(organizations::members::delete),
+        )
+        .route("/organizations/:id/plan", put(organizations::plan::put))
+        .route(
+            "/organizations/:id/storage",
+            get(organizations::storage::get),
+        )
+        .route(
+            "/organizations/:id/storage/:folder_id",
+            put(organizations::storage::put).delete(organizations::storage::delete),
+        )
+        .route(
+            "/password-reset",
+            get(password_reset::get).post(password_reset::post),
+        )
+        .route(
+            "/password-reset/password",
+            post(password_reset::password::post),
+        )
+        .route("/plans", get(plans::get))
+        .route("/profiles/:handle", get(profiles::get))
+        .route("/sessions", post(sessions::post))
+        .route("/sessions/revocation", post(sessions::revocation::post))
+        .route("/unsubscribe", post(unsubscribe::post))
+        .route("/users", post(users::post))
+        .route("/users/:id", delete(users::delete))
+        .route("/users/:id/analytics", get(users::analytics::get))
+        .route("/users/:id/billing", get(users::billing::get))
+        .route(
+            "/users/:id/billing/checkout",
+            post(users::billing::checkout::post),
+        )
+        .route(
+            "/users/:id/billing/portal",
+            post(users::billing::portal::post),
+        )
+        .route("/users/:id/domains", get(users::domains::get))
+        .route(
+            "/users/:id/domains/:domain",
+            put(users::domains::put).delete(users::domains::delete),
+        )
+        .route(
+            "/users/:id/domains/:domain/verification",
+            post(users::domains::verification::post),
+        )
+        .route("/users/:id/email", get(users::email::get))
+        .route(
+            "/users/:id/email/verification",
+            post(users::email::verification::post).put(users::email::verification::put),
+        )
+        .route("/users/:id/events", get(users::events::get))
+        .route("/users/:id/favorites", get(users::favorites::get))
+        .route("/users/:id/handle", put(users::handle::put))
+        .route(
+            "/users/:id/indexing",
+            get(users::indexing::get).put(users::indexing::put),
+        )
+        .route("/users/:id/legal-hold", put(users::legal_hold::put))
+        .route(
+            "/users/:id/most-downloaded",
+            get(users::most_downloaded::get),
+        )
+        .route(
+            "/users/:id/notifications",
+            get(users::notifications::get).put(users::notifications::put),
+        )
+        .route("/users/:id/plan", put(users::plan::put))
+        .route("/users/:id/profile", put(users::profile::put))
+        .route("/users/:id/role", put(users::role::put))
+        .route("/users/:id/sessions", get(users::sessions::get))
+        .route(
+            "/users/:id/sessions/others",
+            delete(users::sessions::others::delete),
+        )
+        .route(
+            "/users/:id/sessions/:session_id",
+            delete(users::sessions::delete),
+        )
+        .route(
+            "/users/:id/tos-acceptance",
+            get(users::tos_acceptance::get).put(users::tos_acceptance::put),
+        )
+        .route("/users/:id/usage", get(users::usage::get))
+        .layer(map_response(signal_deprecation))
+}
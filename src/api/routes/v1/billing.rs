@@ -0,0 +1,3 @@
+//! Billing users for paid plans.
+
+pub mod stripe_webhook;
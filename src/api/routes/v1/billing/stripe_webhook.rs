@@ -0,0 +1,322 @@
+//! Webhook events from Stripe about users' subscriptions and payments.
+
+use std::{collections::HashMap, time::Duration};
+
+use axum::{body::Bytes, extract::State, http::HeaderMap, http::StatusCode};
+use axum_macros::debug_handler;
+use chrono::{DateTime, Utc};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::PgConnection;
+
+use crate::{
+    api::{self, routes::v1::plans::Plan, stripe, Json, Response},
+    db::{self, TxResult},
+    id::Id,
+    AppState,
+};
+
+/// How long the IDs of handled events are kept to ignore them if they're sent again. Stripe stops
+/// retrying an event after 3 days.
+pub(crate) const EVENT_RETENTION: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// A Stripe webhook event.
+#[derive(Deserialize, Debug)]
+struct Event {
+    /// The event's ID.
+    id: String,
+
+    /// The event's type (e.g. `customer.subscription.updated`).
+    r#type: String,
+
+    /// When the event was created.
+    #[serde(with = "chrono::serde::ts_seconds")]
+    created: DateTime<Utc>,
+
+    /// The event's data.
+    data: EventData,
+}
+
+/// The data of a Stripe webhook event.
+#[derive(Deserialize, Debug)]
+struct EventData {
+    /// The Stripe object the event is about, whose type depends on the event's type.
+    object: Value,
+}
+
+/// A completed Stripe Checkout session.
+#[derive(Deserialize, Debug)]
+struct CheckoutSession {
+    /// The ID of the user who checked out.
+    client_reference_id: Option<String>,
+
+    /// The ID of the Stripe customer who checked out.
+    customer: Option<String>,
+}
+
+/// A Stripe subscription.
+#[derive(Deserialize, Debug)]
+struct Subscription {
+    /// The subscription's ID.
+    id: String,
+
+    /// The ID of the subscribed Stripe customer.
+    customer: String,
+
+    /// The subscription's status (e.g. `active` or `past_due`).
+    status: String,
+
+    /// Whether the subscription cancels at the end of its current billing period.
+    cancel_at_period_end: bool,
+
+    /// When the subscription's current billing period ends, in seconds since the Unix epoch. Newer
+    /// Stripe API versions set this on each subscription item instead.
+    current_period_end: Option<i64>,
+
+    /// The subscription's metadata, including the subscribed user's ID.
+    #[serde(default)]
+    metadata: HashMap<String, String>,
+
+    /// The subscription's items.
+    items: SubscriptionItems,
+}
+
+/// The items of a Stripe subscription.
+#[derive(Deserialize, Debug)]
+struct SubscriptionItems {
+    /// The items. Subscriptions to plans have exactly one.
+    data: Vec<SubscriptionItem>,
+}
+
+/// An item of a Stripe subscription.
+#[derive(Deserialize, Debug)]
+struct SubscriptionItem {
+    /// The subscribed price.
+    price: Price,
+
+    /// When the item's current billing period ends, in seconds since the Unix epoch.
+    current_period_end: Option<i64>,
+}
+
+/// A Stripe price.
+#[derive(Deserialize, Debug)]
+struct Price {
+    /// The price's ID.
+    id: String,
+}
+
+/// A Stripe invoice.
+#[derive(Deserialize, Debug)]
+struct Invoice {
+    /// The ID of the invoiced Stripe customer.
+    customer: Option<String>,
+}
+
+/// Handles a Stripe webhook event, changing users' plans as their subscriptions change. The
+/// `Stripe-Signature` header must be a valid signature of the request body.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn post(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response<PostResponse> {
+    let is_signature_valid = headers
+        .get("stripe-signature")
+        .and_then(|signature| signature.to_str().ok())
+        .is_some_and(|signature| stripe::verify_webhook_signature(&body, signature));
+
+    if !is_signature_valid {
+        return Err(api::Error::WebhookSignatureInvalid);
+    }
+
+    let event: Event = parse(&body)?;
+
+    db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+        let is_new = sqlx::query!(
+            "INSERT INTO stripe_events (id)
+                VALUES ($1)
+                ON CONFLICT DO NOTHING
+                RETURNING 1 as inserted",
+            event.id,
+        )
+        .fetch_optional(tx.as_mut())
+        .await?
+        .is_some();
+
+        // Stripe can send an event more than once, but each is only handled once.
+        if is_new {
+            handle_event(tx, &event).await?;
+        }
+
+        Ok(())
+    })
+    .await?;
+
+    Ok((StatusCode::OK, Json(PostResponse {})))
+}
+
+/// A `POST` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PostResponse {}
+
+/// Parses JSON from a webhook event.
+///
+/// # Errors
+///
+/// Returns [`api::Error::JsonSyntax`] if the JSON doesn't match the target type.
+fn parse<T: DeserializeOwned>(json: &[u8]) -> Result<T, api::Error> {
+    serde_json::from_slice(json).map_err(|error| api::Error::JsonSyntax(error.to_string()))
+}
+
+/// Parses the Stripe object a webhook event is about.
+///
+/// # Errors
+///
+/// Returns [`api::Error::JsonSyntax`] if the object doesn't match the target type.
+fn parse_object<T: DeserializeOwned>(event: &Event) -> Result<T, api::Error> {
+    T::deserialize(&event.data.object).map_err(|error| api::Error::JsonSyntax(error.to_string()))
+}
+
+/// Applies a webhook event's changes to the user it's about. Events of other types are ignored.
+///
+/// Stripe doesn't guarantee events arrive in order, so subscription events older than the last one
+/// applied to a user are skipped. Events created in the same second are applied in the order they
+/// arrive.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+async fn handle_event(conn: &mut PgConnection, event: &Event) -> Result<(), api::Error> {
+    match event.r#type.as_str() {
+        "checkout.session.completed" => {
+            let session: CheckoutSession = parse_object(event)?;
+
+            let (Some(user_id), Some(customer_id)) = (
+                session
+                    .client_reference_id
+                    .and_then(|user_id| user_id.parse::<Id>().ok()),
+                session.customer,
+            ) else {
+                return Ok(());
+            };
+
+            sqlx::query!(
+                "UPDATE users
+                    SET stripe_customer_id = $1
+                    WHERE id = $2",
+                customer_id,
+                user_id.as_slice(),
+            )
+            .execute(conn)
+            .await?;
+        }
+        "customer.subscription.created" | "customer.subscription.updated" => {
+            let subscription: Subscription = parse_object(event)?;
+
+            let Some(item) = subscription.items.data.first() else {
+                return Ok(());
+            };
+
+            // Prices not for any plan aren't managed here.
+            let Some(subscribed_plan) = stripe::plan_for_price(&item.price.id) else {
+                return Ok(());
+            };
+
+            // Users keep their plan while Stripe retries failed payments, and while their
+            // subscription is set to cancel at the end of the period they already paid for.
+            let plan = if matches!(
+                subscription.status.as_str(),
+                "active" | "trialing" | "past_due"
+            ) {
+                subscribed_plan
+            } else {
+                Plan::Free
+            };
+
+            let period_end = subscription
+                .current_period_end
+                .or(item.current_period_end)
+                .and_then(|period_end| DateTime::from_timestamp(period_end, 0));
+
+            let user_id = subscription
+                .metadata
+                .get("user_id")
+                .and_then(|user_id| user_id.parse::<Id>().ok());
+
+            // The subscription may start before the checkout completes, so its user is also
+            // identified by the metadata set when checking out. An update arriving after the
+            // subscription was deleted is ignored.
+            sqlx::query!(
+                "UPDATE users
+                    SET plan = $1, stripe_customer_id = $2, stripe_subscription_id = $3,
+                        subscription_status = $4, subscription_period_end = $5,
+                        subscription_canceling = $6, subscription_updated_at = $8
+                    WHERE (stripe_customer_id = $2 OR id = $7)
+                        AND NOT (
+                            stripe_subscription_id IS NOT DISTINCT FROM $3
+                                AND subscription_status = 'canceled'
+                        )
+                        AND (subscription_updated_at IS NULL OR subscription_updated_at <= $8)",
+                plan as Plan,
+                subscription.customer,
+                subscription.id,
+                subscription.status,
+                period_end,
+                subscription.cancel_at_period_end,
+                user_id.as_ref().map(|user_id| user_id.as_slice()),
+                event.created,
+            )
+            .execute(conn)
+            .await?;
+        }
+        "customer.subscription.deleted" => {
+            let subscription: Subscription = parse_object(event)?;
+
+            // Files over the free plan's limits are kept, but the user can't add more until
+            // they're back under them.
+            sqlx::query!(
+                "UPDATE users
+                    SET plan = 'free', subscription_status = 'canceled',
+                        subscription_canceling = FALSE, subscription_updated_at = $2
+                    WHERE stripe_subscription_id = $1
+                        AND (subscription_updated_at IS NULL OR subscription_updated_at <= $2)",
+                subscription.id,
+                event.created,
+            )
+            .execute(conn)
+            .await?;
+        }
+        "invoice.payment_failed" => {
+            let invoice: Invoice = parse_object(event)?;
+
+            sqlx::query!(
+                "UPDATE users
+                    SET payment_failed_at = now()
+                    WHERE stripe_customer_id = $1",
+                invoice.customer,
+            )
+            .execute(conn)
+            .await?;
+        }
+        "invoice.paid" => {
+            let invoice: Invoice = parse_object(event)?;
+
+            sqlx::query!(
+                "UPDATE users
+                    SET payment_failed_at = NULL
+                    WHERE stripe_customer_id = $1",
+                invoice.customer,
+            )
+            .execute(conn)
+            .await?;
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
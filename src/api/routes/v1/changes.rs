@@ -0,0 +1,307 @@
+//! The log of changes to the signed-in user's files and folders, for clients to sync incrementally.
+
+use std::{
+    collections::{HashMap, HashSet},
+    num::NonZeroU16,
+};
+
+use axum::{extract::State, http::StatusCode};
+use axum_macros::debug_handler;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{
+        self,
+        auth::Auth,
+        routes::v1::{
+            files::{FileMetadata, FileStatus},
+            folders::FolderMetadata,
+        },
+        Json, Query, Response,
+    },
+    db::{self, TxResult},
+    id::Id,
+    AppState,
+};
+
+/// The number of changes listed if the request doesn't specify a limit.
+const DEFAULT_LIMIT: u16 = 500;
+
+/// The maximum number of changes that can be listed at once.
+const MAX_LIMIT: u16 = 1000;
+
+/// A type of item whose changes are synced.
+#[derive(sqlx::Type, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[sqlx(type_name = "sync_item_type", rename_all = "lowercase")]
+enum SyncItemType {
+    /// A file.
+    File,
+
+    /// A folder.
+    Folder,
+}
+
+/// A position in the change log, after which changes are listed.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+struct Cursor {
+    /// The ID of the transaction that made the last change read.
+    transaction_id: i64,
+
+    /// The ID of the last change read.
+    change_id: i64,
+}
+
+impl Cursor {
+    /// Parses a cursor from the opaque form returned to clients.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`api::Error::InvalidQueryData`] if the cursor is malformed.
+    fn parse(cursor: &str) -> Result<Self, api::Error> {
+        cursor
+            .split_once('.')
+            .and_then(|(transaction_id, change_id)| {
+                Some(Self {
+                    transaction_id: transaction_id.parse().ok()?,
+                    change_id: change_id.parse().ok()?,
+                })
+            })
+            .ok_or_else(|| {
+                api::Error::InvalidQueryData(api::FieldError {
+                    field: "cursor".into(),
+                    code: "INVALID_VALUE",
+                    message: "invalid cursor".into(),
+                })
+            })
+    }
+}
+
+impl std::fmt::Display for Cursor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.transaction_id, self.change_id)
+    }
+}
+
+/// A `GET` request query for this API route.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GetQuery {
+    /// The cursor returned by the last request, to list only changes after it. If unset, changes
+    /// are listed from the start, which includes every existing file and folder.
+    pub cursor: Option<String>,
+
+    /// The maximum number of changes to read, from 1 up to 1000. Defaults to 500. Fewer may be listed,
+    /// since repeated changes to the same item are combined.
+    pub limit: Option<NonZeroU16>,
+}
+
+/// Lists changes to the signed-in user's files and folders in the order they were made, with each
+/// changed item's current metadata, or a tombstone if it's been deleted.
+///
+/// Changes made by transactions still in progress when the request is made, or by any later
+/// transaction, are listed by a later request. So a client that applies every change and passes
+/// each returned cursor to its next request never misses one.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn get(
+    State(state): State<AppState>,
+    auth: Auth,
+    Query(query): Query<GetQuery>,
+) -> Response<GetResponse> {
+    let cursor = match &query.cursor {
+        Some(cursor) => Cursor::parse(cursor)?,
+        None => Cursor::default(),
+    };
+    let limit = query
+        .limit
+        .map_or(DEFAULT_LIMIT, NonZeroU16::get)
+        .min(MAX_LIMIT);
+
+    // This uses the primary database, since changes visible on a lagging replica could be missing
+    // some whose transactions finished earlier.
+    let (changes, files, folders) =
+        db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+            // Changes aren't listed until every transaction that could still add changes before
+            // them has finished.
+            let changes = sqlx::query!(
+                r#"SELECT id, transaction_id, item_type as "item_type: SyncItemType", item_id
+                    FROM sync_changes
+                    WHERE user_id = $1 AND (transaction_id, id) > ($2, $3)
+                        AND transaction_id < pg_snapshot_xmin(pg_current_snapshot())::text::bigint
+                    ORDER BY transaction_id, id
+                    LIMIT $4"#,
+                auth.user_id.as_slice(),
+                cursor.transaction_id,
+                cursor.change_id,
+                i64::from(limit) + 1,
+            )
+            .fetch_all(tx.as_mut())
+            .await?;
+
+            let mut file_ids = Vec::new();
+            let mut folder_ids = Vec::new();
+
+            for change in changes.iter().take(limit.into()) {
+                match change.item_type {
+                    SyncItemType::File => file_ids.push(change.item_id.as_slice()),
+                    SyncItemType::Folder => folder_ids.push(change.item_id.as_slice()),
+                }
+            }
+
+            let files = sqlx::query!(
+                r#"SELECT id, name, parent_name_path, shared, size, type, created_at,
+                    modified_at, download_count, version, status as "status: FileStatus"
+                    FROM files
+                    WHERE owner_id = $1 AND id = ANY($2)"#,
+                auth.user_id.as_slice(),
+                &file_ids as &[&[u8]],
+            )
+            .fetch_all(tx.as_mut())
+            .await?;
+
+            let folders = sqlx::query!(
+                "SELECT id, name, parent_name_path, size, file_count, retention_days,
+                    created_at, version
+                    FROM folders
+                    WHERE owner_id = $1 AND id = ANY($2)",
+                auth.user_id.as_slice(),
+                &folder_ids as &[&[u8]],
+            )
+            .fetch_all(tx.as_mut())
+            .await?;
+
+            Ok((changes, files, folders))
+        })
+        .await?;
+
+    let mut files: HashMap<_, _> = files
+        .into_iter()
+        .map(|file| {
+            let id = Id::from(file.id);
+
+            (
+                id.clone(),
+                FileMetadata {
+                    id,
+                    name: file.name,
+                    parent_path: file.parent_name_path,
+                    shared: file.shared,
+                    size: file.size,
+                    r#type: file.r#type,
+                    created_at: file.created_at,
+                    modified_at: file.modified_at,
+                    download_count: file.download_count,
+                    version: file.version,
+                    status: file.status,
+                },
+            )
+        })
+        .collect();
+
+    let mut folders: HashMap<_, _> = folders
+        .into_iter()
+        .map(|folder| {
+            let id = Id::from(folder.id);
+
+            (
+                id.clone(),
+                FolderMetadata {
+                    id,
+                    name: folder.name,
+                    parent_path: folder.parent_name_path,
+                    size: folder.size,
+                    file_count: folder.file_count,
+                    retention_days: folder
+                        .retention_days
+                        .and_then(|days| u16::try_from(days).ok())
+                        .and_then(NonZeroU16::new),
+                    created_at: folder.created_at,
+                    version: folder.version,
+                },
+            )
+        })
+        .collect();
+
+    let has_more = changes.len() > limit.into();
+    let changes = &changes[..changes.len().min(limit.into())];
+
+    let next_cursor = changes.last().map_or(cursor, |change| Cursor {
+        transaction_id: change.transaction_id,
+        change_id: change.id,
+    });
+
+    // Each item is only listed once, at its last change, since its current metadata already
+    // reflects every change before that.
+    let mut listed_items = HashSet::new();
+    let mut listed_changes: Vec<_> = changes
+        .iter()
+        .rev()
+        .filter(|change| listed_items.insert((change.item_type, change.item_id.as_slice())))
+        .map(|change| {
+            let id = Id::from(change.item_id.clone());
+
+            match change.item_type {
+                SyncItemType::File => Change::File {
+                    file: files.remove(&id),
+                    id,
+                },
+                SyncItemType::Folder => Change::Folder {
+                    folder: folders.remove(&id),
+                    id,
+                },
+            }
+        })
+        .collect();
+    listed_changes.reverse();
+
+    Ok((
+        StatusCode::OK,
+        Json(GetResponse {
+            changes: listed_changes,
+            cursor: next_cursor.to_string(),
+            has_more,
+        }),
+    ))
+}
+
+/// A `GET` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GetResponse {
+    /// The changed items, in the order of their last change.
+    pub changes: Vec<Change>,
+
+    /// The cursor to pass to the next request to list only changes after these.
+    pub cursor: String,
+
+    /// Whether more changes can already be listed after these.
+    pub has_more: bool,
+}
+
+/// A changed item and its current state.
+#[derive(Serialize, Debug)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum Change {
+    /// A changed file.
+    #[serde(rename_all = "camelCase")]
+    File {
+        /// The file's ID.
+        id: Id,
+
+        /// The file's current metadata, or `None` if it's been deleted.
+        file: Option<FileMetadata>,
+    },
+
+    /// A changed folder.
+    #[serde(rename_all = "camelCase")]
+    Folder {
+        /// The folder's ID.
+        id: Id,
+
+        /// The folder's current metadata, or `None` if it's been deleted.
+        folder: Option<FolderMetadata>,
+    },
+}
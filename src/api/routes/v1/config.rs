@@ -0,0 +1,4 @@
+//! The server's settings.
+
+pub mod maintenance;
+pub mod reload;
@@ -0,0 +1,133 @@
+//! Whether the server is down for maintenance.
+
+use axum::{
+    extract::State,
+    http::{Method, StatusCode},
+};
+use axum_macros::debug_handler;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{
+        auth::{Auth, Permission},
+        Json, Response,
+    },
+    AppState,
+};
+
+/// Which requests are rejected while the server is down for maintenance. Requests from users who can
+/// manage the server's config and health checks are never rejected, so maintenance can always be
+/// ended and the server stays in its load balancer's rotation.
+#[derive(sqlx::Type, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[sqlx(type_name = "maintenance_mode", rename_all = "lowercase")]
+#[serde(rename_all = "camelCase")]
+pub enum MaintenanceMode {
+    /// No requests are rejected.
+    Off,
+
+    /// API requests that can change data are rejected, but anything can still be viewed.
+    Writes,
+
+    /// All API requests are rejected, and user-uploaded content isn't served.
+    All,
+}
+
+impl MaintenanceMode {
+    /// Checks whether an API request with the specified method is rejected in this mode.
+    pub(crate) fn rejects(self, method: &Method) -> bool {
+        match self {
+            Self::Off => false,
+            Self::Writes => !method.is_safe(),
+            Self::All => true,
+        }
+    }
+}
+
+/// Gets the server's current maintenance mode.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn get(State(state): State<AppState>, auth: Auth) -> Response<GetResponse> {
+    auth.require(Permission::ManageConfig)?;
+
+    Ok((
+        StatusCode::OK,
+        Json(GetResponse {
+            mode: state.maintenance_mode.get().await,
+        }),
+    ))
+}
+
+/// A `GET` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GetResponse {
+    /// The server's current maintenance mode.
+    pub mode: MaintenanceMode,
+}
+
+/// A `PUT` request body for this API route.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct PutRequest {
+    /// The maintenance mode to switch to.
+    pub mode: MaintenanceMode,
+}
+
+/// Switches the server's maintenance mode. This applies to every server process within a few seconds
+/// and lasts until it's switched again.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn put(
+    State(state): State<AppState>,
+    auth: Auth,
+    Json(body): Json<PutRequest>,
+) -> Response<PutResponse> {
+    auth.require(Permission::ManageConfig)?;
+
+    state.maintenance_mode.set(body.mode).await?;
+
+    Ok((StatusCode::OK, Json(PutResponse { mode: body.mode })))
+}
+
+/// A `PUT` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PutResponse {
+    /// The server's new maintenance mode.
+    pub mode: MaintenanceMode,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejected_methods() {
+        let methods = [
+            Method::GET,
+            Method::HEAD,
+            Method::OPTIONS,
+            Method::POST,
+            Method::PUT,
+            Method::PATCH,
+            Method::DELETE,
+        ];
+
+        for method in &methods {
+            assert!(!MaintenanceMode::Off.rejects(method), "checking {method}");
+            assert!(MaintenanceMode::All.rejects(method), "checking {method}");
+
+            assert_eq!(
+                !matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS),
+                MaintenanceMode::Writes.rejects(method),
+                "checking {method}",
+            );
+        }
+    }
+}
@@ -0,0 +1,33 @@
+//! Reloading the server's settings without restarting it.
+
+use axum::{extract::State, http::StatusCode};
+use axum_macros::debug_handler;
+use serde::Serialize;
+
+use crate::{
+    api::{
+        auth::{Auth, Permission},
+        Json, Response,
+    },
+    config, AppState,
+};
+
+/// Reads `.env` again and reloads every setting that can change without restarting the server,
+/// like sending the process `SIGHUP` does.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn post(State(state): State<AppState>, auth: Auth) -> Response<PostResponse> {
+    auth.require(Permission::ManageConfig)?;
+
+    config::reload(&state).await?;
+
+    Ok((StatusCode::OK, Json(PostResponse {})))
+}
+
+/// A `POST` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PostResponse {}
@@ -0,0 +1,206 @@
+//! Bounce and complaint notifications from the SMTP provider about emails we sent.
+
+use std::sync::LazyLock;
+
+use axum::{body::Bytes, extract::State, http::HeaderMap, http::StatusCode};
+use axum_macros::debug_handler;
+use chrono::Utc;
+use ring::hmac;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{self, Json, Response},
+    config,
+    crypto::decode_hex,
+    db::{self, TxResult},
+    AppState,
+};
+
+/// How many seconds old a notification's signature can be before it's rejected, so captured
+/// notifications can't be replayed later.
+const WEBHOOK_TOLERANCE_SECONDS: u64 = 5 * 60;
+
+/// The key the SMTP provider signs its notifications with.
+static WEBHOOK_KEY: LazyLock<hmac::Key> = LazyLock::new(|| {
+    let secret = config::var("EMAIL_WEBHOOK_SECRET")
+        .expect("environment variable `EMAIL_WEBHOOK_SECRET` should be a valid string");
+
+    hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes())
+});
+
+/// Why no more emails are sent to an address.
+#[derive(sqlx::Type, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[sqlx(type_name = "email_suppression_reason", rename_all = "lowercase")]
+#[serde(rename_all = "camelCase")]
+pub enum EmailSuppressionReason {
+    /// An email to the address bounced permanently.
+    Bounce,
+
+    /// The recipient marked an email to the address as spam.
+    Complaint,
+}
+
+/// A `POST` request body for this API route.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PostRequest {
+    /// Whether the email bounced or was complained about.
+    pub r#type: EmailSuppressionReason,
+
+    /// The address the email was sent to.
+    pub email: String,
+}
+
+/// Handles a bounce or complaint notification from the SMTP provider, suppressing further emails
+/// to the address and requiring any user with the address to verify a working email. The
+/// `X-Webhook-Timestamp` header must be when the notification was sent in seconds since the Unix
+/// epoch, within five minutes of now. The `X-Webhook-Signature` header must be the hexadecimal
+/// HMAC-SHA256 signature of the timestamp, a `.`, and the request body.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn post(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response<PostResponse> {
+    let header = |name| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+    };
+
+    if !verify_signature(
+        &WEBHOOK_KEY,
+        &body,
+        header("x-webhook-timestamp"),
+        header("x-webhook-signature"),
+        Utc::now().timestamp(),
+    ) {
+        return Err(api::Error::WebhookSignatureInvalid);
+    }
+
+    let notification: PostRequest =
+        serde_json::from_slice(&body).map_err(|error| api::Error::JsonSyntax(error.to_string()))?;
+
+    db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+        sqlx::query!(
+            "INSERT INTO email_suppressions (email, reason)
+                VALUES ($1, $2)
+                ON CONFLICT DO NOTHING",
+            notification.email,
+            notification.r#type as EmailSuppressionReason,
+        )
+        .execute(tx.as_mut())
+        .await?;
+
+        sqlx::query!(
+            "UPDATE users
+                SET email_reverification_required = TRUE
+                WHERE email = $1",
+            notification.email,
+        )
+        .execute(tx.as_mut())
+        .await?;
+
+        Ok(())
+    })
+    .await?;
+
+    Ok((StatusCode::OK, Json(PostResponse {})))
+}
+
+/// A `POST` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PostResponse {}
+
+/// Returns whether a notification's signature header is a valid signature of its timestamp header
+/// and payload by the specified key, and the timestamp is within the tolerance of the specified
+/// current time in seconds since the Unix epoch. The signature is verified in constant time.
+fn verify_signature(
+    key: &hmac::Key,
+    payload: &[u8],
+    timestamp_header: &str,
+    signature_header: &str,
+    now: i64,
+) -> bool {
+    let Ok(timestamp) = timestamp_header.parse::<i64>() else {
+        return false;
+    };
+
+    if now.abs_diff(timestamp) > WEBHOOK_TOLERANCE_SECONDS {
+        return false;
+    }
+
+    let Some(signature) = decode_hex(signature_header) else {
+        return false;
+    };
+
+    let signed_payload = [timestamp_header.as_bytes(), b".", payload].concat();
+
+    hmac::verify(key, &signed_payload, &signature).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The current time in the tests, in seconds since the Unix epoch.
+    const NOW: i64 = 1_800_000_000;
+
+    /// The payload of the tests' notification.
+    const PAYLOAD: &[u8] = br#"{"type":"bounce","email":"test@example.com"}"#;
+
+    /// Signs a payload with a timestamp like the SMTP provider does, returning the hex-encoded
+    /// signature.
+    fn sign(key: &hmac::Key, timestamp: i64, payload: &[u8]) -> String {
+        let signed_payload = [timestamp.to_string().as_bytes(), b".", payload].concat();
+
+        hmac::sign(key, &signed_payload)
+            .as_ref()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+
+    #[test]
+    fn webhook_signature_verification() {
+        let key = hmac::Key::new(hmac::HMAC_SHA256, b"secret");
+        let other_key = hmac::Key::new(hmac::HMAC_SHA256, b"other secret");
+
+        let timestamp = NOW.to_string();
+        let signature = sign(&key, NOW, PAYLOAD);
+
+        assert!(
+            verify_signature(&key, PAYLOAD, &timestamp, &signature, NOW),
+            "verifying a valid signature",
+        );
+
+        let expired_timestamp = NOW - 10 * 60;
+        let expired_signature = sign(&key, expired_timestamp, PAYLOAD);
+
+        let invalid_headers = [
+            (timestamp.clone(), sign(&other_key, NOW, PAYLOAD)),
+            (timestamp.clone(), "not-hex".into()),
+            (String::new(), signature.clone()),
+            ((NOW + 1).to_string(), signature.clone()),
+            (expired_timestamp.to_string(), expired_signature),
+        ];
+
+        for (timestamp, signature) in invalid_headers {
+            assert!(
+                !verify_signature(&key, PAYLOAD, &timestamp, &signature, NOW),
+                "verifying {signature:?} at {timestamp:?}",
+            );
+        }
+
+        assert!(
+            !verify_signature(&key, b"{}", &timestamp, &signature, NOW),
+            "verifying a tampered payload",
+        );
+    }
+}
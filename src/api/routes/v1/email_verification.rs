@@ -5,11 +5,12 @@ use axum_macros::debug_handler;
 use lettre::message::Mailbox;
 use serde::{Deserialize, Serialize};
 use sqlx::Acquire;
+use tokio::time::Instant;
 
 use crate::{
     api::{
-        self, captcha,
-        validation::{CaptchaToken, EmailVerificationCode, UserEmail},
+        self, captcha, pad_response_time,
+        validation::{CaptchaToken, EmailVerificationCode, UserEmail, STRIP_EMAIL_SUBADDRESSES},
         Json, Query, Response,
     },
     crypto::{hash_without_salt, verify_hash},
@@ -126,16 +127,29 @@ pub async fn post(
     State(state): State<AppState>,
     Json(body): Json<PostRequest>,
 ) -> Response<PostResponse> {
+    let started_at = Instant::now();
+
     // We don't want bots creating accounts or spamming people with verification emails.
     if !captcha::verify(&body.captcha_token).await? {
         return Err(api::Error::CaptchaFailed);
     }
 
+    if state
+        .disposable_email_domains
+        .contains(body.email.as_str())
+        .await
+    {
+        return Err(api::Error::EmailDomainDisposable);
+    }
+
     db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
         let existing_user = sqlx::query!(
             "SELECT name FROM users
-                WHERE email = $1",
+                WHERE email = $1 OR ($2 AND canonical_email = $3)
+                LIMIT 1",
             body.email.as_str(),
+            *STRIP_EMAIL_SUBADDRESSES.get(),
+            &*body.email.canonical(),
         )
         .fetch_optional(tx.as_mut())
         .await?;
@@ -145,7 +159,8 @@ pub async fn post(
                 email: body.email.as_str(),
             }
             .to(Mailbox::new(Some(user.name), (*body.email).clone()))
-            .send();
+            .send(tx.as_mut())
+            .await?;
 
             return Ok(());
         }
@@ -195,12 +210,14 @@ pub async fn post(
             verification_url: &format!("{}/verify-email?token={}", *WEBSITE_ORIGIN, token),
         }
         .to(Mailbox::new(None, (*body.email).clone()))
-        .send();
+        .send_requested();
 
         Ok(())
     })
     .await?;
 
+    pad_response_time(started_at).await;
+
     // To prevent user enumeration, send this same successful response even if the email is taken.
     Ok((StatusCode::OK, Json(PostResponse { email: body.email })))
 }
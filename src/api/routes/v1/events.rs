@@ -0,0 +1,3 @@
+//! Live updates about the signed-in user's data.
+
+pub mod stream;
@@ -0,0 +1,58 @@
+//! A stream of the signed-in user's file events as they happen, using [server-sent
+//! events](https://developer.mozilla.org/docs/Web/API/Server-sent_events).
+
+use axum::{
+    extract::State,
+    response::sse::{Event, KeepAlive, Sse},
+};
+use axum_macros::debug_handler;
+use futures_util::{stream, Stream};
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::{
+    api::{self, auth::Auth},
+    AppState,
+};
+
+/// Opens a stream of the signed-in user's file events.
+///
+/// Each file event is sent as a `file` event with the same data as an item in the user's activity
+/// log. If the client falls too far behind and misses events, a `missed` event is sent, after
+/// which the client should refetch any data it's showing.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn get(
+    State(state): State<AppState>,
+    auth: Auth,
+) -> Result<Sse<impl Stream<Item = Result<Event, axum::Error>>>, api::Error> {
+    let receiver = state.file_events.subscribe();
+
+    let events = stream::unfold(
+        (receiver, auth.user_id),
+        |(mut receiver, user_id)| async move {
+            let event = loop {
+                match receiver.recv().await {
+                    Ok(notification) if notification.user_id == user_id => {
+                        break Event::default()
+                            .event("file")
+                            .json_data(&notification.event);
+                    }
+                    Ok(_) => {}
+                    Err(RecvError::Lagged(_)) => {
+                        break Ok(Event::default()
+                            .event("missed")
+                            .data("Some events were missed."));
+                    }
+                    Err(RecvError::Closed) => return None,
+                }
+            };
+
+            Some((event, (receiver, user_id)))
+        },
+    );
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
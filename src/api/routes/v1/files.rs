@@ -0,0 +1,279 @@
+//! The set of all files.
+
+use axum::{extract::State, http::StatusCode};
+use axum_macros::debug_handler;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{
+        self,
+        auth::{Auth, FolderAccess},
+        if_match::{ETag, IfMatch},
+        lock_token::LockToken,
+        validation::FileName,
+        Json, Path,
+    },
+    cdn,
+    db::{self, TxError, TxResult},
+    id::Id,
+    AppState,
+};
+
+pub mod analytics;
+pub mod batch;
+pub mod favorite;
+pub mod legal_hold;
+pub mod lock;
+pub mod qr;
+pub mod sensitive;
+pub mod shortlink;
+pub mod status;
+pub mod view_url;
+
+/// Gets a file's metadata.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn get(
+    State(state): State<AppState>,
+    auth: Auth,
+    Path(file_id): Path<Id>,
+) -> Result<(StatusCode, ETag, Json<FileMetadata>), api::Error> {
+    let Some(file) = db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+        auth.require_file_access(tx, &file_id, FolderAccess::Read)
+            .await?;
+
+        Ok(sqlx::query_as!(
+            FileMetadataRow,
+            r#"SELECT id, name, parent_name_path, shared, size, type, created_at,
+                modified_at, download_count, version, status as "status: FileStatus"
+                FROM files
+                WHERE id = $1"#,
+            file_id.as_slice(),
+        )
+        .fetch_optional(tx.as_mut())
+        .await?)
+    })
+    .await?
+    else {
+        return Err(api::Error::ResourceNotFound);
+    };
+
+    Ok((StatusCode::OK, ETag(file.version), Json(file.into())))
+}
+
+/// A `PATCH` request body for this API route.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct PatchRequest {
+    /// The file's new name, if it should be renamed.
+    pub name: Option<FileName>,
+
+    /// Whether the file should be accessible to anyone with its link, if it should change.
+    pub shared: Option<bool>,
+}
+
+/// Changes a file's metadata. The `If-Match` header must be set to the file's current version, and
+/// if the file is locked, the `Lock-Token` header must be set to its lock's token.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn patch(
+    State(state): State<AppState>,
+    auth: Auth,
+    Path(file_id): Path<Id>,
+    IfMatch(version): IfMatch,
+    LockToken(lock_token): LockToken,
+    Json(body): Json<PatchRequest>,
+) -> Result<(StatusCode, ETag, Json<FileMetadata>), api::Error> {
+    let (file, urls) = db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+        // Lock the file so its version can't change between checking and updating it.
+        let Some(file) = sqlx::query!(
+            "SELECT version FROM files
+                WHERE id = $1
+                FOR UPDATE",
+            file_id.as_slice(),
+        )
+        .fetch_optional(tx.as_mut())
+        .await?
+        else {
+            return Err(TxError::Abort(api::Error::ResourceNotFound));
+        };
+
+        auth.require_file_access(tx, &file_id, FolderAccess::Write)
+            .await?;
+
+        lock::require_unlocked(tx, &file_id, lock_token.as_ref()).await?;
+
+        if version.is_some_and(|version| version != file.version) {
+            return Err(TxError::Abort(api::Error::PreconditionFailed));
+        }
+
+        // Renaming or unsharing the file means it shouldn't be served from its old URLs anymore.
+        let urls = if body.name.is_some() || body.shared == Some(false) {
+            cdn::file_urls(tx, &file_id).await?
+        } else {
+            Vec::new()
+        };
+
+        let file = match sqlx::query_as!(
+            FileMetadataRow,
+            r#"UPDATE files
+                SET name = coalesce($2, name), shared = coalesce($3, shared)
+                WHERE id = $1
+                RETURNING id, name, parent_name_path, shared, size, type, created_at,
+                    modified_at, download_count, version, status as "status: FileStatus""#,
+            file_id.as_slice(),
+            body.name.as_ref().map(FileName::as_str),
+            body.shared,
+        )
+        .fetch_one(tx.as_mut())
+        .await
+        {
+            Err(sqlx::Error::Database(error))
+                if error.constraint() == Some("files_owner_id_parent_name_path_name_key") =>
+            {
+                return Err(TxError::Abort(api::Error::FileNameTaken));
+            }
+            result => result?,
+        };
+
+        Ok((file, urls))
+    })
+    .await?;
+
+    cdn::purge(urls);
+
+    Ok((StatusCode::OK, ETag(file.version), Json(file.into())))
+}
+
+/// A file's metadata as stored in the database.
+#[derive(Debug)]
+struct FileMetadataRow {
+    /// See [`FileMetadata::id`].
+    id: Vec<u8>,
+
+    /// See [`FileMetadata::name`].
+    name: String,
+
+    /// See [`FileMetadata::parent_path`].
+    parent_name_path: Vec<String>,
+
+    /// See [`FileMetadata::shared`].
+    shared: bool,
+
+    /// See [`FileMetadata::size`].
+    size: i64,
+
+    /// See [`FileMetadata::type`].
+    r#type: String,
+
+    /// See [`FileMetadata::created_at`].
+    created_at: DateTime<Utc>,
+
+    /// See [`FileMetadata::modified_at`].
+    modified_at: DateTime<Utc>,
+
+    /// See [`FileMetadata::download_count`].
+    download_count: i64,
+
+    /// See [`FileMetadata::version`].
+    version: i32,
+
+    /// See [`FileMetadata::status`].
+    status: FileStatus,
+}
+
+impl From<FileMetadataRow> for FileMetadata {
+    fn from(row: FileMetadataRow) -> Self {
+        Self {
+            id: row.id.into(),
+            name: row.name,
+            parent_path: row.parent_name_path,
+            shared: row.shared,
+            size: row.size,
+            r#type: row.r#type,
+            created_at: row.created_at,
+            modified_at: row.modified_at,
+            download_count: row.download_count,
+            version: row.version,
+            status: row.status,
+        }
+    }
+}
+
+/// A response body for this API route, containing a file's metadata.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FileMetadata {
+    /// The file's ID.
+    pub id: Id,
+
+    /// The file's name.
+    pub name: String,
+
+    /// The names of the file's ancestor folders.
+    pub parent_path: Vec<String>,
+
+    /// Whether the file is accessible to anyone with its link.
+    pub shared: bool,
+
+    /// The file's size in bytes.
+    pub size: i64,
+
+    /// The file's media type.
+    pub r#type: String,
+
+    /// When the file was created.
+    pub created_at: DateTime<Utc>,
+
+    /// When the file's contents were last modified.
+    pub modified_at: DateTime<Utc>,
+
+    /// How many times the file has been downloaded. This can lag behind by a few seconds.
+    pub download_count: i64,
+
+    /// The version of the file's metadata, which increments whenever it changes. Changes must set
+    /// the `If-Match` header to this as an entity tag (e.g. `"3"`), which is also the response's
+    /// `ETag` header.
+    pub version: i32,
+
+    /// Whether the file is available, or why it isn't.
+    pub status: FileStatus,
+}
+
+/// Whether a file is available, or why it isn't.
+#[derive(sqlx::Type, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[sqlx(type_name = "file_status", rename_all = "snake_case")]
+#[serde(rename_all = "camelCase")]
+pub enum FileStatus {
+    /// The file is available.
+    Active,
+
+    /// The file is unavailable until it's scanned for abusive content.
+    PendingScan,
+
+    /// The file is unavailable while moderators review it.
+    Quarantined,
+
+    /// The file was taken down by moderators for a [`TakedownReason`].
+    TakenDown,
+}
+
+/// Why a file was taken down.
+#[derive(sqlx::Type, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[sqlx(type_name = "takedown_reason", rename_all = "snake_case")]
+#[serde(rename_all = "camelCase")]
+pub enum TakedownReason {
+    /// The file was taken down in response to a legal request, such as a copyright claim or a court
+    /// order.
+    Legal,
+
+    /// The file was taken down for violating the terms of service.
+    TermsViolation,
+}
@@ -0,0 +1,196 @@
+//! A file's traffic over time.
+
+use std::time::Duration;
+
+use axum::{extract::State, http::StatusCode};
+use axum_macros::debug_handler;
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{
+        self,
+        auth::{Auth, FolderAccess},
+        Json, Path, Query, Response,
+    },
+    db::{self, TxResult},
+    id::Id,
+    AppState,
+};
+
+/// How long the hashed IP addresses of a file's visitors are kept to count unique visitors. Buckets
+/// older than this count no unique visitors.
+pub(crate) const VISITOR_RETENTION: Duration = Duration::from_secs(90 * 24 * 60 * 60);
+
+/// The length of time each bucket of traffic covers.
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum Interval {
+    /// An hour.
+    Hour,
+
+    /// A day (in UTC).
+    #[default]
+    Day,
+}
+
+impl Interval {
+    /// Gets the name of the interval's unit, as accepted by Postgres's `date_trunc`.
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::Hour => "hour",
+            Self::Day => "day",
+        }
+    }
+
+    /// Gets the range of time traffic is listed over if the request doesn't specify a start.
+    fn default_range(self) -> TimeDelta {
+        match self {
+            Self::Hour => TimeDelta::hours(24),
+            Self::Day => TimeDelta::days(30),
+        }
+    }
+
+    /// Gets the longest range of time traffic can be listed over at once.
+    fn max_range(self) -> TimeDelta {
+        match self {
+            Self::Hour => TimeDelta::days(7),
+            Self::Day => TimeDelta::days(366),
+        }
+    }
+}
+
+/// A `GET` request query for this API route.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GetQuery {
+    /// The length of time each bucket of traffic covers. Defaults to a day.
+    #[serde(default)]
+    pub interval: Interval,
+
+    /// The start of the range of time to list traffic over. Defaults to 24 hours before `until` for
+    /// hourly buckets, or 30 days before `until` for daily buckets. The range can span at most 7
+    /// days for hourly buckets, or 366 days for daily buckets.
+    pub since: Option<DateTime<Utc>>,
+
+    /// The end of the range of time to list traffic over. Defaults to now.
+    pub until: Option<DateTime<Utc>>,
+}
+
+impl GetQuery {
+    /// Gets the start and end of the range of time to list traffic over.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`api::Error::InvalidQueryData`] if the range is backward or too long.
+    pub(crate) fn range(&self) -> Result<(DateTime<Utc>, DateTime<Utc>), api::Error> {
+        let until = self.until.unwrap_or_else(Utc::now);
+        let since = self
+            .since
+            .unwrap_or_else(|| until - self.interval.default_range());
+
+        if since > until {
+            return Err(api::Error::InvalidQueryData(api::FieldError {
+                field: "since".into(),
+                code: "INVALID_VALUE",
+                message: "`since` must not be after `until`".into(),
+            }));
+        }
+
+        if until - since > self.interval.max_range() {
+            return Err(api::Error::InvalidQueryData(api::FieldError {
+                field: "since".into(),
+                code: "INVALID_VALUE",
+                message: format!(
+                    "the range can span at most {} days for this interval",
+                    self.interval.max_range().num_days(),
+                ),
+            }));
+        }
+
+        Ok((since, until))
+    }
+}
+
+/// Lists a file's traffic over time, bucketed by the requested interval. Only buckets with traffic
+/// are listed.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn get(
+    State(state): State<AppState>,
+    auth: Auth,
+    Path(file_id): Path<Id>,
+    Query(query): Query<GetQuery>,
+) -> Response<GetResponse> {
+    let (since, until) = query.range()?;
+
+    let series = db::transaction!(
+        state.db_replica_pool,
+        async |tx| -> TxResult<_, api::Error> {
+            auth.require_file_access(tx, &file_id, FolderAccess::Read)
+                .await?;
+
+            Ok(sqlx::query_as!(
+                TrafficBucket,
+                r#"WITH traffic AS (
+                    SELECT date_trunc($2, hour, 'UTC') as start, sum(requests) as requests,
+                        sum(bytes) as bytes
+                        FROM file_traffic
+                        WHERE file_id = $1 AND hour >= date_trunc('hour', $3, 'UTC')
+                            AND hour < $4
+                        GROUP BY 1
+                ), visitors AS (
+                    SELECT date_trunc($2, hour, 'UTC') as start,
+                        count(DISTINCT visitor_hash) as unique_visitors
+                        FROM file_visitors
+                        WHERE file_id = $1 AND hour >= date_trunc('hour', $3, 'UTC')
+                            AND hour < $4
+                        GROUP BY 1
+                )
+                SELECT traffic.start as "start!", traffic.requests::bigint as "requests!",
+                    traffic.bytes::bigint as "bytes!",
+                    coalesce(visitors.unique_visitors, 0) as "unique_visitors!"
+                    FROM traffic
+                    LEFT JOIN visitors ON visitors.start = traffic.start
+                    ORDER BY traffic.start"#,
+                file_id.as_slice(),
+                query.interval.as_str(),
+                since,
+                until,
+            )
+            .fetch_all(tx.as_mut())
+            .await?)
+        }
+    )
+    .await?;
+
+    Ok((StatusCode::OK, Json(GetResponse { series })))
+}
+
+/// A `GET` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GetResponse {
+    /// The file's traffic in each bucket with any, from oldest to newest.
+    pub series: Vec<TrafficBucket>,
+}
+
+/// Traffic within one bucket of time.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TrafficBucket {
+    /// When the bucket starts.
+    pub start: DateTime<Utc>,
+
+    /// The number of times files were downloaded.
+    pub requests: i64,
+
+    /// The total number of bytes sent.
+    pub bytes: i64,
+
+    /// The number of distinct IP addresses files were sent to.
+    pub unique_visitors: i64,
+}
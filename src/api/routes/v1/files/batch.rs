@@ -0,0 +1,304 @@
+//! Batches of operations on files, so many files can be changed at once.
+
+use axum::{extract::State, http::StatusCode};
+use axum_macros::debug_handler;
+use serde::{Deserialize, Serialize};
+use sqlx::PgConnection;
+
+use crate::{
+    api::{
+        self,
+        auth::{Auth, FolderAccess},
+        lock_token::LockToken,
+        routes::v1::files::lock,
+        Json, Response,
+    },
+    cdn,
+    db::{self, TxError, TxResult},
+    id::{Id, Token},
+    AppState,
+};
+
+/// The maximum number of operations in a batch.
+const MAX_OPERATIONS: usize = 1000;
+
+/// An operation on a file the signed-in user can change.
+#[derive(Deserialize, Debug)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase",
+    deny_unknown_fields
+)]
+pub enum Operation {
+    /// Deletes a file.
+    Delete {
+        /// The ID of the file to delete.
+        file_id: Id,
+    },
+
+    /// Moves a file into a folder.
+    Move {
+        /// The ID of the file to move.
+        file_id: Id,
+
+        /// The ID of the folder to move the file into, or `None` to move it to the top level.
+        folder_id: Option<Id>,
+    },
+
+    /// Changes whether a file is accessible to anyone with its link.
+    SetShared {
+        /// The ID of the file to change.
+        file_id: Id,
+
+        /// Whether the file should be accessible to anyone with its link.
+        shared: bool,
+    },
+}
+
+impl Operation {
+    /// Gets the ID of the file the operation is on.
+    fn file_id(&self) -> &Id {
+        match self {
+            Self::Delete { file_id }
+            | Self::Move { file_id, .. }
+            | Self::SetShared { file_id, .. } => file_id,
+        }
+    }
+}
+
+/// A `POST` request body for this API route.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct PostRequest {
+    /// The operations to perform, in order.
+    pub operations: Vec<Operation>,
+}
+
+/// Performs a batch of operations on files the signed-in user can change in one transaction,
+/// responding with each operation's result. If any operation fails, none of them are performed, and
+/// the error says which one failed. Operations on locked files fail unless the `Lock-Token` header
+/// is set to their lock's token.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn post(
+    State(state): State<AppState>,
+    auth: Auth,
+    LockToken(lock_token): LockToken,
+    Json(body): Json<PostRequest>,
+) -> Response<PostResponse> {
+    if body.operations.len() > MAX_OPERATIONS {
+        return Err(api::Error::InvalidBodyData(api::FieldError {
+            field: "operations".into(),
+            code: "INVALID_LENGTH",
+            message: format!("at most {MAX_OPERATIONS} operations are allowed in a batch"),
+        }));
+    }
+
+    let (results, urls) = db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+        let mut results = Vec::with_capacity(body.operations.len());
+        let mut urls = Vec::new();
+
+        for (index, operation) in body.operations.iter().enumerate() {
+            match perform(tx, &auth, lock_token.as_ref(), operation).await {
+                Err(TxError::Abort(error)) if !matches!(error, api::Error::Internal(_)) => {
+                    return Err(TxError::Abort(api::Error::BatchOperationFailed {
+                        index,
+                        source: Box::new(error),
+                    }));
+                }
+                result => {
+                    let (result, operation_urls) = result?;
+
+                    results.push(result);
+                    urls.extend(operation_urls);
+                }
+            }
+        }
+
+        Ok((results, urls))
+    })
+    .await?;
+
+    cdn::purge(urls);
+
+    Ok((StatusCode::OK, Json(PostResponse { results })))
+}
+
+/// A `POST` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PostResponse {
+    /// The result of each operation, in the same order as the operations.
+    pub results: Vec<OperationResult>,
+}
+
+/// The result of a performed [`Operation`].
+#[derive(Serialize, Debug)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum OperationResult {
+    /// The file was deleted.
+    Delete {
+        /// The ID of the deleted file.
+        file_id: Id,
+    },
+
+    /// The file was moved.
+    Move {
+        /// The ID of the moved file.
+        file_id: Id,
+
+        /// The names of the folders the file is now in, from the top level down.
+        parent_path: Vec<String>,
+
+        /// The file's new version.
+        version: i32,
+    },
+
+    /// Whether the file is accessible to anyone with its link was changed.
+    SetShared {
+        /// The ID of the changed file.
+        file_id: Id,
+
+        /// Whether the file is now accessible to anyone with its link.
+        shared: bool,
+
+        /// The file's new version.
+        version: i32,
+    },
+}
+
+/// Performs an operation on a file the signed-in user has write access to, returning its result and
+/// the URLs to purge from the CDN's cache once the batch is performed.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+async fn perform(
+    conn: &mut PgConnection,
+    auth: &Auth,
+    lock_token: Option<&Token>,
+    operation: &Operation,
+) -> TxResult<(OperationResult, Vec<String>), api::Error> {
+    let file_id = operation.file_id();
+
+    // Access is checked before locks, so whether an inaccessible file is locked isn't revealed.
+    auth.require_file_access(&mut *conn, file_id, FolderAccess::Write)
+        .await?;
+
+    if let Operation::Move {
+        folder_id: Some(folder_id),
+        ..
+    } = operation
+    {
+        auth.require_folder_access(&mut *conn, folder_id, FolderAccess::Write)
+            .await?;
+    }
+
+    lock::require_unlocked(&mut *conn, file_id, lock_token).await?;
+
+    // Deleting, moving, or unsharing the file means it shouldn't be served from its old URLs anymore.
+    let urls = match operation {
+        Operation::SetShared { shared: true, .. } => Vec::new(),
+        _ => cdn::file_urls(&mut *conn, file_id).await?,
+    };
+
+    let result = match operation {
+        Operation::Delete { file_id } => sqlx::query!(
+            "DELETE FROM files
+                WHERE id = $1",
+            file_id.as_slice(),
+        )
+        .execute(conn)
+        .await
+        .map(|result| {
+            (result.rows_affected() > 0).then(|| OperationResult::Delete {
+                file_id: file_id.clone(),
+            })
+        }),
+        Operation::Move {
+            file_id,
+            folder_id: Some(folder_id),
+        } => sqlx::query!(
+            "UPDATE files
+                SET parent_id_path = folders.parent_id_path || folders.id,
+                    parent_name_path = folders.parent_name_path || folders.name
+                FROM folders
+                WHERE files.id = $1
+                    AND folders.id = $2 AND folders.owner_id = files.owner_id
+                RETURNING files.parent_name_path, files.version",
+            file_id.as_slice(),
+            folder_id.as_slice(),
+        )
+        .fetch_optional(conn)
+        .await
+        .map(|file| {
+            file.map(|file| OperationResult::Move {
+                file_id: file_id.clone(),
+                parent_path: file.parent_name_path,
+                version: file.version,
+            })
+        }),
+        Operation::Move {
+            file_id,
+            folder_id: None,
+        } => sqlx::query!(
+            "UPDATE files
+                SET parent_id_path = '{}', parent_name_path = '{}'
+                WHERE id = $1
+                RETURNING parent_name_path, version",
+            file_id.as_slice(),
+        )
+        .fetch_optional(conn)
+        .await
+        .map(|file| {
+            file.map(|file| OperationResult::Move {
+                file_id: file_id.clone(),
+                parent_path: file.parent_name_path,
+                version: file.version,
+            })
+        }),
+        Operation::SetShared { file_id, shared } => sqlx::query!(
+            "UPDATE files
+                SET shared = $2
+                WHERE id = $1
+                RETURNING shared, version",
+            file_id.as_slice(),
+            shared,
+        )
+        .fetch_optional(conn)
+        .await
+        .map(|file| {
+            file.map(|file| OperationResult::SetShared {
+                file_id: file_id.clone(),
+                shared: file.shared,
+                version: file.version,
+            })
+        }),
+    };
+
+    match result {
+        Err(sqlx::Error::Database(error))
+            if error.constraint() == Some("files_owner_id_parent_name_path_name_key") =>
+        {
+            Err(TxError::Abort(api::Error::FileNameTaken))
+        }
+        Err(sqlx::Error::Database(error)) if error.constraint() == Some("legal_hold") => {
+            Err(TxError::Abort(api::Error::LegalHoldActive))
+        }
+        result => {
+            let Some(result) = result? else {
+                return Err(TxError::Abort(api::Error::ResourceNotFound));
+            };
+
+            Ok((result, urls))
+        }
+    }
+}
@@ -0,0 +1,77 @@
+//! Whether a file is starred by its owner, pinning it to their favorites.
+
+use axum::{extract::State, http::StatusCode};
+use axum_macros::debug_handler;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{self, auth::Auth, Json, Path, Response},
+    db::{self, TxError, TxResult},
+    id::Id,
+    AppState,
+};
+
+/// A `PUT` request body for this API route.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct PutRequest {
+    /// Whether the file should be starred.
+    pub favorite: bool,
+}
+
+/// Stars or unstars a file.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn put(
+    State(state): State<AppState>,
+    auth: Auth,
+    Path(file_id): Path<Id>,
+    Json(body): Json<PutRequest>,
+) -> Response<PutResponse> {
+    db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+        let Some(file) = sqlx::query!(
+            "SELECT owner_id FROM files
+                WHERE id = $1",
+            file_id.as_slice(),
+        )
+        .fetch_optional(tx.as_mut())
+        .await?
+        else {
+            return Err(TxError::Abort(api::Error::ResourceNotFound));
+        };
+
+        auth.require_self_or_manager(&file.owner_id.into())?;
+
+        // Starring an already starred file keeps its original position in the user's favorites.
+        sqlx::query!(
+            "UPDATE files
+                SET favorited_at = CASE WHEN $2 THEN coalesce(favorited_at, now()) END
+                WHERE id = $1",
+            file_id.as_slice(),
+            body.favorite,
+        )
+        .execute(tx.as_mut())
+        .await?;
+
+        Ok(())
+    })
+    .await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(PutResponse {
+            favorite: body.favorite,
+        }),
+    ))
+}
+
+/// A `PUT` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PutResponse {
+    /// Whether the file is starred.
+    pub favorite: bool,
+}
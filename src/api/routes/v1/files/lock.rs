@@ -0,0 +1,337 @@
+//! A file's lock, which stops clients other than the lock's holder from changing the file.
+
+use axum::{extract::State, http::StatusCode};
+use axum_macros::debug_handler;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgConnection;
+
+use crate::{
+    api::{
+        self,
+        auth::{Auth, FolderAccess},
+        lock_token::LockToken,
+        Json, Path, Response,
+    },
+    crypto::hash_without_salt,
+    db::{self, TxError, TxResult},
+    id::{Id, Token},
+    AppState,
+};
+
+/// How many seconds a lock lasts if the request doesn't specify a timeout.
+const DEFAULT_TIMEOUT_SECONDS: u32 = 5 * 60;
+
+/// The maximum number of seconds a lock can last before it must be refreshed.
+const MAX_TIMEOUT_SECONDS: u32 = 60 * 60;
+
+/// Checks that a file isn't locked, or that the specified lock token is for its lock.
+///
+/// # Errors
+///
+/// Returns [`api::Error::FileLocked`] if the file has an unexpired lock the token isn't for.
+pub(crate) async fn require_unlocked(
+    conn: &mut PgConnection,
+    file_id: &Id,
+    lock_token: Option<&Token>,
+) -> Result<(), api::Error> {
+    let Some(lock) = sqlx::query!(
+        "SELECT token_hash FROM file_locks
+            WHERE file_id = $1 AND expires_at > now()",
+        file_id.as_slice(),
+    )
+    .fetch_optional(conn)
+    .await?
+    else {
+        return Ok(());
+    };
+
+    if is_lock_token(lock_token, &lock.token_hash) {
+        Ok(())
+    } else {
+        Err(api::Error::FileLocked)
+    }
+}
+
+/// Checks that no file in a folder or any of its subfolders is locked, except by the specified lock
+/// token's lock. Renaming or moving a folder changes the paths of everything in it, so it must not
+/// happen while another client has any of its files locked.
+///
+/// # Errors
+///
+/// Returns [`api::Error::FileLocked`] if a file in the folder has an unexpired lock the token isn't
+/// for.
+pub(crate) async fn require_descendants_unlocked(
+    conn: &mut PgConnection,
+    folder_id: &Id,
+    lock_token: Option<&Token>,
+) -> Result<(), api::Error> {
+    let locks = sqlx::query!(
+        "SELECT file_locks.token_hash FROM file_locks
+            JOIN files ON files.id = file_locks.file_id
+            WHERE $1 = ANY(files.parent_id_path) AND file_locks.expires_at > now()",
+        folder_id.as_slice(),
+    )
+    .fetch_all(conn)
+    .await?;
+
+    if locks
+        .iter()
+        .all(|lock| is_lock_token(lock_token, &lock.token_hash))
+    {
+        Ok(())
+    } else {
+        Err(api::Error::FileLocked)
+    }
+}
+
+/// Checks if the specified lock token is the one with the specified hash.
+fn is_lock_token(lock_token: Option<&Token>, token_hash: &[u8]) -> bool {
+    lock_token.is_some_and(|token| hash_without_salt(token).as_ref() == token_hash)
+}
+
+/// Gets a file's lock, if it has one.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn get(
+    State(state): State<AppState>,
+    auth: Auth,
+    Path(file_id): Path<Id>,
+) -> Response<GetResponse> {
+    let lock = db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+        auth.require_file_access(tx, &file_id, FolderAccess::Read)
+            .await?;
+
+        Ok(sqlx::query!(
+            "SELECT user_id, created_at, expires_at FROM file_locks
+                WHERE file_id = $1 AND expires_at > now()",
+            file_id.as_slice(),
+        )
+        .fetch_optional(tx.as_mut())
+        .await?)
+    })
+    .await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(GetResponse {
+            lock: lock.map(|lock| LockInfo {
+                user_id: lock.user_id.into(),
+                created_at: lock.created_at,
+                expires_at: lock.expires_at,
+            }),
+        }),
+    ))
+}
+
+/// A `GET` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GetResponse {
+    /// The file's lock, or `None` if it isn't locked.
+    pub lock: Option<LockInfo>,
+}
+
+/// Information about a file's lock, not including its token.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct LockInfo {
+    /// The ID of the user who locked the file.
+    pub user_id: Id,
+
+    /// When the file was locked.
+    pub created_at: DateTime<Utc>,
+
+    /// When the lock expires unless it's refreshed.
+    pub expires_at: DateTime<Utc>,
+}
+
+/// A `POST` or `PUT` request body for this API route.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct LockRequest {
+    /// How many seconds the lock should last before it must be refreshed, at most 3600. Defaults
+    /// to 300.
+    pub timeout_seconds: Option<u32>,
+}
+
+impl LockRequest {
+    /// Gets how many seconds the lock should last.
+    fn timeout_seconds(&self) -> u32 {
+        self.timeout_seconds
+            .unwrap_or(DEFAULT_TIMEOUT_SECONDS)
+            .min(MAX_TIMEOUT_SECONDS)
+    }
+}
+
+/// Locks a file, so it can only be changed by requests setting the `Lock-Token` header to the
+/// returned token until it's unlocked or the lock expires.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn post(
+    State(state): State<AppState>,
+    auth: Auth,
+    Path(file_id): Path<Id>,
+    Json(body): Json<LockRequest>,
+) -> Response<PostResponse> {
+    let token = Token::generate()?;
+    let token_hash = hash_without_salt(&token);
+
+    let expires_at = db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+        auth.require_file_access(tx, &file_id, FolderAccess::Write)
+            .await?;
+
+        // An expired lock is replaced, but an unexpired one is left alone.
+        let Some(lock) = sqlx::query!(
+            "INSERT INTO file_locks (file_id, token_hash, user_id, expires_at)
+                VALUES ($1, $2, $3, now() + make_interval(secs => $4))
+                ON CONFLICT (file_id) DO UPDATE
+                    SET created_at = excluded.created_at, token_hash = excluded.token_hash,
+                        user_id = excluded.user_id, expires_at = excluded.expires_at
+                    WHERE file_locks.expires_at <= now()
+                RETURNING expires_at",
+            file_id.as_slice(),
+            token_hash.as_ref(),
+            auth.user_id.as_slice(),
+            f64::from(body.timeout_seconds()),
+        )
+        .fetch_optional(tx.as_mut())
+        .await?
+        else {
+            return Err(TxError::Abort(api::Error::FileLocked));
+        };
+
+        Ok(lock.expires_at)
+    })
+    .await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(PostResponse {
+            token: token.to_string(),
+            expires_at,
+        }),
+    ))
+}
+
+/// A `POST` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PostResponse {
+    /// The lock's token, to set the `Lock-Token` header to in requests changing, refreshing, or
+    /// unlocking the file.
+    pub token: String,
+
+    /// When the lock expires unless it's refreshed.
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Refreshes a file's lock, extending it to expire the requested number of seconds from now. The
+/// `Lock-Token` header must be set to the lock's token.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn put(
+    State(state): State<AppState>,
+    auth: Auth,
+    Path(file_id): Path<Id>,
+    LockToken(lock_token): LockToken,
+    Json(body): Json<LockRequest>,
+) -> Response<PutResponse> {
+    let expires_at = db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+        auth.require_file_access(tx, &file_id, FolderAccess::Write)
+            .await?;
+
+        let Some(lock) = sqlx::query!(
+            "SELECT token_hash FROM file_locks
+                WHERE file_id = $1 AND expires_at > now()
+                FOR UPDATE",
+            file_id.as_slice(),
+        )
+        .fetch_optional(tx.as_mut())
+        .await?
+        else {
+            return Err(TxError::Abort(api::Error::ResourceNotFound));
+        };
+
+        if !is_lock_token(lock_token.as_ref(), &lock.token_hash) {
+            return Err(TxError::Abort(api::Error::FileLocked));
+        }
+
+        let lock = sqlx::query!(
+            "UPDATE file_locks
+                SET expires_at = now() + make_interval(secs => $2)
+                WHERE file_id = $1
+                RETURNING expires_at",
+            file_id.as_slice(),
+            f64::from(body.timeout_seconds()),
+        )
+        .fetch_one(tx.as_mut())
+        .await?;
+
+        Ok(lock.expires_at)
+    })
+    .await?;
+
+    Ok((StatusCode::OK, Json(PutResponse { expires_at })))
+}
+
+/// A `PUT` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PutResponse {
+    /// When the lock expires unless it's refreshed again.
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Unlocks a file. The `Lock-Token` header must be set to the lock's token.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn delete(
+    State(state): State<AppState>,
+    auth: Auth,
+    Path(file_id): Path<Id>,
+    LockToken(lock_token): LockToken,
+) -> Response<DeleteResponse> {
+    db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+        auth.require_file_access(tx, &file_id, FolderAccess::Write)
+            .await?;
+
+        let Some(lock) = sqlx::query!(
+            "DELETE FROM file_locks
+                WHERE file_id = $1 AND expires_at > now()
+                RETURNING token_hash",
+            file_id.as_slice(),
+        )
+        .fetch_optional(tx.as_mut())
+        .await?
+        else {
+            return Err(TxError::Abort(api::Error::ResourceNotFound));
+        };
+
+        if !is_lock_token(lock_token.as_ref(), &lock.token_hash) {
+            return Err(TxError::Abort(api::Error::FileLocked));
+        }
+
+        Ok(())
+    })
+    .await?;
+
+    Ok((StatusCode::OK, Json(DeleteResponse {})))
+}
+
+/// A `DELETE` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteResponse {}
@@ -0,0 +1,121 @@
+//! A QR code linking to a file, for transferring its link to other devices.
+
+use std::io::Cursor;
+
+use axum::{extract::State, http::header::CONTENT_TYPE, response::IntoResponse};
+use axum_macros::debug_handler;
+use image::{ImageFormat, Luma};
+use qrcode::{render::svg, QrCode};
+use serde::Deserialize;
+
+use crate::{
+    api::{
+        self,
+        auth::{Auth, FolderAccess},
+        Path, Query,
+    },
+    content::{canonical_file_url, SHORT_LINK_PATH_PREFIX},
+    db::{self, TxError, TxResult},
+    id::Id,
+    AppState, CONTENT_ORIGIN,
+};
+
+/// The minimum width and height of a rendered QR code, in pixels.
+const MIN_SIZE: u32 = 256;
+
+/// An image format to render a QR code in.
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum Format {
+    /// An SVG image.
+    #[default]
+    Svg,
+
+    /// A PNG image.
+    Png,
+}
+
+/// A `GET` request query for this API route.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GetQuery {
+    /// The image format to render the QR code in. Defaults to SVG.
+    pub format: Option<Format>,
+}
+
+/// Renders a QR code linking to a file. This uses the file's short link if it has one, since
+/// shorter URLs make simpler QR codes. The file must be accessible to anyone with its link.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn get(
+    State(state): State<AppState>,
+    auth: Auth,
+    Path(file_id): Path<Id>,
+    Query(query): Query<GetQuery>,
+) -> Result<impl IntoResponse, api::Error> {
+    let url = db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+        auth.require_file_access(tx, &file_id, FolderAccess::Read)
+            .await?;
+
+        let file = sqlx::query!(
+            r#"SELECT files.owner_id, users.handle::text, files.name, files.parent_name_path,
+                files.shared, short_links.code as "short_link_code?"
+                FROM files
+                JOIN users ON users.id = files.owner_id
+                LEFT JOIN short_links ON short_links.file_id = files.id
+                WHERE files.id = $1"#,
+            file_id.as_slice(),
+        )
+        .fetch_one(tx.as_mut())
+        .await?;
+
+        if !file.shared {
+            return Err(TxError::Abort(api::Error::FileNotShared));
+        }
+
+        if let Some(code) = file.short_link_code {
+            return Ok(format!(
+                "{}{SHORT_LINK_PATH_PREFIX}{}",
+                *CONTENT_ORIGIN,
+                Id::from(code),
+            ));
+        }
+
+        Ok(canonical_file_url(
+            tx,
+            &file.owner_id.into(),
+            file.handle.as_deref(),
+            &file.parent_name_path,
+            &file.name,
+        )
+        .await?)
+    })
+    .await?;
+
+    let code = QrCode::new(url)?;
+
+    Ok(match query.format.unwrap_or_default() {
+        Format::Svg => {
+            let image = code
+                .render::<svg::Color>()
+                .min_dimensions(MIN_SIZE, MIN_SIZE)
+                .build();
+
+            ([(CONTENT_TYPE, "image/svg+xml")], image.into_bytes())
+        }
+        Format::Png => {
+            let image = code
+                .render::<Luma<u8>>()
+                .min_dimensions(MIN_SIZE, MIN_SIZE)
+                .build();
+
+            let mut bytes = Vec::new();
+            image.write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)?;
+
+            ([(CONTENT_TYPE, "image/png")], bytes)
+        }
+    })
+}
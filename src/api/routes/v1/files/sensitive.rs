@@ -0,0 +1,102 @@
+//! Whether a file is marked as sensitive, so visitors are warned before seeing it.
+
+use axum::{extract::State, http::StatusCode};
+use axum_macros::debug_handler;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{
+        self,
+        auth::{Auth, FolderAccess, Permission},
+        lock_token::LockToken,
+        routes::v1::files::lock,
+        Json, Path, Response,
+    },
+    cdn,
+    db::{self, TxResult},
+    id::Id,
+    AppState,
+};
+
+/// A `PUT` request body for this API route.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct PutRequest {
+    /// Whether the file should be marked as sensitive.
+    pub sensitive: bool,
+}
+
+/// Marks or unmarks a file as sensitive.
+///
+/// Users with [`Permission::ModerateFiles`] set a separate mark that only they can clear, and which
+/// keeps the file sensitive however its owner or collaborators set theirs. Anyone else needs write
+/// access to the file. If the file is locked, the `Lock-Token` header must be set to its lock's
+/// token.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn put(
+    State(state): State<AppState>,
+    auth: Auth,
+    Path(file_id): Path<Id>,
+    LockToken(lock_token): LockToken,
+    Json(body): Json<PutRequest>,
+) -> Response<PutResponse> {
+    let is_moderator = auth.require(Permission::ModerateFiles).is_ok();
+
+    let Some((file, urls)) =
+        db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+            if !is_moderator {
+                auth.require_file_access(tx, &file_id, FolderAccess::Write)
+                    .await?;
+            }
+
+            lock::require_unlocked(tx, &file_id, lock_token.as_ref()).await?;
+
+            let urls = cdn::file_urls(tx, &file_id).await?;
+
+            let file = sqlx::query!(
+                "UPDATE files
+                SET sensitive = CASE WHEN $1 THEN sensitive ELSE $2 END,
+                    sensitive_by_moderator = CASE WHEN $1 THEN $2 ELSE sensitive_by_moderator END
+                WHERE id = $3
+                RETURNING sensitive, sensitive_by_moderator",
+                is_moderator,
+                body.sensitive,
+                file_id.as_slice(),
+            )
+            .fetch_optional(tx.as_mut())
+            .await?;
+
+            Ok(file.map(|file| (file, urls)))
+        })
+        .await?
+    else {
+        return Err(api::Error::ResourceNotFound);
+    };
+
+    // Otherwise, the CDN would keep serving the file without its label or warning page.
+    cdn::purge(urls);
+
+    Ok((
+        StatusCode::OK,
+        Json(PutResponse {
+            sensitive: file.sensitive || file.sensitive_by_moderator,
+            sensitive_by_moderator: file.sensitive_by_moderator,
+        }),
+    ))
+}
+
+/// A `PUT` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PutResponse {
+    /// Whether the file is marked as sensitive by anyone.
+    pub sensitive: bool,
+
+    /// Whether a moderator marked the file as sensitive, in which case only a moderator can unmark
+    /// it.
+    pub sensitive_by_moderator: bool,
+}
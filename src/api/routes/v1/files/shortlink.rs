@@ -0,0 +1,184 @@
+//! A file's short link, a compact URL redirecting to the file.
+
+use axum::{extract::State, http::StatusCode};
+use axum_macros::debug_handler;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::Acquire;
+
+use crate::{
+    api::{
+        self,
+        auth::{Auth, FolderAccess},
+        Json, Path, Response,
+    },
+    content::SHORT_LINK_PATH_PREFIX,
+    db::{self, TxError, TxResult},
+    id::{Id, ShortLinkCode},
+    AppState, CONTENT_ORIGIN,
+};
+
+/// Gets a file's short link.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn get(
+    State(state): State<AppState>,
+    auth: Auth,
+    Path(file_id): Path<Id>,
+) -> Response<ShortLink> {
+    let short_link = db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+        auth.require_file_access(tx, &file_id, FolderAccess::Read)
+            .await?;
+
+        let Some(short_link) = sqlx::query!(
+            "SELECT code, clicks, created_at FROM short_links
+                WHERE file_id = $1",
+            file_id.as_slice(),
+        )
+        .fetch_optional(tx.as_mut())
+        .await?
+        else {
+            return Err(TxError::Abort(api::Error::ResourceNotFound));
+        };
+
+        Ok(ShortLink::new(
+            Id::from(short_link.code),
+            short_link.clicks,
+            short_link.created_at,
+        ))
+    })
+    .await?;
+
+    Ok((StatusCode::OK, Json(short_link)))
+}
+
+/// Creates a short link for a file, or gets its existing one. The short link only redirects while
+/// the file is accessible to anyone with its link.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn post(
+    State(state): State<AppState>,
+    auth: Auth,
+    Path(file_id): Path<Id>,
+) -> Response<ShortLink> {
+    let mut code = ShortLinkCode::generate()?;
+
+    let short_link = db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+        auth.require_file_access(tx, &file_id, FolderAccess::Write)
+            .await?;
+
+        loop {
+            // If this loop's query fails from a code conflict, this savepoint is rolled back to
+            // rather than aborting the entire transaction.
+            let mut savepoint = tx.begin().await?;
+
+            // Updating the existing short link on conflict lets it be returned.
+            let short_link = match sqlx::query!(
+                "INSERT INTO short_links (code, file_id)
+                    VALUES ($1, $2)
+                    ON CONFLICT (file_id) DO UPDATE
+                        SET file_id = excluded.file_id
+                    RETURNING code, clicks, created_at",
+                code.as_slice(),
+                file_id.as_slice(),
+            )
+            .fetch_one(savepoint.as_mut())
+            .await
+            {
+                Err(sqlx::Error::Database(error))
+                    if error.constraint() == Some("short_links_pkey") =>
+                {
+                    code.reroll()?;
+                    continue;
+                }
+                result => result?,
+            };
+
+            savepoint.commit().await?;
+
+            break Ok(ShortLink::new(
+                Id::from(short_link.code),
+                short_link.clicks,
+                short_link.created_at,
+            ));
+        }
+    })
+    .await?;
+
+    Ok((StatusCode::OK, Json(short_link)))
+}
+
+/// Revokes a file's short link, so it no longer redirects. A new short link created for the file
+/// afterward has a different code.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn delete(
+    State(state): State<AppState>,
+    auth: Auth,
+    Path(file_id): Path<Id>,
+) -> Response<DeleteResponse> {
+    db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+        auth.require_file_access(tx, &file_id, FolderAccess::Write)
+            .await?;
+
+        let Some(_) = sqlx::query!(
+            "DELETE FROM short_links
+                WHERE file_id = $1
+                RETURNING 1 as deleted",
+            file_id.as_slice(),
+        )
+        .fetch_optional(tx.as_mut())
+        .await?
+        else {
+            return Err(TxError::Abort(api::Error::ResourceNotFound));
+        };
+
+        Ok(())
+    })
+    .await?;
+
+    Ok((StatusCode::OK, Json(DeleteResponse {})))
+}
+
+/// A `DELETE` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteResponse {}
+
+/// A response body for this API route, containing a file's short link.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ShortLink {
+    /// The short link's code.
+    pub code: Id,
+
+    /// The short link's URL.
+    pub url: String,
+
+    /// How many times the short link has been followed.
+    pub clicks: i64,
+
+    /// When the short link was created.
+    pub created_at: DateTime<Utc>,
+}
+
+impl ShortLink {
+    /// Constructs a [`ShortLink`] from its code, click count, and creation time.
+    fn new(code: Id, clicks: i64, created_at: DateTime<Utc>) -> Self {
+        Self {
+            url: format!("{}{SHORT_LINK_PATH_PREFIX}{code}", *CONTENT_ORIGIN),
+            code,
+            clicks,
+            created_at,
+        }
+    }
+}
@@ -0,0 +1,118 @@
+//! A file's status, determining whether it's available or why it isn't.
+
+use axum::{extract::State, http::StatusCode};
+use axum_macros::debug_handler;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{
+        self,
+        auth::{Auth, Permission},
+        lock_token::LockToken,
+        routes::v1::files::{lock, FileStatus, TakedownReason},
+        FieldError, Json, Path, Response,
+    },
+    cdn,
+    db::{self, TxResult},
+    id::Id,
+    AppState,
+};
+
+/// A `PUT` request body for this API route.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct PutRequest {
+    /// The file's new status.
+    pub status: FileStatus,
+
+    /// Why the file is being taken down. This must be set if and only if the new status is
+    /// [`FileStatus::TakenDown`].
+    pub takedown_reason: Option<TakedownReason>,
+}
+
+/// Sets a file's status, such as to quarantine it or take it down.
+///
+/// Files taken down for legal reasons are served with `451 Unavailable For Legal Reasons`, and
+/// files taken down for violating the terms of service are served with `410 Gone`.
+///
+/// If the file is locked, the `Lock-Token` header must be set to its lock's token.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn put(
+    State(state): State<AppState>,
+    auth: Auth,
+    Path(file_id): Path<Id>,
+    LockToken(lock_token): LockToken,
+    Json(body): Json<PutRequest>,
+) -> Response<PutResponse> {
+    auth.require(Permission::ModerateFiles)?;
+
+    match (body.status, body.takedown_reason) {
+        (FileStatus::TakenDown, None) => {
+            return Err(api::Error::InvalidBodyData(FieldError {
+                field: "takedownReason".into(),
+                code: "MISSING_FIELD",
+                message: "missing field `takedownReason`".into(),
+            }));
+        }
+        (FileStatus::TakenDown, Some(_)) | (_, None) => {}
+        (_, Some(_)) => {
+            return Err(api::Error::InvalidBodyData(FieldError {
+                field: "takedownReason".into(),
+                code: "INVALID_VALUE",
+                message: "a takedown reason can only be set for a file being taken down".into(),
+            }));
+        }
+    }
+
+    let Some((file, urls)) =
+        db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+            lock::require_unlocked(tx, &file_id, lock_token.as_ref()).await?;
+
+            let urls = cdn::file_urls(tx, &file_id).await?;
+
+            let file = sqlx::query!(
+                r#"UPDATE files
+                SET status = $1, takedown_reason = $2
+                WHERE id = $3
+                RETURNING status as "status: FileStatus",
+                    takedown_reason as "takedown_reason: TakedownReason""#,
+                body.status as FileStatus,
+                body.takedown_reason as Option<TakedownReason>,
+                file_id.as_slice(),
+            )
+            .fetch_optional(tx.as_mut())
+            .await?;
+
+            Ok(file.map(|file| (file, urls)))
+        })
+        .await?
+    else {
+        return Err(api::Error::ResourceNotFound);
+    };
+
+    // Otherwise, the CDN would keep serving the file as it was before.
+    cdn::purge(urls);
+
+    Ok((
+        StatusCode::OK,
+        Json(PutResponse {
+            status: file.status,
+            takedown_reason: file.takedown_reason,
+        }),
+    ))
+}
+
+/// A `PUT` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PutResponse {
+    /// The file's status.
+    pub status: FileStatus,
+
+    /// Why the file was taken down, if it was.
+    pub takedown_reason: Option<TakedownReason>,
+}
@@ -0,0 +1,80 @@
+//! A temporary URL for the signed-in user to view a file they have access to, even if it isn't
+//! accessible to anyone with its link.
+
+use axum::{extract::State, http::StatusCode};
+use axum_macros::debug_handler;
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::Serialize;
+
+use crate::{
+    api::{
+        self,
+        auth::{Auth, FolderAccess},
+        Json, Path, Response,
+    },
+    content::{canonical_file_url, viewer_param, FILE_ID_QUERY_PREFIX, VIEWER_QUERY_PREFIX},
+    db::{self, TxResult},
+    id::Id,
+    AppState,
+};
+
+/// How long a view URL works for after it's issued.
+const VIEW_URL_MAX_AGE: TimeDelta = TimeDelta::hours(1);
+
+/// Gets a URL the signed-in user can view a file's raw content at until it expires. The file is
+/// only served while the user still owns it or has access to a folder it's in.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn get(
+    State(state): State<AppState>,
+    auth: Auth,
+    Path(file_id): Path<Id>,
+) -> Response<GetResponse> {
+    let expires_at = Utc::now() + VIEW_URL_MAX_AGE;
+
+    let url = db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+        auth.require_file_access(tx, &file_id, FolderAccess::Read)
+            .await?;
+
+        let file = sqlx::query!(
+            "SELECT files.owner_id, users.handle::text, files.name, files.parent_name_path
+                FROM files
+                JOIN users ON users.id = files.owner_id
+                WHERE files.id = $1",
+            file_id.as_slice(),
+        )
+        .fetch_one(tx.as_mut())
+        .await?;
+
+        Ok(canonical_file_url(
+            tx,
+            &file.owner_id.into(),
+            file.handle.as_deref(),
+            &file.parent_name_path,
+            &file.name,
+        )
+        .await?)
+    })
+    .await?;
+
+    let url = format!(
+        "{url}?{FILE_ID_QUERY_PREFIX}{file_id}&{VIEWER_QUERY_PREFIX}{}",
+        viewer_param(&auth.user_id, expires_at.timestamp()),
+    );
+
+    Ok((StatusCode::OK, Json(GetResponse { url, expires_at })))
+}
+
+/// A `GET` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GetResponse {
+    /// The URL to view the file at.
+    pub url: String,
+
+    /// When the URL stops working.
+    pub expires_at: DateTime<Utc>,
+}
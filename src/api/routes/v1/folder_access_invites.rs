@@ -0,0 +1,96 @@
+//! Acceptance of invites to access folders, sent by email.
+
+use axum::{extract::State, http::StatusCode};
+use axum_macros::debug_handler;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{
+        self,
+        auth::{Auth, FolderAccess},
+        routes::v1::invites::INVITE_MAX_AGE,
+        Json, Response,
+    },
+    crypto::hash_without_salt,
+    db::{self, TxError, TxResult},
+    id::{Id, Token},
+    AppState,
+};
+
+/// A `POST` request body for this API route.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct PostRequest {
+    /// The invite token from the invite email.
+    pub token: Token,
+}
+
+/// Accepts an invite to access a folder, granting the signed-in user the access it was for. The
+/// invite must have been sent to the user's email, and expires like an invite to sign up.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn post(
+    State(state): State<AppState>,
+    auth: Auth,
+    Json(body): Json<PostRequest>,
+) -> Response<PostResponse> {
+    let token_hash = hash_without_salt(&body.token);
+
+    let (folder_id, access) =
+        db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+            let Some(invite) = sqlx::query!(
+                r#"DELETE FROM folder_access_invites
+                    USING users, folders
+                    WHERE folder_access_invites.token_hash = $1
+                        AND folder_access_invites.created_at > now() - make_interval(secs => $3)
+                        AND users.id = $2
+                        AND users.email = folder_access_invites.email
+                        AND folders.id = folder_access_invites.folder_id
+                    RETURNING folder_access_invites.folder_id,
+                        folder_access_invites.access as "access: FolderAccess",
+                        folders.owner_id"#,
+                token_hash.as_ref(),
+                auth.user_id.as_slice(),
+                INVITE_MAX_AGE.as_secs_f64(),
+            )
+            .fetch_optional(tx.as_mut())
+            .await?
+            else {
+                return Err(TxError::Abort(api::Error::ResourceNotFound));
+            };
+
+            // A folder's owner already has access to it, so there's nothing to grant.
+            if invite.owner_id != auth.user_id.as_slice() {
+                sqlx::query!(
+                    "INSERT INTO folder_access_grants (folder_id, user_id, access)
+                        VALUES ($1, $2, $3)
+                        ON CONFLICT (folder_id, user_id) DO UPDATE
+                            SET access = excluded.access",
+                    invite.folder_id,
+                    auth.user_id.as_slice(),
+                    invite.access as FolderAccess,
+                )
+                .execute(tx.as_mut())
+                .await?;
+            }
+
+            Ok((Id::from(invite.folder_id), invite.access))
+        })
+        .await?;
+
+    Ok((StatusCode::OK, Json(PostResponse { folder_id, access })))
+}
+
+/// A `POST` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PostResponse {
+    /// The ID of the folder the invite was for.
+    pub folder_id: Id,
+
+    /// The level of access granted.
+    pub access: FolderAccess,
+}
@@ -0,0 +1,255 @@
+//! The set of all folders.
+
+use std::num::NonZeroU16;
+
+use axum::{extract::State, http::StatusCode};
+use axum_macros::debug_handler;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{
+        self,
+        auth::{Auth, FolderAccess},
+        if_match::{ETag, IfMatch},
+        lock_token::LockToken,
+        routes::v1::files::lock,
+        validation::FileName,
+        Json, Path,
+    },
+    db::{self, TxError, TxResult},
+    id::Id,
+    AppState,
+};
+
+pub mod access;
+pub mod access_invites;
+pub mod favorite;
+pub mod retention;
+
+/// Gets a folder's metadata.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn get(
+    State(state): State<AppState>,
+    auth: Auth,
+    Path(folder_id): Path<Id>,
+) -> Result<(StatusCode, ETag, Json<FolderMetadata>), api::Error> {
+    let Some(folder) = db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+        auth.require_folder_access(tx, &folder_id, FolderAccess::Read)
+            .await?;
+
+        Ok(sqlx::query_as!(
+            FolderMetadataRow,
+            "SELECT id, name, parent_name_path, size, file_count, retention_days,
+                created_at, version
+                FROM folders
+                WHERE id = $1",
+            folder_id.as_slice(),
+        )
+        .fetch_optional(tx.as_mut())
+        .await?)
+    })
+    .await?
+    else {
+        return Err(api::Error::ResourceNotFound);
+    };
+
+    Ok((StatusCode::OK, ETag(folder.version), Json(folder.into())))
+}
+
+/// A `PATCH` request body for this API route.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct PatchRequest {
+    /// The folder's new name, if it should be renamed.
+    pub name: Option<FileName>,
+}
+
+/// Changes a folder's metadata. The `If-Match` header must be set to the folder's current version.
+/// Renaming the folder changes the paths of its files, so if any of them is locked, the `Lock-Token`
+/// header must be set to its lock's token.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn patch(
+    State(state): State<AppState>,
+    auth: Auth,
+    Path(folder_id): Path<Id>,
+    IfMatch(version): IfMatch,
+    LockToken(lock_token): LockToken,
+    Json(body): Json<PatchRequest>,
+) -> Result<(StatusCode, ETag, Json<FolderMetadata>), api::Error> {
+    let folder = db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+        // Lock the folder so its version can't change between checking and updating it.
+        let Some(folder) = sqlx::query!(
+            "SELECT owner_id, version, cardinality(parent_id_path) + 1 as \"depth!\"
+                FROM folders
+                WHERE id = $1
+                FOR UPDATE",
+            folder_id.as_slice(),
+        )
+        .fetch_optional(tx.as_mut())
+        .await?
+        else {
+            return Err(TxError::Abort(api::Error::ResourceNotFound));
+        };
+
+        auth.require_folder_access(tx, &folder_id, FolderAccess::Write)
+            .await?;
+
+        if version.is_some_and(|version| version != folder.version) {
+            return Err(TxError::Abort(api::Error::PreconditionFailed));
+        }
+
+        if let Some(name) = &body.name {
+            lock::require_descendants_unlocked(tx, &folder_id, lock_token.as_ref()).await?;
+
+            match sqlx::query!(
+                "UPDATE folders
+                    SET name = $2
+                    WHERE id = $1",
+                folder_id.as_slice(),
+                name.as_str(),
+            )
+            .execute(tx.as_mut())
+            .await
+            {
+                Err(sqlx::Error::Database(error))
+                    if error.constraint() == Some("folders_owner_id_parent_name_path_name_key") =>
+                {
+                    return Err(TxError::Abort(api::Error::FolderNameTaken));
+                }
+                Err(sqlx::Error::Database(error))
+                    if error.constraint() == Some("organization_storage_folder_names") =>
+                {
+                    return Err(TxError::Abort(api::Error::StorageFolderNameTaken));
+                }
+                result => result?,
+            };
+
+            // The folder's name is at the same index of each of its descendants' name paths as its
+            // ID is in their ID paths.
+            sqlx::query!(
+                "UPDATE folders
+                    SET parent_name_path[$3] = $2
+                    WHERE owner_id = $4 AND parent_id_path[$3] = $1",
+                folder_id.as_slice(),
+                name.as_str(),
+                folder.depth,
+                folder.owner_id,
+            )
+            .execute(tx.as_mut())
+            .await?;
+
+            sqlx::query!(
+                "UPDATE files
+                    SET parent_name_path[$3] = $2
+                    WHERE owner_id = $4 AND parent_id_path[$3] = $1",
+                folder_id.as_slice(),
+                name.as_str(),
+                folder.depth,
+                folder.owner_id,
+            )
+            .execute(tx.as_mut())
+            .await?;
+        }
+
+        Ok(sqlx::query_as!(
+            FolderMetadataRow,
+            "SELECT id, name, parent_name_path, size, file_count, retention_days,
+                created_at, version
+                FROM folders
+                WHERE id = $1",
+            folder_id.as_slice(),
+        )
+        .fetch_one(tx.as_mut())
+        .await?)
+    })
+    .await?;
+
+    Ok((StatusCode::OK, ETag(folder.version), Json(folder.into())))
+}
+
+/// A folder's metadata as stored in the database.
+#[derive(Debug)]
+struct FolderMetadataRow {
+    /// See [`FolderMetadata::id`].
+    id: Vec<u8>,
+
+    /// See [`FolderMetadata::name`].
+    name: String,
+
+    /// See [`FolderMetadata::parent_path`].
+    parent_name_path: Vec<String>,
+
+    /// See [`FolderMetadata::size`].
+    size: i64,
+
+    /// See [`FolderMetadata::file_count`].
+    file_count: i64,
+
+    /// See [`FolderMetadata::retention_days`].
+    retention_days: Option<i32>,
+
+    /// See [`FolderMetadata::created_at`].
+    created_at: DateTime<Utc>,
+
+    /// See [`FolderMetadata::version`].
+    version: i32,
+}
+
+impl From<FolderMetadataRow> for FolderMetadata {
+    fn from(row: FolderMetadataRow) -> Self {
+        Self {
+            id: row.id.into(),
+            name: row.name,
+            parent_path: row.parent_name_path,
+            size: row.size,
+            file_count: row.file_count,
+            retention_days: row
+                .retention_days
+                .and_then(|days| u16::try_from(days).ok())
+                .and_then(NonZeroU16::new),
+            created_at: row.created_at,
+            version: row.version,
+        }
+    }
+}
+
+/// A response body for this API route, containing a folder's metadata.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderMetadata {
+    /// The folder's ID.
+    pub id: Id,
+
+    /// The folder's name.
+    pub name: String,
+
+    /// The names of the folder's ancestor folders.
+    pub parent_path: Vec<String>,
+
+    /// The total size of the folder's files in bytes, including those in subfolders.
+    pub size: i64,
+
+    /// The total number of the folder's files, including those in subfolders.
+    pub file_count: i64,
+
+    /// How many days after their creation the folder's files are deleted, or `None` if they're
+    /// kept indefinitely.
+    pub retention_days: Option<NonZeroU16>,
+
+    /// When the folder was created.
+    pub created_at: DateTime<Utc>,
+
+    /// The version of the folder's metadata, which increments whenever it changes. Changes must set
+    /// the `If-Match` header to this as an entity tag (e.g. `"3"`), which is also the response's
+    /// `ETag` header.
+    pub version: i32,
+}
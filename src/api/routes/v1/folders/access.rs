@@ -0,0 +1,381 @@
+//! The access other users were granted to a folder and everything in it.
+
+use axum::{extract::State, http::StatusCode};
+use axum_macros::debug_handler;
+use chrono::{DateTime, Utc};
+use lettre::message::Mailbox;
+use serde::{Deserialize, Serialize};
+use sqlx::{Acquire, PgConnection};
+
+use crate::{
+    api::{
+        self,
+        auth::{Auth, FolderAccess},
+        validation::{BoundedString, UserEmail},
+        FieldError, Json, Path, Response,
+    },
+    crypto::hash_without_salt,
+    db::{self, TxError, TxResult},
+    email::{FolderAccessInviteMessage, MessageTemplate, SendMessage},
+    id::{Id, Token},
+    AppState, WEBSITE_ORIGIN,
+};
+
+/// Gets the owner of a folder, checking the signed-in user is allowed to manage who can access it.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+pub(super) async fn require_folder_owner(
+    conn: &mut PgConnection,
+    auth: &Auth,
+    folder_id: &Id,
+) -> Result<Id, api::Error> {
+    let Some(folder) = sqlx::query!(
+        "SELECT owner_id FROM folders
+            WHERE id = $1",
+        folder_id.as_slice(),
+    )
+    .fetch_optional(conn)
+    .await?
+    else {
+        return Err(api::Error::ResourceNotFound);
+    };
+
+    let owner_id = folder.owner_id.into();
+    auth.require_self_or_manager(&owner_id)?;
+
+    Ok(owner_id)
+}
+
+/// Lists the users granted access to a folder and the emails invited to access it. This doesn't
+/// include access granted to folders it's in.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn get(
+    State(state): State<AppState>,
+    auth: Auth,
+    Path(folder_id): Path<Id>,
+) -> Response<GetResponse> {
+    let (grants, invites) =
+        db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+            require_folder_owner(tx, &auth, &folder_id).await?;
+
+            let grants = sqlx::query!(
+                r#"SELECT users.id, users.name, users.handle::text, folder_access_grants.access
+                    as "access: FolderAccess", folder_access_grants.created_at
+                FROM folder_access_grants
+                JOIN users ON users.id = folder_access_grants.user_id
+                WHERE folder_access_grants.folder_id = $1
+                ORDER BY folder_access_grants.created_at"#,
+                folder_id.as_slice(),
+            )
+            .fetch_all(tx.as_mut())
+            .await?;
+
+            let invites = sqlx::query!(
+                r#"SELECT email::text as "email!", access as "access: FolderAccess", created_at
+                FROM folder_access_invites
+                WHERE folder_id = $1
+                ORDER BY created_at"#,
+                folder_id.as_slice(),
+            )
+            .fetch_all(tx.as_mut())
+            .await?;
+
+            Ok((grants, invites))
+        })
+        .await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(GetResponse {
+            grants: grants
+                .into_iter()
+                .map(|grant| AccessGrant {
+                    user_id: grant.id.into(),
+                    name: grant.name,
+                    handle: grant.handle,
+                    access: grant.access,
+                    created_at: grant.created_at,
+                })
+                .collect(),
+            invites: invites
+                .into_iter()
+                .map(|invite| AccessInvite {
+                    email: invite.email,
+                    access: invite.access,
+                    created_at: invite.created_at,
+                })
+                .collect(),
+        }),
+    ))
+}
+
+/// A `GET` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GetResponse {
+    /// The access granted to other users, oldest first.
+    pub grants: Vec<AccessGrant>,
+
+    /// The invites to access the folder sent by email and not yet accepted, oldest first.
+    pub invites: Vec<AccessInvite>,
+}
+
+/// Access to a folder granted to another user.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessGrant {
+    /// The ID of the user granted access.
+    pub user_id: Id,
+
+    /// The name of the user granted access.
+    pub name: String,
+
+    /// The handle of the user granted access, if they have one.
+    pub handle: Option<String>,
+
+    /// The level of access granted.
+    pub access: FolderAccess,
+
+    /// When the access was granted.
+    pub created_at: DateTime<Utc>,
+}
+
+/// An invite to access a folder sent by email and not yet accepted.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessInvite {
+    /// The email the invite was sent to.
+    pub email: String,
+
+    /// The level of access the invite grants.
+    pub access: FolderAccess,
+
+    /// When the invite was sent.
+    pub created_at: DateTime<Utc>,
+}
+
+/// A `POST` request body for this API route.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct PostRequest {
+    /// The handle of the user to grant access to, or the email address to invite to access it.
+    pub user: BoundedString<1, 254>,
+
+    /// The level of access to grant.
+    pub access: FolderAccess,
+}
+
+/// Grants another user access to a folder and everything in it, replacing any access they were
+/// already granted to it.
+///
+/// A user specified by handle is granted access immediately. A user specified by email is instead
+/// sent an invite they must accept, whether or not an account has that email, and this responds the
+/// same either way so it can't be used to find out which emails have accounts.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn post(
+    State(state): State<AppState>,
+    auth: Auth,
+    Path(folder_id): Path<Id>,
+    Json(body): Json<PostRequest>,
+) -> Response<PostResponse> {
+    // Handles can't contain `@`, so they can never be mistaken for emails.
+    let email = if body.user.contains('@') {
+        Some(body.user.parse::<UserEmail>().map_err(|error| {
+            api::Error::InvalidBodyData(FieldError {
+                field: "user".into(),
+                code: "INVALID_VALUE",
+                message: error.to_string(),
+            })
+        })?)
+    } else {
+        None
+    };
+
+    let user_id = db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+        let owner_id = require_folder_owner(tx, &auth, &folder_id).await?;
+
+        if let Some(email) = &email {
+            invite(tx, &auth, &folder_id, email, body.access).await?;
+            return Ok(None);
+        }
+
+        let Some(user) = sqlx::query!(
+            "SELECT id FROM users
+                WHERE handle = $1::citext",
+            *body.user,
+        )
+        .fetch_optional(tx.as_mut())
+        .await?
+        else {
+            return Err(TxError::Abort(api::Error::ResourceNotFound));
+        };
+
+        let user_id: Id = user.id.into();
+
+        if user_id == owner_id {
+            return Err(TxError::Abort(api::Error::InvalidBodyData(FieldError {
+                field: "user".into(),
+                code: "INVALID_VALUE",
+                message: "a folder's owner already has access to it".into(),
+            })));
+        }
+
+        sqlx::query!(
+            "INSERT INTO folder_access_grants (folder_id, user_id, access)
+                VALUES ($1, $2, $3)
+                ON CONFLICT (folder_id, user_id) DO UPDATE
+                    SET access = excluded.access",
+            folder_id.as_slice(),
+            user_id.as_slice(),
+            body.access as FolderAccess,
+        )
+        .execute(tx.as_mut())
+        .await?;
+
+        Ok(Some(user_id))
+    })
+    .await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(PostResponse {
+            user_id,
+            access: body.access,
+        }),
+    ))
+}
+
+/// Invites an email to access a folder, replacing any previous invite for the email to it, and
+/// emails the invite.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+async fn invite(
+    conn: &mut PgConnection,
+    auth: &Auth,
+    folder_id: &Id,
+    email: &UserEmail,
+    access: FolderAccess,
+) -> Result<(), api::Error> {
+    let Some(folder) = sqlx::query!(
+        "SELECT folders.name, users.name as sharer_name
+            FROM folders, users
+            WHERE folders.id = $1 AND users.id = $2",
+        folder_id.as_slice(),
+        auth.user_id.as_slice(),
+    )
+    .fetch_optional(&mut *conn)
+    .await?
+    else {
+        return Err(api::Error::ResourceNotFound);
+    };
+
+    let mut token = Token::generate()?;
+
+    loop {
+        // If this loop's query fails from a token conflict, this savepoint is rolled back to rather
+        // than aborting the entire transaction.
+        let mut savepoint = conn.begin().await?;
+
+        let token_hash = hash_without_salt(&token);
+
+        match sqlx::query!(
+            "INSERT INTO folder_access_invites (folder_id, email, token_hash, access)
+                VALUES ($1, $2, $3, $4)
+                ON CONFLICT (folder_id, email) DO UPDATE
+                    SET created_at = excluded.created_at, token_hash = excluded.token_hash,
+                        access = excluded.access",
+            folder_id.as_slice(),
+            email.as_str(),
+            token_hash.as_ref(),
+            access as FolderAccess,
+        )
+        .execute(savepoint.as_mut())
+        .await
+        {
+            Err(sqlx::Error::Database(error))
+                if error.constraint() == Some("folder_access_invites_token_hash_key") =>
+            {
+                token.reroll()?;
+                continue;
+            }
+            result => result?,
+        };
+
+        savepoint.commit().await?;
+        break;
+    }
+
+    FolderAccessInviteMessage {
+        sharer_name: &folder.sharer_name,
+        folder_name: &folder.name,
+        can_write: access >= FolderAccess::Write,
+        invite_url: &format!("{}/folder-invite?token={}", *WEBSITE_ORIGIN, token),
+    }
+    .to(Mailbox::new(None, (**email).clone()))
+    .send(conn)
+    .await?;
+
+    Ok(())
+}
+
+/// A `POST` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PostResponse {
+    /// The ID of the user granted access, or `None` if an email was invited instead.
+    pub user_id: Option<Id>,
+
+    /// The level of access granted.
+    pub access: FolderAccess,
+}
+
+/// Revokes a user's access to a folder. This doesn't affect access granted to folders it's in.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn delete(
+    State(state): State<AppState>,
+    auth: Auth,
+    Path((folder_id, user_id)): Path<(Id, Id)>,
+) -> Response<DeleteResponse> {
+    db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+        require_folder_owner(tx, &auth, &folder_id).await?;
+
+        let Some(_) = sqlx::query!(
+            "DELETE FROM folder_access_grants
+                WHERE folder_id = $1 AND user_id = $2
+                RETURNING 1 as deleted",
+            folder_id.as_slice(),
+            user_id.as_slice(),
+        )
+        .fetch_optional(tx.as_mut())
+        .await?
+        else {
+            return Err(TxError::Abort(api::Error::ResourceNotFound));
+        };
+
+        Ok(())
+    })
+    .await?;
+
+    Ok((StatusCode::OK, Json(DeleteResponse {})))
+}
+
+/// A `DELETE` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteResponse {}
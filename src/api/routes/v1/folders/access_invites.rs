@@ -0,0 +1,53 @@
+//! The invites to access a folder sent by email and not yet accepted.
+
+use axum::{extract::State, http::StatusCode};
+use axum_macros::debug_handler;
+use serde::Serialize;
+
+use crate::{
+    api::{
+        self, auth::Auth, routes::v1::folders::access::require_folder_owner, Json, Path, Response,
+    },
+    db::{self, TxError, TxResult},
+    id::Id,
+    AppState,
+};
+
+/// Revokes an invite to access a folder, so it can no longer be accepted.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn delete(
+    State(state): State<AppState>,
+    auth: Auth,
+    Path((folder_id, email)): Path<(Id, String)>,
+) -> Response<DeleteResponse> {
+    db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+        require_folder_owner(tx, &auth, &folder_id).await?;
+
+        let Some(_) = sqlx::query!(
+            "DELETE FROM folder_access_invites
+                WHERE folder_id = $1 AND email = $2
+                RETURNING 1 as deleted",
+            folder_id.as_slice(),
+            email,
+        )
+        .fetch_optional(tx.as_mut())
+        .await?
+        else {
+            return Err(TxError::Abort(api::Error::ResourceNotFound));
+        };
+
+        Ok(())
+    })
+    .await?;
+
+    Ok((StatusCode::OK, Json(DeleteResponse {})))
+}
+
+/// A `DELETE` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteResponse {}
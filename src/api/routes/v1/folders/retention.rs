@@ -0,0 +1,81 @@
+//! A folder's retention policy, which automatically deletes its files once they're old enough.
+
+use std::num::NonZeroU16;
+
+use axum::{extract::State, http::StatusCode};
+use axum_macros::debug_handler;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{self, auth::Auth, Json, Path, Response},
+    db::{self, TxError, TxResult},
+    id::Id,
+    AppState,
+};
+
+/// A `PUT` request body for this API route.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct PutRequest {
+    /// How many days after their creation the folder's files (including those in subfolders) are
+    /// deleted, or `None` to keep them indefinitely.
+    pub retention_days: Option<NonZeroU16>,
+}
+
+/// Sets a folder's retention policy. Files under a legal hold are never deleted by retention
+/// policies.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn put(
+    State(state): State<AppState>,
+    auth: Auth,
+    Path(folder_id): Path<Id>,
+    Json(body): Json<PutRequest>,
+) -> Response<PutResponse> {
+    db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+        let Some(folder) = sqlx::query!(
+            "SELECT owner_id FROM folders
+                WHERE id = $1",
+            folder_id.as_slice(),
+        )
+        .fetch_optional(tx.as_mut())
+        .await?
+        else {
+            return Err(TxError::Abort(api::Error::ResourceNotFound));
+        };
+
+        auth.require_self_or_manager(&folder.owner_id.into())?;
+
+        sqlx::query!(
+            "UPDATE folders
+                SET retention_days = $1
+                WHERE id = $2",
+            body.retention_days.map(|days| i32::from(days.get())),
+            folder_id.as_slice(),
+        )
+        .execute(tx.as_mut())
+        .await?;
+
+        Ok(())
+    })
+    .await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(PutResponse {
+            retention_days: body.retention_days,
+        }),
+    ))
+}
+
+/// A `PUT` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PutResponse {
+    /// How many days after their creation the folder's files are deleted, or `None` if they're
+    /// kept indefinitely.
+    pub retention_days: Option<NonZeroU16>,
+}
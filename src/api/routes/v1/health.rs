@@ -0,0 +1,36 @@
+//! Whether the server can handle requests, for load balancers and uptime monitors to check.
+
+use axum::{extract::State, http::StatusCode};
+use axum_macros::debug_handler;
+use serde::Serialize;
+
+use crate::{
+    api::{self, Json, Response},
+    db::{self, TxResult},
+    AppState,
+};
+
+/// Checks that the server is up and can reach its database. This works even during maintenance, so
+/// the server isn't taken out of rotation by its load balancer.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn get(State(state): State<AppState>) -> Response<GetResponse> {
+    db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+        sqlx::query!("SELECT 1 as one")
+            .fetch_one(tx.as_mut())
+            .await?;
+
+        Ok(())
+    })
+    .await?;
+
+    Ok((StatusCode::OK, Json(GetResponse {})))
+}
+
+/// A `GET` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GetResponse {}
@@ -0,0 +1,137 @@
+//! The set of invites to sign up.
+
+use std::time::Duration;
+
+use axum::{extract::State, http::StatusCode};
+use axum_macros::debug_handler;
+use lettre::message::Mailbox;
+use serde::{Deserialize, Serialize};
+use sqlx::Acquire;
+
+use crate::{
+    api::{
+        self,
+        auth::{Auth, Permission},
+        routes::v1::organizations::OrganizationRole,
+        validation::UserEmail,
+        Json, Response,
+    },
+    crypto::hash_without_salt,
+    db::{self, TxError, TxResult},
+    email::{InviteMessage, MessageTemplate, SendMessage},
+    id::{Id, Token},
+    AppState, WEBSITE_ORIGIN,
+};
+
+pub mod acceptance;
+
+/// How long an invite takes to expire after its creation.
+pub(crate) const INVITE_MAX_AGE: Duration = Duration::from_secs(14 * 24 * 60 * 60);
+
+/// A `POST` request body for this API route.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct PostRequest {
+    /// The ID of the organization the invitee should become a member of when signing up, if any.
+    pub organization_id: Option<Id>,
+
+    /// The email address to send the invite to, if any. If set, only a user signing up with this
+    /// email can accept the invite.
+    pub email: Option<UserEmail>,
+}
+
+/// Creates an invite to sign up. Invites for an organization can be created by its admins and
+/// owners, and other invites require [`Permission::CreateInvites`]. Users who already have an
+/// account can accept an invite for an organization to join it.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn post(
+    State(state): State<AppState>,
+    auth: Auth,
+    Json(body): Json<PostRequest>,
+) -> Response<PostResponse> {
+    let token = db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+        let organization_name = if let Some(organization_id) = &body.organization_id {
+            let Some(organization) = sqlx::query!(
+                r#"SELECT organizations.name, organization_members.role as "role: OrganizationRole"
+                    FROM organizations JOIN organization_members
+                        ON organization_members.organization_id = organizations.id
+                    WHERE organizations.id = $1 AND organization_members.user_id = $2"#,
+                organization_id.as_slice(),
+                auth.user_id.as_slice(),
+            )
+            .fetch_optional(tx.as_mut())
+            .await?
+            else {
+                return Err(TxError::Abort(api::Error::ResourceNotFound));
+            };
+
+            if organization.role < OrganizationRole::Admin {
+                return Err(TxError::Abort(api::Error::PermissionDenied));
+            }
+
+            Some(organization.name)
+        } else {
+            auth.require(Permission::CreateInvites)?;
+
+            None
+        };
+
+        let mut token = Token::generate()?;
+
+        loop {
+            // If this loop's query fails from a token conflict, this savepoint is rolled back to
+            // rather than aborting the entire transaction.
+            let mut savepoint = tx.begin().await?;
+
+            let token_hash = hash_without_salt(&token);
+
+            match sqlx::query!(
+                "INSERT INTO invites (token_hash, created_by, organization_id, email)
+                    VALUES ($1, $2, $3, $4)",
+                token_hash.as_ref(),
+                auth.user_id.as_slice(),
+                body.organization_id.as_ref().map(|id| id.as_slice()),
+                body.email.as_ref().map(UserEmail::as_str),
+            )
+            .execute(savepoint.as_mut())
+            .await
+            {
+                Err(sqlx::Error::Database(error)) if error.constraint() == Some("invites_pkey") => {
+                    token.reroll()?;
+                    continue;
+                }
+                result => result?,
+            };
+
+            savepoint.commit().await?;
+            break;
+        }
+
+        if let Some(email) = &body.email {
+            InviteMessage {
+                organization_name: organization_name.as_deref(),
+                invite_url: &format!("{}/sign-up?invite={}", *WEBSITE_ORIGIN, token),
+            }
+            .to(Mailbox::new(None, (**email).clone()))
+            .send(tx.as_mut())
+            .await?;
+        }
+
+        Ok(token)
+    })
+    .await?;
+
+    Ok((StatusCode::CREATED, Json(PostResponse { token })))
+}
+
+/// A `POST` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PostResponse {
+    /// The invite token, which someone must specify when signing up to accept the invite.
+    pub token: Token,
+}
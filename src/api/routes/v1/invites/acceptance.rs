@@ -0,0 +1,81 @@
+//! Acceptance of invites to organizations by users who already have an account.
+
+use axum::{extract::State, http::StatusCode};
+use axum_macros::debug_handler;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{self, auth::Auth, routes::v1::invites::INVITE_MAX_AGE, Json, Response},
+    crypto::hash_without_salt,
+    db::{self, TxError, TxResult},
+    id::{Id, Token},
+    AppState,
+};
+
+/// A `POST` request body for this API route.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct PostRequest {
+    /// The invite token.
+    pub token: Token,
+}
+
+/// Accepts an invite to an organization, making the signed-in user a member of it. If the invite
+/// was sent to an email, it must be the user's email.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn post(
+    State(state): State<AppState>,
+    auth: Auth,
+    Json(body): Json<PostRequest>,
+) -> Response<PostResponse> {
+    let token_hash = hash_without_salt(&body.token);
+
+    let organization_id = db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+        let Some(invite) = sqlx::query!(
+            r#"DELETE FROM invites
+                USING users
+                WHERE invites.token_hash = $1
+                    AND invites.created_at > now() - make_interval(secs => $3)
+                    AND invites.organization_id IS NOT NULL
+                    AND users.id = $2
+                    AND (invites.email IS NULL OR invites.email = users.email)
+                RETURNING invites.organization_id as "organization_id!""#,
+            token_hash.as_ref(),
+            auth.user_id.as_slice(),
+            INVITE_MAX_AGE.as_secs_f64(),
+        )
+        .fetch_optional(tx.as_mut())
+        .await?
+        else {
+            return Err(TxError::Abort(api::Error::InviteInvalid));
+        };
+
+        // Someone who's already a member keeps their role.
+        sqlx::query!(
+            "INSERT INTO organization_members (organization_id, user_id, role)
+                VALUES ($1, $2, 'member')
+                ON CONFLICT (organization_id, user_id) DO NOTHING",
+            invite.organization_id,
+            auth.user_id.as_slice(),
+        )
+        .execute(tx.as_mut())
+        .await?;
+
+        Ok(Id::from(invite.organization_id))
+    })
+    .await?;
+
+    Ok((StatusCode::OK, Json(PostResponse { organization_id })))
+}
+
+/// A `POST` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PostResponse {
+    /// The ID of the organization the user is now a member of.
+    pub organization_id: Id,
+}
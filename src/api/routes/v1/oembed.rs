@@ -0,0 +1,231 @@
+//! The [oEmbed](https://oembed.com/) provider endpoint for files, letting other websites embed them.
+
+use axum::{
+    extract::State,
+    http::{StatusCode, Uri},
+};
+use axum_macros::debug_handler;
+use percent_encoding::{percent_decode_str, utf8_percent_encode};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{self, routes::v1::files::FileStatus, Json, Query, Response},
+    content::{
+        find_custom_domain_owner, find_route_owner, find_shared_file, parse_file_route_path,
+        RouteOwner, CONTENT_SCHEME,
+    },
+    db::{self, TxResult},
+    percent_encoding::COMPONENT_IGNORING_SLASH,
+    AppState, CONTENT_ORIGIN, WEBSITE_ORIGIN,
+};
+
+/// The width of an embed if its file's dimensions are unknown.
+const DEFAULT_WIDTH: u32 = 640;
+
+/// The height of an image or video embed if its file's dimensions are unknown.
+const DEFAULT_MEDIA_HEIGHT: u32 = 360;
+
+/// The height of an audio embed, fitting a browser's default audio controls.
+const AUDIO_HEIGHT: u32 = 54;
+
+/// A response format for the oEmbed endpoint.
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum Format {
+    /// The JSON format. This is the only format supported.
+    Json,
+}
+
+/// A `GET` request query for this API route.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GetQuery {
+    /// The URL of the file to embed.
+    pub url: String,
+
+    /// The maximum width of the embed.
+    #[serde(rename = "maxwidth")]
+    pub max_width: Option<u32>,
+
+    /// The maximum height of the embed.
+    #[serde(rename = "maxheight")]
+    pub max_height: Option<u32>,
+
+    /// The response format.
+    pub format: Option<Format>,
+}
+
+/// Gets oEmbed data for a shared file's URL.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn get(
+    State(state): State<AppState>,
+    Query(query): Query<GetQuery>,
+) -> Response<GetResponse> {
+    let Ok(uri) = query.url.parse::<Uri>() else {
+        return Err(api::Error::ResourceNotFound);
+    };
+
+    let (Some(scheme), Some(authority)) = (uri.scheme_str(), uri.authority()) else {
+        return Err(api::Error::ResourceNotFound);
+    };
+
+    let origin = format!("{scheme}://{authority}");
+
+    // Any URL on a different origin than the content origin may be on a user's custom domain.
+    let custom_domain = if origin == *CONTENT_ORIGIN {
+        None
+    } else if scheme == *CONTENT_SCHEME {
+        Some(authority.host().to_ascii_lowercase())
+    } else {
+        return Err(api::Error::ResourceNotFound);
+    };
+
+    let Ok(path) = percent_decode_str(uri.path()).decode_utf8() else {
+        return Err(api::Error::ResourceNotFound);
+    };
+
+    let Some(file) = db::transaction!(
+        state.db_replica_pool,
+        async |tx| -> TxResult<_, api::Error> {
+            let (user_id, file_path) = if let Some(custom_domain) = &custom_domain {
+                let Some(user_id) = find_custom_domain_owner(tx, custom_domain).await? else {
+                    return Ok(None);
+                };
+
+                let Some(file_path) = path.strip_prefix('/') else {
+                    return Ok(None);
+                };
+
+                (user_id, file_path)
+            } else {
+                let Some((identifier, file_path)) = parse_file_route_path(&path) else {
+                    return Ok(None);
+                };
+
+                match find_route_owner(tx, identifier, file_path).await? {
+                    Some(RouteOwner::User(user)) => (user.id, file_path),
+                    Some(RouteOwner::OrganizationFolder { owner_id }) => (owner_id, file_path),
+                    None => return Ok(None),
+                }
+            };
+
+            Ok(find_shared_file(tx, &user_id, file_path, None).await?)
+        }
+    )
+    .await?
+    else {
+        return Err(api::Error::ResourceNotFound);
+    };
+
+    // Unavailable files can't be embedded.
+    if file.status != FileStatus::Active {
+        return Err(api::Error::ResourceNotFound);
+    }
+
+    let file_url = format!(
+        "{origin}{}",
+        utf8_percent_encode(&path, COMPONENT_IGNORING_SLASH),
+    );
+
+    let (embed, default_height) = if file.r#type.starts_with("image/") {
+        (Embed::Photo { url: file_url }, DEFAULT_MEDIA_HEIGHT)
+    } else if file.r#type.starts_with("video/") {
+        let html = format!(r#"<video src="{file_url}" controls></video>"#);
+        (Embed::Video { html }, DEFAULT_MEDIA_HEIGHT)
+    } else if file.r#type.starts_with("audio/") {
+        let html = format!(r#"<audio src="{file_url}" controls></audio>"#);
+        (Embed::Rich { html }, AUDIO_HEIGHT)
+    } else {
+        return Ok((
+            StatusCode::OK,
+            Json(GetResponse::new(Embed::Link, file.name, None)),
+        ));
+    };
+
+    let width = query
+        .max_width
+        .map_or(DEFAULT_WIDTH, |max_width| max_width.min(DEFAULT_WIDTH));
+    let height = query
+        .max_height
+        .map_or(default_height, |max_height| max_height.min(default_height));
+
+    Ok((
+        StatusCode::OK,
+        Json(GetResponse::new(embed, file.name, Some((width, height)))),
+    ))
+}
+
+/// A `GET` response body for this API route, as specified by oEmbed.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "snake_case")]
+pub struct GetResponse {
+    /// The oEmbed version.
+    pub version: &'static str,
+
+    /// The type-specific embed data.
+    #[serde(flatten)]
+    pub embed: Embed,
+
+    /// The file's name.
+    pub title: String,
+
+    /// The name of the service providing the embed.
+    pub provider_name: &'static str,
+
+    /// The URL of the service providing the embed.
+    pub provider_url: String,
+
+    /// The width of the embed in pixels. Required for every type of embed except links.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>,
+
+    /// The height of the embed in pixels. Required for every type of embed except links.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub height: Option<u32>,
+}
+
+impl GetResponse {
+    /// Constructs a new [`GetResponse`] with the specified type-specific embed data, title, and
+    /// dimensions.
+    fn new(embed: Embed, title: String, dimensions: Option<(u32, u32)>) -> Self {
+        Self {
+            version: "1.0",
+            embed,
+            title,
+            provider_name: "File Garden",
+            provider_url: WEBSITE_ORIGIN.clone(),
+            width: dimensions.map(|(width, _)| width),
+            height: dimensions.map(|(_, height)| height),
+        }
+    }
+}
+
+/// A type of oEmbed embed, along with its type-specific data.
+#[derive(Serialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Embed {
+    /// A static image.
+    Photo {
+        /// The image's URL.
+        url: String,
+    },
+
+    /// A playable video.
+    Video {
+        /// The HTML to embed the video.
+        html: String,
+    },
+
+    /// Any other embeddable content (used for audio).
+    Rich {
+        /// The HTML to embed the content.
+        html: String,
+    },
+
+    /// A file that can't be embedded beyond its metadata.
+    Link,
+}
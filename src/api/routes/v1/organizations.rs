@@ -0,0 +1,113 @@
+//! The set of all organizations.
+
+use axum::{extract::State, http::StatusCode};
+use axum_macros::debug_handler;
+use serde::{Deserialize, Serialize};
+use sqlx::Acquire;
+
+use crate::{
+    api::{self, auth::Auth, validation::OrganizationName, Json, Response},
+    db::{self, TxResult},
+    id::NewOrganizationId,
+    AppState,
+};
+
+pub mod folders;
+pub mod members;
+pub mod plan;
+pub mod storage;
+
+/// A member's role in an organization. Roles are ordered from least to most privileged.
+#[derive(
+    sqlx::Type, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug,
+)]
+#[sqlx(type_name = "organization_role", rename_all = "lowercase")]
+#[serde(rename_all = "camelCase")]
+pub enum OrganizationRole {
+    /// A member who can access the organization's files.
+    Member,
+
+    /// A member who can also manage the organization's non-owner members.
+    Admin,
+
+    /// A member who can manage everything about the organization.
+    Owner,
+}
+
+/// A `POST` request body for this API route.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct PostRequest {
+    /// The organization's name.
+    pub name: OrganizationName,
+}
+
+/// Creates a new organization owned by the signed-in user.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn post(
+    State(state): State<AppState>,
+    auth: Auth,
+    Json(body): Json<PostRequest>,
+) -> Response<PostResponse> {
+    let mut organization_id = NewOrganizationId::generate()?;
+
+    db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+        loop {
+            // If this loop's query fails from an ID conflict, this savepoint is rolled back to
+            // rather than aborting the entire transaction.
+            let mut savepoint = tx.begin().await?;
+
+            match sqlx::query!(
+                "INSERT INTO organizations (id, name)
+                    VALUES ($1, $2)",
+                organization_id.as_slice(),
+                *body.name,
+            )
+            .execute(savepoint.as_mut())
+            .await
+            {
+                Err(sqlx::Error::Database(error))
+                    if error.constraint() == Some("organizations_pkey") =>
+                {
+                    organization_id.reroll()?;
+                    continue;
+                }
+                result => result?,
+            };
+
+            savepoint.commit().await?;
+            break;
+        }
+
+        sqlx::query!(
+            "INSERT INTO organization_members (organization_id, user_id, role)
+                VALUES ($1, $2, 'owner')",
+            organization_id.as_slice(),
+            auth.user_id.as_slice(),
+        )
+        .execute(tx.as_mut())
+        .await?;
+
+        Ok(())
+    })
+    .await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(PostResponse {
+            id: organization_id,
+        }),
+    ))
+}
+
+/// A `POST` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PostResponse {
+    /// The organization's ID.
+    pub id: NewOrganizationId,
+}
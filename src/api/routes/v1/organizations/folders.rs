@@ -0,0 +1,227 @@
+//! The set of folders shared with an organization, which every member of it can access.
+
+use axum::{extract::State, http::StatusCode};
+use axum_macros::debug_handler;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{
+        self,
+        auth::{Auth, FolderAccess},
+        routes::v1::organizations::{members::member_role, OrganizationRole},
+        Json, Path, Response,
+    },
+    db::{self, TxError, TxResult},
+    id::Id,
+    AppState,
+};
+
+/// Lists the folders shared with an organization. Only members of the organization can see this.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn get(
+    State(state): State<AppState>,
+    auth: Auth,
+    Path(organization_id): Path<Id>,
+) -> Response<GetResponse> {
+    let folders = db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+        if member_role(tx, &organization_id, &auth.user_id)
+            .await?
+            .is_none()
+        {
+            return Err(TxError::Abort(api::Error::ResourceNotFound));
+        }
+
+        Ok(sqlx::query!(
+            r#"SELECT folders.id, folders.name, folders.owner_id,
+                organization_folders.access as "access: FolderAccess",
+                organization_folders.created_at
+                FROM organization_folders JOIN folders ON folders.id = organization_folders.folder_id
+                WHERE organization_folders.organization_id = $1
+                ORDER BY organization_folders.created_at"#,
+            organization_id.as_slice(),
+        )
+        .fetch_all(tx.as_mut())
+        .await?)
+    })
+    .await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(GetResponse {
+            folders: folders
+                .into_iter()
+                .map(|folder| SharedFolder {
+                    folder_id: folder.id.into(),
+                    name: folder.name,
+                    owner_id: folder.owner_id.into(),
+                    access: folder.access,
+                    created_at: folder.created_at,
+                })
+                .collect(),
+        }),
+    ))
+}
+
+/// A `GET` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GetResponse {
+    /// The folders shared with the organization, oldest first.
+    pub folders: Vec<SharedFolder>,
+}
+
+/// A folder shared with an organization.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SharedFolder {
+    /// The folder's ID.
+    pub folder_id: Id,
+
+    /// The folder's name.
+    pub name: String,
+
+    /// The ID of the member who owns the folder.
+    pub owner_id: Id,
+
+    /// The level of access the organization's members have to the folder.
+    pub access: FolderAccess,
+
+    /// When the folder was shared with the organization.
+    pub created_at: DateTime<Utc>,
+}
+
+/// A `PUT` request body for this API route.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct PutRequest {
+    /// The level of access to grant the organization's members.
+    pub access: FolderAccess,
+}
+
+/// Shares a folder with an organization, or changes the access its members have to it. Only the
+/// folder's owner can do this, and they must be a member of the organization.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn put(
+    State(state): State<AppState>,
+    auth: Auth,
+    Path((organization_id, folder_id)): Path<(Id, Id)>,
+    Json(body): Json<PutRequest>,
+) -> Response<PutResponse> {
+    db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+        let Some(folder) = sqlx::query!(
+            "SELECT owner_id FROM folders
+                WHERE id = $1",
+            folder_id.as_slice(),
+        )
+        .fetch_optional(tx.as_mut())
+        .await?
+        else {
+            return Err(TxError::Abort(api::Error::ResourceNotFound));
+        };
+
+        let owner_id = folder.owner_id.into();
+        auth.require_self_or_manager(&owner_id)?;
+
+        if member_role(tx, &organization_id, &owner_id)
+            .await?
+            .is_none()
+        {
+            return Err(TxError::Abort(api::Error::ResourceNotFound));
+        }
+
+        sqlx::query!(
+            "INSERT INTO organization_folders (organization_id, folder_id, access)
+                VALUES ($1, $2, $3)
+                ON CONFLICT (organization_id, folder_id) DO UPDATE
+                    SET access = excluded.access",
+            organization_id.as_slice(),
+            folder_id.as_slice(),
+            body.access as FolderAccess,
+        )
+        .execute(tx.as_mut())
+        .await?;
+
+        Ok(())
+    })
+    .await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(PutResponse {
+            access: body.access,
+        }),
+    ))
+}
+
+/// A `PUT` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PutResponse {
+    /// The level of access the organization's members have to the folder.
+    pub access: FolderAccess,
+}
+
+/// Stops sharing a folder with an organization. The folder's owner and the organization's admins
+/// and owners can do this.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn delete(
+    State(state): State<AppState>,
+    auth: Auth,
+    Path((organization_id, folder_id)): Path<(Id, Id)>,
+) -> Response<DeleteResponse> {
+    db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+        let Some(folder) = sqlx::query!(
+            "SELECT folders.owner_id FROM organization_folders
+                JOIN folders ON folders.id = organization_folders.folder_id
+                WHERE organization_folders.organization_id = $1
+                    AND organization_folders.folder_id = $2",
+            organization_id.as_slice(),
+            folder_id.as_slice(),
+        )
+        .fetch_optional(tx.as_mut())
+        .await?
+        else {
+            return Err(TxError::Abort(api::Error::ResourceNotFound));
+        };
+
+        let is_admin = member_role(tx, &organization_id, &auth.user_id)
+            .await?
+            .is_some_and(|role| role >= OrganizationRole::Admin);
+
+        if !is_admin {
+            auth.require_self_or_manager(&folder.owner_id.into())?;
+        }
+
+        sqlx::query!(
+            "DELETE FROM organization_folders
+                WHERE organization_id = $1 AND folder_id = $2",
+            organization_id.as_slice(),
+            folder_id.as_slice(),
+        )
+        .execute(tx.as_mut())
+        .await?;
+
+        Ok(())
+    })
+    .await?;
+
+    Ok((StatusCode::OK, Json(DeleteResponse {})))
+}
+
+/// A `DELETE` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteResponse {}
@@ -0,0 +1,252 @@
+//! The set of an organization's members.
+
+use axum::{extract::State, http::StatusCode};
+use axum_macros::debug_handler;
+use serde::{Deserialize, Serialize};
+use sqlx::PgConnection;
+
+use crate::{
+    api::{self, auth::Auth, routes::v1::organizations::OrganizationRole, Json, Path, Response},
+    db::{self, TxError, TxResult},
+    id::Id,
+    AppState,
+};
+
+/// Lists an organization's members. Only members of the organization can see this.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn get(
+    State(state): State<AppState>,
+    auth: Auth,
+    Path(organization_id): Path<Id>,
+) -> Response<GetResponse> {
+    let members = db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+        if member_role(tx, &organization_id, &auth.user_id)
+            .await?
+            .is_none()
+        {
+            return Err(TxError::Abort(api::Error::ResourceNotFound));
+        }
+
+        Ok(sqlx::query!(
+            r#"SELECT users.id, users.name, organization_members.role as "role: OrganizationRole"
+                FROM organization_members JOIN users ON users.id = organization_members.user_id
+                WHERE organization_members.organization_id = $1
+                ORDER BY organization_members.created_at"#,
+            organization_id.as_slice(),
+        )
+        .fetch_all(tx.as_mut())
+        .await?)
+    })
+    .await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(GetResponse {
+            members: members
+                .into_iter()
+                .map(|member| Member {
+                    user_id: member.id.into(),
+                    name: member.name,
+                    role: member.role,
+                })
+                .collect(),
+        }),
+    ))
+}
+
+/// A `GET` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GetResponse {
+    /// The organization's members, from oldest to newest.
+    pub members: Vec<Member>,
+}
+
+/// A member of an organization.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Member {
+    /// The member's user ID.
+    pub user_id: Id,
+
+    /// The member's name.
+    pub name: String,
+
+    /// The member's role in the organization.
+    pub role: OrganizationRole,
+}
+
+/// A `PUT` request body for this API route.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct PutRequest {
+    /// The member's new role in the organization.
+    pub role: OrganizationRole,
+}
+
+/// Changes a member's role in an organization. Only admins and owners of the organization can do
+/// this, and only owners can manage other owners. Users join an organization by accepting an invite
+/// to it rather than through this route, so they can't be added without their consent.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn put(
+    State(state): State<AppState>,
+    auth: Auth,
+    Path((organization_id, user_id)): Path<(Id, Id)>,
+    Json(body): Json<PutRequest>,
+) -> Response<PutResponse> {
+    db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+        let Some(auth_role) = member_role(tx, &organization_id, &auth.user_id).await? else {
+            return Err(TxError::Abort(api::Error::ResourceNotFound));
+        };
+
+        let Some(role) = member_role(tx, &organization_id, &user_id).await? else {
+            return Err(TxError::Abort(api::Error::ResourceNotFound));
+        };
+
+        let is_allowed = if body.role == OrganizationRole::Owner || role == OrganizationRole::Owner
+        {
+            auth_role == OrganizationRole::Owner
+        } else {
+            auth_role >= OrganizationRole::Admin
+        };
+
+        if !is_allowed {
+            return Err(TxError::Abort(api::Error::PermissionDenied));
+        }
+
+        sqlx::query!(
+            "UPDATE organization_members
+                SET role = $3
+                WHERE organization_id = $1 AND user_id = $2",
+            organization_id.as_slice(),
+            user_id.as_slice(),
+            body.role as OrganizationRole,
+        )
+        .execute(tx.as_mut())
+        .await?;
+
+        ensure_owner_remains(tx, &organization_id).await
+    })
+    .await?;
+
+    Ok((StatusCode::OK, Json(PutResponse { role: body.role })))
+}
+
+/// A `PUT` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PutResponse {
+    /// The member's new role in the organization.
+    pub role: OrganizationRole,
+}
+
+/// Removes a member from an organization. Members can always remove themselves, but removing
+/// anyone else has the same requirements as changing their role.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn delete(
+    State(state): State<AppState>,
+    auth: Auth,
+    Path((organization_id, user_id)): Path<(Id, Id)>,
+) -> Response<DeleteResponse> {
+    db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+        let Some(auth_role) = member_role(tx, &organization_id, &auth.user_id).await? else {
+            return Err(TxError::Abort(api::Error::ResourceNotFound));
+        };
+
+        let Some(role) = member_role(tx, &organization_id, &user_id).await? else {
+            return Err(TxError::Abort(api::Error::ResourceNotFound));
+        };
+
+        let is_allowed = if user_id == auth.user_id {
+            true
+        } else if role == OrganizationRole::Owner {
+            auth_role == OrganizationRole::Owner
+        } else {
+            auth_role >= OrganizationRole::Admin
+        };
+
+        if !is_allowed {
+            return Err(TxError::Abort(api::Error::PermissionDenied));
+        }
+
+        sqlx::query!(
+            "DELETE FROM organization_members
+                WHERE organization_id = $1 AND user_id = $2",
+            organization_id.as_slice(),
+            user_id.as_slice(),
+        )
+        .execute(tx.as_mut())
+        .await?;
+
+        ensure_owner_remains(tx, &organization_id).await
+    })
+    .await?;
+
+    Ok((StatusCode::OK, Json(DeleteResponse {})))
+}
+
+/// A `DELETE` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteResponse {}
+
+/// Gets a user's role in an organization, or `None` if they aren't a member of it.
+///
+/// # Errors
+///
+/// Returns an error if the database query fails.
+pub(super) async fn member_role(
+    conn: &mut PgConnection,
+    organization_id: &Id,
+    user_id: &Id,
+) -> sqlx::Result<Option<OrganizationRole>> {
+    Ok(sqlx::query!(
+        r#"SELECT role as "role: OrganizationRole" FROM organization_members
+            WHERE organization_id = $1 AND user_id = $2"#,
+        organization_id.as_slice(),
+        user_id.as_slice(),
+    )
+    .fetch_optional(conn)
+    .await?
+    .map(|member| member.role))
+}
+
+/// Aborts the transaction if a change left an organization without any owners. Otherwise, nobody
+/// would be able to manage it.
+///
+/// # Errors
+///
+/// Returns [`api::Error::OrganizationOwnerRequired`] if the organization has no owners.
+async fn ensure_owner_remains(
+    conn: &mut PgConnection,
+    organization_id: &Id,
+) -> TxResult<(), api::Error> {
+    let has_owner = sqlx::query!(
+        r#"SELECT EXISTS (
+            SELECT 1 FROM organization_members
+                WHERE organization_id = $1 AND role = 'owner'
+        ) as "has_owner!""#,
+        organization_id.as_slice(),
+    )
+    .fetch_one(conn)
+    .await?
+    .has_owner;
+
+    if !has_owner {
+        return Err(TxError::Abort(api::Error::OrganizationOwnerRequired));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,73 @@
+//! The plan an organization is on, which limits everything in its storage.
+
+use axum::{extract::State, http::StatusCode};
+use axum_macros::debug_handler;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{
+        self,
+        auth::{Auth, Permission},
+        routes::v1::plans::Plan,
+        Json, Path, Response,
+    },
+    db::{self, TxResult},
+    id::Id,
+    AppState,
+};
+
+/// A `PUT` request body for this API route.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct PutRequest {
+    /// The organization's new plan.
+    pub plan: Plan,
+}
+
+/// Changes the plan an organization is on.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn put(
+    State(state): State<AppState>,
+    auth: Auth,
+    Path(organization_id): Path<Id>,
+    Json(body): Json<PutRequest>,
+) -> Response<PutResponse> {
+    auth.require(Permission::ManagePlans)?;
+
+    let Some(organization) =
+        db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+            Ok(sqlx::query!(
+                r#"UPDATE organizations
+                    SET plan = $1
+                    WHERE id = $2
+                    RETURNING plan as "plan: Plan""#,
+                body.plan as Plan,
+                organization_id.as_slice(),
+            )
+            .fetch_optional(tx.as_mut())
+            .await?)
+        })
+        .await?
+    else {
+        return Err(api::Error::ResourceNotFound);
+    };
+
+    Ok((
+        StatusCode::OK,
+        Json(PutResponse {
+            plan: organization.plan,
+        }),
+    ))
+}
+
+/// A `PUT` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PutResponse {
+    /// The organization's new plan.
+    pub plan: Plan,
+}
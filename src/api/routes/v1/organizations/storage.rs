@@ -0,0 +1,287 @@
+//! The storage of an organization, made of top-level folders its members moved into it. Everything
+//! in it counts toward the organization's storage quota instead of its owner's, every member of the
+//! organization can change it, and its files are served under the organization's ID.
+
+use axum::{extract::State, http::StatusCode};
+use axum_macros::debug_handler;
+use serde::Serialize;
+use sqlx::PgConnection;
+
+use crate::{
+    api::{
+        self,
+        auth::Auth,
+        routes::v1::{
+            organizations::{members::member_role, OrganizationRole},
+            plans::Plan,
+        },
+        Json, Path, Response,
+    },
+    cdn,
+    db::{self, TxError, TxResult},
+    id::Id,
+    AppState,
+};
+
+/// Gets an organization's plan, storage usage, and the folders in its storage. Only members of the
+/// organization can see this.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn get(
+    State(state): State<AppState>,
+    auth: Auth,
+    Path(organization_id): Path<Id>,
+) -> Response<GetResponse> {
+    let (organization, folders) =
+        db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+            if member_role(tx, &organization_id, &auth.user_id)
+                .await?
+                .is_none()
+            {
+                return Err(TxError::Abort(api::Error::ResourceNotFound));
+            }
+
+            let organization = sqlx::query!(
+                r#"SELECT organizations.plan as "plan: Plan", plan_limits.storage_quota
+                    FROM organizations JOIN plan_limits ON plan_limits.plan = organizations.plan
+                    WHERE organizations.id = $1"#,
+                organization_id.as_slice(),
+            )
+            .fetch_one(tx.as_mut())
+            .await?;
+
+            let folders = sqlx::query!(
+                "SELECT id, name, owner_id, size, file_count FROM folders
+                    WHERE organization_id = $1
+                    ORDER BY name",
+                organization_id.as_slice(),
+            )
+            .fetch_all(tx.as_mut())
+            .await?;
+
+            Ok((organization, folders))
+        })
+        .await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(GetResponse {
+            plan: organization.plan,
+            storage_used: folders.iter().map(|folder| folder.size).sum(),
+            file_count: folders.iter().map(|folder| folder.file_count).sum(),
+            storage_quota: organization.storage_quota,
+            folders: folders
+                .into_iter()
+                .map(|folder| StorageFolder {
+                    folder_id: folder.id.into(),
+                    name: folder.name,
+                    owner_id: folder.owner_id.into(),
+                    size: folder.size,
+                    file_count: folder.file_count,
+                })
+                .collect(),
+        }),
+    ))
+}
+
+/// A `GET` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GetResponse {
+    /// The organization's plan.
+    pub plan: Plan,
+
+    /// The total size in bytes of the files in the organization's storage.
+    pub storage_used: i64,
+
+    /// The number of files in the organization's storage.
+    pub file_count: i64,
+
+    /// The most bytes the organization's plan allows it to store.
+    pub storage_quota: i64,
+
+    /// The folders in the organization's storage, ordered by name.
+    pub folders: Vec<StorageFolder>,
+}
+
+/// A folder in an organization's storage.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageFolder {
+    /// The folder's ID.
+    pub folder_id: Id,
+
+    /// The folder's name, which is the first segment of its files' paths under the organization's
+    /// ID.
+    pub name: String,
+
+    /// The ID of the member who owns the folder.
+    pub owner_id: Id,
+
+    /// The total size in bytes of the files in the folder.
+    pub size: i64,
+
+    /// The number of files in the folder.
+    pub file_count: i64,
+}
+
+/// Moves a top-level folder into an organization's storage. Only the folder's owner can do this,
+/// and they must be a member of the organization. Its files stop being served under their owner's
+/// URLs, which redirect to the organization's instead.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn put(
+    State(state): State<AppState>,
+    auth: Auth,
+    Path((organization_id, folder_id)): Path<(Id, Id)>,
+) -> Response<PutResponse> {
+    let urls = db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+        let Some(folder) = sqlx::query!(
+            "SELECT owner_id, parent_id_path FROM folders
+                WHERE id = $1
+                FOR UPDATE",
+            folder_id.as_slice(),
+        )
+        .fetch_optional(tx.as_mut())
+        .await?
+        else {
+            return Err(TxError::Abort(api::Error::ResourceNotFound));
+        };
+
+        let owner_id = folder.owner_id.into();
+        auth.require_self_or_manager(&owner_id)?;
+
+        if member_role(tx, &organization_id, &owner_id)
+            .await?
+            .is_none()
+        {
+            return Err(TxError::Abort(api::Error::ResourceNotFound));
+        }
+
+        if !folder.parent_id_path.is_empty() {
+            return Err(TxError::Abort(api::Error::FolderNotTopLevel));
+        }
+
+        let urls = folder_file_urls(tx, &folder_id).await?;
+
+        match sqlx::query!(
+            "UPDATE folders
+                SET organization_id = $2
+                WHERE id = $1",
+            folder_id.as_slice(),
+            organization_id.as_slice(),
+        )
+        .execute(tx.as_mut())
+        .await
+        {
+            Err(sqlx::Error::Database(error))
+                if error.constraint() == Some("organization_storage_folder_names") =>
+            {
+                return Err(TxError::Abort(api::Error::StorageFolderNameTaken));
+            }
+            result => result?,
+        };
+
+        Ok(urls)
+    })
+    .await?;
+
+    cdn::purge(urls);
+
+    Ok((StatusCode::OK, Json(PutResponse {})))
+}
+
+/// A `PUT` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PutResponse {}
+
+/// Moves a folder out of an organization's storage and back into its owner's. The folder's owner
+/// and the organization's admins and owners can do this. Its owner's storage quota isn't enforced,
+/// so a folder can always be moved out.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn delete(
+    State(state): State<AppState>,
+    auth: Auth,
+    Path((organization_id, folder_id)): Path<(Id, Id)>,
+) -> Response<DeleteResponse> {
+    let urls = db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+        let Some(folder) = sqlx::query!(
+            "SELECT owner_id FROM folders
+                WHERE id = $1 AND organization_id = $2
+                FOR UPDATE",
+            folder_id.as_slice(),
+            organization_id.as_slice(),
+        )
+        .fetch_optional(tx.as_mut())
+        .await?
+        else {
+            return Err(TxError::Abort(api::Error::ResourceNotFound));
+        };
+
+        let is_admin = member_role(tx, &organization_id, &auth.user_id)
+            .await?
+            .is_some_and(|role| role >= OrganizationRole::Admin);
+
+        if !is_admin {
+            auth.require_self_or_manager(&folder.owner_id.into())?;
+        }
+
+        let urls = folder_file_urls(tx, &folder_id).await?;
+
+        sqlx::query!(
+            "UPDATE folders
+                SET organization_id = NULL
+                WHERE id = $1",
+            folder_id.as_slice(),
+        )
+        .execute(tx.as_mut())
+        .await?;
+
+        Ok(urls)
+    })
+    .await?;
+
+    cdn::purge(urls);
+
+    Ok((StatusCode::OK, Json(DeleteResponse {})))
+}
+
+/// A `DELETE` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteResponse {}
+
+/// Gets the URLs the content of every file in a folder can be cached under, since moving the folder
+/// between storages changes them.
+///
+/// # Errors
+///
+/// Returns an error if a database query fails.
+async fn folder_file_urls(conn: &mut PgConnection, folder_id: &Id) -> sqlx::Result<Vec<String>> {
+    let files = sqlx::query!(
+        "SELECT id FROM files
+            WHERE $1 = ANY (parent_id_path)",
+        folder_id.as_slice(),
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let mut urls = Vec::new();
+
+    for file in files {
+        urls.extend(cdn::file_urls(conn, &file.id.into()).await?);
+    }
+
+    Ok(urls)
+}
@@ -5,10 +5,11 @@ use axum_macros::debug_handler;
 use lettre::message::Mailbox;
 use serde::{Deserialize, Serialize};
 use sqlx::Acquire;
+use tokio::time::Instant;
 
 use crate::{
     api::{
-        self, captcha,
+        self, captcha, pad_response_time,
         validation::{CaptchaToken, UserEmail},
         Json, Query, Response,
     },
@@ -95,6 +96,8 @@ pub async fn post(
     State(state): State<AppState>,
     Json(body): Json<PostRequest>,
 ) -> Response<PostResponse> {
+    let started_at = Instant::now();
+
     // We don't want bots spamming people with password reset emails.
     if !captcha::verify(&body.captcha_token).await? {
         return Err(api::Error::CaptchaFailed);
@@ -113,7 +116,8 @@ pub async fn post(
                 email: body.email.as_str(),
             }
             .to(Mailbox::new(None, (*body.email).clone()))
-            .send();
+            .send(tx.as_mut())
+            .await?;
 
             return Ok(());
         };
@@ -163,12 +167,14 @@ pub async fn post(
             password_reset_url: &format!("{}/password-reset?token={}", *WEBSITE_ORIGIN, token),
         }
         .to(Mailbox::new(Some(user.name), (*body.email).clone()))
-        .send();
+        .send_requested();
 
         Ok(())
     })
     .await?;
 
+    pad_response_time(started_at).await;
+
     // To prevent user enumeration, send this same successful response even if the user doesn't
     // exist.
     Ok((StatusCode::OK, Json(PostResponse {})))
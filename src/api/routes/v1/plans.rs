@@ -0,0 +1,84 @@
+//! The set of plans users can be on, each with different limits and features.
+
+use axum::{extract::State, http::StatusCode};
+use axum_macros::debug_handler;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{self, Json, Response},
+    db::{self, TxResult},
+    AppState,
+};
+
+/// A plan a user can be on, determining their [`PlanLimits`].
+#[derive(sqlx::Type, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[sqlx(type_name = "plan", rename_all = "lowercase")]
+#[serde(rename_all = "camelCase")]
+pub enum Plan {
+    /// The default plan.
+    Free,
+
+    /// A plan for users supporting File Garden.
+    Supporter,
+
+    /// A plan for users needing the most storage and features.
+    Pro,
+}
+
+/// Lists every plan and its limits, from the least to the most generous.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn get(State(state): State<AppState>) -> Response<GetResponse> {
+    let plans = db::transaction!(
+        state.db_replica_pool,
+        async |tx| -> TxResult<_, api::Error> {
+            Ok(sqlx::query_as!(
+                PlanLimits,
+                r#"SELECT plan as "plan: Plan", storage_quota, max_file_size, monthly_bandwidth,
+                    custom_domains, hls
+                    FROM plan_limits
+                    ORDER BY plan"#,
+            )
+            .fetch_all(tx.as_mut())
+            .await?)
+        }
+    )
+    .await?;
+
+    Ok((StatusCode::OK, Json(GetResponse { plans })))
+}
+
+/// A `GET` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GetResponse {
+    /// Every plan's limits, from the least to the most generous plan.
+    pub plans: Vec<PlanLimits>,
+}
+
+/// The limits and features of a plan.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PlanLimits {
+    /// The plan.
+    pub plan: Plan,
+
+    /// The maximum total size of a user's files in bytes.
+    pub storage_quota: i64,
+
+    /// The maximum size of a single file in bytes.
+    pub max_file_size: i64,
+
+    /// The maximum number of bytes served from a user's files each month (in UTC), after which
+    /// they stop being served until the next month.
+    pub monthly_bandwidth: i64,
+
+    /// Whether the plan allows serving files from custom domains.
+    pub custom_domains: bool,
+
+    /// Whether the plan allows streaming videos over HLS.
+    pub hls: bool,
+}
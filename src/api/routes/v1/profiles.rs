@@ -0,0 +1,173 @@
+//! The set of all users' public profiles.
+
+use axum::{extract::State, http::StatusCode};
+use axum_macros::debug_handler;
+use serde::Serialize;
+
+use crate::{
+    api::{self, Json, Path, Response},
+    content::file_url,
+    db::{self, TxError, TxResult},
+    id::Id,
+    AppState, CONTENT_ORIGIN,
+};
+
+/// Gets the public profile of the user with the specified handle. Users without a handle don't
+/// have a public profile.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn get(
+    State(state): State<AppState>,
+    Path(handle): Path<String>,
+) -> Response<GetResponse> {
+    let (user, pins) = db::transaction!(state.db_replica_pool, async |tx| -> TxResult<
+        _,
+        api::Error,
+    > {
+        // The avatar is only shown while it's public.
+        let Some(user) = sqlx::query!(
+            r#"SELECT users.id, users.handle::text as "handle!", users.name, users.bio,
+                    users.indexable, files.name as "avatar_name?",
+                    files.parent_name_path as "avatar_parent_path?"
+                    FROM users
+                    LEFT JOIN files ON files.id = users.avatar_file_id AND files.shared
+                    WHERE users.handle = $1::citext"#,
+            handle,
+        )
+        .fetch_optional(tx.as_mut())
+        .await?
+        else {
+            return Err(TxError::Abort(api::Error::ResourceNotFound));
+        };
+
+        // Pins are only shown while they're public.
+        let pins = sqlx::query!(
+            r#"SELECT files.id as "file_id?", files.name as "file_name?",
+                    files.parent_name_path as "file_parent_path?", files.type as "file_type?",
+                    folders.id as "folder_id?", folders.name as "folder_name?",
+                    folders.share_key as "folder_share_key?"
+                    FROM profile_pins
+                    LEFT JOIN files ON files.id = profile_pins.file_id AND files.shared
+                    LEFT JOIN folders
+                        ON folders.id = profile_pins.folder_id AND folders.share_key IS NOT NULL
+                    WHERE profile_pins.user_id = $1
+                    ORDER BY profile_pins.position"#,
+            user.id,
+        )
+        .fetch_all(tx.as_mut())
+        .await?;
+
+        Ok((user, pins))
+    })
+    .await?;
+
+    let avatar_url = user
+        .avatar_name
+        .zip(user.avatar_parent_path)
+        .map(|(name, parent_path)| {
+            file_url(&CONTENT_ORIGIN, Some(&user.handle), &parent_path, &name)
+        });
+
+    let pins = pins
+        .into_iter()
+        .filter_map(|pin| {
+            if let (Some(id), Some(name), Some(parent_path), Some(media_type)) = (
+                pin.file_id,
+                pin.file_name,
+                pin.file_parent_path,
+                pin.file_type,
+            ) {
+                Some(ProfilePin::File {
+                    id: id.into(),
+                    url: file_url(&CONTENT_ORIGIN, Some(&user.handle), &parent_path, &name),
+                    name,
+                    media_type,
+                })
+            } else if let (Some(id), Some(name), Some(share_key)) =
+                (pin.folder_id, pin.folder_name, pin.folder_share_key)
+            {
+                Some(ProfilePin::Folder {
+                    id: id.into(),
+                    name,
+                    share_key: share_key.into(),
+                })
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    Ok((
+        StatusCode::OK,
+        Json(GetResponse {
+            handle: user.handle,
+            name: user.name,
+            bio: user.bio,
+            avatar_url,
+            pins,
+            indexable: user.indexable,
+        }),
+    ))
+}
+
+/// A `GET` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GetResponse {
+    /// The user's handle.
+    pub handle: String,
+
+    /// The user's name.
+    pub name: String,
+
+    /// The user's bio.
+    pub bio: String,
+
+    /// The URL of the user's avatar image, if any.
+    pub avatar_url: Option<String>,
+
+    /// The public files and folders the user pinned to their profile, in order.
+    pub pins: Vec<ProfilePin>,
+
+    /// Whether the user allows search engines to index their shared files, and so their profile.
+    pub indexable: bool,
+}
+
+/// A public file or folder pinned to a user's profile.
+#[derive(Serialize, Debug)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum ProfilePin {
+    /// A pinned file.
+    File {
+        /// The file's ID.
+        id: Id,
+
+        /// The file's name.
+        name: String,
+
+        /// The file's media type.
+        media_type: String,
+
+        /// The file's URL.
+        url: String,
+    },
+
+    /// A pinned folder.
+    Folder {
+        /// The folder's ID.
+        id: Id,
+
+        /// The folder's name.
+        name: String,
+
+        /// The key of the folder's share link.
+        share_key: Id,
+    },
+}
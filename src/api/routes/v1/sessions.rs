@@ -2,8 +2,13 @@
 
 use std::sync::LazyLock;
 
-use axum::{extract::State, http::StatusCode};
+use axum::{
+    extract::State,
+    http::{header::USER_AGENT, HeaderMap, StatusCode},
+};
 use axum_macros::debug_handler;
+use chrono::TimeDelta;
+use lettre::message::Mailbox;
 use serde::{Deserialize, Serialize};
 use sqlx::Acquire;
 use tower_cookies::{
@@ -14,12 +19,14 @@ use tower_cookies::{
 use crate::{
     api::{
         self,
+        client_ip::ClientIp,
         validation::{UserEmail, UserPassword},
         Json, Response,
     },
-    crypto::{hash_without_salt, verify_hash},
-    db::{self, TxResult},
-    id::Token,
+    crypto::{hash_without_salt, verify_hash, DUMMY_HASH},
+    db::{self, TxError, TxResult},
+    email::{notify, NewSignInMessage, SignInLockedMessage},
+    id::{NewSessionId, Token},
     AppState, WEBSITE_ORIGIN,
 };
 
@@ -27,7 +34,22 @@ use crate::{
 static WEBSITE_DOMAIN: LazyLock<&str> = LazyLock::new(|| domain_from_origin(&WEBSITE_ORIGIN));
 
 /// How long a session takes to expire after its creation.
-const SESSION_MAX_AGE: Duration = Duration::days(60);
+pub(crate) const SESSION_MAX_AGE: Duration = Duration::days(60);
+
+/// How long a failed sign-in attempt counts toward throttling further attempts. This is also how
+/// long an account stays locked after its last failed attempt.
+pub(crate) const FAILED_SIGN_IN_WINDOW: Duration = Duration::minutes(15);
+
+/// The number of recent failed attempts to sign into an account after which each further attempt
+/// must wait twice as long as the last since the previous failure.
+const ACCOUNT_DELAY_THRESHOLD: i64 = 3;
+
+/// The number of recent failed attempts to sign into an account after which it's locked.
+const ACCOUNT_LOCKOUT_THRESHOLD: i64 = 10;
+
+/// The number of recent failed sign-in attempts from an IP address after which it's blocked from
+/// signing in.
+const IP_LOCKOUT_THRESHOLD: i64 = 50;
 
 /// A `POST` request body for this API route.
 #[derive(Deserialize, Debug)]
@@ -40,45 +62,191 @@ pub struct PostRequest {
     pub password: UserPassword,
 }
 
+pub mod revocation;
+
 /// Signs a user in, creating a sign-in session and returning a session cookie.
 ///
+/// If the user has signed in before but never from this combination of IP address and
+/// `User-Agent`, they're notified by email with a link to revoke the new session.
+///
+/// Failed attempts are throttled per account and per IP address. After a few failures, further
+/// attempts on the account are delayed exponentially. After more, the account is temporarily
+/// locked, and its owner is notified by email.
+///
 /// # Errors
 ///
 /// See [`crate::api::Error`].
 #[debug_handler]
 pub async fn post(
     State(state): State<AppState>,
+    ClientIp(client_ip): ClientIp,
+    headers: HeaderMap,
     cookies: Cookies,
     Json(body): Json<PostRequest>,
 ) -> Response<PostResponse> {
+    let client_ip = client_ip.to_string();
+    let user_agent = headers
+        .get(USER_AGENT)
+        .and_then(|user_agent| user_agent.to_str().ok());
+
     let token = db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
-        let Some(user) = sqlx::query!(
+        // Failures are tracked by email rather than by user so that throttling behaves the same
+        // whether or not a user has the email. Otherwise, it could be used for user enumeration.
+        let failures = sqlx::query!(
+            r#"SELECT
+                count(*) FILTER (WHERE email = $1) as "account!",
+                max(created_at) FILTER (WHERE email = $1) as last_account_failure_at,
+                count(*) FILTER (WHERE ip = $2) as "ip!",
+                max(created_at) FILTER (WHERE ip = $2) as last_ip_failure_at,
+                now() as "now!"
+                FROM failed_sign_ins
+                WHERE (email = $1 OR ip = $2) AND created_at > now() - make_interval(secs => $3)"#,
+            body.email.as_str(),
+            client_ip,
+            FAILED_SIGN_IN_WINDOW.as_seconds_f64(),
+        )
+        .fetch_one(tx.as_mut())
+        .await?;
+
+        // A lockout lasts until the last failure counting toward it is out of the window.
+        let lockout_last_failure_at = [
+            (failures.account >= ACCOUNT_LOCKOUT_THRESHOLD)
+                .then_some(failures.last_account_failure_at),
+            (failures.ip >= IP_LOCKOUT_THRESHOLD).then_some(failures.last_ip_failure_at),
+        ]
+        .into_iter()
+        .flatten()
+        .flatten()
+        .max();
+
+        if let Some(last_failure_at) = lockout_last_failure_at {
+            let window = TimeDelta::seconds(FAILED_SIGN_IN_WINDOW.whole_seconds());
+
+            return Err(TxError::Abort(api::Error::TooManyAttempts(
+                last_failure_at + window,
+            )));
+        }
+
+        if let Some(last_failure_at) = failures.last_account_failure_at {
+            if failures.account >= ACCOUNT_DELAY_THRESHOLD {
+                let delay = TimeDelta::seconds(1 << (failures.account - ACCOUNT_DELAY_THRESHOLD));
+
+                if failures.now - last_failure_at < delay {
+                    return Err(TxError::Abort(api::Error::TooManyAttempts(
+                        last_failure_at + delay,
+                    )));
+                }
+            }
+        }
+
+        let user = sqlx::query!(
             "SELECT id, password_hash FROM users
                 WHERE email = $1",
             body.email.as_str(),
         )
         .fetch_optional(tx.as_mut())
+        .await?;
+
+        let user_id = user.as_ref().map(|user| user.id.clone());
+
+        // A hash is verified even if no user has the email, so a failure takes as long either way.
+        // Otherwise, response times could be used for user enumeration.
+        let password_hash = user
+            .as_ref()
+            .map_or(DUMMY_HASH.as_str(), |user| &user.password_hash);
+        let is_password_correct = verify_hash(&body.password, password_hash);
+
+        let Some(user) = user.filter(|_| is_password_correct) else {
+            sqlx::query!(
+                "INSERT INTO failed_sign_ins (email, ip)
+                    VALUES ($1, $2)",
+                body.email.as_str(),
+                client_ip,
+            )
+            .execute(tx.as_mut())
+            .await?;
+
+            if let Some(user_id) = &user_id {
+                if failures.account + 1 == ACCOUNT_LOCKOUT_THRESHOLD {
+                    notify(
+                        tx.as_mut(),
+                        user_id,
+                        Mailbox::new(None, (*body.email).clone()),
+                        &SignInLockedMessage {
+                            email: body.email.as_str(),
+                            lockout_minutes: FAILED_SIGN_IN_WINDOW.whole_minutes(),
+                        },
+                    )
+                    .await?;
+                }
+            }
+
+            // The failure must be committed, so the transaction can't be aborted with an error.
+            return Ok(None);
+        };
+
+        sqlx::query!(
+            "DELETE FROM failed_sign_ins
+                WHERE email = $1",
+            body.email.as_str(),
+        )
+        .execute(tx.as_mut())
+        .await?;
+
+        let device_fingerprint =
+            hash_without_salt(&format!("{client_ip}\n{}", user_agent.unwrap_or_default()));
+
+        let has_known_devices = sqlx::query!(
+            r#"SELECT EXISTS(SELECT 1 FROM known_devices WHERE user_id = $1) as "exists!""#,
+            user.id,
+        )
+        .fetch_one(tx.as_mut())
+        .await?
+        .exists;
+
+        let is_new_device = sqlx::query!(
+            "INSERT INTO known_devices (user_id, fingerprint)
+                VALUES ($1, $2)
+                ON CONFLICT DO NOTHING",
+            user.id,
+            device_fingerprint.as_ref(),
+        )
+        .execute(tx.as_mut())
         .await?
-        .filter(|user| verify_hash(&body.password, &user.password_hash)) else {
-            // To prevent user enumeration, send this same error response whether or not the email
-            // is correct.
-            return Err(db::TxError::Abort(api::Error::UserCredentialsWrong));
+        .rows_affected()
+            == 1;
+
+        // A user's first sign-in isn't notified since every device is new to them.
+        let mut revocation_token = if has_known_devices && is_new_device {
+            Some(Token::generate()?)
+        } else {
+            None
         };
 
         let mut token = Token::generate()?;
+        let mut session_id = NewSessionId::generate()?;
 
         loop {
-            // If this loop's query fails from a token conflict, this savepoint is rolled back to
-            // rather than aborting the entire transaction.
+            // If this loop's query fails from a token or ID conflict, this savepoint is rolled back
+            // to rather than aborting the entire transaction.
             let mut savepoint = tx.begin().await?;
 
             let token_hash = hash_without_salt(&token);
+            let revocation_token_hash = revocation_token.as_ref().map(hash_without_salt);
 
             match sqlx::query!(
-                "INSERT INTO sessions (token_hash, user_id)
-                    VALUES ($1, $2)",
+                "INSERT INTO sessions (
+                    token_hash, id, user_id, ip, user_agent, device_fingerprint,
+                    revocation_token_hash
+                )
+                    VALUES ($1, $2, $3, $4, $5, $6, $7)",
                 token_hash.as_ref(),
+                session_id.as_slice(),
                 user.id,
+                client_ip,
+                user_agent,
+                device_fingerprint.as_ref(),
+                revocation_token_hash.as_ref().map(AsRef::as_ref),
             )
             .execute(savepoint.as_mut())
             .await
@@ -89,6 +257,20 @@ pub async fn post(
                     token.reroll()?;
                     continue;
                 }
+                Err(sqlx::Error::Database(error))
+                    if error.constraint() == Some("sessions_id_key") =>
+                {
+                    session_id.reroll()?;
+                    continue;
+                }
+                Err(sqlx::Error::Database(error))
+                    if error.constraint() == Some("sessions_revocation_token_hash_key") =>
+                {
+                    if let Some(revocation_token) = &mut revocation_token {
+                        revocation_token.reroll()?;
+                    }
+                    continue;
+                }
                 result => result?,
             };
 
@@ -96,10 +278,34 @@ pub async fn post(
             break;
         }
 
-        Ok(token)
+        if let Some(revocation_token) = &revocation_token {
+            notify(
+                tx.as_mut(),
+                &user.id,
+                Mailbox::new(None, (*body.email).clone()),
+                &NewSignInMessage {
+                    email: body.email.as_str(),
+                    ip: &client_ip,
+                    user_agent,
+                    revocation_url: &format!(
+                        "{}/revoke-session?token={}",
+                        *WEBSITE_ORIGIN, revocation_token,
+                    ),
+                },
+            )
+            .await?;
+        }
+
+        Ok(Some(token))
     })
     .await?;
 
+    let Some(token) = token else {
+        // To prevent user enumeration, send this same error response whether or not the email is
+        // correct.
+        return Err(api::Error::UserCredentialsWrong);
+    };
+
     cookies.add(
         Cookie::build(("token", token.to_string()))
             .domain(*WEBSITE_DOMAIN)
@@ -0,0 +1,68 @@
+//! The revocation of a sign-in session from a link in a new sign-in notification email.
+
+use axum::{extract::State, http::StatusCode};
+use axum_macros::debug_handler;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{self, Json, Query, Response},
+    crypto::hash_without_salt,
+    db::{self, TxError, TxResult},
+    id::Token,
+    AppState,
+};
+
+/// A `POST` request query for this API route.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PostQuery {
+    /// The session's revocation token.
+    pub token: Token,
+}
+
+/// Signs out the session a new sign-in notification email was sent for. The session's device is
+/// also forgotten, so signing in from it again sends another notification.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn post(
+    State(state): State<AppState>,
+    Query(query): Query<PostQuery>,
+) -> Response<PostResponse> {
+    let revocation_token_hash = hash_without_salt(&query.token);
+
+    db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+        let Some(session) = sqlx::query!(
+            "DELETE FROM sessions
+                WHERE revocation_token_hash = $1
+                RETURNING user_id, device_fingerprint",
+            revocation_token_hash.as_ref(),
+        )
+        .fetch_optional(tx.as_mut())
+        .await?
+        else {
+            return Err(TxError::Abort(api::Error::ResourceNotFound));
+        };
+
+        sqlx::query!(
+            "DELETE FROM known_devices
+                WHERE user_id = $1 AND fingerprint = $2",
+            session.user_id,
+            session.device_fingerprint,
+        )
+        .execute(tx.as_mut())
+        .await?;
+
+        Ok(())
+    })
+    .await?;
+
+    Ok((StatusCode::OK, Json(PostResponse {})))
+}
+
+/// A `POST` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PostResponse {}
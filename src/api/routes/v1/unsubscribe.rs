@@ -0,0 +1,72 @@
+//! One-click unsubscription from a category of notification emails, using a signed link from one
+//! of the emails.
+
+use axum::{extract::State, http::StatusCode};
+use axum_macros::debug_handler;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{self, routes::v1::users::notifications::NotificationCategory, Json, Query, Response},
+    crypto::verify_signature,
+    db::{self, TxResult},
+    id::Id,
+    AppState,
+};
+
+/// A `POST` request query for this API route.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PostQuery {
+    /// The ID of the user to unsubscribe.
+    pub user: Id,
+
+    /// The category of notifications to unsubscribe from.
+    pub category: NotificationCategory,
+
+    /// The link's signature.
+    pub signature: Id,
+}
+
+/// Unsubscribes a user from a category of notification emails.
+///
+/// Mail clients may send this request directly from the `List-Unsubscribe` header of a
+/// notification (as specified by RFC 8058), in which case its form body is ignored.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn post(
+    State(state): State<AppState>,
+    Query(query): Query<PostQuery>,
+) -> Response<PostResponse> {
+    if !verify_signature(
+        &query.category.unsubscribe_payload(&query.user),
+        &query.signature,
+    ) {
+        return Err(api::Error::ResourceNotFound);
+    }
+
+    db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+        sqlx::query!(
+            "INSERT INTO notification_opt_outs (user_id, category)
+                SELECT id, $2 FROM users
+                    WHERE id = $1
+                ON CONFLICT DO NOTHING",
+            query.user.as_slice(),
+            query.category as NotificationCategory,
+        )
+        .execute(tx.as_mut())
+        .await?;
+
+        Ok(())
+    })
+    .await?;
+
+    Ok((StatusCode::OK, Json(PostResponse {})))
+}
+
+/// A `POST` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PostResponse {}
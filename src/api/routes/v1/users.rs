@@ -8,15 +8,50 @@ use sqlx::Acquire;
 use crate::{
     api::{
         self,
-        validation::{EmailVerificationCode, NewUserPassword, UserEmail, UserName},
-        Json, Response,
+        auth::Auth,
+        routes::v1::{invites::INVITE_MAX_AGE, users::tos_acceptance::TOS_VERSION},
+        validation::{
+            EmailVerificationCode, NewUserPassword, UserEmail, UserName, STRIP_EMAIL_SUBADDRESSES,
+        },
+        Json, Path, Response,
     },
-    crypto::{hash_with_salt, verify_hash},
+    config::{self, Reloadable},
+    crypto::{hash_with_salt, hash_without_salt, verify_hash},
     db::{self, TxError, TxResult},
-    id::NewUserId,
+    id::{Id, NewUserId, Token},
     AppState,
 };
 
+pub mod analytics;
+pub mod billing;
+pub mod domains;
+pub mod email;
+pub mod events;
+pub mod favorites;
+pub mod handle;
+pub mod indexing;
+pub mod legal_hold;
+pub mod most_downloaded;
+pub mod notifications;
+pub mod plan;
+pub mod profile;
+pub mod role;
+pub mod sessions;
+pub mod tos_acceptance;
+pub mod usage;
+
+/// Whether signing up requires an invite.
+pub(crate) static IS_INVITE_REQUIRED: Reloadable<bool> = Reloadable::new(|| {
+    match config::optional_var("INVITE_REQUIRED")? {
+        // If the environment variable is unset, anyone can sign up.
+        None => Ok(false),
+
+        Some(is_invite_required) => is_invite_required.parse().map_err(|_| {
+            "environment variable `INVITE_REQUIRED` should be `true` or `false` if set".into()
+        }),
+    }
+});
+
 /// A `POST` request body for this API route.
 #[derive(Deserialize, Debug)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
@@ -32,6 +67,13 @@ pub struct PostRequest {
 
     /// The user's new password in plain text.
     pub password: NewUserPassword,
+
+    /// The token of an invite to accept, if any. This is required if signing up is invite-only.
+    pub invite_token: Option<Token>,
+
+    /// The version of the terms of service and privacy policy the user accepted. This must be the
+    /// current version.
+    pub tos_version: String,
 }
 
 /// Creates a new user.
@@ -44,10 +86,24 @@ pub async fn post(
     State(state): State<AppState>,
     Json(body): Json<PostRequest>,
 ) -> Response<PostResponse> {
+    if body.tos_version != *TOS_VERSION {
+        return Err(api::Error::TosReacceptanceRequired);
+    }
+
+    if state
+        .disposable_email_domains
+        .contains(body.email.as_str())
+        .await
+    {
+        return Err(api::Error::EmailDomainDisposable);
+    }
+
     let mut user_id = NewUserId::generate()?;
 
     let password_hash = hash_with_salt(&body.password)?;
 
+    let invite_token_hash = body.invite_token.as_ref().map(hash_without_salt);
+
     db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
         let does_code_match = sqlx::query!(
             "DELETE FROM unverified_emails
@@ -64,18 +120,76 @@ pub async fn post(
             return Err(TxError::Abort(api::Error::EmailVerificationCodeWrong));
         }
 
+        let invite = match &invite_token_hash {
+            Some(invite_token_hash) => {
+                let Some(invite) = sqlx::query!(
+                    "DELETE FROM invites
+                        WHERE token_hash = $1
+                            AND created_at > now() - make_interval(secs => $2)
+                            AND (email IS NULL OR email = $3)
+                        RETURNING organization_id",
+                    invite_token_hash.as_ref(),
+                    INVITE_MAX_AGE.as_secs_f64(),
+                    body.email.as_str(),
+                )
+                .fetch_optional(tx.as_mut())
+                .await?
+                else {
+                    return Err(TxError::Abort(api::Error::InviteInvalid));
+                };
+
+                Some(invite)
+            }
+            None if *IS_INVITE_REQUIRED.get() => {
+                return Err(TxError::Abort(api::Error::InviteRequired));
+            }
+            None => None,
+        };
+
+        let canonical_email = body.email.canonical();
+
+        if *STRIP_EMAIL_SUBADDRESSES.get() {
+            // Concurrent sign-ups with the same mailbox wait for each other, so only one can
+            // succeed.
+            sqlx::query!(
+                "SELECT FROM pg_advisory_xact_lock(hashtextextended($1, 0))",
+                &*canonical_email.to_lowercase(),
+            )
+            .execute(tx.as_mut())
+            .await?;
+
+            let is_mailbox_taken = sqlx::query!(
+                r#"SELECT EXISTS (
+                    SELECT 1 FROM users
+                        WHERE canonical_email = $1
+                ) as "is_mailbox_taken!""#,
+                &*canonical_email,
+            )
+            .fetch_one(tx.as_mut())
+            .await?
+            .is_mailbox_taken;
+
+            if is_mailbox_taken {
+                return Err(TxError::Abort(api::Error::EmailTaken));
+            }
+        }
+
         loop {
             // If this loop's query fails from an ID conflict, this savepoint is rolled back to
             // rather than aborting the entire transaction.
             let mut savepoint = tx.begin().await?;
 
             match sqlx::query!(
-                "INSERT INTO users (id, email, name, password_hash)
-                    VALUES ($1, $2, $3, $4)",
+                "INSERT INTO users (
+                    id, email, canonical_email, name, password_hash, tos_version, tos_accepted_at
+                )
+                    VALUES ($1, $2, $3, $4, $5, $6, now())",
                 user_id.as_slice(),
                 body.email.as_str(),
+                &*canonical_email,
                 *body.name,
                 password_hash,
+                body.tos_version,
             )
             .execute(savepoint.as_mut())
             .await
@@ -91,6 +205,17 @@ pub async fn post(
             break;
         }
 
+        if let Some(organization_id) = invite.and_then(|invite| invite.organization_id) {
+            sqlx::query!(
+                "INSERT INTO organization_members (organization_id, user_id, role)
+                    VALUES ($1, $2, 'member')",
+                organization_id,
+                user_id.as_slice(),
+            )
+            .execute(tx.as_mut())
+            .await?;
+        }
+
         Ok(())
     })
     .await?;
@@ -106,3 +231,172 @@ pub struct PostResponse {
     /// The user's ID.
     pub id: NewUserId,
 }
+
+/// Deletes a user's account. They're signed out everywhere, their sign-in and email tokens, known
+/// devices, custom domains, organization memberships, and access to others' folders are deleted,
+/// and their profile is anonymized right away. Their handle is released like a previous handle, so
+/// it's freed once the grace period ends. Their subscription is canceled and their files and
+/// folders are purged in the background.
+///
+/// The sole owner of an organization can't be deleted until another member is made an owner.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn delete(
+    State(state): State<AppState>,
+    auth: Auth,
+    Path(user_id): Path<Id>,
+) -> Response<DeleteResponse> {
+    auth.require_self_or_manager(&user_id)?;
+
+    db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+        let Some(user) = sqlx::query!(
+            "SELECT handle, legal_hold, stripe_subscription_id, subscription_status FROM users
+                WHERE id = $1 AND deleted_at IS NULL
+                FOR UPDATE",
+            user_id.as_slice(),
+        )
+        .fetch_optional(tx.as_mut())
+        .await?
+        else {
+            return Err(TxError::Abort(api::Error::ResourceNotFound));
+        };
+
+        if user.legal_hold {
+            return Err(TxError::Abort(api::Error::LegalHoldActive));
+        }
+
+        let is_sole_owner = sqlx::query!(
+            r#"SELECT EXISTS (
+                SELECT 1 FROM organization_members AS membership
+                    WHERE membership.user_id = $1 AND membership.role = 'owner'
+                        AND NOT EXISTS (
+                            SELECT 1 FROM organization_members
+                                WHERE organization_id = membership.organization_id
+                                    AND role = 'owner' AND user_id != $1
+                        )
+            ) as "is_sole_owner!""#,
+            user_id.as_slice(),
+        )
+        .fetch_one(tx.as_mut())
+        .await?
+        .is_sole_owner;
+
+        // Otherwise, nobody would be able to manage the organization.
+        if is_sole_owner {
+            return Err(TxError::Abort(api::Error::OrganizationOwnerRequired));
+        }
+
+        if let Some(subscription_id) = user.stripe_subscription_id {
+            if user.subscription_status.as_deref() != Some("canceled") {
+                sqlx::query!(
+                    "INSERT INTO subscription_cancellations (subscription_id)
+                        VALUES ($1)
+                        ON CONFLICT DO NOTHING",
+                    subscription_id,
+                )
+                .execute(tx.as_mut())
+                .await?;
+            }
+        }
+
+        if let Some(handle) = user.handle {
+            sqlx::query!(
+                "INSERT INTO previous_handles (handle, user_id)
+                    VALUES ($1, $2)",
+                handle,
+                user_id.as_slice(),
+            )
+            .execute(tx.as_mut())
+            .await?;
+        }
+
+        // The email is replaced with a unique address that can't receive mail, since it must be
+        // unique and can't be null.
+        sqlx::query!(
+            "UPDATE users
+                SET deleted_at = now(),
+                    email = 'deleted-' || encode(id, 'hex') || '@deleted.invalid',
+                    canonical_email = 'deleted-' || encode(id, 'hex') || '@deleted.invalid',
+                    name = '',
+                    password_hash = '',
+                    totp_secret = NULL,
+                    handle = NULL,
+                    bio = '',
+                    avatar_file_id = NULL
+                WHERE id = $1",
+            user_id.as_slice(),
+        )
+        .execute(tx.as_mut())
+        .await?;
+
+        sqlx::query!(
+            "DELETE FROM sessions
+                WHERE user_id = $1",
+            user_id.as_slice(),
+        )
+        .execute(tx.as_mut())
+        .await?;
+
+        sqlx::query!(
+            "DELETE FROM password_resets
+                WHERE user_id = $1",
+            user_id.as_slice(),
+        )
+        .execute(tx.as_mut())
+        .await?;
+
+        sqlx::query!(
+            "DELETE FROM unverified_emails
+                WHERE user_id = $1",
+            user_id.as_slice(),
+        )
+        .execute(tx.as_mut())
+        .await?;
+
+        sqlx::query!(
+            "DELETE FROM known_devices
+                WHERE user_id = $1",
+            user_id.as_slice(),
+        )
+        .execute(tx.as_mut())
+        .await?;
+
+        // The domains stop serving files right away, and can be added to another account.
+        sqlx::query!(
+            "DELETE FROM custom_domains
+                WHERE user_id = $1",
+            user_id.as_slice(),
+        )
+        .execute(tx.as_mut())
+        .await?;
+
+        sqlx::query!(
+            "DELETE FROM organization_members
+                WHERE user_id = $1",
+            user_id.as_slice(),
+        )
+        .execute(tx.as_mut())
+        .await?;
+
+        sqlx::query!(
+            "DELETE FROM folder_access_grants
+                WHERE user_id = $1",
+            user_id.as_slice(),
+        )
+        .execute(tx.as_mut())
+        .await?;
+
+        Ok(())
+    })
+    .await?;
+
+    Ok((StatusCode::OK, Json(DeleteResponse {})))
+}
+
+/// A `DELETE` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteResponse {}
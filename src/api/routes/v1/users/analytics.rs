@@ -0,0 +1,148 @@
+//! The traffic of all a user's files over time.
+
+use axum::{extract::State, http::StatusCode};
+use axum_macros::debug_handler;
+use serde::Serialize;
+
+use crate::{
+    api::{
+        self,
+        auth::Auth,
+        routes::v1::files::analytics::{GetQuery, TrafficBucket},
+        Json, Path, Query, Response,
+    },
+    db::{self, TxResult},
+    id::Id,
+    AppState,
+};
+
+/// The number of most requested files listed.
+const TOP_FILES_LIMIT: i64 = 20;
+
+/// Lists the combined traffic of a user's files over time, bucketed by the requested interval, and
+/// the user's most requested files over the same range. Only buckets with traffic are listed.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn get(
+    State(state): State<AppState>,
+    auth: Auth,
+    Path(user_id): Path<Id>,
+    Query(query): Query<GetQuery>,
+) -> Response<GetResponse> {
+    auth.require_self_or_manager(&user_id)?;
+
+    let (since, until) = query.range()?;
+
+    let (series, top_files) = db::transaction!(state.db_replica_pool, async |tx| -> TxResult<
+        _,
+        api::Error,
+    > {
+        // A visitor to several of the user's files counts as one unique visitor, since IP
+        // addresses hash the same for every file.
+        let series = sqlx::query_as!(
+            TrafficBucket,
+            r#"WITH traffic AS (
+                    SELECT date_trunc($2, file_traffic.hour, 'UTC') as start,
+                        sum(file_traffic.requests) as requests, sum(file_traffic.bytes) as bytes
+                        FROM file_traffic JOIN files ON files.id = file_traffic.file_id
+                        WHERE files.owner_id = $1
+                            AND file_traffic.hour >= date_trunc('hour', $3, 'UTC')
+                            AND file_traffic.hour < $4
+                        GROUP BY 1
+                ), visitors AS (
+                    SELECT date_trunc($2, file_visitors.hour, 'UTC') as start,
+                        count(DISTINCT file_visitors.visitor_hash) as unique_visitors
+                        FROM file_visitors JOIN files ON files.id = file_visitors.file_id
+                        WHERE files.owner_id = $1
+                            AND file_visitors.hour >= date_trunc('hour', $3, 'UTC')
+                            AND file_visitors.hour < $4
+                        GROUP BY 1
+                )
+                SELECT traffic.start as "start!", traffic.requests::bigint as "requests!",
+                    traffic.bytes::bigint as "bytes!",
+                    coalesce(visitors.unique_visitors, 0) as "unique_visitors!"
+                    FROM traffic
+                    LEFT JOIN visitors ON visitors.start = traffic.start
+                    ORDER BY traffic.start"#,
+            user_id.as_slice(),
+            query.interval.as_str(),
+            since,
+            until,
+        )
+        .fetch_all(tx.as_mut())
+        .await?;
+
+        let top_files = sqlx::query!(
+            r#"SELECT files.id, files.name, files.parent_name_path,
+                    sum(file_traffic.requests)::bigint as "requests!",
+                    sum(file_traffic.bytes)::bigint as "bytes!"
+                    FROM file_traffic JOIN files ON files.id = file_traffic.file_id
+                    WHERE files.owner_id = $1
+                        AND file_traffic.hour >= date_trunc('hour', $2, 'UTC')
+                        AND file_traffic.hour < $3
+                    GROUP BY files.id
+                    ORDER BY 4 DESC, files.id
+                    LIMIT $4"#,
+            user_id.as_slice(),
+            since,
+            until,
+            TOP_FILES_LIMIT,
+        )
+        .fetch_all(tx.as_mut())
+        .await?;
+
+        Ok((series, top_files))
+    })
+    .await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(GetResponse {
+            series,
+            top_files: top_files
+                .into_iter()
+                .map(|file| FileTraffic {
+                    id: file.id.into(),
+                    name: file.name,
+                    parent_path: file.parent_name_path,
+                    requests: file.requests,
+                    bytes: file.bytes,
+                })
+                .collect(),
+        }),
+    ))
+}
+
+/// A `GET` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GetResponse {
+    /// The combined traffic of the user's files in each bucket with any, from oldest to newest.
+    pub series: Vec<TrafficBucket>,
+
+    /// The user's 20 most requested files over the requested range, most requested first.
+    pub top_files: Vec<FileTraffic>,
+}
+
+/// A file and its total traffic over the requested range.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FileTraffic {
+    /// The file's ID.
+    pub id: Id,
+
+    /// The file's name.
+    pub name: String,
+
+    /// The names of the file's ancestor folders.
+    pub parent_path: Vec<String>,
+
+    /// The number of times the file was downloaded.
+    pub requests: i64,
+
+    /// The total number of bytes sent.
+    pub bytes: i64,
+}
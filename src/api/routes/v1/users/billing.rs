@@ -0,0 +1,75 @@
+//! A user's subscription to a paid plan.
+
+use axum::{extract::State, http::StatusCode};
+use axum_macros::debug_handler;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::{
+    api::{self, auth::Auth, routes::v1::plans::Plan, Json, Path, Response},
+    db::{self, TxResult},
+    id::Id,
+    AppState,
+};
+
+pub mod checkout;
+pub mod portal;
+
+/// Gets a user's plan and the state of their subscription.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn get(
+    State(state): State<AppState>,
+    auth: Auth,
+    Path(user_id): Path<Id>,
+) -> Response<GetResponse> {
+    auth.require_self_or_manager(&user_id)?;
+
+    let Some(user) = db::transaction!(
+        state.db_replica_pool,
+        async |tx| -> TxResult<_, api::Error> {
+            Ok(sqlx::query_as!(
+                GetResponse,
+                r#"SELECT plan as "plan: Plan", subscription_status, subscription_period_end,
+                    subscription_canceling, payment_failed_at
+                    FROM users
+                    WHERE id = $1"#,
+                user_id.as_slice(),
+            )
+            .fetch_optional(tx.as_mut())
+            .await?)
+        }
+    )
+    .await?
+    else {
+        return Err(api::Error::ResourceNotFound);
+    };
+
+    Ok((StatusCode::OK, Json(user)))
+}
+
+/// A `GET` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GetResponse {
+    /// The user's plan.
+    pub plan: Plan,
+
+    /// The status of the user's latest subscription as reported by Stripe (e.g. `active` or
+    /// `past_due`), or `None` if they've never subscribed.
+    pub subscription_status: Option<String>,
+
+    /// When the subscription's current billing period ends.
+    pub subscription_period_end: Option<DateTime<Utc>>,
+
+    /// Whether the subscription is set to cancel at the end of its current billing period, after
+    /// which the user moves to the free plan.
+    pub subscription_canceling: bool,
+
+    /// When a payment for the subscription last failed, or `None` if no payment has failed since
+    /// the last successful one.
+    pub payment_failed_at: Option<DateTime<Utc>>,
+}
@@ -0,0 +1,88 @@
+//! Checkout pages for users to subscribe to paid plans.
+
+use axum::{extract::State, http::StatusCode};
+use axum_macros::debug_handler;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{self, auth::Auth, routes::v1::plans::Plan, stripe, Json, Path, Response},
+    db::{self, TxError, TxResult},
+    id::Id,
+    AppState,
+};
+
+/// A `POST` request body for this API route.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct PostRequest {
+    /// The paid plan to subscribe to.
+    pub plan: Plan,
+}
+
+/// Creates a checkout page for a user to subscribe to a paid plan. Their plan changes once Stripe
+/// reports the subscription started.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn post(
+    State(state): State<AppState>,
+    auth: Auth,
+    Path(user_id): Path<Id>,
+    Json(body): Json<PostRequest>,
+) -> Response<PostResponse> {
+    auth.require_self_or_manager(&user_id)?;
+
+    let Some(price_id) = stripe::price_id(body.plan) else {
+        return Err(api::Error::InvalidBodyData(api::FieldError {
+            field: "plan".into(),
+            code: "INVALID_VALUE",
+            message: "only paid plans can be subscribed to".into(),
+        }));
+    };
+
+    let user = db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+        let Some(user) = sqlx::query!(
+            r#"SELECT email::text as "email!", stripe_customer_id, subscription_status
+                FROM users
+                WHERE id = $1"#,
+            user_id.as_slice(),
+        )
+        .fetch_optional(tx.as_mut())
+        .await?
+        else {
+            return Err(TxError::Abort(api::Error::ResourceNotFound));
+        };
+
+        // Changing an existing subscription is done through the billing portal, which prorates it.
+        if user
+            .subscription_status
+            .as_deref()
+            .is_some_and(|status| !matches!(status, "canceled" | "incomplete_expired"))
+        {
+            return Err(TxError::Abort(api::Error::SubscriptionActive));
+        }
+
+        Ok(user)
+    })
+    .await?;
+
+    let url = stripe::create_checkout_session(
+        &user_id,
+        user.stripe_customer_id.as_deref(),
+        &user.email,
+        price_id,
+    )
+    .await?;
+
+    Ok((StatusCode::OK, Json(PostResponse { url })))
+}
+
+/// A `POST` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PostResponse {
+    /// The URL of the checkout page to send the user to.
+    pub url: String,
+}
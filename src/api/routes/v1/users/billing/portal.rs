@@ -0,0 +1,57 @@
+//! The billing portal, where users change or cancel their subscriptions.
+
+use axum::{extract::State, http::StatusCode};
+use axum_macros::debug_handler;
+use serde::Serialize;
+
+use crate::{
+    api::{self, auth::Auth, stripe, Json, Path, Response},
+    db::{self, TxResult},
+    id::Id,
+    AppState,
+};
+
+/// Creates a billing portal page for a user to change or cancel their subscription, or to update
+/// their payment details. The user must have subscribed before.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn post(
+    State(state): State<AppState>,
+    auth: Auth,
+    Path(user_id): Path<Id>,
+) -> Response<PostResponse> {
+    auth.require_self_or_manager(&user_id)?;
+
+    let Some(customer_id) = db::transaction!(state.db_replica_pool, async |tx| -> TxResult<
+        _,
+        api::Error,
+    > {
+        Ok(sqlx::query!(
+            "SELECT stripe_customer_id FROM users
+                    WHERE id = $1",
+            user_id.as_slice(),
+        )
+        .fetch_optional(tx.as_mut())
+        .await?
+        .and_then(|user| user.stripe_customer_id))
+    })
+    .await?
+    else {
+        return Err(api::Error::ResourceNotFound);
+    };
+
+    let url = stripe::create_portal_session(&customer_id).await?;
+
+    Ok((StatusCode::OK, Json(PostResponse { url })))
+}
+
+/// A `POST` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PostResponse {
+    /// The URL of the billing portal page to send the user to.
+    pub url: String,
+}
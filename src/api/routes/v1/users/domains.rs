@@ -0,0 +1,270 @@
+//! The set of a user's custom domains, which serve the user's files.
+
+use axum::{extract::State, http::StatusCode};
+use axum_macros::debug_handler;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{self, auth::Auth, validation::DomainName, Json, Path, Response},
+    db::{self, TxError, TxResult},
+    id::{DomainVerificationToken, Id},
+    AppState,
+};
+
+pub mod verification;
+
+/// The subdomain of a custom domain that its verification TXT record must be on.
+const VERIFICATION_RECORD_SUBDOMAIN: &str = "_filegarden";
+
+/// The start of a custom domain's verification TXT record value, followed by its verification
+/// token.
+const VERIFICATION_RECORD_VALUE_PREFIX: &str = "filegarden-verification=";
+
+/// Lists a user's custom domains, including unverified ones.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn get(
+    State(state): State<AppState>,
+    auth: Auth,
+    Path(user_id): Path<Id>,
+) -> Response<GetResponse> {
+    auth.require_self_or_manager(&user_id)?;
+
+    let domains = db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+        Ok(sqlx::query!(
+            r#"SELECT name, verification_token, verified_at IS NOT NULL as "verified!", canonical
+                FROM custom_domains
+                WHERE user_id = $1
+                ORDER BY created_at"#,
+            user_id.as_slice(),
+        )
+        .fetch_all(tx.as_mut())
+        .await?)
+    })
+    .await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(GetResponse {
+            domains: domains
+                .into_iter()
+                .map(|domain| {
+                    Domain::new(
+                        domain.name,
+                        &domain.verification_token,
+                        domain.verified,
+                        domain.canonical,
+                    )
+                })
+                .collect(),
+        }),
+    ))
+}
+
+/// A `GET` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GetResponse {
+    /// The user's custom domains, from oldest to newest.
+    pub domains: Vec<Domain>,
+}
+
+/// A user's custom domain.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Domain {
+    /// The domain name.
+    pub name: String,
+
+    /// Whether the user has proven they own the domain. Only verified domains serve files.
+    pub verified: bool,
+
+    /// Whether requests for the user's files on any other origin redirect to this domain once it's
+    /// verified.
+    pub canonical: bool,
+
+    /// The DNS record the user must add to verify the domain.
+    pub verification_record: VerificationRecord,
+}
+
+impl Domain {
+    /// Constructs a new [`Domain`] from its database fields.
+    pub(crate) fn new(
+        name: String,
+        verification_token: &[u8],
+        verified: bool,
+        canonical: bool,
+    ) -> Self {
+        let verification_record = VerificationRecord {
+            name: format!("{VERIFICATION_RECORD_SUBDOMAIN}.{name}"),
+            value: format!(
+                "{VERIFICATION_RECORD_VALUE_PREFIX}{}",
+                Id::from(verification_token),
+            ),
+        };
+
+        Self {
+            name,
+            verified,
+            canonical,
+            verification_record,
+        }
+    }
+}
+
+/// A TXT record proving ownership of a custom domain.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct VerificationRecord {
+    /// The domain name the record must be on.
+    pub name: String,
+
+    /// The record's text value.
+    pub value: String,
+}
+
+/// A `PUT` request body for this API route.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct PutRequest {
+    /// Whether requests for the user's files on any other origin should redirect to this domain
+    /// once it's verified. Setting this unsets it on the user's other domains.
+    pub canonical: bool,
+}
+
+/// Adds a custom domain to a user, or changes one of their existing custom domains. The domain
+/// doesn't serve their files until it's verified, and only while their plan includes custom domains.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn put(
+    State(state): State<AppState>,
+    auth: Auth,
+    Path((user_id, domain_name)): Path<(Id, DomainName)>,
+    Json(body): Json<PutRequest>,
+) -> Response<Domain> {
+    auth.require_self_or_manager(&user_id)?;
+
+    let verification_token = DomainVerificationToken::generate()?;
+
+    let domain = db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+        let Some(user) = sqlx::query!(
+            "SELECT plan_limits.custom_domains
+                FROM users JOIN plan_limits ON plan_limits.plan = users.plan
+                WHERE users.id = $1",
+            user_id.as_slice(),
+        )
+        .fetch_optional(tx.as_mut())
+        .await?
+        else {
+            return Err(TxError::Abort(api::Error::ResourceNotFound));
+        };
+
+        if !user.custom_domains {
+            return Err(TxError::Abort(api::Error::PlanFeatureRequired));
+        }
+
+        let is_taken = sqlx::query!(
+            r#"SELECT EXISTS (
+                SELECT 1 FROM custom_domains
+                    WHERE name = $1 AND user_id != $2 AND verified_at IS NOT NULL
+            ) as "is_taken!""#,
+            domain_name.as_str(),
+            user_id.as_slice(),
+        )
+        .fetch_one(tx.as_mut())
+        .await?
+        .is_taken;
+
+        if is_taken {
+            return Err(TxError::Abort(api::Error::DomainTaken));
+        }
+
+        if body.canonical {
+            sqlx::query!(
+                "UPDATE custom_domains
+                    SET canonical = FALSE
+                    WHERE user_id = $1 AND name != $2 AND canonical",
+                user_id.as_slice(),
+                domain_name.as_str(),
+            )
+            .execute(tx.as_mut())
+            .await?;
+        }
+
+        match sqlx::query!(
+            r#"INSERT INTO custom_domains (name, user_id, verification_token, canonical)
+                VALUES ($1, $2, $3, $4)
+                ON CONFLICT (name, user_id) DO UPDATE
+                    SET canonical = excluded.canonical
+                RETURNING verification_token, verified_at IS NOT NULL as "verified!", canonical"#,
+            domain_name.as_str(),
+            user_id.as_slice(),
+            verification_token.as_slice(),
+            body.canonical,
+        )
+        .fetch_one(tx.as_mut())
+        .await
+        {
+            Err(sqlx::Error::Database(error))
+                if error.constraint() == Some("custom_domains_user_id_fkey") =>
+            {
+                Err(TxError::Abort(api::Error::ResourceNotFound))
+            }
+            result => Ok(result?),
+        }
+    })
+    .await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(Domain::new(
+            domain_name.to_string(),
+            &domain.verification_token,
+            domain.verified,
+            domain.canonical,
+        )),
+    ))
+}
+
+/// Removes a custom domain from a user.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn delete(
+    State(state): State<AppState>,
+    auth: Auth,
+    Path((user_id, domain_name)): Path<(Id, DomainName)>,
+) -> Response<DeleteResponse> {
+    auth.require_self_or_manager(&user_id)?;
+
+    let Some(_) = db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+        Ok(sqlx::query!(
+            "DELETE FROM custom_domains
+                WHERE name = $1 AND user_id = $2
+                RETURNING 1 as deleted",
+            domain_name.as_str(),
+            user_id.as_slice(),
+        )
+        .fetch_optional(tx.as_mut())
+        .await?)
+    })
+    .await?
+    else {
+        return Err(api::Error::ResourceNotFound);
+    };
+
+    Ok((StatusCode::OK, Json(DeleteResponse {})))
+}
+
+/// A `DELETE` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteResponse {}
@@ -0,0 +1,121 @@
+//! The verification of a user's custom domain.
+
+use axum::{extract::State, http::StatusCode};
+use axum_macros::debug_handler;
+use hickory_resolver::{error::ResolveErrorKind, TokioAsyncResolver};
+
+use crate::{
+    api::{
+        self, auth::Auth, routes::v1::users::domains::Domain, validation::DomainName, Json, Path,
+        Response,
+    },
+    db::{self, TxError, TxResult},
+    id::Id,
+    AppState,
+};
+
+/// Verifies a user's custom domain by checking its DNS records for its verification record. Once
+/// verified, the domain serves the user's files, and any other users' unverified claims to it are
+/// removed.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn post(
+    State(state): State<AppState>,
+    auth: Auth,
+    Path((user_id, domain_name)): Path<(Id, DomainName)>,
+) -> Response<Domain> {
+    auth.require_self_or_manager(&user_id)?;
+
+    let Some(domain) = db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+        Ok(sqlx::query!(
+            r#"SELECT verification_token, verified_at IS NOT NULL as "verified!", canonical
+                FROM custom_domains
+                WHERE name = $1 AND user_id = $2"#,
+            domain_name.as_str(),
+            user_id.as_slice(),
+        )
+        .fetch_optional(tx.as_mut())
+        .await?)
+    })
+    .await?
+    else {
+        return Err(api::Error::ResourceNotFound);
+    };
+
+    let mut domain = Domain::new(
+        domain_name.to_string(),
+        &domain.verification_token,
+        domain.verified,
+        domain.canonical,
+    );
+
+    if domain.verified {
+        return Ok((StatusCode::OK, Json(domain)));
+    }
+
+    // A new resolver is used for each verification so negative responses cached from a previous
+    // attempt don't cause this one to fail.
+    let resolver = TokioAsyncResolver::tokio_from_system_conf()?;
+
+    // The trailing `.` makes the name fully qualified so the system's search domains aren't tried.
+    let records = match resolver
+        .txt_lookup(format!("{}.", domain.verification_record.name))
+        .await
+    {
+        Err(error) if matches!(error.kind(), ResolveErrorKind::NoRecordsFound { .. }) => {
+            return Err(api::Error::DomainVerificationFailed);
+        }
+        result => result?,
+    };
+
+    let has_verification_record = records
+        .iter()
+        .any(|record| record.txt_data().concat() == domain.verification_record.value.as_bytes());
+
+    if !has_verification_record {
+        return Err(api::Error::DomainVerificationFailed);
+    }
+
+    db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+        match sqlx::query!(
+            "UPDATE custom_domains
+                SET verified_at = now()
+                WHERE name = $1 AND user_id = $2",
+            domain_name.as_str(),
+            user_id.as_slice(),
+        )
+        .execute(tx.as_mut())
+        .await
+        {
+            Err(sqlx::Error::Database(error))
+                if error.constraint() == Some("verified_custom_domains") =>
+            {
+                return Err(TxError::Abort(api::Error::DomainTaken));
+            }
+            result => {
+                if result?.rows_affected() == 0 {
+                    return Err(TxError::Abort(api::Error::ResourceNotFound));
+                }
+            }
+        };
+
+        sqlx::query!(
+            "DELETE FROM custom_domains
+                WHERE name = $1 AND user_id != $2",
+            domain_name.as_str(),
+            user_id.as_slice(),
+        )
+        .execute(tx.as_mut())
+        .await?;
+
+        Ok(())
+    })
+    .await?;
+
+    domain.verified = true;
+
+    Ok((StatusCode::OK, Json(domain)))
+}
@@ -0,0 +1,62 @@
+//! A user's email address and whether it's deliverable.
+
+use axum::{extract::State, http::StatusCode};
+use axum_macros::debug_handler;
+use serde::Serialize;
+
+use crate::{
+    api::{self, auth::Auth, Json, Path, Response},
+    db::{self, TxResult},
+    id::Id,
+    AppState,
+};
+
+pub mod verification;
+
+/// Gets a user's email address.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn get(
+    State(state): State<AppState>,
+    auth: Auth,
+    Path(user_id): Path<Id>,
+) -> Response<GetResponse> {
+    auth.require_self_or_manager(&user_id)?;
+
+    let Some(user) = db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+        Ok(sqlx::query!(
+            "SELECT email, email_reverification_required FROM users
+                WHERE id = $1",
+            user_id.as_slice(),
+        )
+        .fetch_optional(tx.as_mut())
+        .await?)
+    })
+    .await?
+    else {
+        return Err(api::Error::ResourceNotFound);
+    };
+
+    Ok((
+        StatusCode::OK,
+        Json(GetResponse {
+            email: user.email,
+            reverification_required: user.email_reverification_required,
+        }),
+    ))
+}
+
+/// A `GET` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GetResponse {
+    /// The user's email address.
+    pub email: String,
+
+    /// Whether an email to the address bounced or was complained about, so no more emails are sent
+    /// to it and the user must verify a working email.
+    pub reverification_required: bool,
+}
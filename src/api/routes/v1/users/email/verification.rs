@@ -0,0 +1,181 @@
+//! A user's request to verify their existing email address works again, after an email to it
+//! bounced or was complained about.
+
+use axum::{extract::State, http::StatusCode};
+use axum_macros::debug_handler;
+use lettre::message::Mailbox;
+use serde::{Deserialize, Serialize};
+use sqlx::Acquire;
+
+use crate::{
+    api::{self, auth::Auth, Json, Path, Response},
+    crypto::hash_without_salt,
+    db::{self, TxError, TxResult},
+    email::{MessageTemplate, SendMessage, VerificationMessage},
+    id::{Id, Token},
+    AppState, WEBSITE_ORIGIN,
+};
+
+/// Sends a verification email to a user's email address. Any previous verification request for the
+/// user is expired.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn post(
+    State(state): State<AppState>,
+    auth: Auth,
+    Path(user_id): Path<Id>,
+) -> Response<PostResponse> {
+    auth.require_self_or_manager(&user_id)?;
+
+    db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+        let Some(user) = sqlx::query!(
+            "SELECT email, name FROM users
+                WHERE id = $1",
+            user_id.as_slice(),
+        )
+        .fetch_optional(tx.as_mut())
+        .await?
+        else {
+            return Err(TxError::Abort(api::Error::ResourceNotFound));
+        };
+
+        // Expire any previous email verification request.
+        sqlx::query!(
+            "DELETE FROM unverified_emails
+                WHERE user_id = $1",
+            user_id.as_slice(),
+        )
+        .execute(tx.as_mut())
+        .await?;
+
+        let mut token = Token::generate()?;
+
+        loop {
+            // If this loop's query fails from a token conflict, this savepoint is rolled back to
+            // rather than aborting the entire transaction.
+            let mut savepoint = tx.begin().await?;
+
+            let token_hash = hash_without_salt(&token);
+
+            match sqlx::query!(
+                "INSERT INTO unverified_emails (token_hash, user_id, email)
+                    VALUES ($1, $2, $3)",
+                token_hash.as_ref(),
+                user_id.as_slice(),
+                user.email,
+            )
+            .execute(savepoint.as_mut())
+            .await
+            {
+                Err(sqlx::Error::Database(error))
+                    if error.constraint() == Some("unverified_emails_pkey") =>
+                {
+                    token.reroll()?;
+                    continue;
+                }
+                result => result?,
+            };
+
+            savepoint.commit().await?;
+            break;
+        }
+
+        VerificationMessage {
+            email: &user.email,
+            verification_url: &format!("{}/reverify-email?token={}", *WEBSITE_ORIGIN, token),
+        }
+        .to(Mailbox::new(
+            Some(user.name),
+            user.email.parse().expect("user's email should be valid"),
+        ))
+        .send_requested();
+
+        Ok(())
+    })
+    .await?;
+
+    Ok((StatusCode::OK, Json(PostResponse {})))
+}
+
+/// A `POST` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PostResponse {}
+
+/// A `PUT` request body for this API route.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct PutRequest {
+    /// The email verification token from the verification email.
+    pub token: Token,
+}
+
+/// Completes a user's email verification request, showing their email address works. This clears
+/// the requirement to reverify it, and lets emails be sent to it again even if it bounced or was
+/// complained about before.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn put(
+    State(state): State<AppState>,
+    auth: Auth,
+    Path(user_id): Path<Id>,
+    Json(body): Json<PutRequest>,
+) -> Response<PutResponse> {
+    auth.require_self_or_manager(&user_id)?;
+
+    let token_hash = hash_without_salt(&body.token);
+
+    db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+        // The request is only valid for the address it was sent to, in case the user's address
+        // changed since.
+        let Some(user) = sqlx::query!(
+            "DELETE FROM unverified_emails
+                USING users
+                WHERE unverified_emails.token_hash = $1
+                    AND unverified_emails.user_id = $2
+                    AND users.id = unverified_emails.user_id
+                    AND users.email = unverified_emails.email
+                RETURNING users.email",
+            token_hash.as_ref(),
+            user_id.as_slice(),
+        )
+        .fetch_optional(tx.as_mut())
+        .await?
+        else {
+            return Err(TxError::Abort(api::Error::ResourceNotFound));
+        };
+
+        sqlx::query!(
+            "UPDATE users
+                SET email_reverification_required = FALSE
+                WHERE id = $1",
+            user_id.as_slice(),
+        )
+        .execute(tx.as_mut())
+        .await?;
+
+        sqlx::query!(
+            "DELETE FROM email_suppressions
+                WHERE email = $1",
+            user.email,
+        )
+        .execute(tx.as_mut())
+        .await?;
+
+        Ok(())
+    })
+    .await?;
+
+    Ok((StatusCode::OK, Json(PutResponse {})))
+}
+
+/// A `PUT` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PutResponse {}
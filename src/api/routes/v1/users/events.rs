@@ -0,0 +1,153 @@
+//! The activity log of a user's file lifecycle events.
+
+use std::num::NonZeroU8;
+
+use axum::{extract::State, http::StatusCode};
+use axum_macros::debug_handler;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{self, auth::Auth, Json, Path, Query, Response},
+    db::{self, TxResult},
+    id::Id,
+    AppState,
+};
+
+/// The number of events listed if the request doesn't specify a limit.
+const DEFAULT_LIMIT: u8 = 50;
+
+/// The maximum number of events that can be listed at once.
+const MAX_LIMIT: u8 = 100;
+
+/// A type of change to a file.
+#[derive(sqlx::Type, Serialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[sqlx(type_name = "file_event_type", rename_all = "lowercase")]
+#[serde(rename_all = "camelCase")]
+pub enum FileEventType {
+    /// The file was created.
+    Uploaded,
+
+    /// The file's name changed.
+    Renamed,
+
+    /// The file moved to a different folder.
+    Moved,
+
+    /// The file was made accessible to people with its link.
+    Shared,
+
+    /// The file was made inaccessible to people with its link.
+    Unshared,
+
+    /// The file was deleted.
+    Deleted,
+}
+
+/// A `GET` request query for this API route.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GetQuery {
+    /// Only list events older than the event with this ID. Used to get the next page of events.
+    pub before: Option<i64>,
+
+    /// The maximum number of events to list, from 1 up to 100. Defaults to 50.
+    pub limit: Option<NonZeroU8>,
+}
+
+/// Lists a user's file lifecycle events from newest to oldest.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn get(
+    State(state): State<AppState>,
+    auth: Auth,
+    Path(user_id): Path<Id>,
+    Query(query): Query<GetQuery>,
+) -> Response<GetResponse> {
+    auth.require_self_or_manager(&user_id)?;
+
+    let limit = query
+        .limit
+        .map_or(DEFAULT_LIMIT, NonZeroU8::get)
+        .min(MAX_LIMIT);
+
+    let events = db::transaction!(
+        state.db_replica_pool,
+        async |tx| -> TxResult<_, api::Error> {
+            Ok(sqlx::query!(
+                r#"SELECT
+                id, created_at, type as "type: FileEventType", file_id, name, parent_name_path,
+                previous_name, previous_parent_name_path
+                FROM file_events
+                WHERE user_id = $1 AND ($2::bigint IS NULL OR id < $2)
+                ORDER BY id DESC
+                LIMIT $3"#,
+                user_id.as_slice(),
+                query.before,
+                i64::from(limit),
+            )
+            .fetch_all(tx.as_mut())
+            .await?)
+        }
+    )
+    .await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(GetResponse {
+            events: events
+                .into_iter()
+                .map(|event| FileEvent {
+                    id: event.id,
+                    created_at: event.created_at,
+                    r#type: event.r#type,
+                    file_id: event.file_id.into(),
+                    name: event.name,
+                    parent_path: event.parent_name_path,
+                    previous_name: event.previous_name,
+                    previous_parent_path: event.previous_parent_name_path,
+                })
+                .collect(),
+        }),
+    ))
+}
+
+/// A `GET` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GetResponse {
+    /// The listed events, from newest to oldest.
+    pub events: Vec<FileEvent>,
+}
+
+/// A change to one of a user's files.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FileEvent {
+    /// The event's ID.
+    pub id: i64,
+
+    /// When the event occurred.
+    pub created_at: DateTime<Utc>,
+
+    /// The type of change.
+    pub r#type: FileEventType,
+
+    /// The ID of the changed file.
+    pub file_id: Id,
+
+    /// The file's name after the change.
+    pub name: String,
+
+    /// The names of the file's ancestor folders after the change.
+    pub parent_path: Vec<String>,
+
+    /// The file's name before the change, if it was renamed or moved.
+    pub previous_name: Option<String>,
+
+    /// The names of the file's ancestor folders before the change, if it was renamed or moved.
+    pub previous_parent_path: Option<Vec<String>>,
+}
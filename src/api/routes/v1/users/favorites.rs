@@ -0,0 +1,125 @@
+//! The files and folders a user has starred.
+
+use axum::{extract::State, http::StatusCode};
+use axum_macros::debug_handler;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::{
+    api::{self, auth::Auth, Json, Path, Response},
+    db::{self, TxResult},
+    id::Id,
+    AppState,
+};
+
+/// Lists the files and folders a user has starred, most recently starred first.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn get(
+    State(state): State<AppState>,
+    auth: Auth,
+    Path(user_id): Path<Id>,
+) -> Response<GetResponse> {
+    auth.require_self_or_manager(&user_id)?;
+
+    let (files, folders) = db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+        let files = sqlx::query!(
+            r#"SELECT id, name, parent_name_path, type, favorited_at as "favorited_at!"
+                FROM files
+                WHERE owner_id = $1 AND favorited_at IS NOT NULL
+                ORDER BY favorited_at DESC"#,
+            user_id.as_slice(),
+        )
+        .fetch_all(tx.as_mut())
+        .await?;
+
+        let folders = sqlx::query!(
+            r#"SELECT id, name, parent_name_path, favorited_at as "favorited_at!"
+                FROM folders
+                WHERE owner_id = $1 AND favorited_at IS NOT NULL
+                ORDER BY favorited_at DESC"#,
+            user_id.as_slice(),
+        )
+        .fetch_all(tx.as_mut())
+        .await?;
+
+        Ok((files, folders))
+    })
+    .await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(GetResponse {
+            files: files
+                .into_iter()
+                .map(|file| FavoriteFile {
+                    id: file.id.into(),
+                    name: file.name,
+                    parent_path: file.parent_name_path,
+                    r#type: file.r#type,
+                    favorited_at: file.favorited_at,
+                })
+                .collect(),
+            folders: folders
+                .into_iter()
+                .map(|folder| FavoriteFolder {
+                    id: folder.id.into(),
+                    name: folder.name,
+                    parent_path: folder.parent_name_path,
+                    favorited_at: folder.favorited_at,
+                })
+                .collect(),
+        }),
+    ))
+}
+
+/// A `GET` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GetResponse {
+    /// The user's starred files.
+    pub files: Vec<FavoriteFile>,
+
+    /// The user's starred folders.
+    pub folders: Vec<FavoriteFolder>,
+}
+
+/// A file a user has starred.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FavoriteFile {
+    /// The file's ID.
+    pub id: Id,
+
+    /// The file's name.
+    pub name: String,
+
+    /// The names of the file's ancestor folders.
+    pub parent_path: Vec<String>,
+
+    /// The file's media type.
+    pub r#type: String,
+
+    /// When the file was starred.
+    pub favorited_at: DateTime<Utc>,
+}
+
+/// A folder a user has starred.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FavoriteFolder {
+    /// The folder's ID.
+    pub id: Id,
+
+    /// The folder's name.
+    pub name: String,
+
+    /// The names of the folder's ancestor folders.
+    pub parent_path: Vec<String>,
+
+    /// When the folder was starred.
+    pub favorited_at: DateTime<Utc>,
+}
@@ -0,0 +1,151 @@
+//! A user's handle, which identifies them in their files' URLs in place of their ID.
+
+use std::time::Duration;
+
+use axum::{extract::State, http::StatusCode};
+use axum_macros::debug_handler;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{
+        self,
+        auth::Auth,
+        validation::{is_handle_unavailable, UserHandle},
+        Json, Path, Response,
+    },
+    db::{self, TxError, TxResult},
+    id::Id,
+    AppState,
+};
+
+/// How long a user's previous handle keeps redirecting to their current one after it changes,
+/// during which no one else can claim it.
+pub(crate) const PREVIOUS_HANDLE_GRACE_PERIOD: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// A `PUT` request body for this API route.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct PutRequest {
+    /// The user's new handle, or `None` to identify them by ID instead.
+    pub handle: Option<UserHandle>,
+}
+
+/// Claims or changes a user's handle. The user's previous handle, if any, redirects to the new one
+/// for a grace period.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn put(
+    State(state): State<AppState>,
+    auth: Auth,
+    Path(user_id): Path<Id>,
+    Json(body): Json<PutRequest>,
+) -> Response<PutResponse> {
+    auth.require_self_or_manager(&user_id)?;
+
+    db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+        let Some(user) = sqlx::query!(
+            r#"SELECT handle::text FROM users
+                WHERE id = $1"#,
+            user_id.as_slice(),
+        )
+        .fetch_optional(tx.as_mut())
+        .await?
+        else {
+            return Err(TxError::Abort(api::Error::ResourceNotFound));
+        };
+
+        let new_handle = body.handle.as_ref().map(UserHandle::as_str);
+
+        if user.handle.as_deref() == new_handle {
+            return Ok(());
+        }
+
+        if body.handle.as_ref().is_some_and(is_handle_unavailable) {
+            return Err(TxError::Abort(api::Error::HandleUnavailable));
+        }
+
+        if let Some(new_handle) = new_handle {
+            // Previous handles past their grace period are free for anyone to claim, and a user can
+            // always reclaim their own previous handle.
+            let previous_handle = sqlx::query!(
+                "DELETE FROM previous_handles
+                    WHERE handle = $1
+                        AND (user_id = $2 OR released_at <= now() - make_interval(secs => $3))
+                    RETURNING 1 as released",
+                new_handle,
+                user_id.as_slice(),
+                PREVIOUS_HANDLE_GRACE_PERIOD.as_secs_f64(),
+            )
+            .fetch_optional(tx.as_mut())
+            .await?;
+
+            if previous_handle.is_none() {
+                let is_held = sqlx::query!(
+                    r#"SELECT EXISTS(
+                        SELECT 1 FROM previous_handles
+                            WHERE handle = $1
+                    ) as "exists!""#,
+                    new_handle,
+                )
+                .fetch_one(tx.as_mut())
+                .await?
+                .exists;
+
+                if is_held {
+                    return Err(TxError::Abort(api::Error::HandleTaken));
+                }
+            }
+        }
+
+        if let Some(old_handle) = &user.handle {
+            sqlx::query!(
+                "INSERT INTO previous_handles (handle, user_id)
+                    VALUES ($1, $2)
+                    ON CONFLICT (handle) DO UPDATE
+                        SET user_id = excluded.user_id, released_at = excluded.released_at",
+                old_handle,
+                user_id.as_slice(),
+            )
+            .execute(tx.as_mut())
+            .await?;
+        }
+
+        match sqlx::query!(
+            "UPDATE users
+                SET handle = $1
+                WHERE id = $2",
+            new_handle,
+            user_id.as_slice(),
+        )
+        .execute(tx.as_mut())
+        .await
+        {
+            Err(sqlx::Error::Database(error)) if error.constraint() == Some("users_handle_key") => {
+                Err(TxError::Abort(api::Error::HandleTaken))
+            }
+            result => {
+                result?;
+                Ok(())
+            }
+        }
+    })
+    .await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(PutResponse {
+            handle: body.handle,
+        }),
+    ))
+}
+
+/// A `PUT` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PutResponse {
+    /// The user's handle, or `None` if they're identified by ID.
+    pub handle: Option<UserHandle>,
+}
@@ -0,0 +1,115 @@
+//! Whether search engines may index a user's shared files.
+
+use axum::{extract::State, http::StatusCode};
+use axum_macros::debug_handler;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{self, auth::Auth, Json, Path, Response},
+    db::{self, TxResult},
+    id::Id,
+    AppState,
+};
+
+/// Gets whether search engines may index a user's shared files.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn get(
+    State(state): State<AppState>,
+    auth: Auth,
+    Path(user_id): Path<Id>,
+) -> Response<GetResponse> {
+    auth.require_self_or_manager(&user_id)?;
+
+    let Some(user) = db::transaction!(
+        state.db_replica_pool,
+        async |tx| -> TxResult<_, api::Error> {
+            Ok(sqlx::query!(
+                "SELECT indexable FROM users
+                    WHERE id = $1",
+                user_id.as_slice(),
+            )
+            .fetch_optional(tx.as_mut())
+            .await?)
+        }
+    )
+    .await?
+    else {
+        return Err(api::Error::ResourceNotFound);
+    };
+
+    Ok((
+        StatusCode::OK,
+        Json(GetResponse {
+            indexable: user.indexable,
+        }),
+    ))
+}
+
+/// A `GET` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GetResponse {
+    /// Whether search engines are allowed to index the user's shared files.
+    pub indexable: bool,
+}
+
+/// A `PUT` request body for this API route.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct PutRequest {
+    /// Whether search engines should be allowed to index the user's shared files.
+    pub indexable: bool,
+}
+
+/// Allows or disallows search engines to index a user's shared files. Disallowed files are served
+/// with `X-Robots-Tag: noindex`, and the `robots.txt` of the user's custom domains disallows
+/// crawling them.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn put(
+    State(state): State<AppState>,
+    auth: Auth,
+    Path(user_id): Path<Id>,
+    Json(body): Json<PutRequest>,
+) -> Response<PutResponse> {
+    auth.require_self_or_manager(&user_id)?;
+
+    let Some(user) = db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+        Ok(sqlx::query!(
+            "UPDATE users
+                SET indexable = $1
+                WHERE id = $2
+                RETURNING indexable",
+            body.indexable,
+            user_id.as_slice(),
+        )
+        .fetch_optional(tx.as_mut())
+        .await?)
+    })
+    .await?
+    else {
+        return Err(api::Error::ResourceNotFound);
+    };
+
+    Ok((
+        StatusCode::OK,
+        Json(PutResponse {
+            indexable: user.indexable,
+        }),
+    ))
+}
+
+/// A `PUT` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PutResponse {
+    /// Whether search engines are allowed to index the user's shared files.
+    pub indexable: bool,
+}
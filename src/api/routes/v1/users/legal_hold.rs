@@ -0,0 +1,71 @@
+//! Whether a user is under a legal hold, preventing their account and files from being deleted.
+
+use axum::{extract::State, http::StatusCode};
+use axum_macros::debug_handler;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{
+        self,
+        auth::{Auth, Permission},
+        Json, Path, Response,
+    },
+    db::{self, TxResult},
+    id::Id,
+    AppState,
+};
+
+/// A `PUT` request body for this API route.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct PutRequest {
+    /// Whether the user should be under a legal hold.
+    pub legal_hold: bool,
+}
+
+/// Places or lifts a legal hold on a user.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn put(
+    State(state): State<AppState>,
+    auth: Auth,
+    Path(user_id): Path<Id>,
+    Json(body): Json<PutRequest>,
+) -> Response<PutResponse> {
+    auth.require(Permission::ManageLegalHolds)?;
+
+    let Some(user) = db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+        Ok(sqlx::query!(
+            "UPDATE users
+                SET legal_hold = $1
+                WHERE id = $2
+                RETURNING legal_hold",
+            body.legal_hold,
+            user_id.as_slice(),
+        )
+        .fetch_optional(tx.as_mut())
+        .await?)
+    })
+    .await?
+    else {
+        return Err(api::Error::ResourceNotFound);
+    };
+
+    Ok((
+        StatusCode::OK,
+        Json(PutResponse {
+            legal_hold: user.legal_hold,
+        }),
+    ))
+}
+
+/// A `PUT` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PutResponse {
+    /// Whether the user is under a legal hold.
+    pub legal_hold: bool,
+}
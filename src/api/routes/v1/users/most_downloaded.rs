@@ -0,0 +1,106 @@
+//! A user's most downloaded files.
+
+use std::num::NonZeroU16;
+
+use axum::{extract::State, http::StatusCode};
+use axum_macros::debug_handler;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{self, auth::Auth, Json, Path, Query, Response},
+    db::{self, TxResult},
+    id::Id,
+    AppState,
+};
+
+/// The number of files listed if the request doesn't specify a limit.
+const DEFAULT_LIMIT: u16 = 20;
+
+/// The maximum number of files that can be listed at once.
+const MAX_LIMIT: u16 = 100;
+
+/// A `GET` request query for this API route.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GetQuery {
+    /// The maximum number of files to list, from 1 up to 100. Defaults to 20.
+    pub limit: Option<NonZeroU16>,
+}
+
+/// Lists a user's most downloaded files, most downloaded first.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn get(
+    State(state): State<AppState>,
+    auth: Auth,
+    Path(user_id): Path<Id>,
+    Query(query): Query<GetQuery>,
+) -> Response<GetResponse> {
+    auth.require_self_or_manager(&user_id)?;
+
+    let limit = query
+        .limit
+        .map_or(DEFAULT_LIMIT, NonZeroU16::get)
+        .min(MAX_LIMIT);
+
+    let files = db::transaction!(
+        state.db_replica_pool,
+        async |tx| -> TxResult<_, api::Error> {
+            Ok(sqlx::query!(
+                "SELECT id, name, parent_name_path, download_count FROM files
+                    WHERE owner_id = $1 AND download_count > 0
+                    ORDER BY download_count DESC, id
+                    LIMIT $2",
+                user_id.as_slice(),
+                i64::from(limit),
+            )
+            .fetch_all(tx.as_mut())
+            .await?)
+        }
+    )
+    .await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(GetResponse {
+            files: files
+                .into_iter()
+                .map(|file| DownloadedFile {
+                    id: file.id.into(),
+                    name: file.name,
+                    parent_path: file.parent_name_path,
+                    download_count: file.download_count,
+                })
+                .collect(),
+        }),
+    ))
+}
+
+/// A `GET` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GetResponse {
+    /// The user's most downloaded files, most downloaded first. Files never downloaded aren't
+    /// listed.
+    pub files: Vec<DownloadedFile>,
+}
+
+/// A file and how many times it's been downloaded.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadedFile {
+    /// The file's ID.
+    pub id: Id,
+
+    /// The file's name.
+    pub name: String,
+
+    /// The names of the file's ancestor folders.
+    pub parent_path: Vec<String>,
+
+    /// How many times the file has been downloaded.
+    pub download_count: i64,
+}
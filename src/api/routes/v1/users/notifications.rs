@@ -0,0 +1,166 @@
+//! A user's settings for which optional notification emails they receive.
+
+use axum::{extract::State, http::StatusCode};
+use axum_macros::debug_handler;
+use serde::{Deserialize, Serialize};
+use strum_macros::IntoStaticStr;
+
+use crate::{
+    api::{self, auth::Auth, Json, Path, Response},
+    db::{self, TxResult},
+    id::Id,
+    AppState,
+};
+
+/// A category of optional notification emails, which users can unsubscribe from.
+#[derive(
+    sqlx::Type, Deserialize, Serialize, IntoStaticStr, Clone, Copy, PartialEq, Eq, Hash, Debug,
+)]
+#[sqlx(type_name = "notification_category", rename_all = "snake_case")]
+#[serde(rename_all = "camelCase")]
+#[strum(serialize_all = "camelCase")]
+pub enum NotificationCategory {
+    /// Alerts about activity that may affect an account's security.
+    SecurityAlerts,
+
+    /// Warnings that an account is running out of storage.
+    QuotaWarnings,
+
+    /// Notices about moderation actions taken on an account or its files.
+    ModerationNotices,
+
+    /// News about changes to File Garden.
+    ProductUpdates,
+}
+
+impl NotificationCategory {
+    /// All notification categories.
+    pub const ALL: [Self; 4] = [
+        Self::SecurityAlerts,
+        Self::QuotaWarnings,
+        Self::ModerationNotices,
+        Self::ProductUpdates,
+    ];
+
+    /// Gets the bytes that must be signed for a link to unsubscribe the specified user from this
+    /// category.
+    pub fn unsubscribe_payload(self, user_id: &[u8]) -> Vec<u8> {
+        let mut payload = user_id.to_vec();
+        payload.push(b':');
+        payload.extend_from_slice(<&str>::from(self).as_bytes());
+        payload
+    }
+}
+
+/// Whether a user receives each category of optional notification emails.
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct NotificationSettings {
+    /// Whether to receive alerts about activity that may affect the account's security.
+    pub security_alerts: bool,
+
+    /// Whether to receive warnings that the account is running out of storage.
+    pub quota_warnings: bool,
+
+    /// Whether to receive notices about moderation actions taken on the account or its files.
+    pub moderation_notices: bool,
+
+    /// Whether to receive news about changes to File Garden.
+    pub product_updates: bool,
+}
+
+impl NotificationSettings {
+    /// Constructs settings with every category enabled except the ones opted out of.
+    fn from_opt_outs(opt_outs: &[NotificationCategory]) -> Self {
+        Self {
+            security_alerts: !opt_outs.contains(&NotificationCategory::SecurityAlerts),
+            quota_warnings: !opt_outs.contains(&NotificationCategory::QuotaWarnings),
+            moderation_notices: !opt_outs.contains(&NotificationCategory::ModerationNotices),
+            product_updates: !opt_outs.contains(&NotificationCategory::ProductUpdates),
+        }
+    }
+
+    /// Checks whether the specified category is enabled.
+    const fn is_enabled(&self, category: NotificationCategory) -> bool {
+        match category {
+            NotificationCategory::SecurityAlerts => self.security_alerts,
+            NotificationCategory::QuotaWarnings => self.quota_warnings,
+            NotificationCategory::ModerationNotices => self.moderation_notices,
+            NotificationCategory::ProductUpdates => self.product_updates,
+        }
+    }
+}
+
+/// Gets a user's notification settings.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn get(
+    State(state): State<AppState>,
+    auth: Auth,
+    Path(user_id): Path<Id>,
+) -> Response<NotificationSettings> {
+    auth.require_self_or_manager(&user_id)?;
+
+    let opt_outs = db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+        Ok(sqlx::query_scalar!(
+            r#"SELECT category as "category: NotificationCategory" FROM notification_opt_outs
+                WHERE user_id = $1"#,
+            user_id.as_slice(),
+        )
+        .fetch_all(tx.as_mut())
+        .await?)
+    })
+    .await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(NotificationSettings::from_opt_outs(&opt_outs)),
+    ))
+}
+
+/// Sets a user's notification settings.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn put(
+    State(state): State<AppState>,
+    auth: Auth,
+    Path(user_id): Path<Id>,
+    Json(body): Json<NotificationSettings>,
+) -> Response<NotificationSettings> {
+    auth.require_self_or_manager(&user_id)?;
+
+    let opt_outs: Vec<_> = NotificationCategory::ALL
+        .into_iter()
+        .filter(|&category| !body.is_enabled(category))
+        .collect();
+
+    db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+        sqlx::query!(
+            "DELETE FROM notification_opt_outs
+                WHERE user_id = $1",
+            user_id.as_slice(),
+        )
+        .execute(tx.as_mut())
+        .await?;
+
+        sqlx::query!(
+            "INSERT INTO notification_opt_outs (user_id, category)
+                SELECT $1, * FROM unnest($2::notification_category[])",
+            user_id.as_slice(),
+            &opt_outs as &[NotificationCategory],
+        )
+        .execute(tx.as_mut())
+        .await?;
+
+        Ok(())
+    })
+    .await?;
+
+    Ok((StatusCode::OK, Json(body)))
+}
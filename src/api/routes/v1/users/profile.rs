@@ -0,0 +1,188 @@
+//! What a user shows on their public profile.
+
+use std::collections::HashSet;
+
+use axum::{extract::State, http::StatusCode};
+use axum_macros::debug_handler;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{self, auth::Auth, validation::UserBio, Json, Path, Response},
+    db::{self, TxError, TxResult},
+    id::Id,
+    AppState,
+};
+
+/// The maximum number of files and folders a user can pin to their profile.
+const MAX_PINS: usize = 12;
+
+/// A file or folder pinned to a user's profile.
+#[derive(Deserialize, Serialize, Clone, PartialEq, Eq, Hash, Debug)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase",
+    deny_unknown_fields
+)]
+pub enum Pin {
+    /// A pinned file. It's only shown on the profile while it's accessible to anyone with its link.
+    File {
+        /// The file's ID.
+        file_id: Id,
+    },
+
+    /// A pinned folder. It's only shown on the profile while it has a share link.
+    Folder {
+        /// The folder's ID.
+        folder_id: Id,
+    },
+}
+
+/// A `PUT` request body for this API route.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct PutRequest {
+    /// The user's bio.
+    pub bio: UserBio,
+
+    /// The ID of one of the user's image files to show as their avatar, or `None` for no avatar.
+    /// It's only shown while it's accessible to anyone with its link.
+    pub avatar_file_id: Option<Id>,
+
+    /// The user's files and folders to show on their profile, in order.
+    pub pins: Vec<Pin>,
+}
+
+/// Sets what a user shows on their public profile.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn put(
+    State(state): State<AppState>,
+    auth: Auth,
+    Path(user_id): Path<Id>,
+    Json(body): Json<PutRequest>,
+) -> Response<PutResponse> {
+    auth.require_self_or_manager(&user_id)?;
+
+    if body.pins.len() > MAX_PINS {
+        return Err(api::Error::InvalidBodyData(api::FieldError {
+            field: "pins".into(),
+            code: "INVALID_LENGTH",
+            message: format!("at most {MAX_PINS} files and folders can be pinned"),
+        }));
+    }
+
+    if body.pins.iter().collect::<HashSet<_>>().len() != body.pins.len() {
+        return Err(api::Error::InvalidBodyData(api::FieldError {
+            field: "pins".into(),
+            code: "INVALID_VALUE",
+            message: "each file or folder can only be pinned once".into(),
+        }));
+    }
+
+    db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+        if let Some(avatar_file_id) = &body.avatar_file_id {
+            let is_avatar_owned = sqlx::query!(
+                r#"SELECT EXISTS(
+                    SELECT 1 FROM files
+                        WHERE id = $1 AND owner_id = $2 AND type LIKE 'image/%'
+                ) as "exists!""#,
+                avatar_file_id.as_slice(),
+                user_id.as_slice(),
+            )
+            .fetch_one(tx.as_mut())
+            .await?
+            .exists;
+
+            if !is_avatar_owned {
+                return Err(TxError::Abort(api::Error::ResourceNotFound));
+            }
+        }
+
+        let Some(_) = sqlx::query!(
+            "UPDATE users
+                SET bio = $1, avatar_file_id = $2
+                WHERE id = $3
+                RETURNING 1 as updated",
+            *body.bio,
+            body.avatar_file_id.as_deref(),
+            user_id.as_slice(),
+        )
+        .fetch_optional(tx.as_mut())
+        .await?
+        else {
+            return Err(TxError::Abort(api::Error::ResourceNotFound));
+        };
+
+        sqlx::query!(
+            "DELETE FROM profile_pins
+                WHERE user_id = $1",
+            user_id.as_slice(),
+        )
+        .execute(tx.as_mut())
+        .await?;
+
+        for (position, pin) in (0_i32..).zip(&body.pins) {
+            // Only the user's own files and folders can be pinned.
+            let result = match pin {
+                Pin::File { file_id } => {
+                    sqlx::query!(
+                        "INSERT INTO profile_pins (user_id, position, file_id)
+                            SELECT owner_id, $2, id FROM files
+                                WHERE id = $3 AND owner_id = $1",
+                        user_id.as_slice(),
+                        position,
+                        file_id.as_slice(),
+                    )
+                    .execute(tx.as_mut())
+                    .await?
+                }
+                Pin::Folder { folder_id } => {
+                    sqlx::query!(
+                        "INSERT INTO profile_pins (user_id, position, folder_id)
+                            SELECT owner_id, $2, id FROM folders
+                                WHERE id = $3 AND owner_id = $1",
+                        user_id.as_slice(),
+                        position,
+                        folder_id.as_slice(),
+                    )
+                    .execute(tx.as_mut())
+                    .await?
+                }
+            };
+
+            if result.rows_affected() == 0 {
+                return Err(TxError::Abort(api::Error::ResourceNotFound));
+            }
+        }
+
+        Ok(())
+    })
+    .await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(PutResponse {
+            bio: body.bio,
+            avatar_file_id: body.avatar_file_id,
+            pins: body.pins,
+        }),
+    ))
+}
+
+/// A `PUT` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PutResponse {
+    /// The user's bio.
+    pub bio: UserBio,
+
+    /// The ID of the user's avatar file, if any.
+    pub avatar_file_id: Option<Id>,
+
+    /// The user's files and folders pinned to their profile, in order.
+    pub pins: Vec<Pin>,
+}
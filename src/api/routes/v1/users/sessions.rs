@@ -0,0 +1,136 @@
+//! The set of a user's sign-in sessions.
+
+use axum::{extract::State, http::StatusCode};
+use axum_macros::debug_handler;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::{
+    api::{
+        self,
+        auth::{Auth, AuthAllowingOutdatedTos},
+        routes::v1::sessions::SESSION_MAX_AGE,
+        Json, Path, Response,
+    },
+    db::{self, TxResult},
+    id::Id,
+    AppState,
+};
+
+pub mod others;
+
+/// Lists a user's unexpired sign-in sessions.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn get(
+    State(state): State<AppState>,
+    auth: Auth,
+    Path(user_id): Path<Id>,
+) -> Response<GetResponse> {
+    auth.require_self_or_manager(&user_id)?;
+
+    let sessions = db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+        Ok(sqlx::query!(
+            "SELECT id, created_at, accessed_at, ip, user_agent FROM sessions
+                WHERE user_id = $1 AND created_at > now() - make_interval(secs => $2)
+                ORDER BY accessed_at DESC",
+            user_id.as_slice(),
+            SESSION_MAX_AGE.as_seconds_f64(),
+        )
+        .fetch_all(tx.as_mut())
+        .await?)
+    })
+    .await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(GetResponse {
+            sessions: sessions
+                .into_iter()
+                .map(|session| {
+                    let id = Id::from(session.id);
+
+                    Session {
+                        current: id == auth.session_id,
+                        id,
+                        created_at: session.created_at,
+                        accessed_at: session.accessed_at,
+                        ip: session.ip,
+                        user_agent: session.user_agent,
+                    }
+                })
+                .collect(),
+        }),
+    ))
+}
+
+/// A `GET` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GetResponse {
+    /// The user's sessions, from most to least recently used.
+    pub sessions: Vec<Session>,
+}
+
+/// A user's sign-in session.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Session {
+    /// The session's ID.
+    pub id: Id,
+
+    /// When the user signed in.
+    pub created_at: DateTime<Utc>,
+
+    /// When the session was last used.
+    pub accessed_at: DateTime<Utc>,
+
+    /// The IP address the user signed in from, if known.
+    pub ip: Option<String>,
+
+    /// The `User-Agent` of the client the user signed in with, if known.
+    pub user_agent: Option<String>,
+
+    /// Whether this is the session making the request.
+    pub current: bool,
+}
+
+/// Signs a user out of one of their sessions.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn delete(
+    State(state): State<AppState>,
+    AuthAllowingOutdatedTos(auth): AuthAllowingOutdatedTos,
+    Path((user_id, session_id)): Path<(Id, Id)>,
+) -> Response<DeleteResponse> {
+    auth.require_self_or_manager(&user_id)?;
+
+    let Some(_) = db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+        Ok(sqlx::query!(
+            "DELETE FROM sessions
+                WHERE id = $1 AND user_id = $2
+                RETURNING 1 as deleted",
+            session_id.as_slice(),
+            user_id.as_slice(),
+        )
+        .fetch_optional(tx.as_mut())
+        .await?)
+    })
+    .await?
+    else {
+        return Err(api::Error::ResourceNotFound);
+    };
+
+    Ok((StatusCode::OK, Json(DeleteResponse {})))
+}
+
+/// A `DELETE` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteResponse {}
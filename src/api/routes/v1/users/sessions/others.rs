@@ -0,0 +1,47 @@
+//! The set of a user's sign-in sessions other than the one making the request.
+
+use axum::{extract::State, http::StatusCode};
+use axum_macros::debug_handler;
+use serde::Serialize;
+
+use crate::{
+    api::{self, auth::AuthAllowingOutdatedTos, Json, Path, Response},
+    db::{self, TxResult},
+    id::Id,
+    AppState,
+};
+
+/// Signs a user out of all their sessions except the one making the request.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn delete(
+    State(state): State<AppState>,
+    AuthAllowingOutdatedTos(auth): AuthAllowingOutdatedTos,
+    Path(user_id): Path<Id>,
+) -> Response<DeleteResponse> {
+    auth.require_self_or_manager(&user_id)?;
+
+    db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+        sqlx::query!(
+            "DELETE FROM sessions
+                WHERE user_id = $1 AND id != $2",
+            user_id.as_slice(),
+            auth.session_id.as_slice(),
+        )
+        .execute(tx.as_mut())
+        .await?;
+
+        Ok(())
+    })
+    .await?;
+
+    Ok((StatusCode::OK, Json(DeleteResponse {})))
+}
+
+/// A `DELETE` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteResponse {}
@@ -0,0 +1,130 @@
+//! Which version of the terms of service and privacy policy a user has accepted.
+
+use std::sync::LazyLock;
+
+use axum::{extract::State, http::StatusCode};
+use axum_macros::debug_handler;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{self, auth::AuthAllowingOutdatedTos, Json, Path, Response},
+    config,
+    db::{self, TxResult},
+    id::Id,
+    AppState,
+};
+
+/// The current version of the terms of service and privacy policy, which every user must accept.
+/// Changing this requires every user to accept the new version before they can continue using the
+/// API.
+pub(crate) static TOS_VERSION: LazyLock<String> = LazyLock::new(|| {
+    config::var("TOS_VERSION").expect("environment variable `TOS_VERSION` should be a valid string")
+});
+
+/// Gets which version of the terms of service and privacy policy a user has accepted.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn get(
+    State(state): State<AppState>,
+    AuthAllowingOutdatedTos(auth): AuthAllowingOutdatedTos,
+    Path(user_id): Path<Id>,
+) -> Response<GetResponse> {
+    auth.require_self_or_manager(&user_id)?;
+
+    let Some(user) = db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+        Ok(sqlx::query!(
+            "SELECT tos_version FROM users
+                WHERE id = $1",
+            user_id.as_slice(),
+        )
+        .fetch_optional(tx.as_mut())
+        .await?)
+    })
+    .await?
+    else {
+        return Err(api::Error::ResourceNotFound);
+    };
+
+    Ok((
+        StatusCode::OK,
+        Json(GetResponse {
+            tos_version: user.tos_version,
+            current_tos_version: TOS_VERSION.clone(),
+        }),
+    ))
+}
+
+/// A `GET` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GetResponse {
+    /// The version the user last accepted, or `None` if they've never accepted any.
+    pub tos_version: Option<String>,
+
+    /// The current version, which the user must accept if they haven't already.
+    pub current_tos_version: String,
+}
+
+/// A `PUT` request body for this API route.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct PutRequest {
+    /// The version being accepted. This must be the current version, so users can't accept a
+    /// version they weren't shown.
+    pub tos_version: String,
+}
+
+/// Accepts the current version of the terms of service and privacy policy for a user. Only the user
+/// themself can do this.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn put(
+    State(state): State<AppState>,
+    AuthAllowingOutdatedTos(auth): AuthAllowingOutdatedTos,
+    Path(user_id): Path<Id>,
+    Json(body): Json<PutRequest>,
+) -> Response<PutResponse> {
+    if auth.user_id != user_id {
+        return Err(api::Error::PermissionDenied);
+    }
+
+    if body.tos_version != *TOS_VERSION {
+        return Err(api::Error::TosReacceptanceRequired);
+    }
+
+    db::transaction!(state.db_pool, async |tx| -> TxResult<_, api::Error> {
+        sqlx::query!(
+            "UPDATE users
+                SET tos_version = $1, tos_accepted_at = now()
+                WHERE id = $2",
+            body.tos_version,
+            user_id.as_slice(),
+        )
+        .execute(tx.as_mut())
+        .await?;
+
+        Ok(())
+    })
+    .await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(PutResponse {
+            tos_version: body.tos_version,
+        }),
+    ))
+}
+
+/// A `PUT` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PutResponse {
+    /// The version the user accepted.
+    pub tos_version: String,
+}
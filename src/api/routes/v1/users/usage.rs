@@ -0,0 +1,135 @@
+//! The storage and bandwidth usage of a user, and the limits of their plan.
+
+use axum::{extract::State, http::StatusCode};
+use axum_macros::debug_handler;
+use serde::Serialize;
+
+use crate::{
+    api::{
+        self,
+        auth::Auth,
+        routes::v1::plans::{Plan, PlanLimits},
+        Json, Path, Response,
+    },
+    db::{self, TxError, TxResult},
+    id::Id,
+    AppState,
+};
+
+/// Gets a user's storage and bandwidth usage, and the limits of their plan.
+///
+/// # Errors
+///
+/// See [`crate::api::Error`].
+#[debug_handler]
+pub async fn get(
+    State(state): State<AppState>,
+    auth: Auth,
+    Path(user_id): Path<Id>,
+) -> Response<GetResponse> {
+    auth.require_self_or_manager(&user_id)?;
+
+    let (user, bandwidth, folders) = db::transaction!(
+        state.db_replica_pool,
+        async |tx| -> TxResult<_, api::Error> {
+            let Some(user) = sqlx::query!(
+                r#"SELECT users.storage_used, users.file_count, plan_limits.plan as "plan: Plan",
+                    plan_limits.storage_quota, plan_limits.max_file_size,
+                    plan_limits.monthly_bandwidth, plan_limits.custom_domains, plan_limits.hls
+                    FROM users JOIN plan_limits ON plan_limits.plan = users.plan
+                    WHERE users.id = $1"#,
+                user_id.as_slice(),
+            )
+            .fetch_optional(tx.as_mut())
+            .await?
+            else {
+                return Err(TxError::Abort(api::Error::ResourceNotFound));
+            };
+
+            let bandwidth = sqlx::query!(
+                "SELECT bytes FROM monthly_bandwidth
+                    WHERE user_id = $1 AND month = date_trunc('month', now())::date",
+                user_id.as_slice(),
+            )
+            .fetch_optional(tx.as_mut())
+            .await?;
+
+            let folders = sqlx::query!(
+                "SELECT id, name, size, file_count FROM folders
+                    WHERE owner_id = $1 AND parent_id_path = '{}' AND organization_id IS NULL
+                    ORDER BY size DESC",
+                user_id.as_slice(),
+            )
+            .fetch_all(tx.as_mut())
+            .await?;
+
+            Ok((user, bandwidth, folders))
+        }
+    )
+    .await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(GetResponse {
+            storage_used: user.storage_used,
+            file_count: user.file_count,
+            bandwidth_this_month: bandwidth.map_or(0, |bandwidth| bandwidth.bytes),
+            limits: PlanLimits {
+                plan: user.plan,
+                storage_quota: user.storage_quota,
+                max_file_size: user.max_file_size,
+                monthly_bandwidth: user.monthly_bandwidth,
+                custom_domains: user.custom_domains,
+                hls: user.hls,
+            },
+            folders: folders
+                .into_iter()
+                .map(|folder| FolderUsage {
+                    id: folder.id.into(),
+                    name: folder.name,
+                    size: folder.size,
+                    file_count: folder.file_count,
+                })
+                .collect(),
+        }),
+    ))
+}
+
+/// A `GET` response body for this API route.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GetResponse {
+    /// The total size of the user's files in bytes.
+    pub storage_used: i64,
+
+    /// The total number of the user's files.
+    pub file_count: i64,
+
+    /// The number of bytes served from the user's files since the start of the current month (in
+    /// UTC).
+    pub bandwidth_this_month: i64,
+
+    /// The limits of the user's plan.
+    pub limits: PlanLimits,
+
+    /// The usage of each of the user's top-level folders in their own storage, from largest to
+    /// smallest.
+    pub folders: Vec<FolderUsage>,
+}
+
+/// The storage usage of a folder, including all of its descendants.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderUsage {
+    /// The folder's ID.
+    pub id: Id,
+
+    /// The folder's name.
+    pub name: String,
+
+    /// The total size of the folder's files in bytes.
+    pub size: i64,
+
+    /// The total number of the folder's files.
+    pub file_count: i64,
+}
@@ -0,0 +1,13 @@
+//! The routes for version 2 of the HTTP API.
+//!
+//! Breaking changes to version 1 land here, so clients can migrate to them before version 1 is
+//! deprecated (see [`super::v1`]).
+
+use axum::Router;
+
+use crate::AppState;
+
+/// Builds the router for this version of the API, to be nested under `/api/v2`.
+pub(super) fn router() -> Router<AppState> {
+    Router::new()
+}
@@ -0,0 +1,264 @@
+//! Utilities for billing users through Stripe.
+
+use std::sync::LazyLock;
+
+use chrono::Utc;
+use ring::hmac;
+use serde::Deserialize;
+
+use crate::{api::routes::v1::plans::Plan, config, crypto::decode_hex, id::Id, WEBSITE_ORIGIN};
+
+/// The base URL of Stripe's API.
+const API_URL: &str = "https://api.stripe.com/v1";
+
+/// How many seconds old a webhook event's signature can be before it's rejected, so captured events
+/// can't be replayed later.
+const WEBHOOK_TOLERANCE_SECONDS: u64 = 5 * 60;
+
+/// The secret key for Stripe's API.
+static SECRET_KEY: LazyLock<String> = LazyLock::new(|| {
+    config::var("STRIPE_SECRET_KEY")
+        .expect("environment variable `STRIPE_SECRET_KEY` should be a valid string")
+});
+
+/// The key Stripe signs webhook events with.
+static WEBHOOK_KEY: LazyLock<hmac::Key> = LazyLock::new(|| {
+    let secret = config::var("STRIPE_WEBHOOK_SECRET")
+        .expect("environment variable `STRIPE_WEBHOOK_SECRET` should be a valid string");
+
+    hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes())
+});
+
+/// The ID of the Stripe price for the [`Plan::Supporter`] subscription.
+static SUPPORTER_PRICE_ID: LazyLock<String> = LazyLock::new(|| {
+    config::var("STRIPE_SUPPORTER_PRICE_ID")
+        .expect("environment variable `STRIPE_SUPPORTER_PRICE_ID` should be a valid string")
+});
+
+/// The ID of the Stripe price for the [`Plan::Pro`] subscription.
+static PRO_PRICE_ID: LazyLock<String> = LazyLock::new(|| {
+    config::var("STRIPE_PRO_PRICE_ID")
+        .expect("environment variable `STRIPE_PRO_PRICE_ID` should be a valid string")
+});
+
+/// Gets the ID of the Stripe price to subscribe to a plan, or `None` if the plan is free.
+pub(crate) fn price_id(plan: Plan) -> Option<&'static str> {
+    match plan {
+        Plan::Free => None,
+        Plan::Supporter => Some(&SUPPORTER_PRICE_ID),
+        Plan::Pro => Some(&PRO_PRICE_ID),
+    }
+}
+
+/// Gets the plan a Stripe price subscribes to, or `None` if it isn't for any plan.
+pub(crate) fn plan_for_price(price_id: &str) -> Option<Plan> {
+    [Plan::Supporter, Plan::Pro]
+        .into_iter()
+        .find(|&plan| self::price_id(plan) == Some(price_id))
+}
+
+/// A session of Stripe's hosted checkout or billing portal page.
+#[derive(Deserialize, Debug)]
+struct Session {
+    /// The URL of the page.
+    url: String,
+}
+
+/// Creates a Stripe Checkout session for a user to subscribe to a plan, returning the checkout
+/// page's URL.
+///
+/// # Errors
+///
+/// Returns an error if the request to Stripe fails.
+pub(crate) async fn create_checkout_session(
+    user_id: &Id,
+    customer_id: Option<&str>,
+    email: &str,
+    price_id: &str,
+) -> Result<String, reqwest::Error> {
+    let user_id = user_id.to_string();
+    let success_url = format!("{}/settings/billing?checkout=success", *WEBSITE_ORIGIN);
+    let cancel_url = format!("{}/settings/billing", *WEBSITE_ORIGIN);
+
+    let mut form = vec![
+        ("mode", "subscription"),
+        ("line_items[0][price]", price_id),
+        ("line_items[0][quantity]", "1"),
+        ("client_reference_id", &user_id),
+        ("subscription_data[metadata][user_id]", &user_id),
+        ("success_url", &success_url),
+        ("cancel_url", &cancel_url),
+    ];
+
+    // Reusing the user's customer keeps all their subscriptions and invoices together.
+    match customer_id {
+        Some(customer_id) => form.push(("customer", customer_id)),
+        None => form.push(("customer_email", email)),
+    }
+
+    let session: Session = reqwest::Client::new()
+        .post(format!("{API_URL}/checkout/sessions"))
+        .bearer_auth(&*SECRET_KEY)
+        .form(&form)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    Ok(session.url)
+}
+
+/// Creates a Stripe billing portal session for a customer to change or cancel their subscription,
+/// returning the portal page's URL. Stripe prorates any plan changes made there.
+///
+/// # Errors
+///
+/// Returns an error if the request to Stripe fails.
+pub(crate) async fn create_portal_session(customer_id: &str) -> Result<String, reqwest::Error> {
+    let return_url = format!("{}/settings/billing", *WEBSITE_ORIGIN);
+
+    let session: Session = reqwest::Client::new()
+        .post(format!("{API_URL}/billing_portal/sessions"))
+        .bearer_auth(&*SECRET_KEY)
+        .form(&[("customer", customer_id), ("return_url", &return_url)])
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    Ok(session.url)
+}
+
+/// Cancels a Stripe subscription immediately, without prorating it. A subscription that no longer
+/// exists counts as canceled.
+///
+/// # Errors
+///
+/// Returns an error if the request to Stripe fails.
+pub(crate) async fn cancel_subscription(subscription_id: &str) -> Result<(), reqwest::Error> {
+    let response = reqwest::Client::new()
+        .delete(format!("{API_URL}/subscriptions/{subscription_id}"))
+        .bearer_auth(&*SECRET_KEY)
+        .send()
+        .await?;
+
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(());
+    }
+
+    response.error_for_status()?;
+
+    Ok(())
+}
+
+/// Returns whether a webhook event's `Stripe-Signature` header is a recent, valid signature of its
+/// payload.
+pub(crate) fn verify_webhook_signature(payload: &[u8], signature_header: &str) -> bool {
+    verify_signature(
+        &WEBHOOK_KEY,
+        payload,
+        signature_header,
+        Utc::now().timestamp(),
+    )
+}
+
+/// Returns whether a `Stripe-Signature` header is a valid signature of a payload by the specified
+/// key, made within the tolerance of the specified current time in seconds since the Unix epoch.
+fn verify_signature(key: &hmac::Key, payload: &[u8], signature_header: &str, now: i64) -> bool {
+    let mut timestamp = None;
+    let mut signatures = Vec::new();
+
+    for item in signature_header.split(',') {
+        match item.trim().split_once('=') {
+            Some(("t", value)) => timestamp = value.parse::<i64>().ok(),
+            Some(("v1", value)) => signatures.extend(decode_hex(value)),
+            _ => {}
+        }
+    }
+
+    let Some(timestamp) = timestamp else {
+        return false;
+    };
+
+    if now.abs_diff(timestamp) > WEBHOOK_TOLERANCE_SECONDS {
+        return false;
+    }
+
+    let signed_payload = [timestamp.to_string().as_bytes(), b".", payload].concat();
+
+    signatures
+        .iter()
+        .any(|signature| hmac::verify(key, &signed_payload, signature).is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The current time in the tests, in seconds since the Unix epoch.
+    const NOW: i64 = 1_800_000_000;
+
+    /// The payload of the tests' webhook event.
+    const PAYLOAD: &[u8] = br#"{"id":"evt_test"}"#;
+
+    /// Signs a payload with a timestamp like Stripe does, returning the hex-encoded signature.
+    fn sign(key: &hmac::Key, timestamp: i64, payload: &[u8]) -> String {
+        let signed_payload = [timestamp.to_string().as_bytes(), b".", payload].concat();
+
+        hmac::sign(key, &signed_payload)
+            .as_ref()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+
+    #[test]
+    fn webhook_signature_verification() {
+        let key = hmac::Key::new(hmac::HMAC_SHA256, b"whsec_test");
+        let other_key = hmac::Key::new(hmac::HMAC_SHA256, b"whsec_other");
+
+        let signature = sign(&key, NOW, PAYLOAD);
+        let other_signature = sign(&other_key, NOW, PAYLOAD);
+        let expired_timestamp = NOW - 10 * 60;
+        let expired_signature = sign(&key, expired_timestamp, PAYLOAD);
+
+        let valid_headers = [
+            format!("t={NOW},v1={signature}"),
+            format!("t={NOW}, v1={signature}"),
+            format!("t={NOW},v1={other_signature},v1={signature}"),
+            format!("t={NOW},v1={signature},v0=ignored"),
+        ];
+
+        for header in valid_headers {
+            assert!(
+                verify_signature(&key, PAYLOAD, &header, NOW),
+                "verifying {header:?}",
+            );
+        }
+
+        let invalid_headers = [
+            format!("v1={signature}"),
+            format!("t={NOW}"),
+            format!("t={NOW},v1={other_signature}"),
+            format!("t={NOW},v1=not-hex"),
+            format!("t={},v1={signature}", NOW + 1),
+            format!("t={expired_timestamp},v1={expired_signature}"),
+        ];
+
+        for header in invalid_headers {
+            assert!(
+                !verify_signature(&key, PAYLOAD, &header, NOW),
+                "verifying {header:?}",
+            );
+        }
+
+        let header = format!("t={NOW},v1={signature}");
+
+        assert!(
+            !verify_signature(&key, br#"{"id":"evt_tampered"}"#, &header, NOW),
+            "verifying a tampered payload",
+        );
+    }
+}
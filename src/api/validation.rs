@@ -1,24 +1,69 @@
 //! Utilities to help with API request validation.
 
-use std::{borrow::Cow, str::FromStr};
+use std::{borrow::Cow, collections::HashSet, iter, str::FromStr};
 
 use derive_more::derive::{AsRef, Deref, Display};
 use idna::uts46::{self, Uts46};
 use lettre::Address;
+use regex::Regex;
 use regex_macro::regex;
 use serde::{Deserialize, Serialize};
 use serde_with::{DeserializeFromStr, SerializeDisplay};
 use thiserror::Error;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::{
+    config::{self, Reloadable},
+    id::NewUserId,
+};
+
+/// Whether new users are checked against the [`UserEmail::canonical`] addresses of existing users,
+/// so they can't make multiple accounts with different subaddresses of one mailbox.
+pub(crate) static STRIP_EMAIL_SUBADDRESSES: Reloadable<bool> = Reloadable::new(|| {
+    match config::optional_var("STRIP_EMAIL_SUBADDRESSES")? {
+        // If the environment variable is unset, only exact addresses are checked.
+        None => Ok(false),
+
+        Some(strip_email_subaddresses) => strip_email_subaddresses.parse().map_err(|_| {
+            "environment variable `STRIP_EMAIL_SUBADDRESSES` should be `true` or `false` if set"
+                .into()
+        }),
+    }
+});
+
+/// Email domains whose mail servers deliver `user+anything@domain` to `user@domain`. The
+/// `canonical_emails` migration backfilled existing users with these same domains.
+const SUBADDRESSING_DOMAINS: [&str; 13] = [
+    "fastmail.com",
+    "gmail.com",
+    "googlemail.com",
+    "hotmail.com",
+    "icloud.com",
+    "live.com",
+    "mac.com",
+    "me.com",
+    "msn.com",
+    "outlook.com",
+    "pm.me",
+    "proton.me",
+    "protonmail.com",
+];
 
 /// A user's name.
 pub type UserName = BoundedString<1, 64>;
 
+/// The bio on a user's public profile.
+pub type UserBio = BoundedString<0, 1024>;
+
 /// A user's new password in plain text.
 pub type NewUserPassword = BoundedString<8, 256>;
 
 /// A user's password in plain text.
 pub type UserPassword = BoundedString<0, 256>;
 
+/// An organization's name.
+pub type OrganizationName = BoundedString<1, 64>;
+
 /// An unverified email's verification code in plain text.
 pub type EmailVerificationCode = BoundedString<6, 6>;
 
@@ -107,6 +152,26 @@ impl UserEmail {
         self.as_ref()
     }
 
+    /// Gets the address with its subaddress stripped if it's at one of the
+    /// [`SUBADDRESSING_DOMAINS`], identifying the mailbox mail to it is delivered to. This is only
+    /// for telling whether a mailbox is already in use; mail is still sent to the address as is.
+    pub fn canonical(&self) -> Cow<'_, str> {
+        let domain = self.0.domain();
+
+        if !SUBADDRESSING_DOMAINS.contains(&domain) {
+            return Cow::Borrowed(self.as_str());
+        }
+
+        let user = self.0.user();
+        let stripped_user = strip_subaddress(user);
+
+        if stripped_user.len() == user.len() {
+            return Cow::Borrowed(self.as_str());
+        }
+
+        Cow::Owned(format!("{stripped_user}@{domain}"))
+    }
+
     /// Consumes the [`UserEmail`], returning the wrapped [`Address`].
     pub fn into_inner(self) -> Address {
         self.0
@@ -166,8 +231,17 @@ impl FromStr for UserEmail {
     }
 }
 
+/// Removes the subaddress (a `+` and everything after it) from an email address's user portion,
+/// unless the user portion is quoted or would be left empty.
+fn strip_subaddress(user: &str) -> &str {
+    match user.split_once('+') {
+        Some((base_user, _)) if !base_user.is_empty() && !user.starts_with('"') => base_user,
+        _ => user,
+    }
+}
+
 /// Normalizes an email address's user portion by removing unnecessary quotes and escapes.
-fn normalize_email_address_user(user: &str) -> Cow<str> {
+fn normalize_email_address_user(user: &str) -> Cow<'_, str> {
     let Some(unquoted_user) = user
         .strip_prefix('"')
         .and_then(|user| user.strip_suffix('"'))
@@ -199,6 +273,332 @@ fn normalize_email_address_user(user: &str) -> Cow<str> {
     }
 }
 
+/// A domain name, normalized to lowercase ASCII (with internationalized labels in Punycode) as it
+/// appears in `Host` headers.
+#[derive(
+    Deref,
+    AsRef,
+    Display,
+    DeserializeFromStr,
+    SerializeDisplay,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Debug,
+)]
+#[as_ref(forward)]
+pub struct DomainName(String);
+
+impl DomainName {
+    /// Gets a reference to the domain name string.
+    pub fn as_str(&self) -> &str {
+        self.as_ref()
+    }
+}
+
+/// An error constructing a [`DomainName`].
+#[derive(Error, Copy, Clone, Debug)]
+#[error("invalid domain name")]
+pub struct DomainNameError;
+
+impl FromStr for DomainName {
+    type Err = DomainNameError;
+
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        let Ok(domain) = Uts46::new().to_ascii(
+            str.as_bytes(),
+            uts46::AsciiDenyList::STD3,
+            uts46::Hyphens::Check,
+            uts46::DnsLength::Verify,
+        ) else {
+            return Err(DomainNameError);
+        };
+
+        // Only allow names under a top-level domain. This also disallows IPv4 addresses, since the
+        // last label of a domain name can't be numeric.
+        let Some((_, top_level_domain)) = domain.rsplit_once('.') else {
+            return Err(DomainNameError);
+        };
+
+        if top_level_domain.bytes().all(|byte| byte.is_ascii_digit()) {
+            return Err(DomainNameError);
+        }
+
+        Ok(Self(domain.into_owned()))
+    }
+}
+
+/// The name of a file or folder. Normalized to Unicode NFC, so visually identical names are
+/// treated as the same name, and so they match file route paths (which are normalized the same
+/// way).
+#[derive(
+    Deref,
+    AsRef,
+    Display,
+    DeserializeFromStr,
+    SerializeDisplay,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Debug,
+)]
+#[as_ref(forward)]
+pub struct FileName(String);
+
+impl FileName {
+    /// The maximum length of a [`FileName`] in bytes.
+    pub const MAX_LENGTH: usize = 255;
+
+    /// Gets a reference to the name string.
+    pub fn as_str(&self) -> &str {
+        self.as_ref()
+    }
+}
+
+/// An error constructing a [`FileName`].
+#[derive(Error, Copy, Clone, Debug)]
+#[non_exhaustive]
+pub enum FileNameError {
+    /// The name was empty or longer than [`FileName::MAX_LENGTH`].
+    #[error("invalid length {0}, expected 1 to {max}", max = FileName::MAX_LENGTH)]
+    Length(usize),
+
+    /// The name contained a slash or control character, or was `.` or `..`, any of which would
+    /// break the file's URL.
+    #[error("invalid characters in file name")]
+    Invalid,
+}
+
+impl FromStr for FileName {
+    type Err = FileNameError;
+
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        let name: String = str.nfc().collect();
+
+        if name.is_empty() || name.len() > Self::MAX_LENGTH {
+            return Err(FileNameError::Length(name.len()));
+        }
+
+        if matches!(name.as_str(), "." | "..")
+            || name.chars().any(|char| char == '/' || char.is_control())
+        {
+            return Err(FileNameError::Invalid);
+        }
+
+        Ok(Self(name))
+    }
+}
+
+/// A user's handle, which identifies them in their files' URLs. Normalized to lowercase.
+#[derive(
+    Deref,
+    AsRef,
+    Display,
+    DeserializeFromStr,
+    SerializeDisplay,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Debug,
+)]
+#[as_ref(forward)]
+pub struct UserHandle(String);
+
+impl UserHandle {
+    /// The minimum length of a [`UserHandle`].
+    pub const MIN_LENGTH: usize = 3;
+
+    /// The maximum length of a [`UserHandle`].
+    pub const MAX_LENGTH: usize = 32;
+
+    /// Gets a reference to the handle string.
+    pub fn as_str(&self) -> &str {
+        self.as_ref()
+    }
+}
+
+/// An error constructing a [`UserHandle`].
+#[derive(Error, Copy, Clone, Debug)]
+#[non_exhaustive]
+pub enum UserHandleError {
+    /// The handle was shorter than [`UserHandle::MIN_LENGTH`] or longer than
+    /// [`UserHandle::MAX_LENGTH`].
+    #[error(
+        "invalid length {0}, expected {min} to {max}",
+        min = UserHandle::MIN_LENGTH,
+        max = UserHandle::MAX_LENGTH,
+    )]
+    Length(usize),
+
+    /// The handle had characters other than letters, digits, `-`, and `_`, or started or ended
+    /// with `-` or `_`.
+    #[error("handles can only have letters, digits, `-`, and `_`, and must start and end with a letter or digit")]
+    Invalid,
+
+    /// The handle could be mistaken for a user ID, which can also identify a user in their files'
+    /// URLs.
+    #[error("handle is indistinguishable from a user ID")]
+    LikeUserId,
+}
+
+impl FromStr for UserHandle {
+    type Err = UserHandleError;
+
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        if str.len() < Self::MIN_LENGTH || str.len() > Self::MAX_LENGTH {
+            return Err(UserHandleError::Length(str.len()));
+        }
+
+        let handle = str.to_ascii_lowercase();
+
+        if !regex!("^[a-z0-9](?:[a-z0-9_-]*[a-z0-9])?$").is_match(&handle) {
+            return Err(UserHandleError::Invalid);
+        }
+
+        if handle.parse::<NewUserId>().is_ok() {
+            return Err(UserHandleError::LikeUserId);
+        }
+
+        Ok(Self(handle))
+    }
+}
+
+/// Handles no user can claim, since they'd shadow system routes or could be mistaken for staff.
+/// More can be added with the `RESERVED_HANDLES` environment variable.
+const DEFAULT_RESERVED_HANDLES: [&str; 30] = [
+    "about",
+    "abuse",
+    "account",
+    "admin",
+    "administrator",
+    "api",
+    "app",
+    "assets",
+    "billing",
+    "blog",
+    "dashboard",
+    "docs",
+    "help",
+    "legal",
+    "login",
+    "mail",
+    "moderator",
+    "official",
+    "privacy",
+    "root",
+    "security",
+    "settings",
+    "signup",
+    "staff",
+    "static",
+    "status",
+    "support",
+    "system",
+    "terms",
+    "www",
+];
+
+/// Words no handle can have as one of its `-` or `_` separated parts, or as the whole handle with
+/// its separators removed, since they're offensive or impersonate File Garden. Only whole words are
+/// blocked, so handles merely containing one (e.g. `scunthorpe`) can still be claimed.
+const BLOCKED_HANDLE_WORDS: [&str; 17] = [
+    "bitch",
+    "cunt",
+    "fag",
+    "faggot",
+    "filegarden",
+    "fuck",
+    "fucker",
+    "fucking",
+    "nigga",
+    "nigger",
+    "retard",
+    "shit",
+    "slut",
+    "twat",
+    "wank",
+    "wanker",
+    "whore",
+];
+
+/// The [`deobfuscate`]d handles no user can claim.
+pub(crate) static RESERVED_HANDLES: Reloadable<HashSet<String>> = Reloadable::new(|| {
+    // If the environment variable is unset, only the default handles are reserved.
+    let configured_handles = config::optional_var("RESERVED_HANDLES")?.unwrap_or_default();
+
+    Ok(DEFAULT_RESERVED_HANDLES
+        .into_iter()
+        .chain(configured_handles.split(','))
+        .map(str::trim)
+        .filter(|handle| !handle.is_empty())
+        .map(|handle| deobfuscate(&handle.to_ascii_lowercase()))
+        .collect())
+});
+
+/// An optional pattern a handle or its [`deobfuscate`]d form can't match anywhere within, for
+/// blocking more than the [`BLOCKED_HANDLE_WORDS`].
+pub(crate) static BLOCKED_HANDLE_PATTERN: Reloadable<Option<Regex>> = Reloadable::new(|| {
+    // If the environment variable is unset, only the blocked words are blocked.
+    config::optional_var("BLOCKED_HANDLE_PATTERN")?
+        .map(|pattern| {
+            Regex::new(&pattern).map_err(|_| {
+                "environment variable `BLOCKED_HANDLE_PATTERN` should be a valid regex if set"
+                    .to_owned()
+            })
+        })
+        .transpose()
+});
+
+/// Strips the separators and common digit-for-letter substitutions people use to get around
+/// blocked words (e.g. `4dm1n` or `a_d-min` for `admin`) from a lowercase handle.
+fn deobfuscate(handle: &str) -> String {
+    handle
+        .chars()
+        .filter(|char| !matches!(char, '-' | '_'))
+        .map(|char| match char {
+            '0' => 'o',
+            '1' => 'i',
+            '3' => 'e',
+            '4' => 'a',
+            '5' => 's',
+            '7' => 't',
+            char => char,
+        })
+        .collect()
+}
+
+/// Returns whether a handle is reserved or blocked, so no user can claim it.
+pub(crate) fn is_handle_unavailable(handle: &UserHandle) -> bool {
+    let deobfuscated_handle = deobfuscate(handle.as_str());
+
+    if RESERVED_HANDLES.get().contains(&deobfuscated_handle) {
+        return true;
+    }
+
+    let is_word_blocked = iter::once(deobfuscated_handle.clone())
+        .chain(handle.split(['-', '_']).map(deobfuscate))
+        .any(|word| BLOCKED_HANDLE_WORDS.contains(&word.as_str()));
+
+    if is_word_blocked {
+        return true;
+    }
+
+    if let Some(pattern) = &*BLOCKED_HANDLE_PATTERN.get() {
+        return pattern.is_match(handle.as_str()) || pattern.is_match(&deobfuscated_handle);
+    }
+
+    false
+}
+
 #[cfg(test)]
 #[expect(clippy::missing_errors_doc, reason = "see rust-lang/rust-clippy#13391")]
 mod tests {
@@ -310,4 +710,219 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn email_subaddress_stripping() {
+        let cases = [
+            ("user+tag", "user"),
+            ("user+tag+more", "user"),
+            ("user", "user"),
+            ("+tag", "+tag"),
+            ("\"user+tag\"", "\"user+tag\""),
+        ];
+
+        for (user, stripped_user) in cases {
+            assert_eq!(stripped_user, strip_subaddress(user), "stripping {user:?}",);
+        }
+    }
+
+    #[test]
+    fn email_canonicalization() -> anyhow::Result<()> {
+        let cases = [
+            ("user+tag@gmail.com", "user@gmail.com"),
+            ("user+tag@GMail.com", "user@gmail.com"),
+            ("user@gmail.com", "user@gmail.com"),
+            ("user+tag@example.com", "user+tag@example.com"),
+        ];
+
+        for (email, canonical_email) in cases {
+            assert_eq!(
+                canonical_email,
+                email.parse::<UserEmail>()?.canonical(),
+                "canonicalizing {email:?}",
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn domain_name_validation() {
+        let invalid_domains = [
+            "localhost",
+            "example.com.",
+            "-example.com",
+            "example-.com",
+            "exa mple.com",
+            "example.com:8080",
+            "127.0.0.1",
+            "example.123",
+            "",
+        ];
+
+        for domain in invalid_domains {
+            domain
+                .parse::<DomainName>()
+                .expect_err("domain name should be invalid");
+        }
+    }
+
+    #[test]
+    fn domain_name_normalization() -> anyhow::Result<()> {
+        let normalized_domain = "sub.xn--bcher-kva.example";
+
+        let equivalent_domains = [
+            normalized_domain,
+            "sub.bücher.example",
+            "SUB.BÜCHER.EXAMPLE",
+            "Sub.xN--bChEr-kVa.Example",
+        ];
+
+        for domain in equivalent_domains {
+            assert_eq!(
+                normalized_domain,
+                domain.parse::<DomainName>()?.as_str(),
+                "normalizing {domain:?}",
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn file_name_validation() {
+        let invalid_names = [
+            "",
+            ".",
+            "..",
+            "folder/file.txt",
+            "/",
+            "line\nbreak",
+            "null\0",
+        ];
+
+        for name in invalid_names {
+            name.parse::<FileName>()
+                .expect_err("file name should be invalid");
+        }
+
+        "a".repeat(FileName::MAX_LENGTH + 1)
+            .parse::<FileName>()
+            .expect_err("file name should be too long");
+
+        let valid_names = ["file.txt", ".hidden", "...", "with spaces", "ünïcödé 🌱"];
+
+        for name in valid_names {
+            name.parse::<FileName>().expect("file name should be valid");
+        }
+    }
+
+    #[test]
+    fn file_name_normalization() -> anyhow::Result<()> {
+        let normalized_name = "caf\u{e9}.txt";
+
+        let equivalent_names = [normalized_name, "cafe\u{301}.txt"];
+
+        for name in equivalent_names {
+            assert_eq!(
+                normalized_name,
+                name.parse::<FileName>()?.as_str(),
+                "normalizing {name:?}",
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn user_handle_validation() {
+        let invalid_handles = [
+            "ab",
+            "more-than-32-characters-is-too-long",
+            "-leading-hyphen",
+            "trailing-underscore_",
+            "with space",
+            "with.dot",
+            "with/slash",
+            "ünïcödé",
+            "abcdefghijk",
+        ];
+
+        for handle in invalid_handles {
+            handle
+                .parse::<UserHandle>()
+                .expect_err("user handle should be invalid");
+        }
+    }
+
+    #[test]
+    fn handle_deobfuscation() {
+        let cases = [
+            ("admin", "admin"),
+            ("4dm1n", "admin"),
+            ("a_d-min", "admin"),
+            ("5t4tu5", "status"),
+            ("s3cur1ty", "security"),
+            ("garden-keeper_42", "gardenkeepera2"),
+        ];
+
+        for (handle, expected) in cases {
+            assert_eq!(expected, deobfuscate(handle), "deobfuscating {handle:?}");
+        }
+    }
+
+    #[test]
+    fn handle_availability() -> anyhow::Result<()> {
+        let unavailable_handles = [
+            "admin",
+            "4dm1n",
+            "a_d-min",
+            "www",
+            "filegarden",
+            "file-garden",
+            "f1l3g4rd3n-official",
+            "official-filegarden",
+            "fuck",
+            "f-u-c-k",
+            "sh1t",
+            "big-shit",
+            "shit_head",
+        ];
+
+        for handle in unavailable_handles {
+            assert!(
+                is_handle_unavailable(&handle.parse()?),
+                "checking {handle:?} is unavailable",
+            );
+        }
+
+        let available_handles = [
+            "scunthorpe",
+            "cocktail-bitches",
+            "shitake",
+            "classic-assets",
+            "administrators-fan",
+            "garden-keeper_42",
+        ];
+
+        for handle in available_handles {
+            assert!(
+                !is_handle_unavailable(&handle.parse()?),
+                "checking {handle:?} is available",
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn user_handle_normalization() -> anyhow::Result<()> {
+        assert_eq!(
+            "garden-keeper_42",
+            "Garden-Keeper_42".parse::<UserHandle>()?.as_str(),
+            "normalizing user handle",
+        );
+
+        Ok(())
+    }
 }
@@ -0,0 +1,108 @@
+//! See [`App`].
+
+use axum::Router;
+use sqlx::PgPool;
+
+use crate::{
+    api, cleanup, disposable_emails, downloads, file_events, maintenance, router, AppState,
+};
+
+/// The whole server, built by an [`AppBuilder`] from the resources it depends on and served through
+/// its [`App::router`]. Apps don't share routers, database pools, or background tasks, so several
+/// can run in one process, each with its own database. Settings from the environment are still
+/// shared by the whole process.
+#[derive(Clone, Debug)]
+pub struct App {
+    /// The state passed to all of the app's routes.
+    state: AppState,
+}
+
+impl App {
+    /// Starts building an [`App`] using the specified database pool.
+    pub const fn builder(db_pool: PgPool) -> AppBuilder {
+        AppBuilder {
+            db_pool,
+            db_replica_pool: None,
+            background_jobs: true,
+        }
+    }
+
+    /// Gets the state passed to all of the app's routes.
+    pub(crate) const fn state(&self) -> &AppState {
+        &self.state
+    }
+
+    /// Builds a router handling every request to the app, which can be served directly or nested in
+    /// another router.
+    pub fn router(&self) -> Router {
+        Router::new()
+            .fallback(router::handle)
+            .with_state(self.state.clone())
+    }
+}
+
+/// A builder for an [`App`].
+#[derive(Debug)]
+pub struct AppBuilder {
+    /// The database pool shared between all routes.
+    db_pool: PgPool,
+
+    /// The database pool for read-only queries that can tolerate replication lag, if different
+    /// from `db_pool`.
+    db_replica_pool: Option<PgPool>,
+
+    /// Whether the app runs background jobs that aren't needed for requests to be handled
+    /// correctly.
+    background_jobs: bool,
+}
+
+impl AppBuilder {
+    /// Sets the database pool for read-only queries that can tolerate replication lag. By default,
+    /// the main database pool is used for these.
+    pub fn db_replica_pool(mut self, db_replica_pool: Option<PgPool>) -> Self {
+        self.db_replica_pool = db_replica_pool;
+        self
+    }
+
+    /// Sets whether the app runs background jobs that aren't needed for requests to be handled
+    /// correctly, like periodic cleanup and downloading the disposable email domain blocklist. By
+    /// default, they run.
+    ///
+    /// Short-lived apps, like those in tests, can turn them off so they don't run against a
+    /// database that's about to be dropped.
+    pub fn background_jobs(mut self, background_jobs: bool) -> Self {
+        self.background_jobs = background_jobs;
+        self
+    }
+
+    /// Builds the [`App`], starting its background tasks.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if listening for file events fails.
+    pub async fn build(self) -> sqlx::Result<App> {
+        let file_events = file_events::listen(&self.db_pool).await?;
+
+        let download_counter = downloads::start(self.db_pool.clone());
+
+        let disposable_email_domains = if self.background_jobs {
+            cleanup::start(self.db_pool.clone());
+
+            disposable_emails::start()
+        } else {
+            disposable_emails::Blocklist::default()
+        };
+
+        Ok(App {
+            state: AppState {
+                db_replica_pool: self.db_replica_pool.unwrap_or_else(|| self.db_pool.clone()),
+                maintenance_mode: maintenance::Switch::new(self.db_pool.clone()),
+                db_pool: self.db_pool,
+                file_events,
+                download_counter,
+                disposable_email_domains,
+                api_router: api::routes::router(),
+            },
+        })
+    }
+}
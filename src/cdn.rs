@@ -0,0 +1,114 @@
+//! Purging files from the CDN's cache when the way they were cached no longer applies, such as when
+//! they're taken down. Files requested by ID are cached immutably, so they'd otherwise be served
+//! from the cache for up to a year.
+
+use sqlx::PgConnection;
+
+use crate::{
+    config::{self, Reloadable},
+    content::{canonical_file_url, FILE_ID_QUERY_PREFIX},
+    id::Id,
+    telemetry,
+};
+
+/// The most URLs purged in one request. This is the limit of Cloudflare's API.
+const MAX_URLS_PER_REQUEST: usize = 30;
+
+/// The URL of the CDN's API endpoint for purging URLs from its cache, which is sent a `POST`
+/// request with a JSON body like `{"files":["https://file.garden/..."]}`. This is compatible with
+/// Cloudflare's API.
+pub(crate) static PURGE_URL: Reloadable<Option<String>> = Reloadable::new(|| {
+    // If the environment variable is unset, there's no CDN in front of the content origin to purge.
+    config::optional_var("CDN_PURGE_URL")
+});
+
+/// The bearer token to authorize purge requests with, if any.
+pub(crate) static PURGE_TOKEN: Reloadable<Option<String>> =
+    Reloadable::new(|| config::optional_var("CDN_PURGE_TOKEN"));
+
+/// Gets the URLs an existing file's content can be cached under (see [`urls`]). This must be called
+/// before the file is changed, since its URL may change.
+///
+/// Returns an empty list if the file doesn't exist.
+///
+/// # Errors
+///
+/// Returns an error if a database query fails.
+pub(crate) async fn file_urls(conn: &mut PgConnection, file_id: &Id) -> sqlx::Result<Vec<String>> {
+    let Some(file) = sqlx::query!(
+        "SELECT files.owner_id, users.handle::text, files.parent_name_path, files.name
+            FROM files JOIN users ON users.id = files.owner_id
+            WHERE files.id = $1",
+        file_id.as_slice(),
+    )
+    .fetch_optional(&mut *conn)
+    .await?
+    else {
+        return Ok(Vec::new());
+    };
+
+    urls(
+        conn,
+        file_id,
+        &file.owner_id.into(),
+        file.handle.as_deref(),
+        &file.parent_name_path,
+        &file.name,
+    )
+    .await
+}
+
+/// Gets the URLs a file's content can be cached under: its canonical URL, and that URL with its ID.
+///
+/// # Errors
+///
+/// Returns an error if a database query fails.
+pub(crate) async fn urls(
+    conn: &mut PgConnection,
+    file_id: &Id,
+    owner_id: &Id,
+    owner_handle: Option<&str>,
+    parent_path: &[String],
+    name: &str,
+) -> sqlx::Result<Vec<String>> {
+    let url = canonical_file_url(conn, owner_id, owner_handle, parent_path, name).await?;
+    let id_url = format!("{url}?{FILE_ID_QUERY_PREFIX}{file_id}");
+
+    Ok(vec![url, id_url])
+}
+
+/// Purges the specified URLs from the CDN's cache in the background. Failures are reported rather
+/// than returned, since the change that required purging has already been made.
+pub(crate) fn purge(urls: Vec<String>) {
+    let Some(purge_url) = PURGE_URL.get().as_ref().clone() else {
+        return;
+    };
+
+    if urls.is_empty() {
+        return;
+    }
+
+    let token = PURGE_TOKEN.get().as_ref().clone();
+
+    tokio::spawn(async move {
+        let client = reqwest::Client::new();
+
+        for urls in urls.chunks(MAX_URLS_PER_REQUEST) {
+            let mut request = client
+                .post(&purge_url)
+                .json(&serde_json::json!({ "files": urls }));
+
+            if let Some(token) = &token {
+                request = request.bearer_auth(token);
+            }
+
+            if let Err(error) = request
+                .send()
+                .await
+                .and_then(reqwest::Response::error_for_status)
+            {
+                telemetry::report_error(&error);
+            }
+        }
+    });
+}
@@ -0,0 +1,331 @@
+//! A scheduler for periodically deleting data that's no longer meant to be kept.
+
+use std::time::Duration;
+
+use sqlx::PgPool;
+
+use crate::{
+    api::{
+        routes::v1::{
+            billing::stripe_webhook::EVENT_RETENTION, files::analytics::VISITOR_RETENTION,
+            sessions::FAILED_SIGN_IN_WINDOW, users::handle::PREVIOUS_HANDLE_GRACE_PERIOD,
+        },
+        stripe,
+    },
+    cdn,
+    db::{self, TxResult},
+    telemetry,
+};
+
+/// How often cleanup runs.
+const INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// How many files of deleted users are purged per transaction, so purging large accounts doesn't
+/// hold locks for long.
+const PURGE_BATCH_SIZE: i64 = 1000;
+
+/// Starts running cleanup periodically in the background.
+pub(crate) fn start(db_pool: PgPool) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            run(&db_pool).await;
+        }
+    });
+}
+
+/// Runs every kind of cleanup once. If any fails, it's reported and retried next time.
+#[tracing::instrument(name = "cleanup", skip_all)]
+async fn run(db_pool: &PgPool) {
+    let results = [
+        delete_expired_files(db_pool).await,
+        delete_old_failed_sign_ins(db_pool).await,
+        delete_expired_previous_handles(db_pool).await,
+        delete_old_visitor_hashes(db_pool).await,
+        delete_expired_file_locks(db_pool).await,
+        delete_old_stripe_events(db_pool).await,
+        purge_deleted_users(db_pool).await,
+    ];
+
+    for error in results.into_iter().filter_map(Result::err) {
+        telemetry::report_error(&error);
+    }
+
+    cancel_queued_subscriptions(db_pool).await;
+}
+
+/// Deletes files older than the retention policy of any folder they're in, unless they're under a
+/// legal hold or locked. A locked file is deleted once its lock expires. Deleted files are purged
+/// from the CDN's cache.
+///
+/// # Errors
+///
+/// Returns an error if the database query fails.
+async fn delete_expired_files(db_pool: &PgPool) -> sqlx::Result<()> {
+    let urls = db::transaction!(db_pool, async |tx| -> TxResult<_, sqlx::Error> {
+        let files = sqlx::query!(
+            r#"DELETE FROM files
+                WHERE NOT legal_hold
+                    AND NOT EXISTS (
+                        SELECT 1 FROM users
+                            WHERE id = files.owner_id AND legal_hold
+                    )
+                    AND NOT EXISTS (
+                        SELECT 1 FROM file_locks
+                            WHERE file_id = files.id AND expires_at > now()
+                    )
+                    AND EXISTS (
+                        SELECT 1 FROM folders
+                            WHERE id = ANY (files.parent_id_path)
+                                AND files.created_at
+                                    < now() - make_interval(days => retention_days)
+                    )
+                RETURNING id, owner_id, parent_name_path, name, (
+                    SELECT handle::text FROM users
+                        WHERE id = files.owner_id
+                ) as handle"#,
+        )
+        .fetch_all(tx.as_mut())
+        .await?;
+
+        let mut urls = Vec::new();
+
+        for file in files {
+            urls.extend(
+                cdn::urls(
+                    tx,
+                    &file.id.into(),
+                    &file.owner_id.into(),
+                    file.handle.as_deref(),
+                    &file.parent_name_path,
+                    &file.name,
+                )
+                .await?,
+            );
+        }
+
+        Ok(urls)
+    })
+    .await?;
+
+    cdn::purge(urls);
+
+    Ok(())
+}
+
+/// Deletes failed sign-in attempts too old to count toward throttling sign-ins.
+///
+/// # Errors
+///
+/// Returns an error if the database query fails.
+async fn delete_old_failed_sign_ins(db_pool: &PgPool) -> sqlx::Result<()> {
+    db::transaction!(db_pool, async |tx| -> TxResult<_, sqlx::Error> {
+        sqlx::query!(
+            "DELETE FROM failed_sign_ins
+                WHERE created_at <= now() - make_interval(secs => $1)",
+            FAILED_SIGN_IN_WINDOW.as_seconds_f64(),
+        )
+        .execute(tx.as_mut())
+        .await?;
+
+        Ok(())
+    })
+    .await
+}
+
+/// Deletes previous handles past their grace period, so they stop redirecting and anyone can claim
+/// them again.
+///
+/// # Errors
+///
+/// Returns an error if the database query fails.
+async fn delete_expired_previous_handles(db_pool: &PgPool) -> sqlx::Result<()> {
+    db::transaction!(db_pool, async |tx| -> TxResult<_, sqlx::Error> {
+        sqlx::query!(
+            "DELETE FROM previous_handles
+                WHERE released_at <= now() - make_interval(secs => $1)",
+            PREVIOUS_HANDLE_GRACE_PERIOD.as_secs_f64(),
+        )
+        .execute(tx.as_mut())
+        .await?;
+
+        Ok(())
+    })
+    .await
+}
+
+/// Deletes hashed IP addresses of files' visitors older than they're kept to count unique visitors.
+///
+/// # Errors
+///
+/// Returns an error if the database query fails.
+async fn delete_old_visitor_hashes(db_pool: &PgPool) -> sqlx::Result<()> {
+    db::transaction!(db_pool, async |tx| -> TxResult<_, sqlx::Error> {
+        sqlx::query!(
+            "DELETE FROM file_visitors
+                WHERE hour <= now() - make_interval(secs => $1)",
+            VISITOR_RETENTION.as_secs_f64(),
+        )
+        .execute(tx.as_mut())
+        .await?;
+
+        Ok(())
+    })
+    .await
+}
+
+/// Deletes expired file locks, which no longer apply anyway.
+///
+/// # Errors
+///
+/// Returns an error if the database query fails.
+async fn delete_expired_file_locks(db_pool: &PgPool) -> sqlx::Result<()> {
+    db::transaction!(db_pool, async |tx| -> TxResult<_, sqlx::Error> {
+        sqlx::query!(
+            "DELETE FROM file_locks
+                WHERE expires_at <= now()",
+        )
+        .execute(tx.as_mut())
+        .await?;
+
+        Ok(())
+    })
+    .await
+}
+
+/// Deletes the IDs of handled Stripe events older than Stripe could send them again.
+///
+/// # Errors
+///
+/// Returns an error if the database query fails.
+async fn delete_old_stripe_events(db_pool: &PgPool) -> sqlx::Result<()> {
+    db::transaction!(db_pool, async |tx| -> TxResult<_, sqlx::Error> {
+        sqlx::query!(
+            "DELETE FROM stripe_events
+                WHERE received_at <= now() - make_interval(secs => $1)",
+            EVENT_RETENTION.as_secs_f64(),
+        )
+        .execute(tx.as_mut())
+        .await?;
+
+        Ok(())
+    })
+    .await
+}
+
+/// Purges the files and folders of deleted users in batches, then marks each user purged once
+/// nothing of theirs is left. Each batch is committed on its own, so progress is kept if purging is
+/// interrupted. Anything under a legal hold is kept until the hold is lifted.
+///
+/// # Errors
+///
+/// Returns an error if a database query fails.
+async fn purge_deleted_users(db_pool: &PgPool) -> sqlx::Result<()> {
+    loop {
+        let purged_file_count = db::transaction!(db_pool, async |tx| -> TxResult<_, sqlx::Error> {
+            Ok(sqlx::query!(
+                "DELETE FROM files
+                    WHERE id IN (
+                        SELECT files.id FROM files
+                            JOIN users ON users.id = files.owner_id
+                            WHERE users.deleted_at IS NOT NULL
+                                AND users.purged_at IS NULL
+                                AND NOT users.legal_hold
+                                AND NOT files.legal_hold
+                            LIMIT $1
+                    )",
+                PURGE_BATCH_SIZE,
+            )
+            .execute(tx.as_mut())
+            .await?
+            .rows_affected())
+        })
+        .await?;
+
+        if purged_file_count == 0 {
+            break;
+        }
+    }
+
+    db::transaction!(db_pool, async |tx| -> TxResult<_, sqlx::Error> {
+        sqlx::query!(
+            "DELETE FROM folders
+                WHERE owner_id IN (
+                    SELECT id FROM users
+                        WHERE deleted_at IS NOT NULL AND purged_at IS NULL
+                )
+                    AND NOT EXISTS (
+                        SELECT 1 FROM files
+                            WHERE owner_id = folders.owner_id
+                                AND folders.id = ANY (parent_id_path)
+                    )",
+        )
+        .execute(tx.as_mut())
+        .await?;
+
+        sqlx::query!(
+            "UPDATE users
+                SET purged_at = now()
+                WHERE deleted_at IS NOT NULL
+                    AND purged_at IS NULL
+                    AND NOT EXISTS (
+                        SELECT 1 FROM files
+                            WHERE owner_id = users.id
+                    )",
+        )
+        .execute(tx.as_mut())
+        .await?;
+
+        Ok(())
+    })
+    .await
+}
+
+/// Cancels the queued Stripe subscriptions of deleted users. Each cancellation is dequeued once
+/// Stripe confirms it, so any that fail are reported and retried next time.
+async fn cancel_queued_subscriptions(db_pool: &PgPool) {
+    let result = db::transaction!(db_pool, async |tx| -> TxResult<_, sqlx::Error> {
+        Ok(sqlx::query_scalar!(
+            "SELECT subscription_id
+                FROM subscription_cancellations",
+        )
+        .fetch_all(tx.as_mut())
+        .await?)
+    })
+    .await;
+
+    let subscription_ids = match result {
+        Ok(subscription_ids) => subscription_ids,
+        Err(error) => {
+            telemetry::report_error(&error);
+            return;
+        }
+    };
+
+    for subscription_id in subscription_ids {
+        if let Err(error) = stripe::cancel_subscription(&subscription_id).await {
+            telemetry::report_error(&error);
+            continue;
+        }
+
+        let result = db::transaction!(db_pool, async |tx| -> TxResult<_, sqlx::Error> {
+            sqlx::query!(
+                "DELETE FROM subscription_cancellations
+                    WHERE subscription_id = $1",
+                subscription_id,
+            )
+            .execute(tx.as_mut())
+            .await?;
+
+            Ok(())
+        })
+        .await;
+
+        if let Err(error) = result {
+            telemetry::report_error(&error);
+        }
+    }
+}
@@ -0,0 +1,250 @@
+//! Settings that can be changed without restarting the server, by editing `.env` and then sending
+//! the process `SIGHUP` or using the API route to reload settings.
+
+use std::{
+    collections::HashMap,
+    env::{self, VarError},
+    io,
+    sync::{Arc, PoisonError, RwLock},
+};
+
+use thiserror::Error;
+use tokio::signal::unix::{signal, SignalKind};
+use tracing::{error, info};
+
+use crate::{
+    api::{
+        routes::v1::{users::IS_INVITE_REQUIRED, DEPRECATED_AT, SUNSET_AT},
+        validation::{BLOCKED_HANDLE_PATTERN, RESERVED_HANDLES, STRIP_EMAIL_SUBADDRESSES},
+    },
+    cdn,
+    content::SENSITIVE_FILE_POLICY,
+    telemetry::{self, LOG_LEVEL},
+    AppState,
+};
+
+/// The variables set in `.env`, read on first use and again whenever settings are [`reload`]ed.
+/// They're kept here rather than set in the process's environment, since changing the environment
+/// while other threads may read it is unsound.
+static DOTENV_VARS: RwLock<Option<Arc<HashMap<String, String>>>> = RwLock::new(None);
+
+/// Gets a variable's value from `.env`, or from the process's environment if `.env` doesn't set it.
+///
+/// # Errors
+///
+/// Returns [`dotenvy::Error::EnvVar`] if the variable is unset or isn't valid Unicode.
+///
+/// # Panics
+///
+/// Panics if this is the first use of `.env` and it exists but can't be read.
+pub(crate) fn var(name: &str) -> Result<String, dotenvy::Error> {
+    let dotenv_vars = get_or_load(&DOTENV_VARS, || {
+        Arc::new(read_dotenv().expect("`.env` should be readable if it exists"))
+    });
+
+    match dotenv_vars.get(name) {
+        Some(value) => Ok(value.clone()),
+        None => env::var(name).map_err(dotenvy::Error::EnvVar),
+    }
+}
+
+/// Gets an optional variable's value (see [`var`]), or `None` if it's unset.
+///
+/// # Errors
+///
+/// Returns an error message if the variable is set but isn't valid Unicode.
+pub(crate) fn optional_var(name: &str) -> Result<Option<String>, String> {
+    match var(name) {
+        Err(dotenvy::Error::EnvVar(VarError::NotPresent)) => Ok(None),
+        Ok(value) => Ok(Some(value)),
+        Err(_) => Err(format!(
+            "environment variable `{name}` should be a valid string if set"
+        )),
+    }
+}
+
+/// Reads the variables set in `.env`, searching the current directory and its ancestors like
+/// [`dotenvy::dotenv`] does. If a variable is set more than once, its first value is used.
+///
+/// # Errors
+///
+/// Returns an error if `.env` exists but can't be read or parsed.
+fn read_dotenv() -> Result<HashMap<String, String>, dotenvy::Error> {
+    let mut dotenv_vars = HashMap::new();
+
+    let lines = match dotenvy::from_filename_iter(".env") {
+        // Settings may be set only by environment variables.
+        Err(error) if error.not_found() => return Ok(dotenv_vars),
+        lines => lines?,
+    };
+
+    for line in lines {
+        let (name, value) = line?;
+        dotenv_vars.entry(name).or_insert(value);
+    }
+
+    Ok(dotenv_vars)
+}
+
+/// Gets a lazily loaded value, loading it if it hasn't been yet.
+fn get_or_load<T>(value: &RwLock<Option<Arc<T>>>, load: impl FnOnce() -> Arc<T>) -> Arc<T> {
+    if let Some(value) = &*value.read().unwrap_or_else(PoisonError::into_inner) {
+        return Arc::clone(value);
+    }
+
+    let mut value = value.write().unwrap_or_else(PoisonError::into_inner);
+
+    // Another thread may have loaded it while this one waited for the lock.
+    Arc::clone(value.get_or_insert_with(load))
+}
+
+/// A setting loaded on first use, which is loaded again whenever settings are [`reload`]ed.
+#[derive(Debug)]
+pub(crate) struct Reloadable<T> {
+    /// The function loading the setting's value, returning an error message if the value is
+    /// invalid.
+    load: fn() -> Result<T, String>,
+
+    /// The setting's current value, or nothing if it hasn't been loaded yet.
+    value: RwLock<Option<Arc<T>>>,
+}
+
+impl<T> Reloadable<T> {
+    /// Constructs a [`Reloadable`] setting loaded by the specified function.
+    pub(crate) const fn new(load: fn() -> Result<T, String>) -> Self {
+        Self {
+            load,
+            value: RwLock::new(None),
+        }
+    }
+
+    /// Gets the setting's current value, loading it if this is its first use.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the setting wasn't loaded by [`init`] and its value is invalid.
+    pub(crate) fn get(&self) -> Arc<T> {
+        get_or_load(&self.value, || {
+            Arc::new((self.load)().unwrap_or_else(|message| panic!("{message}")))
+        })
+    }
+
+    /// Loads the setting again, whether or not it's been used, so invalid values are caught before
+    /// they're needed. If the new value is invalid, the previous value is kept.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error message if the new value is invalid.
+    fn reload(&self) -> Result<(), String> {
+        let new_value = (self.load)()?;
+
+        *self.value.write().unwrap_or_else(PoisonError::into_inner) = Some(Arc::new(new_value));
+
+        Ok(())
+    }
+}
+
+/// An error loading settings.
+#[derive(Error, Debug)]
+pub(crate) enum ReloadError {
+    /// `.env` exists but couldn't be read.
+    #[error("couldn't read `.env`: {0}")]
+    Dotenv(#[from] dotenvy::Error),
+
+    /// Some settings are invalid, so they kept their previous values.
+    #[error("some settings are invalid and kept their previous values: {}", .0.join("; "))]
+    InvalidSettings(Vec<String>),
+}
+
+/// Reads `.env` and loads every reloadable setting, so the server doesn't start with invalid
+/// settings. Values in `.env` override any environment variables of the same name.
+///
+/// # Errors
+///
+/// See [`ReloadError`].
+pub(crate) fn init() -> Result<(), ReloadError> {
+    load_dotenv()?;
+    load_settings()
+}
+
+/// Reads `.env` again and reloads every reloadable setting from it. Values in `.env` override any
+/// environment variables of the same name.
+///
+/// # Errors
+///
+/// See [`ReloadError`]. Valid settings are still reloaded if others are invalid.
+pub(crate) async fn reload(state: &AppState) -> Result<(), ReloadError> {
+    load_dotenv()?;
+    let result = load_settings();
+
+    state.disposable_email_domains.refresh().await;
+
+    result
+}
+
+/// Reads `.env`, replacing the variables read from it before.
+///
+/// # Errors
+///
+/// Returns an error if `.env` exists but can't be read or parsed.
+fn load_dotenv() -> Result<(), dotenvy::Error> {
+    let dotenv_vars = read_dotenv()?;
+
+    *DOTENV_VARS.write().unwrap_or_else(PoisonError::into_inner) = Some(Arc::new(dotenv_vars));
+
+    Ok(())
+}
+
+/// Loads every reloadable setting.
+///
+/// # Errors
+///
+/// Returns [`ReloadError::InvalidSettings`] with the problem with each invalid setting. Valid
+/// settings are still loaded.
+fn load_settings() -> Result<(), ReloadError> {
+    let errors: Vec<String> = [
+        IS_INVITE_REQUIRED.reload(),
+        RESERVED_HANDLES.reload(),
+        BLOCKED_HANDLE_PATTERN.reload(),
+        DEPRECATED_AT.reload(),
+        SUNSET_AT.reload(),
+        STRIP_EMAIL_SUBADDRESSES.reload(),
+        SENSITIVE_FILE_POLICY.reload(),
+        cdn::PURGE_URL.reload(),
+        cdn::PURGE_TOKEN.reload(),
+        LOG_LEVEL.reload(),
+    ]
+    .into_iter()
+    .filter_map(Result::err)
+    .collect();
+
+    telemetry::apply_log_level();
+
+    if !errors.is_empty() {
+        return Err(ReloadError::InvalidSettings(errors));
+    }
+
+    Ok(())
+}
+
+/// Starts reloading settings in the background whenever the process receives `SIGHUP`.
+///
+/// # Errors
+///
+/// Returns an error if listening for the signal fails.
+pub(crate) fn reload_on_hangup(state: AppState) -> io::Result<()> {
+    let mut hangups = signal(SignalKind::hangup())?;
+
+    tokio::spawn(async move {
+        while hangups.recv().await.is_some() {
+            info!("reloading settings");
+
+            match reload(&state).await {
+                Ok(()) => info!("settings reloaded"),
+                Err(error) => error!(%error, "reloading settings failed"),
+            }
+        }
+    });
+
+    Ok(())
+}
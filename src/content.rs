@@ -1,24 +1,216 @@
 //! A web server for user-uploaded content. File Garden exposes this via `https://file.garden/`.
 
-use std::borrow::Cow;
+use std::{borrow::Cow, sync::LazyLock};
 
+use askama::Template;
 use axum::{
-    extract::Request,
+    extract::{FromRequestParts, Request, State},
     http::{
-        header::{ACCESS_CONTROL_ALLOW_ORIGIN, ALLOW, CONTENT_SECURITY_POLICY},
-        Method, StatusCode,
+        header::{
+            ACCESS_CONTROL_ALLOW_ORIGIN, ALLOW, CACHE_CONTROL, CONTENT_SECURITY_POLICY,
+            CONTENT_TYPE, HOST, RETRY_AFTER, USER_AGENT, VARY,
+        },
+        HeaderMap, HeaderName, Method, StatusCode,
     },
 };
+use chrono::Utc;
 use percent_encoding::{percent_decode_str, utf8_percent_encode};
+use sqlx::PgConnection;
+use unicode_normalization::{is_nfc, UnicodeNormalization};
 
-use crate::{percent_encoding::COMPONENT_IGNORING_SLASH, response::Response, WEBSITE_ORIGIN};
+use crate::{
+    api::{
+        client_ip::ClientIp,
+        routes::v1::{
+            config::maintenance::MaintenanceMode,
+            files::{FileStatus, TakedownReason},
+            users::handle::PREVIOUS_HANDLE_GRACE_PERIOD,
+        },
+    },
+    config::{self, Reloadable},
+    crypto::{sign, verify_signature},
+    db::{self, TxResult},
+    id::{Id, NewOrganizationId, NewUserId, ShortLinkCode},
+    percent_encoding::{COMPONENT, COMPONENT_IGNORING_SLASH},
+    response::Response,
+    AppState, CONTENT_ORIGIN, WEBSITE_ORIGIN,
+};
+
+/// The URI scheme of the content origin, which users' custom domains are also served over.
+pub(crate) static CONTENT_SCHEME: LazyLock<&str> = LazyLock::new(|| {
+    CONTENT_ORIGIN
+        .split_once("://")
+        .expect("content origin should contain \"://\"")
+        .0
+});
+
+/// The start of the path of a file's short link on the content origin, followed by its code.
+pub(crate) const SHORT_LINK_PATH_PREFIX: &str = "/s/";
+
+/// How many seconds clients are told to wait before retrying a request for a file pending a scan.
+const PENDING_SCAN_RETRY_AFTER_SECONDS: u64 = 60;
 
 /// The start of a file ID query parameter.
-const FILE_ID_QUERY_PREFIX: &str = "_id=";
+pub(crate) const FILE_ID_QUERY_PREFIX: &str = "_id=";
+
+/// The start of a viewer query parameter, which lets a signed-in user view files they have access to
+/// that aren't accessible to anyone with their link. See [`viewer_param`].
+pub(crate) const VIEWER_QUERY_PREFIX: &str = "_viewer=";
+
+/// The `Cache-Control` header for a file requested only by its path, which can refer to a different
+/// file once the file is moved, renamed, or replaced.
+const PATH_CACHE_CONTROL: &str = "public, max-age=300";
+
+/// The `Cache-Control` header for a file requested with its current ID, which can never refer to a
+/// different file. The file is purged from the CDN's cache if it's taken down, marked as sensitive,
+/// unshared, renamed, or deleted (see [`crate::cdn`]).
+const ID_CACHE_CONTROL: &str = "public, max-age=31536000, immutable";
+
+/// The `Rating` header, which search engines use to filter sensitive content out of safe search.
+static RATING: HeaderName = HeaderName::from_static("rating");
+
+/// The `X-Robots-Tag` header, which tells search engines whether they may index a response.
+static X_ROBOTS_TAG: HeaderName = HeaderName::from_static("x-robots-tag");
+
+/// The path of the file telling crawlers which files they may index.
+const ROBOTS_TXT_PATH: &str = "/robots.txt";
+
+/// How files marked as sensitive are served.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum SensitiveFilePolicy {
+    /// Sensitive files are served like any other, labeled with a `Rating` header.
+    Label,
+
+    /// Sensitive files are labeled, and visitors are shown a warning page linking to the file
+    /// instead of the file itself.
+    Interstitial,
+}
+
+/// How files marked as sensitive are served.
+pub(crate) static SENSITIVE_FILE_POLICY: Reloadable<SensitiveFilePolicy> = Reloadable::new(|| {
+    match config::optional_var("SENSITIVE_FILE_POLICY")?.as_deref() {
+        // If the environment variable is unset, sensitive files are only labeled, so they're hidden
+        // from safe search without getting in the way of people who were sent the link.
+        None | Some("label") => Ok(SensitiveFilePolicy::Label),
+        Some("interstitial") => Ok(SensitiveFilePolicy::Interstitial),
+        Some(_) => Err(
+            "environment variable `SENSITIVE_FILE_POLICY` should be `label` or \
+            `interstitial` if set"
+                .into(),
+        ),
+    }
+});
+
+/// Substrings of the `User-Agent` headers of crawlers that generate link previews.
+const CRAWLER_USER_AGENTS: [&str; 10] = [
+    "Discordbot",
+    "Embedly",
+    "facebookexternalhit",
+    "LinkedInBot",
+    "Mastodon",
+    "redditbot",
+    "Slackbot",
+    "TelegramBot",
+    "Twitterbot",
+    "WhatsApp",
+];
+
+/// An HTML page with metadata for crawlers to generate a link preview of a file.
+#[derive(Template, Debug)]
+#[template(path = "content/preview.html")]
+struct PreviewPage<'a> {
+    /// The file's name.
+    name: &'a str,
+
+    /// The file's media type.
+    media_type: &'a str,
+
+    /// The URL of the file's route.
+    url: &'a str,
+
+    /// The URL of the file's raw content, which is never served as a preview page.
+    raw_url: &'a str,
+
+    /// The URL of the file's oEmbed data.
+    oembed_url: &'a str,
+}
+
+/// An HTML page warning visitors that a file is sensitive before they view it.
+#[derive(Template, Debug)]
+#[template(path = "content/sensitive.html")]
+struct SensitivePage<'a> {
+    /// The file's name.
+    name: &'a str,
+
+    /// The URL of the file's raw content, which is never served as a warning page.
+    raw_url: &'a str,
+}
+
+/// An HTML page telling visitors user-uploaded content is unavailable during maintenance.
+#[derive(Template, Debug)]
+#[template(path = "content/maintenance.html")]
+struct MaintenancePage;
+
+/// A user's verified custom domain that a request was sent to.
+#[derive(Debug)]
+struct CustomDomain {
+    /// The domain name.
+    name: String,
+
+    /// The ID of the user whose files the domain serves.
+    user_id: Id,
+}
+
+/// The service function to handle incoming requests for user-uploaded content on the content
+/// origin.
+pub(super) async fn handle(State(state): State<AppState>, request: Request) -> Response {
+    serve(&state, request, None).await
+}
+
+/// The service function to handle incoming requests for user-uploaded content on users' custom
+/// domains.
+pub(super) async fn handle_custom_domain(
+    State(state): State<AppState>,
+    request: Request,
+) -> Response {
+    let Some(name) = request
+        .headers()
+        .get(HOST)
+        .and_then(|host| host.to_str().ok())
+        // Remove the port, if any.
+        .map(|host| host.split_once(':').map_or(host, |(name, _)| name))
+        .map(str::to_ascii_lowercase)
+    else {
+        return Response::new().plain_error(StatusCode::MISDIRECTED_REQUEST);
+    };
+
+    let user_id = db::transaction!(state.db_replica_pool, async |tx| -> TxResult<
+        _,
+        sqlx::Error,
+    > {
+        Ok(find_custom_domain_owner(tx, &name).await?)
+    })
+    .await;
+
+    match user_id {
+        Ok(Some(user_id)) => serve(&state, request, Some(CustomDomain { name, user_id })).await,
+        Ok(None) => Response::new().plain_error(StatusCode::MISDIRECTED_REQUEST),
+        Err(_) => Response::new().plain_error(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+/// Handles a request for user-uploaded content, either on the content origin or on the specified
+/// custom domain.
+async fn serve(
+    state: &AppState,
+    request: Request,
+    custom_domain: Option<CustomDomain>,
+) -> Response {
+    let (mut request, _body) = request.into_parts();
+
+    // This is only used to count unique visitors to files, so it's fine if it's unknown.
+    let client_ip = ClientIp::from_request_parts(&mut request, &()).await.ok();
 
-/// The service function to handle incoming requests for user-uploaded content.
-pub(super) fn handle(request: Request) -> Response {
-    let (request, _body) = request.into_parts();
     let mut response = Response::new();
 
     response
@@ -42,20 +234,34 @@ pub(super) fn handle(request: Request) -> Response {
         return response;
     }
 
+    if state.maintenance_mode.get().await == MaintenanceMode::All {
+        response
+            .status(StatusCode::SERVICE_UNAVAILABLE)
+            .header_valid(CONTENT_TYPE, "text/html; charset=utf-8")
+            .header_valid(CACHE_CONTROL, "no-store");
+
+        return response.body(MaintenancePage.to_string());
+    }
+
     let encoded_path = request.uri.path();
 
-    if encoded_path == "/" {
+    if encoded_path == "/" && custom_domain.is_none() {
         return response.permanent_redirect(format!("{}/", *WEBSITE_ORIGIN).as_str());
     }
 
-    let Ok(path) = percent_decode_str(encoded_path).decode_utf8() else {
+    if encoded_path == ROBOTS_TXT_PATH {
+        return robots_txt(state, response, custom_domain.as_ref()).await;
+    }
+
+    let Some(path) = decode_path(encoded_path) else {
         return response.plain_error(StatusCode::BAD_REQUEST);
     };
 
-    // The above can decode `%00` into a null byte, so disallow null bytes as a defensive measure.
-    if path.contains('\x00') {
+    // Likewise, equivalent paths with different segments are canonicalized. Paths trying to go
+    // above the root are rejected outright rather than resolved, since no legitimate link has them.
+    let Some(path) = canonicalize_path(&path) else {
         return response.plain_error(StatusCode::BAD_REQUEST);
-    }
+    };
 
     let normalized_encoded_path: Cow<str> =
         utf8_percent_encode(&path, COMPONENT_IGNORING_SLASH).into();
@@ -63,23 +269,151 @@ pub(super) fn handle(request: Request) -> Response {
     let query = request.uri.query();
 
     if encoded_path != normalized_encoded_path {
-        // Redirect to the same URI with normalized path encoding. This reduces how many URLs must
-        // be purged from the CDN's cache when a file changes. It's impossible to purge every
-        // possible variation of encoding for a URL.
+        // Redirect to the same URI with normalized path encoding, Unicode, and segments. This
+        // reduces how many URLs must be purged from the CDN's cache when a file changes. It's
+        // impossible to purge every possible variation of encoding for a URL.
 
         let normalized_uri = concat_path_and_query(&normalized_encoded_path, query);
 
         return response.permanent_redirect(&normalized_uri);
     }
 
-    let Some((user_identifier, file_path)) = path
-        .strip_prefix('/')
-        .expect("path should start with `/`")
-        .split_once('/')
-    else {
-        return response.plain_error(StatusCode::BAD_REQUEST);
+    // Short links can't be mistaken for files, since user identifiers are never only one character.
+    if custom_domain.is_none() {
+        if let Some(code) = path.strip_prefix(SHORT_LINK_PATH_PREFIX) {
+            return follow_short_link(state, response, code).await;
+        }
+    }
+
+    let (origin, owner_id, file_path, in_organization_route) =
+        if let Some(custom_domain) = &custom_domain {
+            let Some(file_path) = path.strip_prefix('/') else {
+                return response.plain_error(StatusCode::BAD_REQUEST);
+            };
+
+            (
+                Cow::Owned(format!("{}://{}", *CONTENT_SCHEME, custom_domain.name)),
+                custom_domain.user_id.clone(),
+                file_path,
+                false,
+            )
+        } else {
+            let Some((identifier, file_path)) = parse_file_route_path(&path) else {
+                return response.plain_error(StatusCode::BAD_REQUEST);
+            };
+
+            let route_owner = db::transaction!(state.db_replica_pool, async |tx| -> TxResult<
+                _,
+                sqlx::Error,
+            > {
+                Ok(find_route_owner(tx, identifier, file_path).await?)
+            })
+            .await;
+
+            match route_owner {
+                Ok(Some(RouteOwner::User(user))) => {
+                    // Redirect to the same file under the user's current handle, if they have one and
+                    // it wasn't already requested. This covers requests by ID, by a previous handle, or
+                    // by a handle in a different case.
+                    if let Some(handle) = &user.handle {
+                        if handle != identifier {
+                            let handle_path = format!(
+                                "/{}",
+                                utf8_percent_encode(
+                                    &format!("{handle}/{file_path}"),
+                                    COMPONENT_IGNORING_SLASH,
+                                ),
+                            );
+
+                            return response
+                                .permanent_redirect(&concat_path_and_query(&handle_path, query));
+                        }
+                    }
+
+                    (
+                        Cow::Borrowed(CONTENT_ORIGIN.as_str()),
+                        user.id,
+                        file_path,
+                        false,
+                    )
+                }
+                Ok(Some(RouteOwner::OrganizationFolder { owner_id })) => (
+                    Cow::Borrowed(CONTENT_ORIGIN.as_str()),
+                    owner_id,
+                    file_path,
+                    true,
+                ),
+                Ok(None) => return response.plain_error(StatusCode::NOT_FOUND),
+                Err(_) => return response.plain_error(StatusCode::INTERNAL_SERVER_ERROR),
+            }
+        };
+
+    // Files in an organization's storage are only served under the organization's ID, so requests
+    // for them under their owner's identifier or custom domain are redirected there.
+    if !in_organization_route {
+        if let Some((folder_name, _)) = file_path.split_once('/') {
+            let organization_id = db::transaction!(state.db_replica_pool, async |tx| -> TxResult<
+                _,
+                sqlx::Error,
+            > {
+                Ok(find_storage_organization(tx, &owner_id, folder_name).await?)
+            })
+            .await;
+
+            match organization_id {
+                Ok(Some(organization_id)) => {
+                    let organization_url = format!(
+                        "{}/{}",
+                        *CONTENT_ORIGIN,
+                        utf8_percent_encode(
+                            &format!("{organization_id}/{file_path}"),
+                            COMPONENT_IGNORING_SLASH,
+                        ),
+                    );
+
+                    return response
+                        .permanent_redirect(&concat_path_and_query(&organization_url, query));
+                }
+                Ok(None) => {}
+                Err(_) => return response.plain_error(StatusCode::INTERNAL_SERVER_ERROR),
+            }
+        }
+    }
+
+    // Files in an organization's storage aren't on their owner's canonical domain.
+    let canonical_domain = if in_organization_route {
+        Ok(None)
+    } else {
+        db::transaction!(state.db_replica_pool, async |tx| -> TxResult<
+            _,
+            sqlx::Error,
+        > {
+            Ok(find_canonical_domain(tx, &owner_id).await?)
+        })
+        .await
     };
 
+    let Ok(canonical_domain) = canonical_domain else {
+        return response.plain_error(StatusCode::INTERNAL_SERVER_ERROR);
+    };
+
+    // Redirect to the same file on the user's canonical domain, if they have one and it wasn't
+    // already requested. Like the above path normalization, this keeps each file at only one URL.
+    if let Some(canonical_domain) = canonical_domain {
+        if custom_domain
+            .as_ref()
+            .is_none_or(|custom_domain| custom_domain.name != canonical_domain)
+        {
+            let canonical_url = format!(
+                "{}://{canonical_domain}/{}",
+                *CONTENT_SCHEME,
+                utf8_percent_encode(file_path, COMPONENT_IGNORING_SLASH),
+            );
+
+            return response.permanent_redirect(&concat_path_and_query(&canonical_url, query));
+        }
+    }
+
     let file_id = match query {
         Some(query) => query
             .split('&')
@@ -87,6 +421,82 @@ pub(super) fn handle(request: Request) -> Response {
         None => None,
     };
 
+    // Session cookies are never sent to the content origin, so signed-in users are identified by a
+    // viewer parameter instead. An invalid or expired one is ignored.
+    let viewer_id = match query {
+        Some(query) => query
+            .split('&')
+            .find_map(|param| param.strip_prefix(VIEWER_QUERY_PREFIX))
+            .and_then(|param| verify_viewer_param(param, Utc::now().timestamp())),
+        None => None,
+    };
+
+    let file = db::transaction!(state.db_replica_pool, async |tx| -> TxResult<
+        _,
+        sqlx::Error,
+    > {
+        Ok(find_shared_file(tx, &owner_id, file_path, viewer_id.as_ref()).await?)
+    })
+    .await;
+
+    let file = match file {
+        Ok(Some(file)) => file,
+        Ok(None) => return response.plain_error(StatusCode::NOT_FOUND),
+        Err(_) => return response.plain_error(StatusCode::INTERNAL_SERVER_ERROR),
+    };
+
+    // Unavailable files get an error saying why instead of their content or a preview page.
+    if let Some(status) = unavailable_status_code(file.status, file.takedown_reason) {
+        if file.status == FileStatus::PendingScan {
+            response.header_valid(RETRY_AFTER, PENDING_SCAN_RETRY_AFTER_SECONDS);
+        }
+
+        return response.plain_error(status);
+    }
+
+    if !file.indexable {
+        response.header_valid(X_ROBOTS_TAG.clone(), "noindex");
+    }
+
+    // Sensitive files are labeled so search engines can hide them from safe search. Requests
+    // specifying a file ID skip any warning page, so it can link to the file's raw content.
+    if file.sensitive {
+        response.header_valid(RATING.clone(), "adult");
+
+        if file_id.is_none() && *SENSITIVE_FILE_POLICY.get() == SensitiveFilePolicy::Interstitial {
+            return sensitive_page(response, &file);
+        }
+    }
+
+    // Requests specifying a file ID always get the file's raw content, so preview pages can
+    // link to the raw content without crawlers being served another preview page.
+    if file_id.is_none() {
+        // Crawlers get a different response than other clients. Only these responses vary, so
+        // the CDN's cache of raw content isn't split by every client's `User-Agent`.
+        response.header_valid(VARY, "User-Agent");
+
+        if is_crawler(&request.headers) {
+            let url = format!("{origin}{normalized_encoded_path}");
+            return preview_page(response, &url, &file);
+        }
+    }
+
+    let is_current_id = file_id
+        .and_then(|file_id| file_id.parse::<Id>().ok())
+        .is_some_and(|file_id| file_id.as_slice() == file.id.as_slice());
+
+    response.header_valid(
+        CACHE_CONTROL,
+        // A response for a viewer depends on their access, so no one else may be served it.
+        if viewer_id.is_some() {
+            "private, no-store"
+        } else if is_current_id {
+            ID_CACHE_CONTROL
+        } else {
+            PATH_CACHE_CONTROL
+        },
+    );
+
     // response
     //     .header_valid(CONTENT_LENGTH, 0)
     //     .header_valid(CONTENT_TYPE, "")
@@ -96,12 +506,605 @@ pub(super) fn handle(request: Request) -> Response {
         return response;
     }
 
+    // Only responses actually sending a file's content count as downloads. Previews and `HEAD`
+    // requests barely use bandwidth, so they're still allowed.
+    if file.bandwidth_exceeded {
+        return response.plain_error(StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    state
+        .download_counter
+        .record(file.id, file.size, client_ip.map(|ClientIp(ip)| ip))
+        .await;
+
     response.body(format!(
-        "{user_identifier} - {file_path} - {}",
+        "{owner_id} - {file_path} - {}",
         file_id.unwrap_or("None")
     ))
 }
 
+/// Redirects to the file a short link is for, counting the click. Only files accessible to anyone
+/// with their link can be reached through short links.
+async fn follow_short_link(state: &AppState, mut response: Response, code: &str) -> Response {
+    let Ok(code) = code.parse::<ShortLinkCode>() else {
+        return response.plain_error(StatusCode::NOT_FOUND);
+    };
+
+    let url = db::transaction!(state.db_pool, async |tx| -> TxResult<_, sqlx::Error> {
+        let Some(file) = sqlx::query!(
+            "UPDATE short_links
+                SET clicks = clicks + 1
+                FROM files JOIN users ON users.id = files.owner_id
+                WHERE short_links.code = $1 AND files.id = short_links.file_id AND files.shared
+                    AND users.deleted_at IS NULL
+                RETURNING users.id as owner_id, users.handle::text, files.name,
+                    files.parent_name_path",
+            code.as_slice(),
+        )
+        .fetch_optional(tx.as_mut())
+        .await?
+        else {
+            return Ok(None);
+        };
+
+        // Redirect straight to the file's canonical URL rather than through more redirects.
+        let url = canonical_file_url(
+            tx,
+            &file.owner_id.into(),
+            file.handle.as_deref(),
+            &file.parent_name_path,
+            &file.name,
+        )
+        .await?;
+
+        Ok(Some(url))
+    })
+    .await;
+
+    match url {
+        Ok(Some(url)) => {
+            // Each click must reach the server to be counted.
+            response.header_valid(CACHE_CONTROL, "no-store");
+
+            response.found_redirect(&url)
+        }
+        Ok(None) => response.plain_error(StatusCode::NOT_FOUND),
+        Err(_) => response.plain_error(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+/// Gets the URL a file is served at without redirecting, from its owner's ID and handle (if any)
+/// and its path. This is under the organization's ID if the file is in an organization's storage,
+/// or else on its owner's canonical domain if they have one.
+///
+/// # Errors
+///
+/// Returns an error if a database query fails.
+pub(crate) async fn canonical_file_url(
+    conn: &mut PgConnection,
+    owner_id: &Id,
+    owner_handle: Option<&str>,
+    parent_path: &[String],
+    name: &str,
+) -> sqlx::Result<String> {
+    if let Some(folder_name) = parent_path.first() {
+        if let Some(organization_id) =
+            find_storage_organization(conn, owner_id, folder_name).await?
+        {
+            return Ok(file_url(
+                &CONTENT_ORIGIN,
+                Some(&organization_id.to_string()),
+                parent_path,
+                name,
+            ));
+        }
+    }
+
+    Ok(match find_canonical_domain(conn, owner_id).await? {
+        Some(canonical_domain) => file_url(
+            &format!("{}://{canonical_domain}", *CONTENT_SCHEME),
+            None,
+            parent_path,
+            name,
+        ),
+        None => file_url(
+            &CONTENT_ORIGIN,
+            Some(&owner_handle.map_or_else(|| owner_id.to_string(), str::to_owned)),
+            parent_path,
+            name,
+        ),
+    })
+}
+
+/// Gets the URL of a file from the origin serving it, the identifier of its owner or organization in
+/// its route (or `None` if the origin is its owner's custom domain), and its path.
+pub(crate) fn file_url(
+    origin: &str,
+    identifier: Option<&str>,
+    parent_path: &[String],
+    name: &str,
+) -> String {
+    let path = identifier
+        .into_iter()
+        .chain(parent_path.iter().map(String::as_str))
+        .chain([name])
+        .collect::<Vec<_>>()
+        .join("/");
+
+    format!(
+        "{origin}/{}",
+        utf8_percent_encode(&path, COMPONENT_IGNORING_SLASH),
+    )
+}
+
+/// Percent-decodes a URI path and normalizes it to NFC.
+///
+/// Returns `None` if the path isn't valid UTF-8 or contains a null byte.
+fn decode_path(encoded_path: &str) -> Option<Cow<'_, str>> {
+    let path = percent_decode_str(encoded_path).decode_utf8().ok()?;
+
+    // Decoding can turn `%00` into a null byte, so disallow null bytes as a defensive measure.
+    if path.contains('\x00') {
+        return None;
+    }
+
+    // Visually identical paths can have different Unicode representations, so they're normalized
+    // to NFC like file names are. Otherwise, lookups and the CDN's cache would be split across
+    // equivalent URLs.
+    Some(if is_nfc(&path) {
+        path
+    } else {
+        path.nfc().collect::<String>().into()
+    })
+}
+
+/// Resolves `.` and `..` segments in a percent-decoded URI path, and removes empty segments (from
+/// duplicate or trailing slashes). None of these can be in a file's path, since file names can't be
+/// empty, `.`, or `..`.
+///
+/// Returns `None` if a `..` segment would go above the root.
+fn canonicalize_path(path: &str) -> Option<Cow<'_, str>> {
+    let mut segments = Vec::new();
+
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop()?;
+            }
+            segment => segments.push(segment),
+        }
+    }
+
+    let canonical_path = format!("/{}", segments.join("/"));
+
+    Some(if canonical_path == path {
+        Cow::Borrowed(path)
+    } else {
+        Cow::Owned(canonical_path)
+    })
+}
+
+/// Splits a percent-decoded URI path on the content origin into the identifier of the user or
+/// organization whose files it's in, and the path of the file within those files.
+///
+/// Returns `None` if the path doesn't start with an identifier between `/`s.
+pub(crate) fn parse_file_route_path(path: &str) -> Option<(&str, &str)> {
+    path.strip_prefix('/')?.split_once('/')
+}
+
+/// Gets the status code to respond with for a file with the specified status and takedown reason,
+/// or `None` if it's available.
+const fn unavailable_status_code(
+    status: FileStatus,
+    takedown_reason: Option<TakedownReason>,
+) -> Option<StatusCode> {
+    match status {
+        FileStatus::Active => None,
+        FileStatus::PendingScan => Some(StatusCode::SERVICE_UNAVAILABLE),
+        FileStatus::Quarantined => Some(StatusCode::FORBIDDEN),
+
+        // `451` is reserved for legal demands, so other takedowns are served as permanently gone.
+        FileStatus::TakenDown => Some(match takedown_reason {
+            Some(TakedownReason::Legal) => StatusCode::UNAVAILABLE_FOR_LEGAL_REASONS,
+            Some(TakedownReason::TermsViolation) | None => StatusCode::GONE,
+        }),
+    }
+}
+
+/// Gets the value of a viewer query parameter identifying the specified user until the specified
+/// Unix timestamp. It's signed, so only the API can issue one.
+pub(crate) fn viewer_param(user_id: &Id, expires_at: i64) -> String {
+    let payload = viewer_payload(user_id, expires_at);
+
+    format!(
+        "{user_id}.{expires_at}.{}",
+        Id::from(sign(&payload).as_ref()),
+    )
+}
+
+/// Gets the ID of the user a viewer query parameter identifies.
+///
+/// Returns `None` if the parameter is malformed, its signature is invalid, or it expired before the
+/// specified Unix timestamp.
+fn verify_viewer_param(param: &str, now: i64) -> Option<Id> {
+    let mut parts = param.split('.');
+    let user_id = parts.next()?.parse::<Id>().ok()?;
+    let expires_at = parts.next()?.parse::<i64>().ok()?;
+    let signature = parts.next()?.parse::<Id>().ok()?;
+
+    if parts.next().is_some() || expires_at < now {
+        return None;
+    }
+
+    verify_signature(&viewer_payload(&user_id, expires_at), &signature).then_some(user_id)
+}
+
+/// Gets the signed payload of a viewer query parameter. It's prefixed so it can't be mistaken for
+/// another signed payload.
+fn viewer_payload(user_id: &Id, expires_at: i64) -> Vec<u8> {
+    let mut payload = b"viewer:".to_vec();
+    payload.extend_from_slice(user_id);
+    payload.push(b':');
+    payload.extend_from_slice(expires_at.to_string().as_bytes());
+    payload
+}
+
+/// Metadata of a file accessible to anyone with its link, or to the viewer it was found for.
+#[derive(Debug)]
+pub(crate) struct SharedFile {
+    /// The file's ID.
+    pub(crate) id: Vec<u8>,
+
+    /// The file's name.
+    pub(crate) name: String,
+
+    /// The file's media type.
+    pub(crate) r#type: String,
+
+    /// The file's size in bytes.
+    pub(crate) size: i64,
+
+    /// Whether the file is available, or why it isn't.
+    pub(crate) status: FileStatus,
+
+    /// Why the file was taken down, if it was.
+    pub(crate) takedown_reason: Option<TakedownReason>,
+
+    /// Whether the file is marked as sensitive, by a moderator or otherwise.
+    pub(crate) sensitive: bool,
+
+    /// Whether the file's owner allows search engines to index their files.
+    pub(crate) indexable: bool,
+
+    /// Whether the file's owner has used up their plan's bandwidth for the month.
+    pub(crate) bandwidth_exceeded: bool,
+}
+
+/// A user identified in a file's route on the content origin.
+#[derive(Debug)]
+pub(crate) struct RouteUser {
+    /// The user's ID.
+    pub(crate) id: Id,
+
+    /// The user's current handle, if any.
+    pub(crate) handle: Option<String>,
+}
+
+/// The owner of the files in a route on the content origin.
+#[derive(Debug)]
+pub(crate) enum RouteOwner {
+    /// A user, whose route has their files outside organizations' storage.
+    User(RouteUser),
+
+    /// An organization, whose route has the files in its storage.
+    OrganizationFolder {
+        /// The ID of the user who owns the folder in the organization's storage that the route's
+        /// file path is in.
+        owner_id: Id,
+    },
+}
+
+/// Finds the owner of the files in a route from its identifier and file path (as returned by
+/// [`parse_file_route_path`]). The identifier can be a user's ID, handle, or previous handle (see
+/// [`find_route_user`]), or an organization's ID, in which case the file path's first segment is
+/// the name of a folder in its storage.
+///
+/// # Errors
+///
+/// Returns an error if a database query fails.
+pub(crate) async fn find_route_owner(
+    conn: &mut PgConnection,
+    identifier: &str,
+    file_path: &str,
+) -> sqlx::Result<Option<RouteOwner>> {
+    if let Some(user) = find_route_user(conn, identifier).await? {
+        return Ok(Some(RouteOwner::User(user)));
+    }
+
+    let Ok(organization_id) = identifier.parse::<NewOrganizationId>() else {
+        return Ok(None);
+    };
+
+    // Organizations' storage only has folders, so there are no files directly in their routes.
+    let Some((folder_name, _)) = file_path.split_once('/') else {
+        return Ok(None);
+    };
+
+    Ok(sqlx::query!(
+        "SELECT owner_id FROM folders
+            WHERE organization_id = $1 AND name = $2",
+        organization_id.as_slice(),
+        folder_name,
+    )
+    .fetch_optional(conn)
+    .await?
+    .map(|folder| RouteOwner::OrganizationFolder {
+        owner_id: folder.owner_id.into(),
+    }))
+}
+
+/// Finds the ID of the organization whose storage a user's top-level folder with the specified name
+/// is in, if it's in one.
+///
+/// # Errors
+///
+/// Returns an error if the database query fails.
+pub(crate) async fn find_storage_organization(
+    conn: &mut PgConnection,
+    owner_id: &Id,
+    folder_name: &str,
+) -> sqlx::Result<Option<Id>> {
+    Ok(sqlx::query!(
+        r#"SELECT organization_id as "organization_id!" FROM folders
+            WHERE owner_id = $1 AND parent_id_path = '{}' AND name = $2
+                AND organization_id IS NOT NULL"#,
+        owner_id.as_slice(),
+        folder_name,
+    )
+    .fetch_optional(conn)
+    .await?
+    .map(|folder| folder.organization_id.into()))
+}
+
+/// Finds the user referred to by the user identifier in a file's route (as returned by
+/// [`parse_file_route_path`]). The identifier can be the user's ID, their handle, or one of their
+/// previous handles within its grace period.
+///
+/// # Errors
+///
+/// Returns an error if the database query fails.
+async fn find_route_user(
+    conn: &mut PgConnection,
+    user_identifier: &str,
+) -> sqlx::Result<Option<RouteUser>> {
+    // Handles are validated to never look like user IDs, so this is unambiguous.
+    if let Ok(user_id) = user_identifier.parse::<NewUserId>() {
+        return Ok(sqlx::query!(
+            "SELECT id, handle::text FROM users
+                WHERE id = $1",
+            user_id.as_slice(),
+        )
+        .fetch_optional(conn)
+        .await?
+        .map(|user| RouteUser {
+            id: user.id.into(),
+            handle: user.handle,
+        }));
+    }
+
+    Ok(sqlx::query!(
+        r#"SELECT id as "id!", handle::text FROM users
+                WHERE handle = $1::citext
+            UNION ALL
+            SELECT users.id, users.handle::text
+                FROM previous_handles JOIN users ON users.id = previous_handles.user_id
+                WHERE previous_handles.handle = $1::citext
+                    AND previous_handles.released_at > now() - make_interval(secs => $2)
+            LIMIT 1"#,
+        user_identifier,
+        PREVIOUS_HANDLE_GRACE_PERIOD.as_secs_f64(),
+    )
+    .fetch_optional(conn)
+    .await?
+    .map(|user| RouteUser {
+        id: user.id.into(),
+        handle: user.handle,
+    }))
+}
+
+/// Finds a file by its owner's ID and the file path in its route (as returned by
+/// [`parse_file_route_path`]). The file must be accessible to anyone with its link, unless a viewer
+/// is specified who owns it or was granted access to a folder it's in.
+///
+/// # Errors
+///
+/// Returns an error if the database query fails.
+pub(crate) async fn find_shared_file(
+    conn: &mut PgConnection,
+    owner_id: &Id,
+    file_path: &str,
+    viewer_id: Option<&Id>,
+) -> sqlx::Result<Option<SharedFile>> {
+    let mut parent_names: Vec<&str> = file_path.split('/').collect();
+    let name = parent_names
+        .pop()
+        .expect("split should return at least one item");
+
+    sqlx::query_as!(
+        SharedFile,
+        r#"SELECT files.id, files.name, files.type, files.size,
+            files.status as "status: FileStatus",
+            files.takedown_reason as "takedown_reason: TakedownReason",
+            files.sensitive OR files.sensitive_by_moderator as "sensitive!", users.indexable,
+            coalesce(monthly_bandwidth.bytes >= plan_limits.monthly_bandwidth, FALSE)
+                as "bandwidth_exceeded!"
+            FROM files
+            JOIN users ON users.id = files.owner_id
+            JOIN plan_limits ON plan_limits.plan = users.plan
+            LEFT JOIN monthly_bandwidth ON monthly_bandwidth.user_id = files.owner_id
+                AND monthly_bandwidth.month = date_trunc('month', now())::date
+            WHERE files.owner_id = $1 AND files.parent_name_path = $2 AND files.name = $3
+                AND users.deleted_at IS NULL
+                AND (files.shared OR files.owner_id = $4 OR EXISTS (
+                    SELECT FROM effective_folder_access_grants
+                        WHERE user_id = $4 AND folder_id = ANY(files.parent_id_path)
+                ))"#,
+        owner_id.as_slice(),
+        &parent_names as &[&str],
+        name,
+        viewer_id.map(|viewer_id| viewer_id.as_slice()),
+    )
+    .fetch_optional(conn)
+    .await
+}
+
+/// Finds the ID of the user whose files a verified custom domain serves. Custom domains only serve
+/// files while their owners' plans include custom domains and their owners aren't deleted.
+///
+/// # Errors
+///
+/// Returns an error if the database query fails.
+pub(crate) async fn find_custom_domain_owner(
+    conn: &mut PgConnection,
+    name: &str,
+) -> sqlx::Result<Option<Id>> {
+    Ok(sqlx::query!(
+        "SELECT custom_domains.user_id
+            FROM custom_domains
+            JOIN users ON users.id = custom_domains.user_id
+            JOIN plan_limits ON plan_limits.plan = users.plan
+            WHERE custom_domains.name = $1 AND custom_domains.verified_at IS NOT NULL
+                AND plan_limits.custom_domains AND users.deleted_at IS NULL",
+        name,
+    )
+    .fetch_optional(conn)
+    .await?
+    .map(|domain| domain.user_id.into()))
+}
+
+/// Finds the verified custom domain a user set as canonical, if their plan includes custom domains.
+///
+/// # Errors
+///
+/// Returns an error if the database query fails.
+async fn find_canonical_domain(
+    conn: &mut PgConnection,
+    user_id: &Id,
+) -> sqlx::Result<Option<String>> {
+    Ok(sqlx::query!(
+        "SELECT custom_domains.name
+            FROM custom_domains
+            JOIN users ON users.id = custom_domains.user_id
+            JOIN plan_limits ON plan_limits.plan = users.plan
+            WHERE custom_domains.user_id = $1 AND custom_domains.canonical
+                AND custom_domains.verified_at IS NOT NULL AND plan_limits.custom_domains",
+        user_id.as_slice(),
+    )
+    .fetch_optional(conn)
+    .await?
+    .map(|domain| domain.name))
+}
+
+/// Returns whether a request's headers indicate it's from a crawler generating a link preview.
+fn is_crawler(headers: &HeaderMap) -> bool {
+    headers
+        .get(USER_AGENT)
+        .and_then(|user_agent| user_agent.to_str().ok())
+        .is_some_and(|user_agent| {
+            CRAWLER_USER_AGENTS
+                .iter()
+                .any(|crawler| user_agent.contains(crawler))
+        })
+}
+
+/// Sets an HTML [`PreviewPage`] as the response for a file at the specified URL.
+fn preview_page(mut response: Response, url: &str, file: &SharedFile) -> Response {
+    let raw_url = format!(
+        "{url}?{FILE_ID_QUERY_PREFIX}{}",
+        Id::from(file.id.as_slice())
+    );
+    let oembed_url = format!(
+        "{}/api/v1/oembed?url={}",
+        *WEBSITE_ORIGIN,
+        utf8_percent_encode(url, COMPONENT),
+    );
+
+    let page = PreviewPage {
+        name: &file.name,
+        media_type: &file.r#type,
+        url,
+        raw_url: &raw_url,
+        oembed_url: &oembed_url,
+    };
+
+    response.header_valid(CONTENT_TYPE, "text/html; charset=utf-8");
+
+    response.body(page.to_string())
+}
+
+/// Responds with a `robots.txt`. On a custom domain, this disallows crawling the whole domain if its
+/// owner doesn't allow search engines to index their files.
+///
+/// The content origin's allows crawling everything, since listing the users who don't would publish
+/// who they are. Their files are kept out of search results by `X-Robots-Tag: noindex` instead,
+/// which crawlers can only see on files they're allowed to crawl.
+async fn robots_txt(
+    state: &AppState,
+    mut response: Response,
+    custom_domain: Option<&CustomDomain>,
+) -> Response {
+    let is_indexable = match custom_domain {
+        Some(custom_domain) => {
+            db::transaction!(state.db_replica_pool, async |tx| -> TxResult<
+                _,
+                sqlx::Error,
+            > {
+                Ok(sqlx::query!(
+                    "SELECT indexable FROM users
+                        WHERE id = $1",
+                    custom_domain.user_id.as_slice(),
+                )
+                .fetch_one(tx.as_mut())
+                .await?
+                .indexable)
+            })
+            .await
+        }
+        None => Ok(true),
+    };
+
+    let Ok(is_indexable) = is_indexable else {
+        return response.plain_error(StatusCode::INTERNAL_SERVER_ERROR);
+    };
+
+    response
+        .header_valid(CONTENT_TYPE, "text/plain; charset=utf-8")
+        .header_valid(CACHE_CONTROL, PATH_CACHE_CONTROL);
+
+    response.body(if is_indexable {
+        "User-agent: *\nDisallow:\n"
+    } else {
+        "User-agent: *\nDisallow: /\n"
+    })
+}
+
+/// Sets an HTML [`SensitivePage`] as the response for a sensitive file.
+fn sensitive_page(mut response: Response, file: &SharedFile) -> Response {
+    let raw_url = format!("?{FILE_ID_QUERY_PREFIX}{}", Id::from(file.id.as_slice()));
+
+    let page = SensitivePage {
+        name: &file.name,
+        raw_url: &raw_url,
+    };
+
+    response
+        .header_valid(CONTENT_TYPE, "text/html; charset=utf-8")
+        .header_valid(CACHE_CONTROL, PATH_CACHE_CONTROL);
+
+    response.body(page.to_string())
+}
+
 /// Joins a path and a query into one string, separated by a `?` if there exists a query.
 fn concat_path_and_query<'a>(path: &'a str, query: Option<&'a str>) -> Cow<'a, str> {
     let mut path_and_query = Cow::from(path);
@@ -113,3 +1116,84 @@ fn concat_path_and_query<'a>(path: &'a str, query: Option<&'a str>) -> Cow<'a, s
 
     path_and_query
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn path_canonicalization() {
+        let cases = [
+            ("/", "/"),
+            ("//", "/"),
+            ("/user/file.txt", "/user/file.txt"),
+            ("/user//file.txt", "/user/file.txt"),
+            ("/user/folder/", "/user/folder"),
+            ("/.", "/"),
+            ("/user/./file.txt", "/user/file.txt"),
+            ("/a/../b", "/b"),
+            ("/user/folder/../file.txt", "/user/file.txt"),
+        ];
+
+        for (path, canonical_path) in cases {
+            assert_eq!(
+                Some(canonical_path),
+                canonicalize_path(path).as_deref(),
+                "canonicalizing {path:?}",
+            );
+        }
+
+        assert!(
+            matches!(canonicalize_path("/user/file.txt"), Some(Cow::Borrowed(_))),
+            "canonical paths should be borrowed",
+        );
+    }
+
+    #[test]
+    fn path_unicode_normalization() {
+        let cases = [
+            // A decomposed "é" is composed, which changes the path's encoding and so redirects.
+            ("/user/e%CC%81t%C3%A9.txt", "/user/%C3%A9t%C3%A9.txt"),
+            // An unencoded decomposed "é" is normalized and encoded the same way.
+            ("/user/e\u{301}.txt", "/user/%C3%A9.txt"),
+            // A precomposed "é" is already normalized, so it doesn't redirect.
+            ("/user/%C3%A9.txt", "/user/%C3%A9.txt"),
+            // "Å" as an angstrom sign is normalized to the letter.
+            ("/user/%E2%84%AB", "/user/%C3%85"),
+        ];
+
+        for (encoded_path, normalized_encoded_path) in cases {
+            let path = decode_path(encoded_path).expect("path should be valid");
+            let path = canonicalize_path(&path).expect("path should be valid");
+
+            assert_eq!(
+                normalized_encoded_path,
+                utf8_percent_encode(&path, COMPONENT_IGNORING_SLASH).to_string(),
+                "normalizing {encoded_path:?}",
+            );
+        }
+
+        assert!(
+            matches!(decode_path("/user/file.txt"), Some(Cow::Borrowed(_))),
+            "normalized paths should be borrowed",
+        );
+    }
+
+    #[test]
+    fn invalid_path_encoding_rejected() {
+        let encoded_paths = ["/user/%00", "/user/file%00.txt", "/user/%FF", "/user/%C3"];
+
+        for encoded_path in encoded_paths {
+            assert_eq!(None, decode_path(encoded_path), "decoding {encoded_path:?}");
+        }
+    }
+
+    #[test]
+    fn path_traversal_rejected() {
+        let paths = ["/..", "/a/../..", "/../a", "/a/./../../b"];
+
+        for path in paths {
+            assert_eq!(None, canonicalize_path(path), "canonicalizing {path:?}");
+        }
+    }
+}
@@ -1,11 +1,18 @@
 //! Utilities for cryptographic operations.
 
+use std::sync::LazyLock;
+
 use argon2::{
     password_hash::{Salt, SaltString},
     Argon2, PasswordHash, PasswordHasher, PasswordVerifier,
 };
 use rand::{distributions::Uniform, prelude::Distribution, RngCore};
-use ring::digest::{digest, Digest, SHA256};
+use ring::{
+    digest::{digest, Digest, SHA256},
+    hmac,
+};
+
+use crate::config;
 
 /// Hashes the input using SHA-256.
 ///
@@ -15,6 +22,37 @@ pub(crate) fn hash_without_salt<T: AsRef<[u8]>>(bytes: &T) -> Digest {
     digest(&SHA256, bytes.as_ref())
 }
 
+/// The key for [`sign`] and [`verify_signature`].
+static SIGNING_KEY: LazyLock<hmac::Key> = LazyLock::new(|| {
+    let secret = config::var("SIGNING_SECRET")
+        .expect("environment variable `SIGNING_SECRET` should be a valid string");
+
+    hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes())
+});
+
+/// Signs the input using HMAC-SHA256, so anyone presenting the signature later must have gotten it
+/// from us.
+pub(crate) fn sign<T: AsRef<[u8]>>(bytes: &T) -> hmac::Tag {
+    hmac::sign(&SIGNING_KEY, bytes.as_ref())
+}
+
+/// Checks if the signature was outputted by [`sign`] for the input, in constant time.
+pub(crate) fn verify_signature<T: AsRef<[u8]>>(bytes: &T, signature: &[u8]) -> bool {
+    hmac::verify(&SIGNING_KEY, bytes.as_ref(), signature).is_ok()
+}
+
+/// Decodes a hexadecimal string, or returns `None` if it's malformed.
+pub(crate) fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(hex.get(index..index + 2)?, 16).ok())
+        .collect()
+}
+
 /// Salts and hashes the input using Argon2, returning a hash in PHC string format.
 ///
 /// Salt is necessary for secrets that may be short or guessable, but it has a drawback: a database
@@ -36,6 +74,12 @@ pub(crate) fn hash_with_salt<T: AsRef<[u8]>>(bytes: &T) -> Result<String, rand::
         .to_string())
 }
 
+/// An Argon2 hash of nothing in particular, in PHC string format. Verifying input against this when
+/// there's no real hash to check takes as long as verifying a real hash, so response times can't
+/// reveal which case happened.
+pub(crate) static DUMMY_HASH: LazyLock<String> =
+    LazyLock::new(|| hash_with_salt(&"").expect("CSPRNG should be able to generate salt"));
+
 /// Checks if the input bytes match the Argon2 hash specified in PHC string format (as outputted by
 /// [`hash_with_salt`]).
 ///
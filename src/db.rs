@@ -32,6 +32,32 @@ pub(super) async fn initialize(db_url: &str) -> sqlx::Result<PgPool, sqlx::Error
     Ok(pool)
 }
 
+/// Initializes a SQLx database pool for a read-only replica of the database.
+///
+/// Replicas can't run `SERIALIZABLE` transactions, so transactions on this pool use `REPEATABLE
+/// READ` instead. Since they're read-only, they still can't cause the races `SERIALIZABLE` prevents,
+/// but they may read data that's slightly out of date due to replication lag.
+///
+/// # Errors
+///
+/// Returns an error if the initial database connection fails.
+pub(super) async fn initialize_replica(db_url: &str) -> sqlx::Result<PgPool, sqlx::Error> {
+    PgPoolOptions::new()
+        .after_connect(|conn, _| {
+            Box::pin(async move {
+                conn.execute(
+                    "SET default_transaction_isolation TO 'repeatable read';
+                        SET default_transaction_read_only TO on;",
+                )
+                .await?;
+
+                Ok(())
+            })
+        })
+        .connect(db_url)
+        .await
+}
+
 /// The error result of a database transaction.
 ///
 /// Doesn't implement [`Error`] to prevent an impl conflict.
@@ -75,6 +101,8 @@ pub(crate) type TxResult<T, E> = Result<T, TxError<E>>;
 ///
 /// Maximum isolation is used to minimize the possibility of data races. This generally greatly
 /// simplifies database operations and reduces the mental overhead of working with them.
+///
+/// The transaction runs in a tracing span, so the queries it runs are grouped under it.
 macro_rules! transaction {
     ($db_pool:expr, $($ident:ident)* |$tx:ident| $(-> $Return:ty)? $block:block$(,)?) => {
         $crate::db::transaction!(
@@ -86,7 +114,7 @@ macro_rules! transaction {
     };
 
     ($db_pool:expr, $callback:expr$(,)?) => {
-        async {
+        ::tracing::Instrument::instrument(async {
             #[expect(clippy::allow_attributes, reason = "`unused_mut` isn't always expected")]
             #[allow(unused_mut, reason = "some callers need this to be `mut`")]
             let mut callback = $callback;
@@ -112,7 +140,7 @@ macro_rules! transaction {
                     Err($crate::db::TxError::Retry) => {}
                 }
             }
-        }
+        }, ::tracing::info_span!("transaction"))
     };
 }
 
@@ -0,0 +1,142 @@
+//! A blocklist of disposable email domains, which can't be used to sign up.
+
+use std::{collections::HashSet, env::VarError, sync::Arc, time::Duration};
+
+use tokio::sync::RwLock;
+
+use crate::{config, telemetry};
+
+/// How often the blocklist is downloaded again.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// A set of lowercase disposable email domains. Clones share the same set.
+#[derive(Clone, Default, Debug)]
+pub(crate) struct Blocklist(Arc<RwLock<HashSet<String>>>);
+
+impl Blocklist {
+    /// Checks whether an email address's domain, or any domain it's a subdomain of, is blocked.
+    pub(crate) async fn contains(&self, email: &str) -> bool {
+        let Some((_, domain)) = email.rsplit_once('@') else {
+            return false;
+        };
+
+        let domain = domain.to_ascii_lowercase();
+        let mut domain = domain.as_str();
+
+        let domains = self.0.read().await;
+
+        loop {
+            if domains.contains(domain) {
+                return true;
+            }
+
+            let Some((_, parent_domain)) = domain.split_once('.') else {
+                return false;
+            };
+
+            domain = parent_domain;
+        }
+    }
+
+    /// Downloads the blocklist again from the URL in the `DISPOSABLE_EMAIL_DOMAINS_URL` environment
+    /// variable. The list must have one domain per line, ignoring blank lines and lines starting
+    /// with `#`.
+    ///
+    /// If the environment variable is unset, the blocklist is emptied. If downloading fails, the
+    /// error is reported and the previous blocklist is kept.
+    #[tracing::instrument(name = "refresh_disposable_email_domains", skip_all)]
+    pub(crate) async fn refresh(&self) {
+        let domains = match config::var("DISPOSABLE_EMAIL_DOMAINS_URL") {
+            // If the environment variable is unset, self-hosters don't need to maintain a
+            // blocklist.
+            Err(dotenvy::Error::EnvVar(VarError::NotPresent)) => HashSet::new(),
+
+            url => {
+                let url = url.expect(
+                    "environment variable `DISPOSABLE_EMAIL_DOMAINS_URL` should be a valid string \
+                        if set",
+                );
+
+                match download(&url).await {
+                    Ok(domains) => domains,
+                    Err(error) => {
+                        telemetry::report_error(&error);
+                        return;
+                    }
+                }
+            }
+        };
+
+        *self.0.write().await = domains;
+    }
+}
+
+/// Starts downloading the blocklist periodically in the background, returning the [`Blocklist`]
+/// it's downloaded into.
+pub(crate) fn start() -> Blocklist {
+    let blocklist = Blocklist::default();
+    let task_blocklist = blocklist.clone();
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(REFRESH_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            task_blocklist.refresh().await;
+        }
+    });
+
+    blocklist
+}
+
+/// Downloads and parses the blocklist.
+///
+/// # Errors
+///
+/// Returns an error if the request fails.
+async fn download(url: &str) -> reqwest::Result<HashSet<String>> {
+    let list = reqwest::get(url).await?.error_for_status()?.text().await?;
+
+    Ok(list
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_ascii_lowercase)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn subdomains_blocked() {
+        let blocklist = Blocklist::default();
+        *blocklist.0.write().await = HashSet::from(["mailinator.com".to_owned()]);
+
+        let blocked_emails = [
+            "user@mailinator.com",
+            "user@MailInator.COM",
+            "user@inbox.mailinator.com",
+            "user@a.b.mailinator.com",
+            "\"user@example.com\"@mailinator.com",
+        ];
+
+        for email in blocked_emails {
+            assert!(blocklist.contains(email).await, "checking {email:?}");
+        }
+
+        let allowed_emails = [
+            "user@example.com",
+            "user@notmailinator.com",
+            "user@mailinator.com.example",
+            "user@com",
+            "mailinator.com",
+        ];
+
+        for email in allowed_emails {
+            assert!(!blocklist.contains(email).await, "checking {email:?}");
+        }
+    }
+}
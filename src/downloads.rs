@@ -0,0 +1,186 @@
+//! Counting of files' downloads and traffic, buffered in memory and written to the database in
+//! batches so serving files doesn't wait on a write for every download.
+
+use std::{
+    collections::{HashMap, HashSet},
+    mem,
+    net::IpAddr,
+    sync::Arc,
+    time::Duration,
+};
+
+use sqlx::PgPool;
+use tokio::sync::Mutex;
+
+use crate::{
+    crypto::sign,
+    db::{self, TxResult},
+    telemetry,
+};
+
+/// How often buffered download counts are written to the database. Counts buffered since the last
+/// write are lost if the server stops.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// A file's downloads not yet written to the database.
+#[derive(Default, Debug)]
+struct BufferedDownloads {
+    /// The number of downloads.
+    count: i64,
+
+    /// The total number of bytes sent.
+    bytes: i64,
+
+    /// Keyed hashes of the IP addresses the file was sent to.
+    visitor_hashes: HashSet<Vec<u8>>,
+}
+
+impl BufferedDownloads {
+    /// Adds other buffered downloads of the same file into these.
+    fn merge(&mut self, other: Self) {
+        self.count += other.count;
+        self.bytes += other.bytes;
+        self.visitor_hashes.extend(other.visitor_hashes);
+    }
+}
+
+/// A buffer of downloads not yet written to the database, by file ID. Clones share the same buffer.
+#[derive(Clone, Default, Debug)]
+pub(crate) struct Counter(Arc<Mutex<HashMap<Vec<u8>, BufferedDownloads>>>);
+
+impl Counter {
+    /// Counts a download of the specified file, sending the specified number of bytes to the
+    /// specified IP address (if known).
+    pub(crate) async fn record(&self, file_id: Vec<u8>, bytes: i64, ip: Option<IpAddr>) {
+        let mut buffer = self.0.lock().await;
+        let downloads = buffer.entry(file_id).or_default();
+
+        downloads.count += 1;
+        downloads.bytes += bytes;
+
+        // The hash is keyed so IP addresses can't be recovered by hashing every possible one.
+        if let Some(ip) = ip {
+            downloads
+                .visitor_hashes
+                .insert(sign(&ip.to_string()).as_ref().to_vec());
+        }
+    }
+}
+
+/// Starts writing buffered downloads to the database periodically in the background, returning a
+/// [`Counter`] to buffer them with.
+pub(crate) fn start(db_pool: PgPool) -> Counter {
+    let counter = Counter::default();
+    let task_counter = counter.clone();
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(FLUSH_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            let buffer = mem::take(&mut *task_counter.0.lock().await);
+
+            if buffer.is_empty() {
+                continue;
+            }
+
+            // If this fails, the downloads are put back to be retried next time.
+            if let Err(error) = flush(&db_pool, &buffer).await {
+                telemetry::report_error(&error);
+
+                let mut current_buffer = task_counter.0.lock().await;
+
+                for (file_id, downloads) in buffer {
+                    current_buffer.entry(file_id).or_default().merge(downloads);
+                }
+            }
+        }
+    });
+
+    counter
+}
+
+/// Writes buffered downloads to the files' download counts, traffic, and owners' bandwidth usage.
+///
+/// # Errors
+///
+/// Returns an error if a database query fails.
+#[tracing::instrument(name = "flush_downloads", skip_all)]
+async fn flush(db_pool: &PgPool, buffer: &HashMap<Vec<u8>, BufferedDownloads>) -> sqlx::Result<()> {
+    let mut file_ids = Vec::with_capacity(buffer.len());
+    let mut counts = Vec::with_capacity(buffer.len());
+    let mut bytes = Vec::with_capacity(buffer.len());
+    let mut visitor_file_ids = Vec::new();
+    let mut visitor_hashes = Vec::new();
+
+    for (file_id, downloads) in buffer {
+        file_ids.push(file_id.as_slice());
+        counts.push(downloads.count);
+        bytes.push(downloads.bytes);
+
+        for visitor_hash in &downloads.visitor_hashes {
+            visitor_file_ids.push(file_id.as_slice());
+            visitor_hashes.push(visitor_hash.as_slice());
+        }
+    }
+
+    db::transaction!(db_pool, async |tx| -> TxResult<_, sqlx::Error> {
+        sqlx::query!(
+            "UPDATE files
+                SET download_count = download_count + counts.count
+                FROM unnest($1::bytea[], $2::bigint[]) as counts (file_id, count)
+                WHERE files.id = counts.file_id",
+            &file_ids as &[&[u8]],
+            &counts,
+        )
+        .execute(tx.as_mut())
+        .await?;
+
+        // Files deleted since they were downloaded are skipped by joining on them.
+        sqlx::query!(
+            "INSERT INTO file_traffic (file_id, hour, requests, bytes)
+                SELECT files.id, date_trunc('hour', now(), 'UTC'), counts.count, counts.bytes
+                    FROM unnest($1::bytea[], $2::bigint[], $3::bigint[])
+                        as counts (file_id, count, bytes)
+                    JOIN files ON files.id = counts.file_id
+                ON CONFLICT (file_id, hour) DO UPDATE
+                    SET requests = file_traffic.requests + excluded.requests,
+                        bytes = file_traffic.bytes + excluded.bytes",
+            &file_ids as &[&[u8]],
+            &counts,
+            &bytes,
+        )
+        .execute(tx.as_mut())
+        .await?;
+
+        sqlx::query!(
+            "INSERT INTO file_visitors (file_id, hour, visitor_hash)
+                SELECT files.id, date_trunc('hour', now(), 'UTC'), visitors.visitor_hash
+                    FROM unnest($1::bytea[], $2::bytea[]) as visitors (file_id, visitor_hash)
+                    JOIN files ON files.id = visitors.file_id
+                ON CONFLICT DO NOTHING",
+            &visitor_file_ids as &[&[u8]],
+            &visitor_hashes as &[&[u8]],
+        )
+        .execute(tx.as_mut())
+        .await?;
+
+        sqlx::query!(
+            "INSERT INTO monthly_bandwidth (user_id, month, bytes)
+                SELECT files.owner_id, date_trunc('month', now())::date, sum(counts.bytes)
+                    FROM unnest($1::bytea[], $2::bigint[]) as counts (file_id, bytes)
+                    JOIN files ON files.id = counts.file_id
+                    GROUP BY files.owner_id
+                ON CONFLICT (user_id, month) DO UPDATE
+                    SET bytes = monthly_bandwidth.bytes + excluded.bytes",
+            &file_ids as &[&[u8]],
+            &bytes,
+        )
+        .execute(tx.as_mut())
+        .await?;
+
+        Ok(())
+    })
+    .await
+}
@@ -5,12 +5,19 @@ use std::{env::VarError, sync::LazyLock};
 use askama::Template;
 use html2text::render::text_renderer::TrivialDecorator;
 use lettre::{
-    message::{Mailbox, MultiPart},
+    message::{
+        header::{HeaderName, HeaderValue},
+        Mailbox, MultiPart,
+    },
     transport::smtp::{authentication::Credentials, extension::ClientId},
     AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
 };
+use sqlx::PgConnection;
 
-use crate::WEBSITE_ORIGIN;
+use crate::{
+    api::routes::v1::users::notifications::NotificationCategory, config, crypto::sign, id::Id,
+    WEBSITE_ORIGIN,
+};
 
 /// An email template asking a user to verify their email.
 #[derive(Template, Debug)]
@@ -76,9 +83,111 @@ impl MessageTemplate for PasswordResetFailedMessage<'_> {
     }
 }
 
+/// An email template inviting someone to sign up, optionally as a member of an organization.
+#[derive(Template, Debug)]
+#[template(path = "email/invite.html")]
+pub(crate) struct InviteMessage<'a> {
+    /// The name of the organization the invite is for, if any.
+    pub(crate) organization_name: Option<&'a str>,
+
+    /// The URL the recipient must visit to accept the invite.
+    pub(crate) invite_url: &'a str,
+}
+
+impl MessageTemplate for InviteMessage<'_> {
+    fn subject(&self) -> String {
+        "You're invited to File Garden".into()
+    }
+}
+
+/// An email template inviting someone to access a folder another user shared with them.
+#[derive(Template, Debug)]
+#[template(path = "email/folder_access_invite.html")]
+pub(crate) struct FolderAccessInviteMessage<'a> {
+    /// The name of the user who shared the folder.
+    pub(crate) sharer_name: &'a str,
+
+    /// The name of the shared folder.
+    pub(crate) folder_name: &'a str,
+
+    /// Whether the invite grants access to change the folder's contents.
+    pub(crate) can_write: bool,
+
+    /// The URL the recipient must visit to accept the invite.
+    pub(crate) invite_url: &'a str,
+}
+
+impl MessageTemplate for FolderAccessInviteMessage<'_> {
+    fn subject(&self) -> String {
+        format!("{} shared a folder with you", self.sharer_name)
+    }
+}
+
+/// An email template informing a user that signing into their account is temporarily locked after
+/// too many failed attempts.
+#[derive(Template, Debug)]
+#[template(path = "email/sign_in_locked.html")]
+pub(crate) struct SignInLockedMessage<'a> {
+    /// The email address of the locked account.
+    pub(crate) email: &'a str,
+
+    /// How many minutes the lock lasts.
+    pub(crate) lockout_minutes: i64,
+}
+
+impl MessageTemplate for SignInLockedMessage<'_> {
+    fn subject(&self) -> String {
+        "Sign-in temporarily locked".into()
+    }
+}
+
+impl NotificationTemplate for SignInLockedMessage<'_> {
+    const CATEGORY: NotificationCategory = NotificationCategory::SecurityAlerts;
+}
+
+/// An email template informing a user that their account was signed into from a device it hadn't
+/// been signed into from before.
+#[derive(Template, Debug)]
+#[template(path = "email/new_sign_in.html")]
+pub(crate) struct NewSignInMessage<'a> {
+    /// The email address of the account that was signed into.
+    pub(crate) email: &'a str,
+
+    /// The IP address the account was signed into from.
+    pub(crate) ip: &'a str,
+
+    /// The `User-Agent` of the client the account was signed into with, if known.
+    pub(crate) user_agent: Option<&'a str>,
+
+    /// The URL the user can visit to sign the new session out.
+    pub(crate) revocation_url: &'a str,
+}
+
+impl MessageTemplate for NewSignInMessage<'_> {
+    fn subject(&self) -> String {
+        "New sign-in to your account".into()
+    }
+}
+
+impl NotificationTemplate for NewSignInMessage<'_> {
+    const CATEGORY: NotificationCategory = NotificationCategory::SecurityAlerts;
+}
+
+/// The layout of a notification's HTML body, adding a link to unsubscribe from its category after the
+/// notification's own body.
+#[derive(Template, Debug)]
+#[template(path = "email/notification.html")]
+struct NotificationLayout<'a> {
+    /// The notification's own rendered HTML body.
+    body: &'a str,
+
+    /// The URL the user can visit to unsubscribe from the notification's category.
+    unsubscribe_url: &'a str,
+}
+
 /// The mailbox automated emails are sent from.
 static FROM_MAILBOX: LazyLock<Mailbox> = LazyLock::new(|| {
-    dotenvy::var("FROM_MAILBOX")
+    config::var("FROM_MAILBOX")
         .expect("environment variable `FROM_MAILBOX` should be a valid string")
         .parse()
         .expect("environment variable `FROM_MAILBOX` should be a valid mailbox")
@@ -91,37 +200,108 @@ pub(crate) trait MessageTemplate: Template {
 
     /// Generates a subject and multipart HTML and plain text body for the email message template.
     fn to(&self, mailbox: Mailbox) -> Message {
-        let mut subject = self.subject();
-        subject.push_str(" | File Garden");
-
-        let html = self.to_string();
-        let plain = html2text::config::with_decorator(TrivialDecorator::new())
-            .string_from_read(html.as_bytes(), usize::MAX)
-            .expect("message HTML should be convertible to text");
-
-        Message::builder()
-            .from(FROM_MAILBOX.clone())
-            .to(mailbox)
-            .subject(subject)
-            .multipart(MultiPart::alternative_plain_html(plain, html))
-            .expect("message should be valid")
+        build_message(self.subject(), self.to_string(), mailbox)
+    }
+}
+
+/// Builds a message from a subject and HTML body, adding a plain text alternative to the body.
+fn build_message(mut subject: String, html: String, mailbox: Mailbox) -> Message {
+    subject.push_str(" | File Garden");
+
+    let plain = html2text::config::with_decorator(TrivialDecorator::new())
+        .string_from_read(html.as_bytes(), usize::MAX)
+        .expect("message HTML should be convertible to text");
+
+    Message::builder()
+        .from(FROM_MAILBOX.clone())
+        .to(mailbox)
+        .subject(subject)
+        .multipart(MultiPart::alternative_plain_html(plain, html))
+        .expect("message should be valid")
+}
+
+/// A [`MessageTemplate`] for an optional notification, which isn't sent to users who unsubscribed
+/// from its category.
+pub(crate) trait NotificationTemplate: MessageTemplate {
+    /// The category the notification belongs to.
+    const CATEGORY: NotificationCategory;
+}
+
+/// Sends a notification to a user in the background unless they unsubscribed from its category.
+///
+/// The message includes a signed link to unsubscribe from the category in one click, both in its
+/// body and in its `List-Unsubscribe` header (as specified by RFC 8058).
+///
+/// # Errors
+///
+/// Fails if checking the user's notification settings or whether their address is suppressed
+/// fails.
+pub(crate) async fn notify<T: NotificationTemplate + Sync>(
+    tx: &mut PgConnection,
+    user_id: &[u8],
+    mailbox: Mailbox,
+    template: &T,
+) -> sqlx::Result<()> {
+    let unsubscribed = sqlx::query!(
+        r#"SELECT EXISTS(
+            SELECT 1 FROM notification_opt_outs
+                WHERE user_id = $1 AND category = $2
+        ) as "exists!""#,
+        user_id,
+        T::CATEGORY as NotificationCategory,
+    )
+    .fetch_one(&mut *tx)
+    .await?
+    .exists;
+
+    if unsubscribed {
+        return Ok(());
+    }
+
+    let query = format!(
+        "user={}&category={}&signature={}",
+        Id::from(user_id),
+        <&str>::from(T::CATEGORY),
+        Id::from(sign(&T::CATEGORY.unsubscribe_payload(user_id)).as_ref()),
+    );
+    let unsubscribe_url = format!("{}/unsubscribe?{query}", *WEBSITE_ORIGIN);
+    let one_click_unsubscribe_url = format!("{}/api/v1/unsubscribe?{query}", *WEBSITE_ORIGIN);
+
+    let html = NotificationLayout {
+        body: &template.to_string(),
+        unsubscribe_url: &unsubscribe_url,
     }
+    .to_string();
+
+    let mut message = build_message(template.subject(), html, mailbox);
+
+    let headers = message.headers_mut();
+    headers.insert_raw(HeaderValue::new(
+        HeaderName::new_from_ascii_str("List-Unsubscribe"),
+        format!("<{one_click_unsubscribe_url}>"),
+    ));
+    headers.insert_raw(HeaderValue::new(
+        HeaderName::new_from_ascii_str("List-Unsubscribe-Post"),
+        "List-Unsubscribe=One-Click".into(),
+    ));
+
+    message.send(tx).await
 }
 
 /// The SMTP transport used to send automated emails.
 static MAILER: LazyLock<AsyncSmtpTransport<Tokio1Executor>> = LazyLock::new(|| {
-    let hostname = dotenvy::var("SMTP_HOSTNAME")
+    let hostname = config::var("SMTP_HOSTNAME")
         .expect("environment variable `SMTP_HOSTNAME` should be a valid string");
-    let username = dotenvy::var("SMTP_USERNAME")
+    let username = config::var("SMTP_USERNAME")
         .expect("environment variable `SMTP_USERNAME` should be a valid string");
-    let password = dotenvy::var("SMTP_PASSWORD")
+    let password = config::var("SMTP_PASSWORD")
         .expect("environment variable `SMTP_PASSWORD` should be a valid string");
 
     let mut smtp_transport = AsyncSmtpTransport::<Tokio1Executor>::relay(&hostname)
         .expect("SMTP relay couldn't be initialized")
         .credentials(Credentials::new(username, password));
 
-    match dotenvy::var("SMTP_HELO_DOMAIN") {
+    match config::var("SMTP_HELO_DOMAIN") {
         // If the environment variable is unset, let `lettre` default to using the OS hostname.
         Err(dotenvy::Error::EnvVar(VarError::NotPresent)) => {}
 
@@ -138,15 +318,55 @@ static MAILER: LazyLock<AsyncSmtpTransport<Tokio1Executor>> = LazyLock::new(|| {
 
 /// A trait for sending messages using the SMTP configuration from `.env`.
 pub(crate) trait SendMessage {
-    /// Sends the message in the background.
+    /// Sends the message in the background, unless any of its recipients' addresses bounced or
+    /// complained about a previous email.
+    ///
+    /// Errors sending are ignored so they can't propagate to end users. Otherwise, users could tell
+    /// if an email sent successfully or not, which can allow for user enumeration in some
+    /// circumstances. For the same reason, suppressed messages are silently dropped.
     ///
-    /// Errors are ignored so they can't propagate to end users. Otherwise, users could tell if an
-    /// email sent successfully or not, which can allow for user enumeration in some circumstances.
-    fn send(self);
+    /// # Errors
+    ///
+    /// Fails if checking whether the recipients' addresses are suppressed fails.
+    async fn send(self, conn: &mut PgConnection) -> sqlx::Result<()>;
+
+    /// Sends the message in the background, even if its recipients' addresses bounced or complained
+    /// about a previous email. This is only for messages the recipient just asked for, such as
+    /// password resets and verification emails, so a user whose address was suppressed can still
+    /// recover their account and show their address works again.
+    ///
+    /// Like [`SendMessage::send`], errors sending are ignored.
+    fn send_requested(self);
 }
 
 impl SendMessage for Message {
-    fn send(self) {
+    async fn send(self, conn: &mut PgConnection) -> sqlx::Result<()> {
+        let recipients: Vec<String> = self
+            .envelope()
+            .to()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+
+        let is_suppressed = sqlx::query!(
+            r#"SELECT EXISTS(
+                SELECT 1 FROM email_suppressions
+                    WHERE email = ANY($1::text[]::citext[])
+            ) as "exists!""#,
+            &recipients,
+        )
+        .fetch_one(conn)
+        .await?
+        .exists;
+
+        if !is_suppressed {
+            self.send_requested();
+        }
+
+        Ok(())
+    }
+
+    fn send_requested(self) {
         tokio::spawn(MAILER.send(self));
     }
 }
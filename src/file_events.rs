@@ -0,0 +1,166 @@
+//! Live notifications of users' file events, broadcast to subscribers as they happen.
+
+use std::{sync::Arc, time::Duration};
+
+use sqlx::{pool::CloseEvent, postgres::PgListener, PgPool};
+use tokio::sync::broadcast;
+use tracing::warn;
+
+use crate::{
+    api::routes::v1::users::events::{FileEvent, FileEventType},
+    db::{self, TxResult},
+    id::Id,
+};
+
+/// The database notification channel new file events are announced on.
+const CHANNEL: &str = "file_events";
+
+/// The number of notifications buffered for each subscriber. A subscriber falling further behind
+/// than this misses notifications.
+const CAPACITY: usize = 1024;
+
+/// How long to wait before reconnecting after listening first fails. This doubles with each
+/// consecutive failure, up to [`MAX_RECONNECT_DELAY`].
+const MIN_RECONNECT_DELAY: Duration = Duration::from_millis(500);
+
+/// The longest to wait before reconnecting after listening fails.
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(60);
+
+/// A notification of a new file event.
+#[derive(Debug)]
+pub(crate) struct FileEventNotification {
+    /// The ID of the user whose file the event is for.
+    pub(crate) user_id: Id,
+
+    /// The new event.
+    pub(crate) event: FileEvent,
+}
+
+/// The sending half of the file event broadcast channel. Call [`broadcast::Sender::subscribe`] to
+/// receive notifications.
+pub(crate) type Sender = broadcast::Sender<Arc<FileEventNotification>>;
+
+/// Starts listening for new file events in the database in the background, returning a [`Sender`]
+/// to subscribe to them with.
+///
+/// # Errors
+///
+/// Returns an error if the database connection fails.
+pub(crate) async fn listen(db_pool: &PgPool) -> sqlx::Result<Sender> {
+    let mut listener = connect(db_pool).await?;
+
+    let (sender, _) = broadcast::channel(CAPACITY);
+
+    let db_pool = db_pool.clone();
+    let task_sender = sender.clone();
+
+    tokio::spawn(async move {
+        // Listening stops once the pool closes, so closing it doesn't wait on the listener's
+        // connection forever.
+        let mut close_event = db_pool.close_event();
+        let mut reconnect_delay = MIN_RECONNECT_DELAY;
+
+        loop {
+            let notification = match close_event.do_until(listener.recv()).await {
+                Err(_) => break,
+                Ok(Ok(notification)) => notification,
+
+                // Any notifications sent until the listener reconnects are lost.
+                Ok(Err(error)) => {
+                    warn!(%error, "listening for file events failed");
+
+                    let Some(new_listener) =
+                        reconnect(&db_pool, &mut close_event, &mut reconnect_delay).await
+                    else {
+                        break;
+                    };
+
+                    listener = new_listener;
+                    continue;
+                }
+            };
+
+            reconnect_delay = MIN_RECONNECT_DELAY;
+
+            // Don't bother fetching the event if nobody would receive it.
+            if task_sender.receiver_count() == 0 {
+                continue;
+            }
+
+            let Ok(event_id) = notification.payload().parse::<i64>() else {
+                continue;
+            };
+
+            let Ok(Some(event)) =
+                db::transaction!(db_pool, async |tx| -> TxResult<_, sqlx::Error> {
+                    Ok(sqlx::query!(
+                        r#"SELECT
+                            user_id, id, created_at, type as "type: FileEventType", file_id,
+                            name, parent_name_path, previous_name, previous_parent_name_path
+                            FROM file_events
+                            WHERE id = $1"#,
+                        event_id,
+                    )
+                    .fetch_optional(tx.as_mut())
+                    .await?)
+                })
+                .await
+            else {
+                continue;
+            };
+
+            // This only fails if there are no subscribers anymore, in which case nothing's lost.
+            let _ = task_sender.send(Arc::new(FileEventNotification {
+                user_id: event.user_id.into(),
+                event: FileEvent {
+                    id: event.id,
+                    created_at: event.created_at,
+                    r#type: event.r#type,
+                    file_id: event.file_id.into(),
+                    name: event.name,
+                    parent_path: event.parent_name_path,
+                    previous_name: event.previous_name,
+                    previous_parent_path: event.previous_parent_name_path,
+                },
+            }));
+        }
+    });
+
+    Ok(sender)
+}
+
+/// Connects a new listener for file event notifications.
+///
+/// # Errors
+///
+/// Returns an error if the database connection fails.
+async fn connect(db_pool: &PgPool) -> sqlx::Result<PgListener> {
+    let mut listener = PgListener::connect_with(db_pool).await?;
+    listener.listen(CHANNEL).await?;
+
+    Ok(listener)
+}
+
+/// Connects a new listener after listening failed, waiting longer before each attempt. The delay
+/// before the next attempt is left in `reconnect_delay`.
+///
+/// Returns `None` if the pool closes first.
+async fn reconnect(
+    db_pool: &PgPool,
+    close_event: &mut CloseEvent,
+    reconnect_delay: &mut Duration,
+) -> Option<PgListener> {
+    loop {
+        close_event
+            .do_until(tokio::time::sleep(*reconnect_delay))
+            .await
+            .ok()?;
+
+        *reconnect_delay = (*reconnect_delay * 2).min(MAX_RECONNECT_DELAY);
+
+        match close_event.do_until(connect(db_pool)).await.ok()? {
+            Ok(listener) => return Some(listener),
+            Err(error) => warn!(%error, "reconnecting to listen for file events failed"),
+        }
+    }
+}
@@ -14,6 +14,21 @@ use thiserror::Error;
 /// The type to create new user IDs with.
 pub(crate) type NewUserId = Id<[u8; 8]>;
 
+/// The type to create new sign-in session IDs with.
+pub(crate) type NewSessionId = Id<[u8; 8]>;
+
+/// The type to create new organization IDs with.
+pub(crate) type NewOrganizationId = Id<[u8; 8]>;
+
+/// The code in a file's short link.
+pub(crate) type ShortLinkCode = Id<[u8; 6]>;
+
+/// A token proving ownership of a custom domain.
+pub(crate) type DomainVerificationToken = Id<[u8; 16]>;
+
+/// An ID identifying a request in logs and error reports.
+pub(crate) type RequestId = Id<[u8; 8]>;
+
 /// A 128-byte token.
 pub type Token = Id<[u8; 128]>;
 
@@ -0,0 +1,137 @@
+//! File Garden's backend web server, as a library so it can be built into tests as well as the
+//! server binary.
+
+use std::{env::VarError, net::SocketAddr, sync::LazyLock};
+
+use tokio::net::TcpListener;
+
+use crate::app::App;
+
+pub mod api;
+pub mod app;
+mod cdn;
+mod cleanup;
+mod config;
+mod content;
+mod crypto;
+mod db;
+mod disposable_emails;
+mod downloads;
+mod email;
+mod file_events;
+pub mod id;
+mod maintenance;
+mod percent_encoding;
+mod response;
+mod router;
+mod telemetry;
+mod tls;
+mod website;
+
+/// The URI origin for user-uploaded content.
+pub(crate) static CONTENT_ORIGIN: LazyLock<String> = LazyLock::new(|| {
+    config::var("CONTENT_ORIGIN")
+        .expect("environment variable `CONTENT_ORIGIN` should be a valid string")
+});
+
+/// The URI origin for the website.
+pub(crate) static WEBSITE_ORIGIN: LazyLock<String> = LazyLock::new(|| {
+    config::var("WEBSITE_ORIGIN")
+        .expect("environment variable `WEBSITE_ORIGIN` should be a valid string")
+});
+
+/// The state passed to all of the routes.
+#[derive(Clone, Debug)]
+pub struct AppState {
+    /// The database pool shared between all routes.
+    db_pool: sqlx::PgPool,
+
+    /// The database pool for read-only queries that can tolerate replication lag, such as those
+    /// serving user-uploaded content. This is connected to a replica if one is configured, or is
+    /// the same as `db_pool` otherwise.
+    db_replica_pool: sqlx::PgPool,
+
+    /// The sender to subscribe to notifications of new file events with.
+    file_events: file_events::Sender,
+
+    /// The buffer to count files' downloads in.
+    download_counter: downloads::Counter,
+
+    /// The disposable email domains users can't sign up with.
+    disposable_email_domains: disposable_emails::Blocklist,
+
+    /// Whether the server is down for maintenance.
+    maintenance_mode: maintenance::Switch,
+
+    /// The router to handle API requests with.
+    api_router: axum::Router<Self>,
+}
+
+/// Runs the server with the settings in the environment until it stops.
+///
+/// # Errors
+///
+/// See implementation.
+pub async fn run() -> anyhow::Result<()> {
+    config::init()?;
+
+    telemetry::init()?;
+
+    // This must be held until the server stops so reports queued before then are sent.
+    let _error_reporting = telemetry::init_error_reporting()?;
+
+    let db_url = config::var("DATABASE_URL")?;
+    let address = config::var("ADDRESS")?;
+
+    let tls_address = match config::var("TLS_ADDRESS") {
+        // If the environment variable is unset, TLS is left to a reverse proxy.
+        Err(dotenvy::Error::EnvVar(VarError::NotPresent)) => None,
+
+        tls_address => Some(tls_address?),
+    };
+
+    println!("Initializing database...");
+
+    let db_pool = db::initialize(&db_url).await?;
+
+    let db_replica_pool = match config::var("DATABASE_REPLICA_URL") {
+        // If the environment variable is unset, there's no replica to offload reads to.
+        Err(dotenvy::Error::EnvVar(VarError::NotPresent)) => None,
+
+        db_replica_url => Some(db::initialize_replica(&db_replica_url?).await?),
+    };
+
+    let app = App::builder(db_pool)
+        .db_replica_pool(db_replica_pool)
+        .build()
+        .await?;
+
+    config::reload_on_hangup(app.state().clone())?;
+
+    println!("Listening to {address}...");
+
+    let listener = TcpListener::bind(address).await?;
+
+    if let Some(tls_address) = &tls_address {
+        println!("Listening to {tls_address} over TLS...");
+    }
+
+    println!("Ready!");
+
+    let service = app
+        .router()
+        .into_make_service_with_connect_info::<SocketAddr>();
+
+    // Without TLS, HTTP/2 is only used by clients that know to speak it, like reverse proxies
+    // configured to.
+    let serve = async move { anyhow::Ok(axum::serve(listener, service).await?) };
+
+    match tls_address {
+        Some(tls_address) => {
+            tokio::try_join!(serve, tls::serve(&tls_address, &app))?;
+        }
+        None => serve.await?,
+    }
+
+    Ok(())
+}
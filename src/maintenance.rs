@@ -0,0 +1,100 @@
+//! See [`Switch`].
+
+use std::{
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+use sqlx::PgPool;
+use tracing::warn;
+
+use crate::{
+    api::routes::v1::config::maintenance::MaintenanceMode,
+    db::{self, TxResult},
+};
+
+/// How long the maintenance mode read from the database is reused before it's read again. Switching
+/// modes takes up to this long to apply to other server processes.
+const CACHE_DURATION: Duration = Duration::from_secs(5);
+
+/// The server's current [`MaintenanceMode`], stored in the database so it's shared by every server
+/// process and kept across restarts. Clones share the same cache.
+#[derive(Clone, Debug)]
+pub(crate) struct Switch {
+    /// The pool of connections to the database the mode is stored in.
+    db_pool: PgPool,
+
+    /// The mode last read from the database, and when it was read.
+    cache: Arc<Mutex<Option<(MaintenanceMode, Instant)>>>,
+}
+
+impl Switch {
+    /// Creates a switch for the maintenance mode stored in the specified database.
+    pub(crate) fn new(db_pool: PgPool) -> Self {
+        Self {
+            db_pool,
+            cache: Arc::default(),
+        }
+    }
+
+    /// Gets the current maintenance mode, reading it from the database if the cached mode is
+    /// stale. If reading it fails, the last mode read is kept, or [`MaintenanceMode::Off`] if there
+    /// is none, so a database outage alone doesn't take the server down for maintenance.
+    pub(crate) async fn get(&self) -> MaintenanceMode {
+        let cached = *self.cache.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if let Some((mode, read_at)) = cached {
+            if read_at.elapsed() < CACHE_DURATION {
+                return mode;
+            }
+        }
+
+        let result = db::transaction!(self.db_pool, async |tx| -> TxResult<_, sqlx::Error> {
+            Ok(sqlx::query_scalar!(
+                r#"SELECT mode as "mode: MaintenanceMode"
+                    FROM maintenance"#,
+            )
+            .fetch_one(tx.as_mut())
+            .await?)
+        })
+        .await;
+
+        let mode = match result {
+            Ok(mode) => mode,
+            Err(error) => {
+                warn!(%error, "reading maintenance mode failed");
+                cached.map_or(MaintenanceMode::Off, |(mode, _)| mode)
+            }
+        };
+
+        self.cache_mode(mode);
+        mode
+    }
+
+    /// Sets the current maintenance mode for every server process.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database query fails.
+    pub(crate) async fn set(&self, mode: MaintenanceMode) -> sqlx::Result<()> {
+        db::transaction!(self.db_pool, async |tx| -> TxResult<_, sqlx::Error> {
+            sqlx::query!(
+                "UPDATE maintenance SET mode = $1, updated_at = now()",
+                mode as MaintenanceMode,
+            )
+            .execute(tx.as_mut())
+            .await?;
+
+            Ok(())
+        })
+        .await?;
+
+        self.cache_mode(mode);
+        Ok(())
+    }
+
+    /// Caches the specified maintenance mode as just read.
+    fn cache_mode(&self, mode: MaintenanceMode) {
+        *self.cache.lock().unwrap_or_else(PoisonError::into_inner) = Some((mode, Instant::now()));
+    }
+}
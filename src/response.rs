@@ -76,6 +76,20 @@ impl Response {
         self
     }
 
+    /// Sets the response to a [`302
+    /// Found`](https://developer.mozilla.org/docs/Web/HTTP/Status/302) redirect.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the location isn't a valid header value. See "Panics" section of
+    /// [`Response::header_valid`].
+    pub(crate) fn found_redirect(mut self, location: &str) -> Self {
+        self.status(StatusCode::FOUND)
+            .header_valid(LOCATION, location);
+
+        self
+    }
+
     /// Sets a [`StatusCode`], and sets it along with its canonical reason text (e.g. `404 Not
     /// Found`) as a `text/plain` body on the response.
     pub(crate) fn plain_error(mut self, status: StatusCode) -> Self {
@@ -1,15 +1,21 @@
 //! See [`handle`].
 
-use std::sync::LazyLock;
+use std::{
+    sync::{Arc, LazyLock},
+    time::Instant,
+};
 
 use axum::{
+    body::HttpBody,
     extract::{Request, State},
     http::{header::HOST, StatusCode},
     response::{IntoResponse, Response},
 };
 use axum_macros::debug_handler;
+use sentry::{Hub, SentryFutureExt};
+use tracing::{field, info, info_span, Instrument};
 
-use crate::{api, content, website, AppState, CONTENT_ORIGIN, WEBSITE_ORIGIN};
+use crate::{api, content, id::RequestId, website, AppState, CONTENT_ORIGIN, WEBSITE_ORIGIN};
 
 /// The URI host for user-uploaded content.
 static CONTENT_HOST: LazyLock<&str> = LazyLock::new(|| host_from_origin(&CONTENT_ORIGIN));
@@ -17,16 +23,71 @@ static CONTENT_HOST: LazyLock<&str> = LazyLock::new(|| host_from_origin(&CONTENT
 /// The URI host for the website.
 static WEBSITE_HOST: LazyLock<&str> = LazyLock::new(|| host_from_origin(&WEBSITE_ORIGIN));
 
-/// Handles all incoming requests and routes them to other services based on the request URI.
+/// Handles all incoming requests and routes them to other services based on the request URI, each
+/// in its own tracing span and with its own scope for reported errors. Once a response is ready,
+/// it's logged along with how long it took.
 #[debug_handler]
 pub(super) async fn handle(State(state): State<AppState>, request: Request) -> Response {
+    let start = Instant::now();
+
+    // An ID isn't necessary to handle the request, so it's left out if generating one fails.
+    let request_id = RequestId::generate().ok();
+
+    let span = info_span!(
+        "request",
+        request.id = request_id.as_ref().map(field::display),
+        user.id = field::Empty,
+        http.request.method = %request.method(),
+        server.address = request
+            .headers()
+            .get(HOST)
+            .and_then(|host| host.to_str().ok()),
+        url.path = request.uri().path(),
+        http.response.status_code = field::Empty,
+    );
+
+    // Errors and panics reported while handling the request are tagged with its details.
+    let hub = Arc::new(Hub::new_from_top(Hub::current()));
+    hub.configure_scope(|scope| {
+        if let Some(request_id) = &request_id {
+            scope.set_tag("request.id", request_id);
+        }
+
+        scope.set_tag("http.request.method", request.method());
+        scope.set_tag("url.path", request.uri().path());
+
+        if let Some(host) = request.headers().get(HOST) {
+            scope.set_tag("server.address", String::from_utf8_lossy(host.as_bytes()));
+        }
+    });
+
+    let response = route(state, request)
+        .bind_hub(hub)
+        .instrument(span.clone())
+        .await;
+
+    span.record("http.response.status_code", response.status().as_u16());
+
+    span.in_scope(|| {
+        info!(
+            http.server.request.duration = start.elapsed().as_secs_f64(),
+            http.response.body.size = response.body().size_hint().exact(),
+            "request handled",
+        );
+    });
+
+    response
+}
+
+/// Routes a request to other services based on the request URI.
+async fn route(state: AppState, request: Request) -> Response {
     let host = request
         .headers()
         .get(HOST)
         .and_then(|host| host.to_str().ok());
 
     if host == Some(*CONTENT_HOST) {
-        return content::handle(request).into_response();
+        return content::handle(State(state), request).await.into_response();
     }
 
     if host == Some(*WEBSITE_HOST) {
@@ -37,6 +98,13 @@ pub(super) async fn handle(State(state): State<AppState>, request: Request) -> R
         return website::handle(request).await;
     }
 
+    // Any other host may be a user's custom domain.
+    if host.is_some() {
+        return content::handle_custom_domain(State(state), request)
+            .await
+            .into_response();
+    }
+
     StatusCode::MISDIRECTED_REQUEST.into_response()
 }
 
@@ -0,0 +1,164 @@
+//! Logging and tracing of requests, database transactions, and background jobs, optionally
+//! exported to an OpenTelemetry collector so operators can follow a slow request through everything
+//! it waited on. Unexpected errors and panics are optionally reported to Sentry or any compatible
+//! service.
+
+use std::{borrow::Cow, env::VarError, error::Error, sync::OnceLock};
+
+use opentelemetry::{global, trace::TracerProvider as _, KeyValue};
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::{runtime, trace::TracerProvider, Resource};
+use tracing::{field, Span};
+use tracing_subscriber::{
+    filter::LevelFilter, fmt, layer::SubscriberExt, reload, util::SubscriberInitExt, Layer,
+    Registry,
+};
+
+use crate::{
+    config::{self, Reloadable},
+    id::Id,
+};
+
+/// The name this service is identified by in exported traces.
+const SERVICE_NAME: &str = "filegarden-backend";
+
+/// The least severe level of events that are logged.
+pub(crate) static LOG_LEVEL: Reloadable<LevelFilter> = Reloadable::new(|| {
+    // If the environment variable is unset, only informational events and worse are logged.
+    config::optional_var("LOG_LEVEL")?.map_or(Ok(LevelFilter::INFO), |level| {
+        level.parse().map_err(|_| {
+            "environment variable `LOG_LEVEL` should be `trace`, `debug`, `info`, `warn`, \
+            `error`, or `off` if set"
+                .into()
+        })
+    })
+});
+
+/// The handle to change the filter of logs by [`LOG_LEVEL`] with, once logging has started.
+static LOG_FILTER: OnceLock<reload::Handle<LevelFilter, Registry>> = OnceLock::new();
+
+/// Starts logging to stdout and collecting spans.
+///
+/// Only events at least as severe as the `LOG_LEVEL` environment variable are logged, which can be
+/// changed by reloading settings. Logs are human-readable unless the `LOG_FORMAT` environment
+/// variable is `json`, in which case each is a line of JSON with the fields of the spans it
+/// occurred in, for log aggregators to ingest.
+///
+/// Spans are exported over OTLP (gRPC) to the collector at the URL in the
+/// `OTEL_EXPORTER_OTLP_ENDPOINT` environment variable, or discarded if that's unset.
+///
+/// # Errors
+///
+/// Returns an error if `LOG_FORMAT` is invalid, the exporter can't be built, or a global subscriber
+/// is already set.
+pub(crate) fn init() -> anyhow::Result<()> {
+    let log_layer = match config::var("LOG_FORMAT") {
+        // If the environment variable is unset, logs are read by a person rather than a program.
+        Err(dotenvy::Error::EnvVar(VarError::NotPresent)) => fmt::layer().boxed(),
+
+        log_format => match log_format?.as_str() {
+            "text" => fmt::layer().boxed(),
+            "json" => fmt::layer()
+                .json()
+                .flatten_event(true)
+                .with_current_span(false)
+                .with_span_list(true)
+                .boxed(),
+            log_format => anyhow::bail!(
+                "environment variable `LOG_FORMAT` should be `text` or `json`, not {log_format:?}"
+            ),
+        },
+    };
+
+    let otel_layer = match config::var("OTEL_EXPORTER_OTLP_ENDPOINT") {
+        // If the environment variable is unset, there's nowhere to export spans to.
+        Err(dotenvy::Error::EnvVar(VarError::NotPresent)) => None,
+
+        endpoint => {
+            let exporter = SpanExporter::builder()
+                .with_tonic()
+                .with_endpoint(endpoint?)
+                .build()?;
+
+            let provider = TracerProvider::builder()
+                .with_batch_exporter(exporter, runtime::Tokio)
+                .with_resource(Resource::new([KeyValue::new("service.name", SERVICE_NAME)]))
+                .build();
+
+            let tracer = provider.tracer(SERVICE_NAME);
+            global::set_tracer_provider(provider);
+
+            Some(tracing_opentelemetry::layer().with_tracer(tracer))
+        }
+    };
+
+    let (log_filter, log_filter_handle) = reload::Layer::new(*LOG_LEVEL.get());
+
+    tracing_subscriber::registry()
+        .with(log_layer.with_filter(log_filter))
+        .with(otel_layer)
+        .try_init()?;
+
+    // Logging is only started once, so the handle can't already be set.
+    let _ = LOG_FILTER.set(log_filter_handle);
+
+    Ok(())
+}
+
+/// Applies the current [`LOG_LEVEL`] to logs, if logging has started.
+pub(crate) fn apply_log_level() {
+    if let Some(log_filter) = LOG_FILTER.get() {
+        // This only fails if the subscriber was dropped, in which case nothing is logged anyway.
+        let _ = log_filter.reload(*LOG_LEVEL.get());
+    }
+}
+
+/// Starts reporting unexpected errors and panics to the Sentry DSN in the `SENTRY_DSN` environment
+/// variable, tagged with this release and the environment in `SENTRY_ENVIRONMENT`. Nothing is
+/// reported if `SENTRY_DSN` is unset.
+///
+/// Reporting stops once the returned guard is dropped, after sending any queued reports.
+///
+/// # Errors
+///
+/// Returns an error if `SENTRY_DSN` or `SENTRY_ENVIRONMENT` is set but invalid.
+pub(crate) fn init_error_reporting() -> anyhow::Result<sentry::ClientInitGuard> {
+    let dsn = match config::var("SENTRY_DSN") {
+        // If the environment variable is unset, there's nowhere to report errors to.
+        Err(dotenvy::Error::EnvVar(VarError::NotPresent)) => None,
+
+        dsn => Some(dsn?.parse()?),
+    };
+
+    let environment = match config::var("SENTRY_ENVIRONMENT") {
+        // If the environment variable is unset, Sentry's default environment is used.
+        Err(dotenvy::Error::EnvVar(VarError::NotPresent)) => None,
+
+        environment => Some(Cow::Owned(environment?)),
+    };
+
+    Ok(sentry::init(sentry::ClientOptions {
+        dsn,
+        release: sentry::release_name!(),
+        environment,
+        ..Default::default()
+    }))
+}
+
+/// Reports an unexpected error to Sentry, along with any context of the request it occurred in. Does
+/// nothing if error reporting isn't enabled.
+pub(crate) fn report_error<E: Error + ?Sized>(error: &E) {
+    sentry::capture_error(error);
+}
+
+/// Attributes logs and errors reported during the current request to the specified user.
+pub(crate) fn set_user(user_id: &Id) {
+    Span::current().record("user.id", field::display(user_id));
+
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            id: Some(user_id.to_string()),
+            ..Default::default()
+        }));
+    });
+}
@@ -0,0 +1,338 @@
+//! An optional HTTPS listener with certificates provisioned and renewed automatically over ACME, so
+//! small deployments don't need a separate reverse proxy to terminate TLS.
+
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    io,
+    net::SocketAddr,
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+use axum_server::tls_rustls::RustlsConfig;
+use futures_util::StreamExt;
+use rustls_acme::{
+    caches::DirCache,
+    futures_rustls::rustls::{
+        server::{ClientHello, ResolvesServerCert},
+        sign::CertifiedKey,
+        ServerConfig,
+    },
+    AcmeConfig, AcmeState, ResolvesServerCertAcme,
+};
+use sqlx::PgPool;
+use tokio::{sync::mpsc, task::AbortHandle};
+use tracing::{error, warn};
+
+use crate::{
+    app::App,
+    config,
+    content::find_custom_domain_owner,
+    db::{self, TxResult},
+    CONTENT_ORIGIN, WEBSITE_ORIGIN,
+};
+
+/// How long after a server name is checked for a verified custom domain before it's checked again,
+/// so handshakes for unknown names can't flood the database.
+const CUSTOM_DOMAIN_RECHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The most server names that can wait to be checked for verified custom domains. Names requested
+/// while this many are waiting aren't checked, so the handshake fails and the client must retry.
+const MAX_PENDING_DOMAIN_CHECKS: usize = 100;
+
+/// How long to wait after checking a server name before checking the next, so handshakes for many
+/// unknown names can't flood the database.
+const DOMAIN_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How often custom domains with certificates are checked again, so certificates stop being served
+/// and renewed for domains that were deleted or unverified, or whose owners were deleted or moved
+/// to a plan without custom domains.
+const CUSTOM_DOMAIN_REVALIDATION_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// The ALPN protocol ID of TLS-ALPN-01 challenges, which Let's Encrypt uses to validate domains.
+const ACME_TLS_ALPN_PROTOCOL: &[u8] = b"acme-tls/1";
+
+/// Serves HTTPS on the specified address until the server stops, using certificates from Let's
+/// Encrypt. The website and the content origin share one certificate ordered on startup. Each
+/// verified custom domain gets its own certificate, ordered the first time a client connects to it,
+/// so domains verified while the server is running are covered without restarting it. Clients can
+/// negotiate HTTP/2 over ALPN.
+///
+/// Certificates are cached in the directory set by the `ACME_CACHE_DIR` environment variable, and
+/// Let's Encrypt sends expiry notices to the address set by `ACME_EMAIL`.
+///
+/// # Errors
+///
+/// Returns an error if the address or the ACME settings are invalid, or the listener fails.
+pub(crate) async fn serve(address: &str, app: &App) -> anyhow::Result<()> {
+    let address: SocketAddr = address.parse()?;
+    let acme = AcmeSettings {
+        contact: format!("mailto:{}", config::var("ACME_EMAIL")?),
+        cache_dir: config::var("ACME_CACHE_DIR")?,
+    };
+
+    let platform_domains = vec![
+        domain_from_origin(&WEBSITE_ORIGIN),
+        domain_from_origin(&CONTENT_ORIGIN),
+    ];
+
+    let platform_state = acme.state(&platform_domains);
+    let crypto_provider = Arc::clone(platform_state.default_rustls_config().crypto_provider());
+
+    let (requested_domains, requested_domain_rx) = mpsc::channel(MAX_PENDING_DOMAIN_CHECKS);
+
+    let resolver = Arc::new(CertResolver {
+        platform_domains,
+        platform: platform_state.resolver(),
+        custom_domains: Mutex::default(),
+        checked_domains: Mutex::default(),
+        requested_domains,
+    });
+
+    tokio::spawn(poll_acme(platform_state));
+    tokio::spawn(issue_custom_domain_certs(
+        app.state().db_pool.clone(),
+        acme,
+        Arc::clone(&resolver),
+        requested_domain_rx,
+    ));
+    revalidate_custom_domains(app.state().db_pool.clone(), Arc::clone(&resolver));
+
+    let mut config = ServerConfig::builder_with_provider(crypto_provider)
+        .with_safe_default_protocol_versions()?
+        .with_no_client_auth()
+        .with_cert_resolver(resolver);
+
+    // Let's Encrypt validates domains by connecting with the ACME protocol ID, which the resolver
+    // answers with the challenge's certificate rather than the domain's.
+    config.alpn_protocols = vec![
+        b"h2".to_vec(),
+        b"http/1.1".to_vec(),
+        ACME_TLS_ALPN_PROTOCOL.to_vec(),
+    ];
+
+    axum_server::bind_rustls(address, RustlsConfig::from_config(Arc::new(config)))
+        .serve(
+            app.router()
+                .into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// The settings for ordering certificates from Let's Encrypt.
+#[derive(Debug)]
+struct AcmeSettings {
+    /// The contact URI Let's Encrypt sends expiry notices to.
+    contact: String,
+
+    /// The directory to cache certificates and the ACME account in.
+    cache_dir: String,
+}
+
+impl AcmeSettings {
+    /// Creates the ACME state for one certificate covering the specified domains. Nothing is ordered
+    /// until the state is polled.
+    fn state(&self, domains: &[String]) -> AcmeState<io::Error> {
+        AcmeConfig::new(domains)
+            .contact_push(&self.contact)
+            .cache(DirCache::new(self.cache_dir.clone()))
+            .directory_lets_encrypt(true)
+            .state()
+    }
+}
+
+/// Provisions and renews an ACME state's certificate until the server stops or the task polling it
+/// is aborted. Certificates are only provisioned and renewed while their state is polled.
+async fn poll_acme(mut state: AcmeState<io::Error>) {
+    while let Some(event) = state.next().await {
+        if let Err(error) = event {
+            error!(?error, "provisioning certificate failed");
+        }
+    }
+}
+
+/// Resolves the certificate for each TLS handshake by its server name.
+#[derive(Debug)]
+struct CertResolver {
+    /// The domains of the website and the content origin.
+    platform_domains: Vec<String>,
+
+    /// The resolver of the certificate for the website and the content origin.
+    platform: Arc<ResolvesServerCertAcme>,
+
+    /// The certificates for each verified custom domain requested so far.
+    custom_domains: Mutex<HashMap<String, CustomDomainCert>>,
+
+    /// When each server name without a certificate was last sent to be checked.
+    checked_domains: Mutex<HashMap<String, Instant>>,
+
+    /// Sends server names to check for verified custom domains needing certificates.
+    requested_domains: mpsc::Sender<String>,
+}
+
+/// The certificate for a verified custom domain.
+#[derive(Debug)]
+struct CustomDomainCert {
+    /// The resolver of the certificate.
+    resolver: Arc<ResolvesServerCertAcme>,
+
+    /// The handle to stop provisioning and renewing the certificate with.
+    renewal: AbortHandle,
+}
+
+impl ResolvesServerCert for CertResolver {
+    fn resolve(&self, client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        let Some(domain) = client_hello.server_name().map(str::to_ascii_lowercase) else {
+            return self.platform.resolve(client_hello);
+        };
+
+        if self.platform_domains.contains(&domain) {
+            return self.platform.resolve(client_hello);
+        }
+
+        let custom_domain = self
+            .custom_domains
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&domain)
+            .map(|custom_domain| Arc::clone(&custom_domain.resolver));
+
+        if let Some(custom_domain) = custom_domain {
+            return custom_domain.resolve(client_hello);
+        }
+
+        let mut checked_domains = self
+            .checked_domains
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+
+        let now = Instant::now();
+        checked_domains.retain(|_, checked_at| {
+            now.duration_since(*checked_at) < CUSTOM_DOMAIN_RECHECK_INTERVAL
+        });
+
+        // The handshake fails this time, but the client can retry once the certificate is issued.
+        // If too many names are waiting to be checked, this one isn't marked as checked, so it can
+        // be requested again once there's room.
+        if let Entry::Vacant(entry) = checked_domains.entry(domain) {
+            if self.requested_domains.try_send(entry.key().clone()).is_ok() {
+                entry.insert(now);
+            }
+        }
+
+        None
+    }
+}
+
+/// Orders a certificate for each requested server name that's a verified custom domain serving
+/// files, adding it to the resolver. Each certificate is renewed until its domain stops serving
+/// files (see [`revalidate_custom_domains`]).
+async fn issue_custom_domain_certs(
+    db_pool: PgPool,
+    acme: AcmeSettings,
+    resolver: Arc<CertResolver>,
+    mut requested_domains: mpsc::Receiver<String>,
+) {
+    let mut checks = tokio::time::interval(DOMAIN_CHECK_INTERVAL);
+
+    while let Some(domain) = requested_domains.recv().await {
+        checks.tick().await;
+
+        let owner_id = db::transaction!(db_pool, async |tx| -> TxResult<_, sqlx::Error> {
+            Ok(find_custom_domain_owner(tx, &domain).await?)
+        })
+        .await;
+
+        match owner_id {
+            Ok(Some(_)) => {}
+            Ok(None) => continue,
+            Err(error) => {
+                warn!(%error, domain, "checking custom domain failed");
+                continue;
+            }
+        }
+
+        let state = acme.state(std::slice::from_ref(&domain));
+        let cert = CustomDomainCert {
+            resolver: state.resolver(),
+            renewal: tokio::spawn(poll_acme(state)).abort_handle(),
+        };
+
+        resolver
+            .custom_domains
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(domain, cert);
+    }
+}
+
+/// Starts periodically checking that each custom domain with a certificate still serves files,
+/// removing the certificates of those that don't and stopping their renewal. If they serve files
+/// again later, their certificates are ordered again once requested.
+fn revalidate_custom_domains(db_pool: PgPool, resolver: Arc<CertResolver>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(CUSTOM_DOMAIN_REVALIDATION_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            let domains: Vec<String> = resolver
+                .custom_domains
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .keys()
+                .cloned()
+                .collect();
+
+            let invalid_domains =
+                db::transaction!(db_pool, async |tx| -> TxResult<_, sqlx::Error> {
+                    let mut invalid_domains = Vec::new();
+
+                    for domain in &domains {
+                        if find_custom_domain_owner(tx, domain).await?.is_none() {
+                            invalid_domains.push(domain.clone());
+                        }
+                    }
+
+                    Ok(invalid_domains)
+                })
+                .await;
+
+            let invalid_domains = match invalid_domains {
+                Ok(invalid_domains) => invalid_domains,
+                Err(error) => {
+                    warn!(%error, "revalidating custom domains failed");
+                    continue;
+                }
+            };
+
+            let mut custom_domains = resolver
+                .custom_domains
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+
+            for domain in invalid_domains {
+                if let Some(cert) = custom_domains.remove(&domain) {
+                    cert.renewal.abort();
+                }
+            }
+        }
+    });
+}
+
+/// Gets the domain name from an origin URI string, without its scheme or port.
+///
+/// # Panics
+///
+/// Panics if the origin string doesn't contain "://".
+fn domain_from_origin(origin: &str) -> String {
+    let (_, host) = origin
+        .split_once("://")
+        .expect("origin should contain \"://\"");
+
+    host.split_once(':')
+        .map_or(host, |(domain, _)| domain)
+        .into()
+}
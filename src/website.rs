@@ -12,9 +12,11 @@ use axum::{
     response::{IntoResponse, Response},
 };
 
+use crate::config;
+
 /// The local address of the internal server for the website.
 static INTERNAL_ADDRESS: LazyLock<Authority> = LazyLock::new(|| {
-    dotenvy::var("INTERNAL_WEBSITE_ADDRESS")
+    config::var("INTERNAL_WEBSITE_ADDRESS")
         .expect("environment variable `INTERNAL_WEBSITE_ADDRESS` should be a valid string")
         .parse()
         .expect("environment variable `INTERNAL_WEBSITE_ADDRESS` should be a valid URI authority")
@@ -0,0 +1,1163 @@
+//! Tests running the whole server, each against its own temporary database.
+//!
+//! These need `DATABASE_URL` set to a database that can create other databases, as well as
+//! `CONTENT_ORIGIN`, `WEBSITE_ORIGIN`, `TOS_VERSION`, and `SIGNING_SECRET`.
+
+// Every other dependency is used by the library instead.
+#![expect(
+    unused_crate_dependencies,
+    reason = "tests only drive the library's server"
+)]
+#![expect(
+    clippy::tests_outside_test_module,
+    reason = "integration tests are only ever compiled for testing"
+)]
+
+use std::net::SocketAddr;
+
+use argon2::{
+    password_hash::{PasswordHasher, SaltString},
+    Argon2,
+};
+use axum::{
+    body::{self, Body},
+    extract::ConnectInfo,
+    http::{
+        header::{CONTENT_TYPE, COOKIE, ETAG, HOST, IF_MATCH, LOCATION, SET_COOKIE},
+        Method, Request, StatusCode,
+    },
+    Router,
+};
+use backend::{
+    app::App,
+    id::{Id, Token},
+};
+use ring::digest::{digest, SHA256};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use tower::ServiceExt;
+
+/// The type of IDs created for test users, files, and folders.
+type TestId = Id<[u8; 8]>;
+
+/// A user created directly in the database, signed into a session.
+struct TestUser {
+    /// The user's ID.
+    id: TestId,
+
+    /// The `Cookie` header value for the user's session.
+    cookie: String,
+}
+
+/// Builds a router for an app using the specified database, without background jobs.
+///
+/// # Errors
+///
+/// Returns an error if building the app fails.
+async fn router(db_pool: PgPool) -> anyhow::Result<Router> {
+    let app = App::builder(db_pool).background_jobs(false).build().await?;
+
+    Ok(app.router())
+}
+
+/// Builds a `GET` request for the specified path on the host of the origin in the specified
+/// environment variable.
+///
+/// # Errors
+///
+/// Returns an error if the environment variable is unset.
+fn get(origin_var: &str, path: &str) -> anyhow::Result<Request<Body>> {
+    let origin = dotenvy::var(origin_var)?;
+    let host = origin
+        .split_once("://")
+        .map_or(origin.as_str(), |(_, host)| host);
+
+    Ok(Request::get(path).header(HOST, host).body(Body::empty())?)
+}
+
+/// Creates a user who has accepted the current terms of service, and signs them in.
+///
+/// # Errors
+///
+/// Returns an error if a database query fails or `TOS_VERSION` is unset.
+async fn create_user(db_pool: &PgPool, name: &str) -> anyhow::Result<TestUser> {
+    let id = TestId::generate()?;
+    let token = Token::generate()?;
+
+    sqlx::query(
+        "INSERT INTO users (
+            id, email, canonical_email, name, password_hash, tos_version, tos_accepted_at
+        )
+            VALUES ($1, $2, $2, $3, '', $4, now())",
+    )
+    .bind(id.as_slice())
+    .bind(format!("{name}@example.com"))
+    .bind(name)
+    .bind(dotenvy::var("TOS_VERSION")?)
+    .execute(db_pool)
+    .await?;
+
+    sqlx::query(
+        "INSERT INTO sessions (id, user_id, token_hash)
+            VALUES ($1, $2, $3)",
+    )
+    .bind(TestId::generate()?.as_slice())
+    .bind(id.as_slice())
+    .bind(digest(&SHA256, token.as_ref()).as_ref())
+    .execute(db_pool)
+    .await?;
+
+    Ok(TestUser {
+        id,
+        cookie: format!("token={token}"),
+    })
+}
+
+/// Creates a folder at the top level of a user's files.
+///
+/// # Errors
+///
+/// Returns an error if the database query fails.
+async fn create_folder(db_pool: &PgPool, owner: &TestUser, name: &str) -> anyhow::Result<TestId> {
+    let id = TestId::generate()?;
+
+    sqlx::query(
+        "INSERT INTO folders (id, name, owner_id, parent_id_path, parent_name_path)
+            VALUES ($1, $2, $3, '{}', '{}')",
+    )
+    .bind(id.as_slice())
+    .bind(name)
+    .bind(owner.id.as_slice())
+    .execute(db_pool)
+    .await?;
+
+    Ok(id)
+}
+
+/// Creates an empty file, either at the top level of a user's files or in one of their top-level
+/// folders.
+///
+/// # Errors
+///
+/// Returns an error if the database query fails.
+async fn create_file(
+    db_pool: &PgPool,
+    owner: &TestUser,
+    folder: Option<(&TestId, &str)>,
+    name: &str,
+) -> anyhow::Result<TestId> {
+    let id = TestId::generate()?;
+    let (parent_id_path, parent_name_path) = match folder {
+        Some((folder_id, folder_name)) => (vec![folder_id.to_vec()], vec![folder_name]),
+        None => (Vec::new(), Vec::new()),
+    };
+
+    sqlx::query(
+        "INSERT INTO files (
+            id, name, owner_id, parent_id_path, parent_name_path, size, encoded_size, type
+        )
+            VALUES ($1, $2, $3, $4, $5, 0, 0, 'text/plain')",
+    )
+    .bind(id.as_slice())
+    .bind(name)
+    .bind(owner.id.as_slice())
+    .bind(parent_id_path)
+    .bind(parent_name_path)
+    .execute(db_pool)
+    .await?;
+
+    Ok(id)
+}
+
+/// Builds an API request as the specified user.
+///
+/// # Errors
+///
+/// Returns an error if the request can't be built.
+fn api_request(
+    user: &TestUser,
+    method: Method,
+    path: &str,
+    body: Option<Value>,
+) -> anyhow::Result<Request<Body>> {
+    let mut request = get("WEBSITE_ORIGIN", path)?;
+    *request.method_mut() = method;
+    request.headers_mut().insert(COOKIE, user.cookie.parse()?);
+
+    if let Some(body) = body {
+        request
+            .headers_mut()
+            .insert(CONTENT_TYPE, "application/json".parse()?);
+        *request.body_mut() = Body::from(body.to_string());
+    }
+
+    Ok(request)
+}
+
+/// Sends an API request, returning the response's status and JSON body.
+///
+/// # Errors
+///
+/// Returns an error if the request can't be sent, or the response isn't JSON.
+async fn send(router: &Router, request: Request<Body>) -> anyhow::Result<(StatusCode, Value)> {
+    let response = router.clone().oneshot(request).await?;
+    let status = response.status();
+    let body = body::to_bytes(response.into_body(), usize::MAX).await?;
+
+    Ok((status, serde_json::from_slice(&body)?))
+}
+
+/// Sends an API request as the specified user, returning the response's status and JSON body.
+///
+/// # Errors
+///
+/// Returns an error if the request can't be built or sent, or the response isn't JSON.
+async fn api(
+    router: &Router,
+    user: &TestUser,
+    method: Method,
+    path: &str,
+    body: Option<Value>,
+) -> anyhow::Result<(StatusCode, Value)> {
+    send(router, api_request(user, method, path, body)?).await
+}
+
+#[sqlx::test]
+async fn signed_in_session_used(db_pool: PgPool) -> anyhow::Result<()> {
+    let router = router(db_pool.clone()).await?;
+    let user = create_user(&db_pool, "signer").await?;
+    let password = "correct horse battery staple";
+
+    // Salt only protects against precomputed hashes, so a fixed one is fine here.
+    let salt = SaltString::encode_b64(b"test salt").map_err(anyhow::Error::msg)?;
+    let password_hash = Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map_err(anyhow::Error::msg)?
+        .to_string();
+
+    sqlx::query("UPDATE users SET password_hash = $1 WHERE id = $2")
+        .bind(password_hash)
+        .bind(user.id.as_slice())
+        .execute(&db_pool)
+        .await?;
+
+    let sign_in = |password: &str| -> anyhow::Result<Request<Body>> {
+        let mut request = get("WEBSITE_ORIGIN", "/api/v1/sessions")?;
+        *request.method_mut() = Method::POST;
+        request
+            .headers_mut()
+            .insert(CONTENT_TYPE, "application/json".parse()?);
+        request
+            .extensions_mut()
+            .insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 0))));
+        *request.body_mut() =
+            Body::from(json!({ "email": "signer@example.com", "password": password }).to_string());
+
+        Ok(request)
+    };
+
+    let response = router.clone().oneshot(sign_in("wrong password")?).await?;
+
+    assert_eq!(
+        StatusCode::FORBIDDEN,
+        response.status(),
+        "a wrong password shouldn't sign in",
+    );
+
+    let response = router.clone().oneshot(sign_in(password)?).await?;
+
+    assert_eq!(StatusCode::OK, response.status(), "the user should sign in");
+
+    let cookie = response
+        .headers()
+        .get(SET_COOKIE)
+        .and_then(|cookie| cookie.to_str().ok())
+        .and_then(|cookie| cookie.split(';').next())
+        .unwrap_or_default()
+        .to_owned();
+
+    let session_user = TestUser {
+        id: user.id.clone(),
+        cookie,
+    };
+
+    let (status, body) = api(
+        &router,
+        &session_user,
+        Method::GET,
+        &format!("/api/v1/users/{}/email", user.id),
+        None,
+    )
+    .await?;
+
+    assert_eq!(
+        StatusCode::OK,
+        status,
+        "the new session should be signed in: {body}",
+    );
+    assert_eq!(json!("signer@example.com"), body["email"]);
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn plans_listed(db_pool: PgPool) -> anyhow::Result<()> {
+    let response = router(db_pool)
+        .await?
+        .oneshot(get("WEBSITE_ORIGIN", "/api/v1/plans")?)
+        .await?;
+
+    assert_eq!(StatusCode::OK, response.status(), "plans should be listed");
+
+    let body = body::to_bytes(response.into_body(), usize::MAX).await?;
+    let body: serde_json::Value = serde_json::from_slice(&body)?;
+    let plans = body["plans"].as_array().map(Vec::len);
+
+    assert_eq!(Some(3), plans, "every plan should be listed");
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn unknown_user_files_not_found(db_pool: PgPool) -> anyhow::Result<()> {
+    let response = router(db_pool)
+        .await?
+        .oneshot(get("CONTENT_ORIGIN", "/nobody/file.txt")?)
+        .await?;
+
+    assert_eq!(
+        StatusCode::NOT_FOUND,
+        response.status(),
+        "an unknown user's files shouldn't be found",
+    );
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn unknown_custom_domain_misdirected(db_pool: PgPool) -> anyhow::Result<()> {
+    let request = Request::get("/file.txt")
+        .header(HOST, "unknown.example")
+        .body(Body::empty())?;
+
+    let response = router(db_pool).await?.oneshot(request).await?;
+
+    assert_eq!(
+        StatusCode::MISDIRECTED_REQUEST,
+        response.status(),
+        "a domain no user verified shouldn't be served",
+    );
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn file_batch_performed(db_pool: PgPool) -> anyhow::Result<()> {
+    let router = router(db_pool.clone()).await?;
+    let user = create_user(&db_pool, "batcher").await?;
+    let folder_id = create_folder(&db_pool, &user, "folder").await?;
+    let moved_file_id = create_file(&db_pool, &user, None, "moved.txt").await?;
+    let deleted_file_id = create_file(&db_pool, &user, None, "deleted.txt").await?;
+
+    let (status, body) = api(
+        &router,
+        &user,
+        Method::POST,
+        "/api/v1/files/batch",
+        Some(json!({
+            "operations": [
+                {
+                    "type": "move",
+                    "fileId": moved_file_id.to_string(),
+                    "folderId": folder_id.to_string(),
+                },
+                { "type": "setShared", "fileId": moved_file_id.to_string(), "shared": true },
+                { "type": "delete", "fileId": deleted_file_id.to_string() },
+            ],
+        })),
+    )
+    .await?;
+
+    assert_eq!(StatusCode::OK, status, "the batch should succeed: {body}");
+
+    let results = &body["results"];
+    assert_eq!(
+        json!(["folder"]),
+        results[0]["parentPath"],
+        "the file should be moved"
+    );
+    assert_eq!(
+        json!(true),
+        results[1]["shared"],
+        "the file should be shared"
+    );
+    assert_eq!(
+        json!("delete"),
+        results[2]["type"],
+        "the file should be deleted"
+    );
+
+    let remaining_files: i64 = sqlx::query_scalar("SELECT count(*) FROM files")
+        .fetch_one(&db_pool)
+        .await?;
+
+    assert_eq!(1, remaining_files, "only the deleted file should be gone");
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn file_batch_all_or_nothing(db_pool: PgPool) -> anyhow::Result<()> {
+    let router = router(db_pool.clone()).await?;
+    let user = create_user(&db_pool, "batcher").await?;
+    let other_user = create_user(&db_pool, "other").await?;
+    let file_id = create_file(&db_pool, &user, None, "file.txt").await?;
+    let other_file_id = create_file(&db_pool, &other_user, None, "other.txt").await?;
+
+    // Access to someone else's file is denied before its lock is checked, so whether it's locked
+    // isn't revealed.
+    sqlx::query(
+        "INSERT INTO file_locks (file_id, user_id, token_hash, expires_at)
+            VALUES ($1, $2, $3, now() + interval '1 hour')",
+    )
+    .bind(other_file_id.as_slice())
+    .bind(other_user.id.as_slice())
+    .bind(digest(&SHA256, b"token").as_ref())
+    .execute(&db_pool)
+    .await?;
+
+    let (status, body) = api(
+        &router,
+        &user,
+        Method::POST,
+        "/api/v1/files/batch",
+        Some(json!({
+            "operations": [
+                { "type": "delete", "fileId": file_id.to_string() },
+                { "type": "delete", "fileId": other_file_id.to_string() },
+            ],
+        })),
+    )
+    .await?;
+
+    assert_eq!(
+        StatusCode::FORBIDDEN,
+        status,
+        "the batch should fail: {body}"
+    );
+    assert_eq!(json!("BATCH_OPERATION_FAILED"), body["code"]);
+    assert_eq!(
+        json!("operations[1]"),
+        body["details"][0]["field"],
+        "the failed operation should be identified",
+    );
+    assert_eq!(json!("PERMISSION_DENIED"), body["details"][0]["code"]);
+
+    let remaining_files: i64 = sqlx::query_scalar("SELECT count(*) FROM files")
+        .fetch_one(&db_pool)
+        .await?;
+
+    assert_eq!(2, remaining_files, "no operation should be performed");
+
+    Ok(())
+}
+
+/// Grants a user access to a folder.
+///
+/// # Errors
+///
+/// Returns an error if the database query fails.
+async fn grant_folder_access(
+    db_pool: &PgPool,
+    folder_id: &TestId,
+    user: &TestUser,
+    access: &str,
+) -> anyhow::Result<()> {
+    sqlx::query(
+        "INSERT INTO folder_access_grants (folder_id, user_id, access)
+            VALUES ($1, $2, $3::folder_access)",
+    )
+    .bind(folder_id.as_slice())
+    .bind(user.id.as_slice())
+    .bind(access)
+    .execute(db_pool)
+    .await?;
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn file_batch_requires_write_access(db_pool: PgPool) -> anyhow::Result<()> {
+    let router = router(db_pool.clone()).await?;
+    let owner = create_user(&db_pool, "owner").await?;
+    let reader = create_user(&db_pool, "reader").await?;
+    let writer = create_user(&db_pool, "writer").await?;
+    let folder_id = create_folder(&db_pool, &owner, "folder").await?;
+    let file_id = create_file(&db_pool, &owner, Some((&folder_id, "folder")), "file.txt").await?;
+
+    grant_folder_access(&db_pool, &folder_id, &reader, "read").await?;
+    grant_folder_access(&db_pool, &folder_id, &writer, "write").await?;
+
+    let body = json!({
+        "operations": [{ "type": "setShared", "fileId": file_id.to_string(), "shared": true }],
+    });
+
+    let (status, _) = api(
+        &router,
+        &reader,
+        Method::POST,
+        "/api/v1/files/batch",
+        Some(body.clone()),
+    )
+    .await?;
+
+    assert_eq!(
+        StatusCode::FORBIDDEN,
+        status,
+        "read access shouldn't allow changing the file",
+    );
+
+    let (status, body) = api(
+        &router,
+        &writer,
+        Method::POST,
+        "/api/v1/files/batch",
+        Some(body),
+    )
+    .await?;
+
+    assert_eq!(
+        StatusCode::OK,
+        status,
+        "write access should allow changing the file: {body}",
+    );
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn granted_file_viewable(db_pool: PgPool) -> anyhow::Result<()> {
+    let router = router(db_pool.clone()).await?;
+    let owner = create_user(&db_pool, "owner").await?;
+    let reader = create_user(&db_pool, "reader").await?;
+    let folder_id = create_folder(&db_pool, &owner, "folder").await?;
+    let file_id = create_file(&db_pool, &owner, Some((&folder_id, "folder")), "file.txt").await?;
+
+    let (status, _) = api(
+        &router,
+        &reader,
+        Method::GET,
+        &format!("/api/v1/files/{file_id}/view-url"),
+        None,
+    )
+    .await?;
+
+    assert_eq!(
+        StatusCode::FORBIDDEN,
+        status,
+        "a user without access shouldn't get a view URL",
+    );
+
+    grant_folder_access(&db_pool, &folder_id, &reader, "read").await?;
+
+    let (status, body) = api(
+        &router,
+        &reader,
+        Method::GET,
+        &format!("/api/v1/files/{file_id}/view-url"),
+        None,
+    )
+    .await?;
+
+    assert_eq!(
+        StatusCode::OK,
+        status,
+        "a view URL should be issued: {body}"
+    );
+
+    let url = body["url"].as_str().unwrap_or_default();
+    let path_and_query = url
+        .strip_prefix(&dotenvy::var("CONTENT_ORIGIN")?)
+        .unwrap_or_default();
+
+    let response = router
+        .clone()
+        .oneshot(get("CONTENT_ORIGIN", path_and_query)?)
+        .await?;
+
+    assert_eq!(
+        StatusCode::OK,
+        response.status(),
+        "the viewer should be served the unshared file",
+    );
+
+    let (path, _) = path_and_query
+        .split_once('?')
+        .unwrap_or((path_and_query, ""));
+    let response = router.oneshot(get("CONTENT_ORIGIN", path)?).await?;
+
+    assert_eq!(
+        StatusCode::NOT_FOUND,
+        response.status(),
+        "anyone else shouldn't be served the unshared file",
+    );
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn maintenance_exempts_admins_and_health_checks(db_pool: PgPool) -> anyhow::Result<()> {
+    let router = router(db_pool.clone()).await?;
+    let user = create_user(&db_pool, "user").await?;
+    let admin = create_user(&db_pool, "admin").await?;
+
+    sqlx::query("UPDATE users SET role = 'admin' WHERE id = $1")
+        .bind(admin.id.as_slice())
+        .execute(&db_pool)
+        .await?;
+
+    sqlx::query("UPDATE maintenance SET mode = 'all'")
+        .execute(&db_pool)
+        .await?;
+
+    let (status, _) = api(&router, &user, Method::GET, "/api/v1/plans", None).await?;
+
+    assert_eq!(
+        StatusCode::SERVICE_UNAVAILABLE,
+        status,
+        "users should be rejected during maintenance",
+    );
+
+    let (status, _) = api(&router, &user, Method::GET, "/api/v1/health", None).await?;
+
+    assert_eq!(
+        StatusCode::OK,
+        status,
+        "health checks should work during maintenance",
+    );
+
+    let (status, body) = api(
+        &router,
+        &admin,
+        Method::PUT,
+        "/api/v1/config/maintenance",
+        Some(json!({ "mode": "off" })),
+    )
+    .await?;
+
+    assert_eq!(
+        StatusCode::OK,
+        status,
+        "admins should be able to end maintenance: {body}",
+    );
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn file_etag_is_version(db_pool: PgPool) -> anyhow::Result<()> {
+    let router = router(db_pool.clone()).await?;
+    let user = create_user(&db_pool, "user").await?;
+    let file_id = create_file(&db_pool, &user, None, "file.txt").await?;
+    let path = format!("/api/v1/files/{file_id}");
+
+    let mut request = get("WEBSITE_ORIGIN", &path)?;
+    request.headers_mut().insert(COOKIE, user.cookie.parse()?);
+
+    let response = router.clone().oneshot(request).await?;
+    let etag = response.headers().get(ETAG).cloned();
+
+    assert_eq!(StatusCode::OK, response.status());
+    assert_eq!(
+        Some("\"1\"".parse()?),
+        etag,
+        "the file's version should be its entity tag"
+    );
+
+    let mut request = get("WEBSITE_ORIGIN", &path)?;
+    *request.method_mut() = Method::PATCH;
+    *request.body_mut() = Body::from(json!({ "shared": true }).to_string());
+
+    let headers = request.headers_mut();
+    headers.insert(COOKIE, user.cookie.parse()?);
+    headers.insert(CONTENT_TYPE, "application/json".parse()?);
+    headers.extend(etag.map(|etag| (IF_MATCH, etag)));
+
+    let response = router.oneshot(request).await?;
+
+    assert_eq!(StatusCode::OK, response.status(), "the file should change");
+    assert_eq!(
+        Some(&"\"2\"".parse()?),
+        response.headers().get(ETAG),
+        "the file's new version should be its entity tag",
+    );
+
+    Ok(())
+}
+
+/// Sends a file lock API request as the specified user, with the specified lock token in the
+/// `Lock-Token` header if any.
+///
+/// # Errors
+///
+/// Returns an error if the request can't be built or sent, or the response isn't JSON.
+async fn lock_api(
+    router: &Router,
+    user: &TestUser,
+    method: Method,
+    file_id: &TestId,
+    lock_token: Option<&str>,
+) -> anyhow::Result<(StatusCode, Value)> {
+    let body = (method != Method::DELETE).then(|| json!({}));
+    let mut request = api_request(user, method, &format!("/api/v1/files/{file_id}/lock"), body)?;
+
+    if let Some(lock_token) = lock_token {
+        request
+            .headers_mut()
+            .insert("lock-token", format!("<{lock_token}>").parse()?);
+    }
+
+    send(router, request).await
+}
+
+#[sqlx::test]
+async fn file_lock_lifecycle(db_pool: PgPool) -> anyhow::Result<()> {
+    let router = router(db_pool.clone()).await?;
+    let user = create_user(&db_pool, "user").await?;
+    let file_id = create_file(&db_pool, &user, None, "file.txt").await?;
+
+    let (status, body) = lock_api(&router, &user, Method::POST, &file_id, None).await?;
+
+    assert_eq!(StatusCode::CREATED, status, "the file should lock: {body}");
+
+    let token = body["token"]
+        .as_str()
+        .ok_or_else(|| anyhow::anyhow!("the lock should have a token: {body}"))?
+        .to_owned();
+
+    let (status, body) = lock_api(&router, &user, Method::POST, &file_id, None).await?;
+
+    assert_eq!(
+        StatusCode::LOCKED,
+        status,
+        "an unexpired lock shouldn't be replaced: {body}",
+    );
+    assert_eq!(json!("FILE_LOCKED"), body["code"]);
+
+    let mut request = api_request(
+        &user,
+        Method::PATCH,
+        &format!("/api/v1/files/{file_id}"),
+        Some(json!({ "shared": true })),
+    )?;
+    request.headers_mut().insert(IF_MATCH, "\"1\"".parse()?);
+
+    let (status, _) = send(&router, request).await?;
+
+    assert_eq!(
+        StatusCode::LOCKED,
+        status,
+        "the file shouldn't change without the lock token",
+    );
+
+    let (status, _) = lock_api(&router, &user, Method::PUT, &file_id, Some("wrong")).await?;
+
+    assert_eq!(
+        StatusCode::LOCKED,
+        status,
+        "the lock shouldn't refresh with the wrong token",
+    );
+
+    let (status, body) = lock_api(&router, &user, Method::PUT, &file_id, Some(&token)).await?;
+
+    assert_eq!(StatusCode::OK, status, "the lock should refresh: {body}");
+
+    sqlx::query("UPDATE file_locks SET expires_at = now() - interval '1 second'")
+        .execute(&db_pool)
+        .await?;
+
+    let (status, _) = lock_api(&router, &user, Method::PUT, &file_id, Some(&token)).await?;
+
+    assert_eq!(
+        StatusCode::NOT_FOUND,
+        status,
+        "an expired lock shouldn't refresh",
+    );
+
+    let (status, body) = lock_api(&router, &user, Method::POST, &file_id, None).await?;
+
+    assert_eq!(
+        StatusCode::CREATED,
+        status,
+        "an expired lock should be replaced: {body}",
+    );
+
+    let new_token = body["token"]
+        .as_str()
+        .ok_or_else(|| anyhow::anyhow!("the lock should have a token: {body}"))?;
+
+    let (status, _) = lock_api(&router, &user, Method::DELETE, &file_id, Some(&token)).await?;
+
+    assert_eq!(
+        StatusCode::LOCKED,
+        status,
+        "the expired lock's token shouldn't unlock the file",
+    );
+
+    let (status, body) =
+        lock_api(&router, &user, Method::DELETE, &file_id, Some(new_token)).await?;
+
+    assert_eq!(StatusCode::OK, status, "the file should unlock: {body}");
+
+    let (_, body) = lock_api(&router, &user, Method::GET, &file_id, None).await?;
+
+    assert_eq!(Value::Null, body["lock"], "the file should be unlocked");
+
+    Ok(())
+}
+
+/// Lists changes to a user's files and folders after the specified query's cursor, retrying until
+/// at least the specified number are listed. Changes aren't listed while any older transaction is
+/// in progress, including other tests' on the same database server.
+///
+/// # Errors
+///
+/// Returns an error if a request fails, or too few changes are listed after retrying.
+async fn list_changes(
+    router: &Router,
+    user: &TestUser,
+    query: &str,
+    min_changes: usize,
+) -> anyhow::Result<Value> {
+    for _ in 0..50 {
+        let (status, body) = api(
+            router,
+            user,
+            Method::GET,
+            &format!("/api/v1/changes?{query}"),
+            None,
+        )
+        .await?;
+
+        assert_eq!(StatusCode::OK, status, "changes should be listed: {body}");
+
+        if body["changes"].as_array().map_or(0, Vec::len) >= min_changes {
+            return Ok(body);
+        }
+
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+
+    anyhow::bail!("fewer than {min_changes} changes were listed");
+}
+
+#[sqlx::test]
+async fn changes_listed_after_cursor(db_pool: PgPool) -> anyhow::Result<()> {
+    let router = router(db_pool.clone()).await?;
+    let user = create_user(&db_pool, "user").await?;
+    let folder_id = create_folder(&db_pool, &user, "folder").await?;
+    let file_id = create_file(&db_pool, &user, Some((&folder_id, "folder")), "file.txt").await?;
+
+    let body = list_changes(&router, &user, "limit=1", 1).await?;
+
+    assert_eq!(json!("folder"), body["changes"][0]["type"]);
+    assert_eq!(json!(folder_id.to_string()), body["changes"][0]["id"]);
+    assert_eq!(json!(true), body["hasMore"], "the file should be left");
+
+    let cursor = body["cursor"].as_str().unwrap_or_default().to_owned();
+    let body = list_changes(&router, &user, &format!("cursor={cursor}"), 1).await?;
+
+    assert_eq!(
+        1,
+        body["changes"].as_array().map_or(0, Vec::len),
+        "only changes after the cursor should be listed: {body}",
+    );
+    assert_eq!(json!("file"), body["changes"][0]["type"]);
+    assert_eq!(json!("file.txt"), body["changes"][0]["file"]["name"]);
+    assert_eq!(json!(false), body["hasMore"]);
+
+    let cursor = body["cursor"].as_str().unwrap_or_default().to_owned();
+    let body = list_changes(&router, &user, &format!("cursor={cursor}"), 0).await?;
+
+    assert_eq!(
+        json!([]),
+        body["changes"],
+        "nothing should have changed since",
+    );
+    assert_eq!(
+        json!(cursor),
+        body["cursor"],
+        "the cursor shouldn't move without changes",
+    );
+
+    sqlx::query("DELETE FROM files WHERE id = $1")
+        .bind(file_id.as_slice())
+        .execute(&db_pool)
+        .await?;
+
+    let body = list_changes(&router, &user, &format!("cursor={cursor}"), 1).await?;
+
+    assert_eq!(json!(file_id.to_string()), body["changes"][0]["id"]);
+    assert_eq!(
+        Value::Null,
+        body["changes"][0]["file"],
+        "the deleted file should have a tombstone",
+    );
+
+    let (status, body) = api(
+        &router,
+        &user,
+        Method::GET,
+        "/api/v1/changes?cursor=invalid",
+        None,
+    )
+    .await?;
+
+    assert_eq!(
+        StatusCode::BAD_REQUEST,
+        status,
+        "an invalid cursor should be rejected: {body}",
+    );
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn folder_grants_inherited(db_pool: PgPool) -> anyhow::Result<()> {
+    let router = router(db_pool.clone()).await?;
+    let owner = create_user(&db_pool, "owner").await?;
+    let reader = create_user(&db_pool, "reader").await?;
+    let member = create_user(&db_pool, "member").await?;
+    let stranger = create_user(&db_pool, "stranger").await?;
+    let folder_id = create_folder(&db_pool, &owner, "folder").await?;
+    let subfolder_id = TestId::generate()?;
+    let file_id = TestId::generate()?;
+    let organization_id = TestId::generate()?;
+
+    sqlx::query(
+        "INSERT INTO folders (id, name, owner_id, parent_id_path, parent_name_path)
+            VALUES ($1, 'subfolder', $2, ARRAY[$3], '{folder}')",
+    )
+    .bind(subfolder_id.as_slice())
+    .bind(owner.id.as_slice())
+    .bind(folder_id.as_slice())
+    .execute(&db_pool)
+    .await?;
+
+    sqlx::query(
+        "INSERT INTO files (
+            id, name, owner_id, parent_id_path, parent_name_path, size, encoded_size, type
+        )
+            VALUES ($1, 'file.txt', $2, ARRAY[$3, $4], '{folder,subfolder}', 0, 0, 'text/plain')",
+    )
+    .bind(file_id.as_slice())
+    .bind(owner.id.as_slice())
+    .bind(folder_id.as_slice())
+    .bind(subfolder_id.as_slice())
+    .execute(&db_pool)
+    .await?;
+
+    sqlx::query("INSERT INTO organizations (id, name) VALUES ($1, 'Organization')")
+        .bind(organization_id.as_slice())
+        .execute(&db_pool)
+        .await?;
+
+    sqlx::query(
+        "INSERT INTO organization_members (organization_id, user_id, role)
+            VALUES ($1, $2, 'member')",
+    )
+    .bind(organization_id.as_slice())
+    .bind(member.id.as_slice())
+    .execute(&db_pool)
+    .await?;
+
+    sqlx::query(
+        "INSERT INTO organization_folders (organization_id, folder_id, access)
+            VALUES ($1, $2, 'write')",
+    )
+    .bind(organization_id.as_slice())
+    .bind(folder_id.as_slice())
+    .execute(&db_pool)
+    .await?;
+
+    grant_folder_access(&db_pool, &folder_id, &reader, "read").await?;
+
+    let file_path = format!("/api/v1/files/{file_id}");
+    let patch = |user| -> anyhow::Result<Request<Body>> {
+        let mut request = api_request(
+            user,
+            Method::PATCH,
+            &file_path,
+            Some(json!({ "shared": true })),
+        )?;
+        request.headers_mut().insert(IF_MATCH, "\"1\"".parse()?);
+
+        Ok(request)
+    };
+
+    let (status, _) = api(&router, &stranger, Method::GET, &file_path, None).await?;
+
+    assert_eq!(
+        StatusCode::FORBIDDEN,
+        status,
+        "a user without a grant shouldn't see the file",
+    );
+
+    let (status, body) = api(&router, &reader, Method::GET, &file_path, None).await?;
+
+    assert_eq!(
+        StatusCode::OK,
+        status,
+        "read access to an ancestor folder should allow seeing the file: {body}",
+    );
+
+    let (status, _) = send(&router, patch(&reader)?).await?;
+
+    assert_eq!(
+        StatusCode::FORBIDDEN,
+        status,
+        "read access to an ancestor folder shouldn't allow changing the file",
+    );
+
+    let (status, body) = send(&router, patch(&member)?).await?;
+
+    assert_eq!(
+        StatusCode::OK,
+        status,
+        "an organization's write access to an ancestor folder should allow its members to change \
+            the file: {body}",
+    );
+
+    sqlx::query("DELETE FROM organization_members WHERE user_id = $1")
+        .bind(member.id.as_slice())
+        .execute(&db_pool)
+        .await?;
+
+    let (status, _) = api(&router, &member, Method::GET, &file_path, None).await?;
+
+    assert_eq!(
+        StatusCode::FORBIDDEN,
+        status,
+        "leaving the organization should revoke its access",
+    );
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn organization_storage_used(db_pool: PgPool) -> anyhow::Result<()> {
+    let router = router(db_pool.clone()).await?;
+    let member = create_user(&db_pool, "member").await?;
+    let folder_id = create_folder(&db_pool, &member, "folder").await?;
+    let file_id = create_file(&db_pool, &member, Some((&folder_id, "folder")), "file.txt").await?;
+    let organization_id = TestId::generate()?;
+
+    sqlx::query("INSERT INTO organizations (id, name) VALUES ($1, 'Organization')")
+        .bind(organization_id.as_slice())
+        .execute(&db_pool)
+        .await?;
+
+    sqlx::query(
+        "INSERT INTO organization_members (organization_id, user_id, role)
+            VALUES ($1, $2, 'owner')",
+    )
+    .bind(organization_id.as_slice())
+    .bind(member.id.as_slice())
+    .execute(&db_pool)
+    .await?;
+
+    sqlx::query("UPDATE files SET size = 20, shared = TRUE WHERE id = $1")
+        .bind(file_id.as_slice())
+        .execute(&db_pool)
+        .await?;
+
+    sqlx::query("UPDATE plan_limits SET storage_quota = 10 WHERE plan = 'free'")
+        .execute(&db_pool)
+        .await?;
+
+    let storage_path = format!("/api/v1/organizations/{organization_id}/storage");
+    let folder_path = format!("{storage_path}/{folder_id}");
+
+    let (status, body) = api(&router, &member, Method::PUT, &folder_path, None).await?;
+
+    assert_eq!(
+        StatusCode::FORBIDDEN,
+        status,
+        "a folder exceeding the organization's quota shouldn't be moved into its storage",
+    );
+    assert_eq!(json!("STORAGE_QUOTA_EXCEEDED"), body["code"]);
+
+    sqlx::query("UPDATE files SET size = 5 WHERE id = $1")
+        .bind(file_id.as_slice())
+        .execute(&db_pool)
+        .await?;
+
+    let (status, body) = api(&router, &member, Method::PUT, &folder_path, None).await?;
+
+    assert_eq!(
+        StatusCode::OK,
+        status,
+        "a folder within the organization's quota should be moved into its storage: {body}",
+    );
+
+    let (_, body) = api(&router, &member, Method::GET, &storage_path, None).await?;
+
+    assert_eq!(json!(5), body["storageUsed"]);
+    assert_eq!(json!(10), body["storageQuota"]);
+
+    let (_, body) = api(
+        &router,
+        &member,
+        Method::GET,
+        &format!("/api/v1/users/{}/usage", member.id),
+        None,
+    )
+    .await?;
+
+    assert_eq!(
+        json!(0),
+        body["storageUsed"],
+        "files in the organization's storage shouldn't count toward their owner's",
+    );
+
+    let response = sqlx::query("UPDATE files SET size = 20 WHERE id = $1")
+        .bind(file_id.as_slice())
+        .execute(&db_pool)
+        .await;
+
+    assert!(
+        response.is_err(),
+        "a file growing past the organization's quota should be rejected",
+    );
+
+    let organization_url = format!("/{organization_id}/folder/file.txt");
+    let response = router
+        .clone()
+        .oneshot(get("CONTENT_ORIGIN", &organization_url)?)
+        .await?;
+
+    assert_eq!(
+        StatusCode::OK,
+        response.status(),
+        "the file should be served under the organization's ID",
+    );
+
+    let response = router
+        .oneshot(get(
+            "CONTENT_ORIGIN",
+            &format!("/{}/folder/file.txt", member.id),
+        )?)
+        .await?;
+
+    assert_eq!(StatusCode::PERMANENT_REDIRECT, response.status());
+    assert_eq!(
+        Some(format!("{}{organization_url}", dotenvy::var("CONTENT_ORIGIN")?).as_str()),
+        response
+            .headers()
+            .get(LOCATION)
+            .and_then(|location| location.to_str().ok()),
+        "the owner's URL should redirect to the organization's",
+    );
+
+    Ok(())
+}